@@ -0,0 +1,6 @@
+-- 017_audit_log_events/down.sql
+
+DROP TRIGGER IF EXISTS audit_log_graph_event_trg ON audit_log;
+DROP FUNCTION IF EXISTS audit_log_notify_graph_event();
+DROP INDEX IF EXISTS audit_log_seq_idx;
+ALTER TABLE audit_log DROP COLUMN IF EXISTS seq;
@@ -0,0 +1,23 @@
+-- 017_audit_log_events/up.sql
+
+-- Monotonic sequence used as SSE event id (resume via Last-Event-ID)
+ALTER TABLE audit_log
+    ADD COLUMN IF NOT EXISTS seq BIGSERIAL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS audit_log_seq_idx ON audit_log (seq);
+
+-- Every audit row is announced on commit; payload is only the seq,
+-- listeners read the row back from audit_log.
+CREATE OR REPLACE FUNCTION audit_log_notify_graph_event() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('graph_events', NEW.seq::text);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS audit_log_graph_event_trg ON audit_log;
+
+CREATE TRIGGER audit_log_graph_event_trg
+AFTER INSERT ON audit_log
+FOR EACH ROW
+EXECUTE FUNCTION audit_log_notify_graph_event();
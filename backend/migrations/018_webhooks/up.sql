@@ -0,0 +1,62 @@
+-- 018_webhooks/up.sql
+
+CREATE TABLE IF NOT EXISTS webhook_subscriptions (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  name TEXT NOT NULL,
+  url TEXT NOT NULL,
+  secret TEXT NOT NULL,
+
+  -- Event names to deliver; empty = all
+  events TEXT[] NOT NULL DEFAULT '{}',
+  active BOOLEAN NOT NULL DEFAULT true,
+
+  created_by TEXT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- Durable outbox: rows are written in the same transaction as the change
+-- and picked up by the delivery worker.
+CREATE TABLE IF NOT EXISTS webhook_outbox (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  subscription_id UUID NOT NULL REFERENCES webhook_subscriptions(id) ON DELETE CASCADE,
+
+  event TEXT NOT NULL,
+  payload JSONB NOT NULL,
+
+  status TEXT NOT NULL DEFAULT 'pending',
+  attempts INT NOT NULL DEFAULT 0,
+  next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  last_error TEXT NULL,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  delivered_at TIMESTAMPTZ NULL,
+
+  CONSTRAINT webhook_outbox_status_check CHECK (status IN ('pending', 'delivered', 'failed'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_outbox_due
+  ON webhook_outbox (next_attempt_at)
+  WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS idx_webhook_outbox_subscription
+  ON webhook_outbox (subscription_id, created_at DESC);
+
+-- One row per HTTP attempt
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  outbox_id UUID NOT NULL REFERENCES webhook_outbox(id) ON DELETE CASCADE,
+  subscription_id UUID NOT NULL REFERENCES webhook_subscriptions(id) ON DELETE CASCADE,
+
+  event TEXT NOT NULL,
+  attempt INT NOT NULL,
+  at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+  success BOOLEAN NOT NULL,
+  status_code INT NULL,
+  error TEXT NULL,
+  duration_ms BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_subscription
+  ON webhook_deliveries (subscription_id, at DESC);
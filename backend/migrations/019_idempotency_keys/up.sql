@@ -0,0 +1,24 @@
+-- 019_idempotency_keys/up.sql
+
+-- First response per (actor, Idempotency-Key); replayed on retries until
+-- expires_at. status_code IS NULL while the first request is still running.
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+  actor TEXT NOT NULL,
+  key TEXT NOT NULL,
+
+  method TEXT NOT NULL,
+  path TEXT NOT NULL,
+  request_hash TEXT NOT NULL,
+
+  status_code INT NULL,
+  response_headers JSONB NOT NULL DEFAULT '{}'::jsonb,
+  response_body BYTEA NULL,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  expires_at TIMESTAMPTZ NOT NULL,
+
+  PRIMARY KEY (actor, key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_idempotency_keys_expires_at
+  ON idempotency_keys (expires_at);
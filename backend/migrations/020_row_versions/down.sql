@@ -0,0 +1,12 @@
+-- 020_row_versions/down.sql
+
+DROP TRIGGER IF EXISTS node_claims_version_trg ON node_claims;
+DROP TRIGGER IF EXISTS edge_claims_version_trg ON edge_claims;
+DROP TRIGGER IF EXISTS edges_version_trg ON edges;
+DROP TRIGGER IF EXISTS nodes_version_trg ON nodes;
+DROP FUNCTION IF EXISTS bump_row_version();
+
+ALTER TABLE node_claims DROP COLUMN IF EXISTS version;
+ALTER TABLE edge_claims DROP COLUMN IF EXISTS version;
+ALTER TABLE edges DROP COLUMN IF EXISTS version;
+ALTER TABLE nodes DROP COLUMN IF EXISTS version;
@@ -0,0 +1,40 @@
+-- 020_row_versions/up.sql
+
+-- Monotonic row version used for ETags / optimistic locking. Bumped by a
+-- trigger on every UPDATE, so it moves even when two writes share a
+-- timestamp or the clock goes backwards.
+ALTER TABLE nodes ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE edges ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE edge_claims ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE node_claims ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 1;
+
+CREATE OR REPLACE FUNCTION bump_row_version() RETURNS trigger AS $$
+BEGIN
+    NEW.version := OLD.version + 1;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS nodes_version_trg ON nodes;
+CREATE TRIGGER nodes_version_trg
+BEFORE UPDATE ON nodes
+FOR EACH ROW
+EXECUTE FUNCTION bump_row_version();
+
+DROP TRIGGER IF EXISTS edges_version_trg ON edges;
+CREATE TRIGGER edges_version_trg
+BEFORE UPDATE ON edges
+FOR EACH ROW
+EXECUTE FUNCTION bump_row_version();
+
+DROP TRIGGER IF EXISTS edge_claims_version_trg ON edge_claims;
+CREATE TRIGGER edge_claims_version_trg
+BEFORE UPDATE ON edge_claims
+FOR EACH ROW
+EXECUTE FUNCTION bump_row_version();
+
+DROP TRIGGER IF EXISTS node_claims_version_trg ON node_claims;
+CREATE TRIGGER node_claims_version_trg
+BEFORE UPDATE ON node_claims
+FOR EACH ROW
+EXECUTE FUNCTION bump_row_version();
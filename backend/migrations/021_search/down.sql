@@ -0,0 +1,17 @@
+-- 021_search/down.sql
+
+DROP TRIGGER IF EXISTS owners_search_trg ON owners;
+DROP TRIGGER IF EXISTS suppliers_search_trg ON suppliers;
+DROP TRIGGER IF EXISTS node_owners_search_trg ON node_owners;
+DROP TRIGGER IF EXISTS node_suppliers_search_trg ON node_suppliers;
+DROP TRIGGER IF EXISTS node_software_search_trg ON node_software;
+DROP TRIGGER IF EXISTS nodes_search_trg ON nodes;
+DROP FUNCTION IF EXISTS node_search_party_trg();
+DROP FUNCTION IF EXISTS node_search_refresh_trg();
+DROP FUNCTION IF EXISTS refresh_node_search(UUID);
+
+DROP INDEX IF EXISTS idx_edge_claim_evidence_reference_trgm;
+DROP TABLE IF EXISTS node_search;
+
+DROP FUNCTION IF EXISTS search_vector(TEXT, "char");
+DROP FUNCTION IF EXISTS jsonb_search_text(JSONB);
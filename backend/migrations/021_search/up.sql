@@ -0,0 +1,149 @@
+-- 021_search/up.sql
+
+-- Full-text (Swedish + English) and trigram search.
+--
+-- Node search documents span several tables (name, metadata, software
+-- description, suppliers, owners), so they are kept in a side table that
+-- triggers refresh. Writing to `nodes` itself would bump its row version.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+CREATE EXTENSION IF NOT EXISTS unaccent;
+
+-- All string/number values in a JSON document, space separated.
+CREATE OR REPLACE FUNCTION jsonb_search_text(j JSONB) RETURNS TEXT AS $$
+    SELECT string_agg(v #>> '{}', ' ')
+    FROM jsonb_path_query(COALESCE(j, '{}'::jsonb), 'strict $.**') AS v
+    WHERE jsonb_typeof(v) IN ('string', 'number')
+$$ LANGUAGE sql IMMUTABLE;
+
+-- Both configurations, so Swedish stems and English stems both match.
+CREATE OR REPLACE FUNCTION search_vector(t TEXT, w "char") RETURNS tsvector AS $$
+    SELECT setweight(to_tsvector('swedish', COALESCE(t, '')), w)
+        || setweight(to_tsvector('english', COALESCE(t, '')), w)
+$$ LANGUAGE sql IMMUTABLE;
+
+CREATE TABLE IF NOT EXISTS node_search (
+    node_id UUID PRIMARY KEY REFERENCES nodes(id) ON DELETE CASCADE,
+    -- Human readable text, used for highlighted snippets.
+    document TEXT NOT NULL,
+    -- lower(unaccent(document)), used for trigram matching.
+    search_text TEXT NOT NULL,
+    search_vector tsvector NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_node_search_vector
+    ON node_search USING gin (search_vector);
+CREATE INDEX IF NOT EXISTS idx_node_search_trgm
+    ON node_search USING gin (search_text gin_trgm_ops);
+
+CREATE INDEX IF NOT EXISTS idx_edge_claim_evidence_reference_trgm
+    ON edge_claim_evidence USING gin (lower(reference) gin_trgm_ops);
+
+CREATE OR REPLACE FUNCTION refresh_node_search(p_node_id UUID) RETURNS void AS $$
+    INSERT INTO node_search (node_id, document, search_text, search_vector, updated_at)
+    SELECT
+        x.id,
+        x.document,
+        lower(unaccent(x.document)),
+        search_vector(x.name, 'A')
+            || search_vector(x.description, 'B')
+            || search_vector(x.parties, 'C')
+            || search_vector(x.meta, 'D'),
+        now()
+    FROM (
+        SELECT
+            n.id,
+            n.name,
+            concat_ws(' ', sw.software_name, sw.purpose, sw.description) AS description,
+            concat_ws(' ',
+                (SELECT string_agg(s.name, ' ')
+                 FROM node_suppliers ns JOIN suppliers s ON s.id = ns.supplier_id
+                 WHERE ns.node_id = n.id),
+                (SELECT string_agg(o.name, ' ')
+                 FROM node_owners no JOIN owners o ON o.id = no.owner_id
+                 WHERE no.node_id = n.id)
+            ) AS parties,
+            jsonb_search_text(n.metadata) AS meta,
+            concat_ws(' — ',
+                n.name,
+                NULLIF(concat_ws(' ', sw.software_name, sw.purpose, sw.description), ''),
+                NULLIF(jsonb_search_text(n.metadata), ''),
+                (SELECT string_agg(s.name, ', ')
+                 FROM node_suppliers ns JOIN suppliers s ON s.id = ns.supplier_id
+                 WHERE ns.node_id = n.id),
+                (SELECT string_agg(o.name, ', ')
+                 FROM node_owners no JOIN owners o ON o.id = no.owner_id
+                 WHERE no.node_id = n.id)
+            ) AS document
+        FROM nodes n
+        LEFT JOIN node_software sw ON sw.node_id = n.id
+        WHERE n.id = p_node_id
+    ) x
+    ON CONFLICT (node_id) DO UPDATE
+    SET document = EXCLUDED.document,
+        search_text = EXCLUDED.search_text,
+        search_vector = EXCLUDED.search_vector,
+        updated_at = EXCLUDED.updated_at
+$$ LANGUAGE sql;
+
+CREATE OR REPLACE FUNCTION node_search_refresh_trg() RETURNS trigger AS $$
+BEGIN
+    IF TG_TABLE_NAME = 'nodes' THEN
+        PERFORM refresh_node_search(NEW.id);
+    ELSIF TG_OP = 'DELETE' THEN
+        PERFORM refresh_node_search(OLD.node_id);
+    ELSE
+        PERFORM refresh_node_search(NEW.node_id);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION node_search_party_trg() RETURNS trigger AS $$
+BEGIN
+    IF TG_TABLE_NAME = 'suppliers' THEN
+        PERFORM refresh_node_search(node_id) FROM node_suppliers WHERE supplier_id = NEW.id;
+    ELSE
+        PERFORM refresh_node_search(node_id) FROM node_owners WHERE owner_id = NEW.id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS nodes_search_trg ON nodes;
+CREATE TRIGGER nodes_search_trg
+AFTER INSERT OR UPDATE OF name, metadata ON nodes
+FOR EACH ROW
+EXECUTE FUNCTION node_search_refresh_trg();
+
+DROP TRIGGER IF EXISTS node_software_search_trg ON node_software;
+CREATE TRIGGER node_software_search_trg
+AFTER INSERT OR UPDATE OR DELETE ON node_software
+FOR EACH ROW
+EXECUTE FUNCTION node_search_refresh_trg();
+
+DROP TRIGGER IF EXISTS node_suppliers_search_trg ON node_suppliers;
+CREATE TRIGGER node_suppliers_search_trg
+AFTER INSERT OR UPDATE OR DELETE ON node_suppliers
+FOR EACH ROW
+EXECUTE FUNCTION node_search_refresh_trg();
+
+DROP TRIGGER IF EXISTS node_owners_search_trg ON node_owners;
+CREATE TRIGGER node_owners_search_trg
+AFTER INSERT OR UPDATE OR DELETE ON node_owners
+FOR EACH ROW
+EXECUTE FUNCTION node_search_refresh_trg();
+
+DROP TRIGGER IF EXISTS suppliers_search_trg ON suppliers;
+CREATE TRIGGER suppliers_search_trg
+AFTER UPDATE OF name ON suppliers
+FOR EACH ROW
+EXECUTE FUNCTION node_search_party_trg();
+
+DROP TRIGGER IF EXISTS owners_search_trg ON owners;
+CREATE TRIGGER owners_search_trg
+AFTER UPDATE OF name ON owners
+FOR EACH ROW
+EXECUTE FUNCTION node_search_party_trg();
+
+SELECT refresh_node_search(id) FROM nodes;
@@ -0,0 +1,3 @@
+-- 022_saved_searches/down.sql
+
+DROP TABLE IF EXISTS saved_searches;
@@ -0,0 +1,25 @@
+-- 022_saved_searches/up.sql
+
+-- Named searches: a search string, facet selections and/or a FilterSpec.
+-- Private to the owner unless `shared`.
+CREATE TABLE IF NOT EXISTS saved_searches (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  name TEXT NOT NULL,
+  owner TEXT NOT NULL,
+  shared BOOLEAN NOT NULL DEFAULT false,
+
+  q TEXT NULL,
+  -- { "kind": "...", "owning_department": "...", ... }
+  facets JSONB NOT NULL DEFAULT '{}'::jsonb,
+  -- FilterSpec, usable as the filter for exports
+  filter JSONB NULL,
+
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+  CONSTRAINT saved_searches_owner_name_unique UNIQUE (owner, name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_saved_searches_shared
+  ON saved_searches (shared)
+  WHERE shared;
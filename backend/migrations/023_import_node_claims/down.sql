@@ -0,0 +1,9 @@
+-- 023_import_node_claims/down.sql
+
+ALTER TABLE node_claims
+    DROP COLUMN IF EXISTS proposed_changes;
+
+DROP INDEX IF EXISTS idx_node_claims_import_batch_id;
+
+ALTER TABLE node_claims
+    DROP COLUMN IF EXISTS import_batch_id;
@@ -0,0 +1,19 @@
+-- 023_import_node_claims/up.sql
+
+-- Node proposals from imports: link node claims to their batch, like
+-- edge_claims.import_batch_id (008).
+ALTER TABLE node_claims
+    ADD COLUMN IF NOT EXISTS import_batch_id UUID NULL
+        REFERENCES import_batches(id)
+        ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_node_claims_import_batch_id
+    ON node_claims (import_batch_id);
+
+-- What an import proposes for the node:
+--   { "op": "create_node" }
+--   { "op": "update_node", "metadata": {...}, "details": {...},
+--     "previous_claim_id": "..." }
+-- Update proposals are applied to the node when the claim is approved.
+ALTER TABLE node_claims
+    ADD COLUMN IF NOT EXISTS proposed_changes JSONB NULL;
@@ -0,0 +1,6 @@
+-- 024_import_batch_review/down.sql
+
+DROP INDEX IF EXISTS idx_edges_import_batch_id;
+
+ALTER TABLE edges
+    DROP COLUMN IF EXISTS import_batch_id;
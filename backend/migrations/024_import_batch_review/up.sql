@@ -0,0 +1,12 @@
+-- 024_import_batch_review/up.sql
+
+-- The import batch whose proposal created the edge. Rolling a batch back
+-- removes the edges it created once none of their claims come from
+-- elsewhere; edges that already existed are left alone.
+ALTER TABLE edges
+    ADD COLUMN IF NOT EXISTS import_batch_id UUID NULL
+        REFERENCES import_batches(id)
+        ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_edges_import_batch_id
+    ON edges (import_batch_id);
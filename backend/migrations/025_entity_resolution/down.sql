@@ -0,0 +1,13 @@
+-- 025_entity_resolution/down.sql
+
+DROP TABLE IF EXISTS node_duplicate_dismissals;
+DROP TABLE IF EXISTS node_aliases;
+
+DROP INDEX IF EXISTS idx_nodes_hostnames;
+DROP INDEX IF EXISTS idx_nodes_ips;
+DROP INDEX IF EXISTS idx_nodes_normalized_name_trgm;
+DROP INDEX IF EXISTS idx_nodes_normalized_name;
+
+DROP FUNCTION IF EXISTS node_hostnames(JSONB);
+DROP FUNCTION IF EXISTS node_ips(JSONB);
+DROP FUNCTION IF EXISTS normalize_node_name(TEXT);
@@ -0,0 +1,87 @@
+-- 025_entity_resolution/up.sql
+
+-- Entity resolution: the `ux_nodes_kind_name` index only stops exact
+-- duplicates, so "Postgres16", "postgres-16" and "PG16 prod" can coexist.
+-- Names are compared in a normalized form (lower case, no accents, common
+-- product abbreviations spelled out, only letters and digits), per node
+-- aliases count as names, and `ip`/`hostname` metadata is compared as sets.
+
+-- unaccent() is only STABLE; with the dictionary named explicitly the
+-- result does not depend on search_path, so the wrapper may be IMMUTABLE
+-- and indexed.
+CREATE OR REPLACE FUNCTION normalize_node_name(t TEXT) RETURNS TEXT AS $$
+    SELECT regexp_replace(
+        regexp_replace(
+            regexp_replace(
+                regexp_replace(
+                    lower(public.unaccent('public.unaccent'::regdictionary, COALESCE(t, ''))),
+                    '\m(postgresql|postgre|pgsql|psql|pg)(?=[0-9]|\M)', 'postgres', 'g'),
+                '\m(ms[ _-]?sql|sql[ _-]?server)\M', 'mssql', 'g'),
+            '\mk8s\M', 'kubernetes', 'g'),
+        '[^a-z0-9]+', '', 'g')
+$$ LANGUAGE sql IMMUTABLE PARALLEL SAFE;
+
+-- Addresses in `metadata.ip`: a string ("10.0.2.20, 10.0.2.21") or an
+-- array, without a trailing /32.
+CREATE OR REPLACE FUNCTION node_ips(m JSONB) RETURNS TEXT[] AS $$
+    SELECT COALESCE(array_agg(DISTINCT x) FILTER (WHERE x <> ''), '{}')
+    FROM (
+        SELECT regexp_replace(trim(s), '/32$', '') AS x
+        FROM jsonb_array_elements_text(
+                CASE jsonb_typeof(m -> 'ip')
+                    WHEN 'array' THEN m -> 'ip'
+                    WHEN 'string' THEN jsonb_build_array(m -> 'ip')
+                    ELSE '[]'::jsonb
+                END) AS e,
+             regexp_split_to_table(e, '[,; ]+') AS s
+    ) t
+$$ LANGUAGE sql IMMUTABLE PARALLEL SAFE;
+
+-- `metadata.hostname` and `metadata.fqdn`, lower case, each also without
+-- its domain.
+CREATE OR REPLACE FUNCTION node_hostnames(m JSONB) RETURNS TEXT[] AS $$
+    SELECT COALESCE(array_agg(DISTINCT x) FILTER (WHERE x <> ''), '{}')
+    FROM (
+        SELECT unnest(ARRAY[h, split_part(h, '.', 1)]) AS x
+        FROM (
+            SELECT lower(trim(m ->> k)) AS h
+            FROM unnest(ARRAY['hostname', 'fqdn']) AS k
+            WHERE jsonb_typeof(m -> k) = 'string'
+        ) v
+    ) t
+$$ LANGUAGE sql IMMUTABLE PARALLEL SAFE;
+
+CREATE INDEX IF NOT EXISTS idx_nodes_normalized_name
+    ON nodes (normalize_node_name(name));
+CREATE INDEX IF NOT EXISTS idx_nodes_normalized_name_trgm
+    ON nodes USING gin (normalize_node_name(name) gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_nodes_ips
+    ON nodes USING gin (node_ips(metadata));
+-- Host names as matched, the node's own name included.
+CREATE INDEX IF NOT EXISTS idx_nodes_hostnames
+    ON nodes USING gin ((node_hostnames(metadata) || lower(name)));
+
+CREATE TABLE IF NOT EXISTS node_aliases (
+    node_id UUID NOT NULL REFERENCES nodes(id) ON DELETE CASCADE,
+    alias TEXT NOT NULL CHECK (btrim(alias) <> ''),
+    created_by TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (node_id, alias)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS ux_node_aliases_normalized
+    ON node_aliases (node_id, normalize_node_name(alias));
+CREATE INDEX IF NOT EXISTS idx_node_aliases_normalized
+    ON node_aliases (normalize_node_name(alias));
+CREATE INDEX IF NOT EXISTS idx_node_aliases_normalized_trgm
+    ON node_aliases USING gin (normalize_node_name(alias) gin_trgm_ops);
+
+-- Pairs a reviewer has marked as not duplicates; `node_a < node_b`.
+CREATE TABLE IF NOT EXISTS node_duplicate_dismissals (
+    node_a UUID NOT NULL REFERENCES nodes(id) ON DELETE CASCADE,
+    node_b UUID NOT NULL REFERENCES nodes(id) ON DELETE CASCADE,
+    dismissed_by TEXT NOT NULL,
+    dismissed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (node_a, node_b),
+    CHECK (node_a < node_b)
+);
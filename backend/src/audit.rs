@@ -1,5 +1,5 @@
 use serde_json::Value;
-use sqlx::Postgres;
+use sqlx::PgConnection;
 use uuid::Uuid;
 
 use crate::auth::AuthActor;
@@ -13,6 +13,8 @@ pub struct RequestContext {
 pub enum EntityType {
     Node,
     Edge,
+    Webhook,
+    ImportBatch,
 }
 
 impl EntityType {
@@ -20,6 +22,8 @@ impl EntityType {
         match self {
             EntityType::Node => "node",
             EntityType::Edge => "edge",
+            EntityType::Webhook => "webhook",
+            EntityType::ImportBatch => "import_batch",
         }
     }
 }
@@ -41,8 +45,8 @@ impl AuditAction {
     }
 }
 
-pub async fn write_audit<'e, E>(
-    executor: E,
+pub async fn write_audit(
+    conn: &mut PgConnection,
     ctx: RequestContext,
     actor: Option<&AuthActor>,
     entity_type: EntityType,
@@ -51,10 +55,7 @@ pub async fn write_audit<'e, E>(
     before: Option<Value>,
     patch: Option<Value>,
     after: Option<Value>,
-) -> Result<(), sqlx::Error>
-where
-    E: sqlx::Executor<'e, Database = Postgres>,
-{
+) -> Result<(), sqlx::Error> {
     sqlx::query(
         r#"
         INSERT INTO audit_log (
@@ -76,15 +77,26 @@ where
     .bind(entity_type.as_str())
     .bind(entity_id)
     .bind(action.as_str())
-    .bind(before)
-    .bind(patch)
+    .bind(&before)
+    .bind(&patch)
     .bind(after)
     .bind(ctx.request_id)
     .bind(actor.map(|_| "user").unwrap_or("system"))
     .bind(None::<Uuid>)
     .bind(actor.map(|a| a.username.as_str()))
     .bind(actor.map(|a| a.role.as_str()))
-    .execute(executor)
+    .execute(&mut *conn)
+    .await?;
+
+    crate::webhooks::enqueue_for_audit(
+        conn,
+        ctx,
+        actor,
+        entity_type,
+        entity_id,
+        action,
+        before.as_ref(),
+        patch.as_ref(),
+    )
     .await
-    .map(|_| ())
 }
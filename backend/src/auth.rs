@@ -1,5 +1,5 @@
 use axum::extract::State;
-use axum::http::{header, HeaderMap, Method, StatusCode};
+use axum::http::{header, HeaderMap, Method};
 use axum::middleware::Next;
 use axum::response::{IntoResponse, Response};
 use serde::{Deserialize, Serialize};
@@ -7,6 +7,7 @@ use time::OffsetDateTime;
 
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
 
+use crate::error::ApiError;
 use crate::routes::AppState;
 
 #[derive(Debug, Clone)]
@@ -103,6 +104,14 @@ fn is_safe_method(m: &Method) -> bool {
     matches!(*m, Method::GET | Method::HEAD | Method::OPTIONS)
 }
 
+/// Reads that expose secrets, third-party URLs or per-user data, or that
+/// are expensive enough to be worth rationing, still require a token.
+pub(crate) fn is_private_read_path(path: &str) -> bool {
+    path.starts_with("/webhooks")
+        || path.starts_with("/saved-searches")
+        || path == "/nodes/duplicates"
+}
+
 pub async fn require_auth_for_writes(
     State(state): State<AppState>,
     req: axum::http::Request<axum::body::Body>,
@@ -111,17 +120,21 @@ pub async fn require_auth_for_writes(
     let method = req.method().clone();
     let path = req.uri().path().to_string();
 
-    if is_public_path(&path) || is_safe_method(&method) {
+    if is_public_path(&path) || (is_safe_method(&method) && !is_private_read_path(&path)) {
+        // Anonymous is fine here, but a valid token still identifies the caller.
+        let mut req = req;
+        let actor = extract_bearer(req.headers()).and_then(|t| verify_token(&state, &t).ok());
+        if let Some(actor) = actor {
+            req.extensions_mut().insert(actor);
+        }
         return next.run(req).await;
     }
 
     let token = match extract_bearer(req.headers()) {
         Some(t) => t,
         None => {
-            return (
-                StatusCode::UNAUTHORIZED,
-                "Missing Authorization: Bearer <token>",
-            )
+            return ApiError::unauthorized("Inloggning krävs")
+                .with_detail("Missing Authorization: Bearer <token>")
                 .into_response()
         }
     };
@@ -132,6 +145,8 @@ pub async fn require_auth_for_writes(
             req.extensions_mut().insert(actor);
             next.run(req).await
         }
-        Err(_) => (StatusCode::UNAUTHORIZED, "Invalid or expired token").into_response(),
+        Err(_) => ApiError::unauthorized("Ogiltig eller utgången inloggning")
+            .with_detail("Invalid or expired token")
+            .into_response(),
     }
 }
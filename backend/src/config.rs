@@ -9,6 +9,13 @@ pub struct Config {
     pub local_admin_password: String,
     pub auth_jwt_secret: String,
     pub auth_token_ttl_seconds: u64,
+    pub webhook_poll_interval_seconds: u64,
+    pub webhook_max_attempts: i32,
+    pub webhook_timeout_seconds: u64,
+    /// Hosts a webhook may target even though they resolve to a loopback,
+    /// private or link-local address.
+    pub webhook_allowed_hosts: Vec<String>,
+    pub idempotency_ttl_seconds: u64,
 }
 
 impl Config {
@@ -51,6 +58,37 @@ impl Config {
             .and_then(|v| v.parse::<u64>().ok())
             .unwrap_or(12 * 60 * 60);
 
+        let webhook_poll_interval_seconds: u64 = env::var("WEBHOOK_POLL_INTERVAL_SECONDS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(5)
+            .max(1);
+
+        let webhook_max_attempts: i32 = env::var("WEBHOOK_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|v| v.parse::<i32>().ok())
+            .unwrap_or(8)
+            .max(1);
+
+        let webhook_timeout_seconds: u64 = env::var("WEBHOOK_TIMEOUT_SECONDS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(10)
+            .max(1);
+
+        let webhook_allowed_hosts: Vec<String> = env::var("WEBHOOK_ALLOWED_HOSTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let idempotency_ttl_seconds: u64 = env::var("IDEMPOTENCY_TTL_SECONDS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(24 * 60 * 60)
+            .max(1);
+
         Ok(Self {
             database_url,
             bind_addr,
@@ -59,6 +97,11 @@ impl Config {
             local_admin_password,
             auth_jwt_secret,
             auth_token_ttl_seconds,
+            webhook_poll_interval_seconds,
+            webhook_max_attempts,
+            webhook_timeout_seconds,
+            webhook_allowed_hosts,
+            idempotency_ttl_seconds,
         })
     }
 }
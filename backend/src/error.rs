@@ -0,0 +1,336 @@
+use std::fmt::Display;
+
+use axum::body::Body;
+use axum::http::{header, HeaderValue, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Stable, machine-readable error codes. Clients branch on these; the
+/// Swedish text in `message_sv` may change freely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidRequest,
+    ValidationFailed,
+    InvalidIfMatch,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    MethodNotAllowed,
+    RequestTimeout,
+    Conflict,
+    EtagMismatch,
+    IdempotencyKeyReused,
+    IdempotencyKeyInProgress,
+    PayloadTooLarge,
+    UnsupportedMediaType,
+    UnprocessableEntity,
+    IfMatchRequired,
+    TooManyRequests,
+    InternalError,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidRequest => "invalid_request",
+            ErrorCode::ValidationFailed => "validation_failed",
+            ErrorCode::InvalidIfMatch => "invalid_if_match",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::Forbidden => "forbidden",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::MethodNotAllowed => "method_not_allowed",
+            ErrorCode::RequestTimeout => "request_timeout",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::EtagMismatch => "etag_mismatch",
+            ErrorCode::IdempotencyKeyReused => "idempotency_key_reused",
+            ErrorCode::IdempotencyKeyInProgress => "idempotency_key_in_progress",
+            ErrorCode::PayloadTooLarge => "payload_too_large",
+            ErrorCode::UnsupportedMediaType => "unsupported_media_type",
+            ErrorCode::UnprocessableEntity => "unprocessable_entity",
+            ErrorCode::IfMatchRequired => "if_match_required",
+            ErrorCode::TooManyRequests => "too_many_requests",
+            ErrorCode::InternalError => "internal_error",
+        }
+    }
+
+    /// Used for errors that did not come from a handler (extractor
+    /// rejections, router 404/405, timeouts).
+    fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED => ErrorCode::Unauthorized,
+            StatusCode::FORBIDDEN => ErrorCode::Forbidden,
+            StatusCode::NOT_FOUND => ErrorCode::NotFound,
+            StatusCode::METHOD_NOT_ALLOWED => ErrorCode::MethodNotAllowed,
+            StatusCode::REQUEST_TIMEOUT => ErrorCode::RequestTimeout,
+            StatusCode::CONFLICT => ErrorCode::Conflict,
+            StatusCode::PRECONDITION_FAILED => ErrorCode::EtagMismatch,
+            StatusCode::PAYLOAD_TOO_LARGE => ErrorCode::PayloadTooLarge,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => ErrorCode::UnsupportedMediaType,
+            StatusCode::UNPROCESSABLE_ENTITY => ErrorCode::UnprocessableEntity,
+            StatusCode::PRECONDITION_REQUIRED => ErrorCode::IfMatchRequired,
+            StatusCode::TOO_MANY_REQUESTS => ErrorCode::TooManyRequests,
+            s if s.is_server_error() => ErrorCode::InternalError,
+            _ => ErrorCode::InvalidRequest,
+        }
+    }
+
+    pub fn default_message_sv(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidRequest => "Ogiltig begäran",
+            ErrorCode::ValidationFailed => "Ogiltiga värden",
+            ErrorCode::InvalidIfMatch => "Ogiltig If-Match",
+            ErrorCode::Unauthorized => "Inloggning krävs",
+            ErrorCode::Forbidden => "Behörighet saknas",
+            ErrorCode::NotFound => "Resursen finns inte",
+            ErrorCode::MethodNotAllowed => "Metoden stöds inte",
+            ErrorCode::RequestTimeout => "Begäran tog för lång tid",
+            ErrorCode::Conflict => "Konflikt med befintliga data",
+            ErrorCode::EtagMismatch => "Objektet har uppdaterats av någon annan",
+            ErrorCode::IdempotencyKeyReused => {
+                "Idempotency-Key har redan använts för en annan begäran"
+            }
+            ErrorCode::IdempotencyKeyInProgress => {
+                "En begäran med samma Idempotency-Key pågår redan"
+            }
+            ErrorCode::PayloadTooLarge => "Begäran är för stor",
+            ErrorCode::UnsupportedMediaType => "Innehållstypen stöds inte",
+            ErrorCode::UnprocessableEntity => "Begäran kunde inte tolkas",
+            ErrorCode::IfMatchRequired => "If-Match saknas (optimistisk låsning)",
+            ErrorCode::TooManyRequests => "För många förfrågningar",
+            ErrorCode::InternalError => "Internt fel",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message_sv: String,
+}
+
+/// `application/problem+json` body (RFC 9457) plus our own members.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Problem {
+    /// `urn:infra-graph:problem:<code>`
+    #[serde(rename = "type")]
+    pub type_uri: String,
+    /// HTTP reason phrase.
+    pub title: String,
+    pub status: u16,
+    pub code: ErrorCode,
+    pub message_sv: String,
+    /// Untranslated technical detail, e.g. a JSON parse error. Never set on 5xx.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: ErrorCode,
+    pub message_sv: String,
+    pub detail: Option<String>,
+    pub errors: Vec<FieldError>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: ErrorCode, message_sv: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message_sv: message_sv.into(),
+            detail: None,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn bad_request(message_sv: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, ErrorCode::InvalidRequest, message_sv)
+    }
+
+    /// A 400 tied to one input field; add more with [`ApiError::with_field`].
+    pub fn validation(field: impl Into<String>, message_sv: impl Into<String>) -> Self {
+        let message_sv = message_sv.into();
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::ValidationFailed,
+            message_sv.clone(),
+        )
+        .with_field(field, message_sv)
+    }
+
+    pub fn unauthorized(message_sv: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, ErrorCode::Unauthorized, message_sv)
+    }
+
+    pub fn forbidden(message_sv: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, ErrorCode::Forbidden, message_sv)
+    }
+
+    pub fn not_found(message_sv: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, ErrorCode::NotFound, message_sv)
+    }
+
+    pub fn conflict(message_sv: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, ErrorCode::Conflict, message_sv)
+    }
+
+    /// Optimistic locking: the `If-Match` ETag no longer matches.
+    pub fn etag_mismatch() -> Self {
+        Self::new(
+            StatusCode::CONFLICT,
+            ErrorCode::EtagMismatch,
+            ErrorCode::EtagMismatch.default_message_sv(),
+        )
+    }
+
+    pub fn if_match_required() -> Self {
+        Self::new(
+            StatusCode::PRECONDITION_REQUIRED,
+            ErrorCode::IfMatchRequired,
+            ErrorCode::IfMatchRequired.default_message_sv(),
+        )
+    }
+
+    pub fn invalid_if_match() -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidIfMatch,
+            ErrorCode::InvalidIfMatch.default_message_sv(),
+        )
+    }
+
+    /// Logs `err` and returns a 500 that does not leak it to the client.
+    pub fn internal(err: impl Display) -> Self {
+        tracing::error!(error = %err, "internal error");
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::InternalError,
+            ErrorCode::InternalError.default_message_sv(),
+        )
+    }
+
+    /// Constraint violations are the client's fault; everything else is ours.
+    pub fn from_sqlx(err: sqlx::Error) -> Self {
+        let sqlx::Error::Database(db_err) = &err else {
+            return Self::internal(err);
+        };
+
+        let constraint = db_err.constraint().map(str::to_string);
+        let out = match db_err.code().as_deref() {
+            Some("23505") => Self::conflict("Objektet finns redan"),
+            Some("23503") => Self::bad_request("Refererat objekt finns inte"),
+            Some("23502") | Some("23514") | Some("22P02") => Self::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::ValidationFailed,
+                format!("Ogiltig begäran: {}", db_err.message()),
+            ),
+            _ => Self::bad_request(format!("Ogiltig begäran: {}", db_err.message())),
+        };
+
+        match constraint {
+            Some(c) => out.with_detail(format!("constraint: {c}")),
+            None => out,
+        }
+    }
+
+    pub fn with_field(mut self, field: impl Into<String>, message_sv: impl Into<String>) -> Self {
+        self.errors.push(FieldError {
+            field: field.into(),
+            message_sv: message_sv.into(),
+        });
+        self
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn to_problem(&self) -> Problem {
+        Problem {
+            type_uri: format!("urn:infra-graph:problem:{}", self.code.as_str()),
+            title: self
+                .status
+                .canonical_reason()
+                .unwrap_or("Error")
+                .to_string(),
+            status: self.status.as_u16(),
+            code: self.code,
+            message_sv: self.message_sv.clone(),
+            detail: if self.status.is_server_error() {
+                None
+            } else {
+                self.detail.clone()
+            },
+            errors: self.errors.clone(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::to_vec(&self.to_problem()).unwrap_or_default();
+        let mut res = (self.status, body).into_response();
+        res.headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        res
+    }
+}
+
+/// Turns error responses produced outside our handlers (axum rejections,
+/// router 404/405, timeouts, body limits) into problem+json as well.
+pub async fn problem_json_errors(req: Request<Body>, next: Next) -> Response {
+    let res = next.run(req).await;
+    let status = res.status();
+
+    if !(status.is_client_error() || status.is_server_error()) {
+        return res;
+    }
+    let is_problem = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with(PROBLEM_JSON));
+    if is_problem {
+        return res;
+    }
+
+    let (parts, body) = res.into_parts();
+    let text = axum::body::to_bytes(body, 16 * 1024)
+        .await
+        .map(|b| String::from_utf8_lossy(&b).trim().to_string())
+        .unwrap_or_default();
+
+    let code = ErrorCode::from_status(status);
+    let mut err = ApiError::new(status, code, code.default_message_sv());
+    if !text.is_empty() {
+        err = err.with_detail(text);
+    }
+
+    let mut out = err.into_response();
+    for (name, value) in parts.headers.iter() {
+        if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+            out.headers_mut().insert(name.clone(), value.clone());
+        }
+    }
+    out
+}
+
+/// `CatchPanicLayer` handler: a panicking handler is a 500, not a dropped
+/// connection.
+pub fn panic_response(err: Box<dyn std::any::Any + Send + 'static>) -> Response {
+    let msg = err
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| err.downcast_ref::<&str>().copied())
+        .unwrap_or("panic");
+    ApiError::internal(format!("handler panicked: {msg}")).into_response()
+}
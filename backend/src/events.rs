@@ -0,0 +1,236 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Postgres channel fed by the `audit_log` insert trigger (migration 017).
+pub const GRAPH_EVENTS_CHANNEL: &str = "graph_events";
+
+const BUS_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventEntity {
+    Node,
+    Edge,
+    Claim,
+}
+
+impl EventEntity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventEntity::Node => "node",
+            EventEntity::Edge => "edge",
+            EventEntity::Claim => "claim",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim() {
+            "node" | "nodes" => Some(EventEntity::Node),
+            "edge" | "edges" => Some(EventEntity::Edge),
+            "claim" | "claims" => Some(EventEntity::Claim),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventOp {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl EventOp {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventOp::Created => "created",
+            EventOp::Updated => "updated",
+            EventOp::Deleted => "deleted",
+        }
+    }
+}
+
+/// One change to the graph, derived from a single `audit_log` row.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct GraphEvent {
+    pub seq: i64,
+    #[schema(value_type = crate::openapi::Timestamp)]
+    pub at: OffsetDateTime,
+    pub entity: EventEntity,
+    pub op: EventOp,
+    pub node_id: Option<Uuid>,
+    pub edge_id: Option<Uuid>,
+    pub claim_id: Option<Uuid>,
+    /// `patch.action` of the audit row (e.g. `claim_approved`), if any.
+    pub action: Option<String>,
+    pub actor_username: Option<String>,
+    pub correlation_id: Option<Uuid>,
+}
+
+impl GraphEvent {
+    /// SSE event name, e.g. `node.created` or `claim.updated`.
+    pub fn name(&self) -> String {
+        format!("{}.{}", self.entity.as_str(), self.op.as_str())
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct AuditEventRow {
+    pub seq: i64,
+    pub at: OffsetDateTime,
+    pub entity_type: String,
+    pub entity_id: Uuid,
+    pub action: String,
+    pub patch_action: Option<String>,
+    pub patch_claim_id: Option<String>,
+    pub actor_username: Option<String>,
+    pub correlation_id: Option<Uuid>,
+}
+
+/// Column list shared by the live listener and the resume replay.
+pub const AUDIT_EVENT_COLUMNS: &str = r#"
+    seq,
+    at,
+    entity_type,
+    entity_id,
+    action,
+    patch->>'action' AS patch_action,
+    COALESCE(patch->>'claim_id', patch->>'active_id', patch->>'proposal_id') AS patch_claim_id,
+    actor_username,
+    correlation_id
+"#;
+
+impl AuditEventRow {
+    /// Claim changes are audited against their edge/node with `patch.action`
+    /// set; those become `claim.*` events, everything else maps 1:1.
+    pub fn into_event(self) -> Option<GraphEvent> {
+        let parent = match self.entity_type.as_str() {
+            "node" => EventEntity::Node,
+            "edge" => EventEntity::Edge,
+            _ => return None,
+        };
+
+        let claim_op = match self.patch_action.as_deref() {
+            Some("claim_created")
+            | Some("proposal_created")
+            | Some("edge_marked_needs_review")
+            | Some("node_claim_created")
+            | Some("node_marked_needs_review") => Some(EventOp::Created),
+            Some("claim_approved")
+            | Some("claim_rejected")
+            | Some("node_claim_approved")
+            | Some("node_claim_rejected") => Some(EventOp::Updated),
+            _ => None,
+        };
+
+        let (entity, op) = match claim_op {
+            Some(op) => (EventEntity::Claim, op),
+            None => {
+                let op = match self.action.as_str() {
+                    "create" => EventOp::Created,
+                    "patch" => EventOp::Updated,
+                    "delete" => EventOp::Deleted,
+                    _ => return None,
+                };
+                (parent, op)
+            }
+        };
+
+        let (node_id, edge_id) = match parent {
+            EventEntity::Node => (Some(self.entity_id), None),
+            _ => (None, Some(self.entity_id)),
+        };
+
+        Some(GraphEvent {
+            seq: self.seq,
+            at: self.at,
+            entity,
+            op,
+            node_id,
+            edge_id,
+            claim_id: self
+                .patch_claim_id
+                .as_deref()
+                .and_then(|s| Uuid::parse_str(s).ok()),
+            action: self.patch_action,
+            actor_username: self.actor_username,
+            correlation_id: self.correlation_id,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<GraphEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(BUS_CAPACITY);
+        Self { tx }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<GraphEvent> {
+        self.tx.subscribe()
+    }
+
+    fn publish(&self, ev: GraphEvent) {
+        // No subscribers is not an error.
+        let _ = self.tx.send(ev);
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn load_event(pool: &PgPool, seq: i64) -> Result<Option<GraphEvent>, sqlx::Error> {
+    let sql = format!("SELECT {AUDIT_EVENT_COLUMNS} FROM audit_log WHERE seq = $1");
+    let row = sqlx::query_as::<_, AuditEventRow>(&sql)
+        .bind(seq)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.and_then(AuditEventRow::into_event))
+}
+
+async fn listen_once(pool: &PgPool, bus: &EventBus) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(GRAPH_EVENTS_CHANNEL).await?;
+    tracing::info!("listening for graph events on '{}'", GRAPH_EVENTS_CHANNEL);
+
+    loop {
+        let n = listener.recv().await?;
+        let Ok(seq) = n.payload().parse::<i64>() else {
+            tracing::warn!("ignoring malformed graph event payload: {}", n.payload());
+            continue;
+        };
+
+        match load_event(pool, seq).await {
+            Ok(Some(ev)) => bus.publish(ev),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("failed to load graph event {}: {}", seq, e),
+        }
+    }
+}
+
+/// Forwards `graph_events` notifications into the in-process bus until shutdown.
+pub fn spawn_listener(pool: PgPool, bus: EventBus) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = listen_once(&pool, &bus).await {
+                tracing::warn!("graph event listener failed: {}; reconnecting", e);
+            }
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+    });
+}
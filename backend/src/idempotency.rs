@@ -0,0 +1,344 @@
+use std::collections::BTreeMap;
+
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::auth::AuthActor;
+use crate::error::{ApiError, ErrorCode};
+use crate::routes::AppState;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+const KEY_MAX_LEN: usize = 255;
+const REQUEST_BODY_MAX: usize = 10 * 1024 * 1024;
+
+/// Response headers worth replaying; everything else is regenerated.
+const STORED_HEADERS: [HeaderName; 3] = [header::CONTENT_TYPE, header::ETAG, header::LOCATION];
+
+#[derive(sqlx::FromRow)]
+struct StoredKey {
+    request_hash: String,
+    status_code: Option<i32>,
+    response_headers: serde_json::Value,
+    response_body: Option<Vec<u8>>,
+}
+
+/// Multipart bodies are hashed with the boundary taken out: clients pick a
+/// fresh random boundary per request, retries included.
+fn request_hash(method: &Method, path: &str, boundary: Option<&str>, body: &[u8]) -> String {
+    let mut h = Sha256::new();
+    h.update(method.as_str().as_bytes());
+    h.update(b" ");
+    h.update(path.as_bytes());
+    h.update(b"\n");
+    match boundary {
+        Some(boundary) => {
+            let delimiter = format!("--{boundary}");
+            let delimiter = delimiter.as_bytes();
+            let mut rest = body;
+            while let Some(i) = rest.windows(delimiter.len()).position(|w| w == delimiter) {
+                h.update(&rest[..i]);
+                h.update(b"--boundary");
+                rest = &rest[i + delimiter.len()..];
+            }
+            h.update(rest);
+        }
+        None => h.update(body),
+    }
+    hex::encode(h.finalize())
+}
+
+/// The `boundary` parameter of a `multipart/form-data` content type.
+fn multipart_boundary(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+    let (mime, params) = content_type.split_once(';')?;
+    if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params
+        .split(';')
+        .filter_map(|p| p.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value.trim().trim_matches('"').to_string())
+        .filter(|b| !b.is_empty())
+}
+
+fn replay(stored: StoredKey) -> Response {
+    let status = stored
+        .status_code
+        .and_then(|s| u16::try_from(s).ok())
+        .and_then(|s| StatusCode::from_u16(s).ok())
+        .unwrap_or(StatusCode::OK);
+
+    let mut res = (status, stored.response_body.unwrap_or_default()).into_response();
+    if let Some(headers) = stored.response_headers.as_object() {
+        for (name, value) in headers {
+            if let (Ok(name), Some(Ok(value))) = (
+                HeaderName::try_from(name.as_str()),
+                value.as_str().map(HeaderValue::from_str),
+            ) {
+                res.headers_mut().insert(name, value);
+            }
+        }
+    }
+    res.headers_mut()
+        .insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+    res
+}
+
+fn stored_headers(headers: &HeaderMap) -> serde_json::Value {
+    let map: BTreeMap<&str, &str> = STORED_HEADERS
+        .iter()
+        .filter_map(|name| {
+            let value = headers.get(name)?.to_str().ok()?;
+            Some((name.as_str(), value))
+        })
+        .collect();
+    serde_json::json!(map)
+}
+
+/// Claims `key` for this request. `Ok(None)` means we own it and should run
+/// the handler; `Ok(Some(_))` is an earlier request with the same key.
+async fn claim(
+    pool: &PgPool,
+    actor: &str,
+    key: &str,
+    method: &Method,
+    path: &str,
+    hash: &str,
+    ttl_seconds: u64,
+) -> Result<Option<StoredKey>, sqlx::Error> {
+    sqlx::query("DELETE FROM idempotency_keys WHERE expires_at < now()")
+        .execute(pool)
+        .await?;
+
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO idempotency_keys (actor, key, method, path, request_hash, expires_at)
+        VALUES ($1, $2, $3, $4, $5, now() + make_interval(secs => $6))
+        ON CONFLICT (actor, key) DO NOTHING
+        "#,
+    )
+    .bind(actor)
+    .bind(key)
+    .bind(method.as_str())
+    .bind(path)
+    .bind(hash)
+    .bind(ttl_seconds as f64)
+    .execute(pool)
+    .await?;
+
+    if inserted.rows_affected() == 1 {
+        return Ok(None);
+    }
+
+    sqlx::query_as::<_, StoredKey>(
+        r#"
+        SELECT request_hash, status_code, response_headers, response_body
+        FROM idempotency_keys
+        WHERE actor = $1 AND key = $2
+        "#,
+    )
+    .bind(actor)
+    .bind(key)
+    .fetch_optional(pool)
+    .await
+}
+
+async fn store(
+    pool: &PgPool,
+    actor: &str,
+    key: &str,
+    status: StatusCode,
+    headers: serde_json::Value,
+    body: &[u8],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE idempotency_keys
+        SET status_code = $3, response_headers = $4, response_body = $5
+        WHERE actor = $1 AND key = $2
+        "#,
+    )
+    .bind(actor)
+    .bind(key)
+    .bind(i32::from(status.as_u16()))
+    .bind(headers)
+    .bind(body)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+async fn release(pool: &PgPool, actor: &str, key: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM idempotency_keys WHERE actor = $1 AND key = $2")
+        .bind(actor)
+        .bind(key)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// A claimed key that is still in progress. Dropping it releases the key:
+/// the request future is dropped on a timeout or client disconnect, the
+/// handler's transaction rolls back with it, and a retry has to run again
+/// rather than get 409 until the key expires.
+struct Claim {
+    pool: PgPool,
+    actor: String,
+    key: String,
+    done: bool,
+}
+
+impl Claim {
+    async fn release(mut self) {
+        self.done = true;
+        if let Err(e) = release(&self.pool, &self.actor, &self.key).await {
+            tracing::warn!("idempotency key release failed: {}", e);
+        }
+    }
+
+    async fn store(mut self, status: StatusCode, headers: serde_json::Value, body: &[u8]) {
+        self.done = true;
+        if let Err(e) = store(&self.pool, &self.actor, &self.key, status, headers, body).await {
+            tracing::warn!("idempotency key store failed: {}", e);
+            let _ = release(&self.pool, &self.actor, &self.key).await;
+        }
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let (pool, actor, key) = (
+            self.pool.clone(),
+            std::mem::take(&mut self.actor),
+            std::mem::take(&mut self.key),
+        );
+        runtime.spawn(async move {
+            if let Err(e) = release(&pool, &actor, &key).await {
+                tracing::warn!("idempotency key release failed: {}", e);
+            }
+        });
+    }
+}
+
+/// `Idempotency-Key` on POST: the first response per actor and key is
+/// stored and replayed for retries within `idempotency_ttl_seconds`. Reusing
+/// a key with a different body is a 409. 5xx responses are not kept, so a
+/// retry after a server error runs again, as does one after a request that
+/// timed out or was abandoned. Multipart bodies compare without their
+/// boundary.
+///
+/// Runs after `auth::require_auth_for_writes`, which puts the actor on
+/// every POST.
+pub async fn idempotent_posts(
+    State(state): State<AppState>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    if req.method() != Method::POST {
+        return next.run(req).await;
+    }
+    let Some(raw_key) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return next.run(req).await;
+    };
+    let Some(actor) = req
+        .extensions()
+        .get::<AuthActor>()
+        .map(|a| a.username.clone())
+    else {
+        return next.run(req).await;
+    };
+
+    let key = match raw_key.to_str().map(str::trim) {
+        Ok(k) if !k.is_empty() && k.len() <= KEY_MAX_LEN => k.to_string(),
+        _ => {
+            return ApiError::validation(
+                "Idempotency-Key",
+                format!("Idempotency-Key måste vara 1–{KEY_MAX_LEN} tecken"),
+            )
+            .into_response()
+        }
+    };
+
+    let method = req.method().clone();
+    let path = req
+        .uri()
+        .path_and_query()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_default();
+
+    let boundary = multipart_boundary(req.headers());
+    let (parts, body) = req.into_parts();
+    let body: Bytes = match axum::body::to_bytes(body, REQUEST_BODY_MAX).await {
+        Ok(b) => b,
+        Err(_) => {
+            return ApiError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                ErrorCode::PayloadTooLarge,
+                ErrorCode::PayloadTooLarge.default_message_sv(),
+            )
+            .into_response()
+        }
+    };
+    let hash = request_hash(&method, &path, boundary.as_deref(), &body);
+
+    let pool = &state.pool;
+    let ttl = state.idempotency_ttl_seconds;
+    match claim(pool, &actor, &key, &method, &path, &hash, ttl).await {
+        Ok(None) => {}
+        Ok(Some(stored)) if stored.request_hash != hash => {
+            return conflict(ErrorCode::IdempotencyKeyReused).into_response()
+        }
+        Ok(Some(stored)) if stored.status_code.is_none() => {
+            return conflict(ErrorCode::IdempotencyKeyInProgress).into_response()
+        }
+        Ok(Some(stored)) => return replay(stored),
+        Err(e) => return ApiError::internal(e).into_response(),
+    }
+
+    let claim = Claim {
+        pool: pool.clone(),
+        actor,
+        key,
+        done: false,
+    };
+
+    let res = next.run(Request::from_parts(parts, Body::from(body))).await;
+    let status = res.status();
+
+    if status.is_server_error() {
+        claim.release().await;
+        return res;
+    }
+
+    let (res_parts, res_body) = res.into_parts();
+    let res_bytes = match axum::body::to_bytes(res_body, usize::MAX).await {
+        Ok(b) => b,
+        Err(e) => {
+            claim.release().await;
+            return ApiError::internal(e).into_response();
+        }
+    };
+
+    let headers = stored_headers(&res_parts.headers);
+    claim.store(status, headers, &res_bytes).await;
+
+    Response::from_parts(res_parts, Body::from(res_bytes))
+}
+
+fn conflict(code: ErrorCode) -> ApiError {
+    ApiError::new(StatusCode::CONFLICT, code, code.default_message_sv())
+}
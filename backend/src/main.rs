@@ -2,19 +2,26 @@ mod audit;
 mod auth;
 mod config;
 mod db;
+mod error;
+mod events;
 mod graph;
+mod idempotency;
 mod metrics;
 mod models;
+mod openapi;
 mod routes;
 mod validation;
+mod webhooks;
 
 use axum::body::Body;
 use axum::http::{HeaderName, HeaderValue, Request, StatusCode};
 use axum::response::Response;
 use axum::{middleware, routing::get, Router};
+use axum_prometheus::metrics_exporter_prometheus::PrometheusHandle;
 use axum_prometheus::PrometheusMetricLayer;
 use std::time::Duration;
 use tower::limit::ConcurrencyLimitLayer;
+use tower_http::catch_panic::CatchPanicLayer;
 use tower_http::cors::{AllowOrigin, CorsLayer};
 use tower_http::services::{ServeDir, ServeFile};
 use tower_http::timeout::TimeoutLayer;
@@ -43,6 +50,23 @@ async fn main() -> anyhow::Result<()> {
         db::seed_if_empty(&pool).await?;
     }
 
+    let event_bus = events::EventBus::new();
+    events::spawn_listener(pool.clone(), event_bus.clone());
+
+    let webhook_targets = webhooks::TargetPolicy {
+        allowed_hosts: cfg.webhook_allowed_hosts.clone(),
+    };
+
+    webhooks::spawn_worker(
+        pool.clone(),
+        webhooks::WorkerConfig {
+            poll_interval: Duration::from_secs(cfg.webhook_poll_interval_seconds),
+            max_attempts: cfg.webhook_max_attempts,
+            request_timeout: Duration::from_secs(cfg.webhook_timeout_seconds),
+            targets: webhook_targets.clone(),
+        },
+    );
+
     let app_state = routes::AppState {
         pool,
         auth: routes::AuthState {
@@ -51,6 +75,9 @@ async fn main() -> anyhow::Result<()> {
             jwt_secret: cfg.auth_jwt_secret.clone(),
             token_ttl_seconds: cfg.auth_token_ttl_seconds,
         },
+        events: event_bus,
+        idempotency_ttl_seconds: cfg.idempotency_ttl_seconds,
+        webhook_targets,
     };
 
     let (prometheus_layer, metric_handle) = PrometheusMetricLayer::pair();
@@ -73,70 +100,112 @@ async fn main() -> anyhow::Result<()> {
         .allow_headers([
             axum::http::header::CONTENT_TYPE,
             axum::http::header::IF_MATCH,
+            axum::http::header::IF_NONE_MATCH,
             axum::http::header::AUTHORIZATION,
+            HeaderName::from_static("last-event-id"),
+            HeaderName::from_static(idempotency::IDEMPOTENCY_KEY_HEADER),
         ])
         .expose_headers([
             axum::http::header::ETAG,
             HeaderName::from_static("x-request-id"),
+            HeaderName::from_static(idempotency::REPLAYED_HEADER),
         ]);
 
-    async fn attach_request_id(mut req: Request<Body>, next: middleware::Next) -> Response {
-        let request_id = Uuid::new_v4();
-
-        req.extensions_mut()
-            .insert(audit::RequestContext { request_id });
-
-        let mut res = next.run(req).await;
-        let _ = res.headers_mut().insert(
-            HeaderName::from_static("x-request-id"),
-            HeaderValue::from_str(&request_id.to_string())
-                .unwrap_or_else(|_| HeaderValue::from_static("invalid")),
-        );
-        res
-    }
-
-    async fn attach_api_security_headers(
-        req: Request<Body>,
-        next: middleware::Next,
-    ) -> Response {
-        let mut res = next.run(req).await;
-
-        res.headers_mut().insert(
-            HeaderName::from_static("x-content-type-options"),
-            HeaderValue::from_static("nosniff"),
-        );
-        res.headers_mut().insert(
-            HeaderName::from_static("x-frame-options"),
-            HeaderValue::from_static("DENY"),
-        );
-        res.headers_mut().insert(
-            HeaderName::from_static("referrer-policy"),
-            HeaderValue::from_static("no-referrer"),
-        );
-        res.headers_mut().insert(
-            HeaderName::from_static("content-security-policy"),
-            HeaderValue::from_static(
-                "default-src 'none'; frame-ancestors 'none'; base-uri 'none'",
-            ),
-        );
-
-        res
-    }
-
     let default_body_limit = axum::extract::DefaultBodyLimit::max(1 * 1024 * 1024);
-    let import_body_limit = axum::extract::DefaultBodyLimit::max(10 * 1024 * 1024);
 
     let timeout =
         TimeoutLayer::with_status_code(StatusCode::REQUEST_TIMEOUT, Duration::from_secs(20));
 
     let global_concurrency_limit = ConcurrencyLimitLayer::new(200);
+
+    let api = api_router(app_state, metric_handle);
+
+    // -------------------------
+    // Frontend
+    // -------------------------
+    let frontend = ServeDir::new("/app/frontend")
+        .fallback(ServeFile::new("/app/frontend/index.html"));
+
+    // -------------------------
+    // App
+    // -------------------------
+    let app = Router::new()
+        .nest("/api", api)
+        .fallback_service(frontend)
+        .layer(prometheus_layer)
+        .layer(middleware::from_fn(attach_request_id))
+        .layer(TraceLayer::new_for_http())
+        .layer(timeout)
+        .layer(default_body_limit)
+        .layer(global_concurrency_limit)
+        .layer(cors);
+
+    tracing::info!("listening on {}", cfg.bind_addr);
+
+    let listener = tokio::net::TcpListener::bind(&cfg.bind_addr).await?;
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+async fn attach_request_id(mut req: Request<Body>, next: middleware::Next) -> Response {
+    let request_id = Uuid::new_v4();
+
+    req.extensions_mut()
+        .insert(audit::RequestContext { request_id });
+
+    let mut res = next.run(req).await;
+    let _ = res.headers_mut().insert(
+        HeaderName::from_static("x-request-id"),
+        HeaderValue::from_str(&request_id.to_string())
+            .unwrap_or_else(|_| HeaderValue::from_static("invalid")),
+    );
+    res
+}
+
+async fn attach_api_security_headers(
+    req: Request<Body>,
+    next: middleware::Next,
+) -> Response {
+    let mut res = next.run(req).await;
+
+    res.headers_mut().insert(
+        HeaderName::from_static("x-content-type-options"),
+        HeaderValue::from_static("nosniff"),
+    );
+    res.headers_mut().insert(
+        HeaderName::from_static("x-frame-options"),
+        HeaderValue::from_static("DENY"),
+    );
+    res.headers_mut().insert(
+        HeaderName::from_static("referrer-policy"),
+        HeaderValue::from_static("no-referrer"),
+    );
+    res.headers_mut().insert(
+        HeaderName::from_static("content-security-policy"),
+        HeaderValue::from_static(
+            "default-src 'none'; frame-ancestors 'none'; base-uri 'none'",
+        ),
+    );
+
+    res
+}
+
+/// Everything under `/api`; shared by `main` and the OpenAPI drift test.
+fn api_router(app_state: routes::AppState, metric_handle: PrometheusHandle) -> Router {
+    let import_body_limit = axum::extract::DefaultBodyLimit::max(10 * 1024 * 1024);
     let import_concurrency_limit = ConcurrencyLimitLayer::new(10);
+    let collection_get = middleware::from_fn_with_state(
+        app_state.clone(),
+        routes::conditional::collection_get,
+    );
 
     // -------------------------
     // Public API (NO AUTH)
     // -------------------------
     let public_api = Router::new()
         .route("/health", get(routes::health::health))
+        .route("/openapi.json", get(openapi::openapi_json))
         .nest("/auth", routes::auth::router());
 
     // -------------------------
@@ -145,13 +214,15 @@ async fn main() -> anyhow::Result<()> {
     let protected_api = Router::new()
         .nest("/nodes", routes::nodes::router())
         .nest("/edges", routes::edges::router())
-        .nest("/graph", routes::graph::router())
+        .nest("/batch", routes::batch::router())
+        .nest("/graph", routes::graph::router().layer(collection_get.clone()))
         .nest("/schema", routes::schema::router())
         .nest("/query", routes::query::router())
         .nest("/search", routes::search::router())
+        .nest("/saved-searches", routes::saved_searches::router())
         .nest("/data-domains", routes::data_domains::router())
         .nest("/audit", routes::audit::router())
-        .nest("/export", routes::export::router())
+        .nest("/export", routes::export::router().layer(collection_get))
         .nest(
             "/imports",
             routes::imports::router()
@@ -159,11 +230,17 @@ async fn main() -> anyhow::Result<()> {
                 .layer(import_concurrency_limit),
         )
         .nest("/claims", routes::claims::router())
+        .nest("/events", routes::events::router())
         .nest("/node-claims", routes::node_claims::router())
+        .nest("/webhooks", routes::webhooks::router())
         .route(
             "/metrics",
             get(move || async move { metric_handle.render() }),
         )
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            idempotency::idempotent_posts,
+        ))
         .layer(middleware::from_fn_with_state(
             app_state.clone(),
             auth::require_auth_for_writes,
@@ -172,36 +249,12 @@ async fn main() -> anyhow::Result<()> {
     // -------------------------
     // API root
     // -------------------------
-    let api = Router::new()
+    Router::new()
         .merge(public_api)
         .merge(protected_api)
-        .with_state(app_state.clone())
-        .layer(middleware::from_fn(attach_api_security_headers));
-
-    // -------------------------
-    // Frontend
-    // -------------------------
-    let frontend = ServeDir::new("/app/frontend")
-        .fallback(ServeFile::new("/app/frontend/index.html"));
-
-    // -------------------------
-    // App
-    // -------------------------
-    let app = Router::new()
-        .nest("/api", api)
-        .fallback_service(frontend)
-        .layer(prometheus_layer)
-        .layer(middleware::from_fn(attach_request_id))
-        .layer(TraceLayer::new_for_http())
-        .layer(timeout)
-        .layer(default_body_limit)
-        .layer(global_concurrency_limit)
-        .layer(cors);
-
-    tracing::info!("listening on {}", cfg.bind_addr);
-
-    let listener = tokio::net::TcpListener::bind(&cfg.bind_addr).await?;
-    axum::serve(listener, app).await?;
-
-    Ok(())
+        .with_state(app_state)
+        .layer(CatchPanicLayer::custom(error::panic_response))
+        .layer(middleware::from_fn(routes::conditional::conditional_get))
+        .layer(middleware::from_fn(error::problem_json_errors))
+        .layer(middleware::from_fn(attach_api_security_headers))
 }
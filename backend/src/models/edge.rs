@@ -1,9 +1,10 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use time::OffsetDateTime;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum EdgeKind {
     DependsOn,
@@ -33,18 +34,22 @@ impl EdgeKind {
     }
 }
 
-#[derive(Debug, Clone, Serialize, FromRow)]
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
 pub struct Edge {
     pub id: Uuid,
     pub from_id: Uuid,
     pub to_id: Uuid,
     pub kind: String,
     pub metadata: serde_json::Value,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub created_at: OffsetDateTime,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub updated_at: OffsetDateTime,
+    /// Row version; the ETag is `"v<version>"`.
+    pub version: i64,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct NewEdge {
     pub from_id: Uuid,
     pub to_id: Uuid,
@@ -53,7 +58,7 @@ pub struct NewEdge {
     pub metadata: serde_json::Value,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct UpdateEdge {
     pub kind: Option<EdgeKind>,
     pub metadata: Option<serde_json::Value>,
@@ -1,9 +1,10 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use time::OffsetDateTime;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, FromRow)]
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
 pub struct EdgeClaim {
     pub id: Uuid,
     pub edge_id: Uuid,
@@ -15,15 +16,20 @@ pub struct EdgeClaim {
     pub confidence: i16,
     pub status: String,
     pub created_by: String,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub created_at: OffsetDateTime,
 
     #[sqlx(default)]
+    #[schema(value_type = Option<crate::openapi::Timestamp>)]
     pub updated_at: Option<OffsetDateTime>,
 
+    #[schema(value_type = Option<crate::openapi::Timestamp>)]
     pub last_verified_at: Option<OffsetDateTime>,
+
+    pub version: i64,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct NewEdgeClaim {
     pub source: String,
     pub confidence: i16,
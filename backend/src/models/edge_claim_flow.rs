@@ -1,9 +1,10 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use time::OffsetDateTime;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct EdgeClaimFlow {
     pub id: Uuid,
     pub claim_id: Uuid,
@@ -12,5 +13,6 @@ pub struct EdgeClaimFlow {
     pub data_category_id: Option<Uuid>,
     pub protocol: Option<String>,
     pub frequency: Option<String>,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub created_at: OffsetDateTime,
 }
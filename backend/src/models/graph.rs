@@ -3,19 +3,21 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use time::OffsetDateTime;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, FromRow)]
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
 pub struct EdgeClaimEvidence {
     pub id: Uuid,
     pub claim_id: Uuid,
     pub evidence_type: String,
     pub reference: String,
     pub note: Option<String>,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub created_at: OffsetDateTime,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct NewEdgeClaimEvidence {
     pub evidence_type: String,
     pub reference: String,
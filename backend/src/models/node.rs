@@ -1,9 +1,10 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use time::OffsetDateTime;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum NodeKind {
     System,
@@ -35,19 +36,24 @@ impl NodeKind {
     }
 }
 
-#[derive(Debug, Clone, Serialize, FromRow)]
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
 pub struct Node {
     pub id: Uuid,
     pub kind: String,
     pub name: String,
     pub metadata: serde_json::Value,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub created_at: OffsetDateTime,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub updated_at: OffsetDateTime,
+    #[schema(value_type = Option<crate::openapi::Timestamp>)]
     pub deleted_at: Option<OffsetDateTime>,
     pub deleted_by: Option<String>,
+    /// Row version; the ETag is `"v<version>"`.
+    pub version: i64,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct NewNode {
     pub kind: NodeKind,
     pub name: String,
@@ -55,7 +61,7 @@ pub struct NewNode {
     pub metadata: serde_json::Value,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct UpdateNode {
     pub kind: Option<NodeKind>,
     pub name: Option<String>,
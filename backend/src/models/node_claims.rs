@@ -1,9 +1,10 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use time::OffsetDateTime;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, FromRow)]
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
 pub struct NodeClaim {
     pub id: Uuid,
     pub node_id: Uuid,
@@ -14,13 +15,23 @@ pub struct NodeClaim {
     pub status: String,
     pub created_by: String,
 
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub created_at: OffsetDateTime,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub updated_at: OffsetDateTime,
 
+    #[schema(value_type = Option<crate::openapi::Timestamp>)]
     pub last_verified_at: Option<OffsetDateTime>,
+
+    pub version: i64,
+
+    /// Set for proposals made by an import.
+    pub import_batch_id: Option<Uuid>,
+    /// What an import proposal would change; see `NodeProposalChange`.
+    pub proposed_changes: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct NewNodeClaim {
     pub source: String,
     pub confidence: i16,
@@ -0,0 +1,433 @@
+use axum::Json;
+use utoipa::openapi::path::{
+    HttpMethod, OperationBuilder, ParameterBuilder, ParameterIn, PathItem,
+};
+use utoipa::openapi::response::ResponseBuilder;
+use utoipa::openapi::schema::{ArrayBuilder, ObjectBuilder, Schema, Type};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityRequirement, SecurityScheme};
+use utoipa::openapi::{ContentBuilder, RefOr};
+use utoipa::{Modify, OpenApi, PartialSchema, ToResponse, ToSchema};
+
+use crate::error::Problem;
+use crate::routes;
+
+/// `time::OffsetDateTime` as the API serializes it:
+/// `[year, ordinal_day, hour, minute, second, nanosecond, offset_h, offset_m, offset_s]`.
+pub struct Timestamp;
+
+impl PartialSchema for Timestamp {
+    fn schema() -> RefOr<Schema> {
+        ArrayBuilder::new()
+            .items(ObjectBuilder::new().schema_type(Type::Integer))
+            .min_items(Some(9))
+            .max_items(Some(9))
+            .description(Some(
+                "[year, ordinal_day, hour, minute, second, nanosecond, offset_h, offset_m, offset_s]",
+            ))
+            .into()
+    }
+}
+
+impl ToSchema for Timestamp {}
+
+/// Every error is an `application/problem+json` body, see `crate::error`.
+#[derive(ToResponse)]
+#[response(description = "Fel (RFC 9457)", content_type = "application/problem+json")]
+#[allow(dead_code)]
+pub struct ErrorResponse(#[to_schema] Problem);
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "infra_graph",
+        description = "Infrastrukturgraf: noder, kopplingar, claims, import och export."
+    ),
+    paths(routes::health::health, openapi_json),
+    components(responses(ErrorResponse)),
+    nest(
+        (path = "/api/auth", api = routes::auth::AuthApi, tags = ["auth"]),
+        (path = "/api/nodes", api = routes::nodes::NodesApi, tags = ["nodes"]),
+        (path = "/api/nodes", api = routes::nodes::details::DetailsApi, tags = ["nodes"]),
+        (path = "/api/edges", api = routes::edges::EdgesApi, tags = ["edges"]),
+        (path = "/api/batch", api = routes::batch::BatchApi, tags = ["batch"]),
+        (path = "/api/graph", api = routes::graph::GraphApi, tags = ["graph"]),
+        (path = "/api/schema", api = routes::schema::SchemaApi, tags = ["schema"]),
+        (path = "/api/query", api = routes::query::QueryApi, tags = ["query"]),
+        (path = "/api/search", api = routes::search::SearchApi, tags = ["search"]),
+        (path = "/api/saved-searches", api = routes::saved_searches::SavedSearchesApi, tags = ["search"]),
+        (path = "/api/data-domains", api = routes::data_domains::DataDomainsApi, tags = ["data-domains"]),
+        (path = "/api/audit", api = routes::audit::AuditApi, tags = ["audit"]),
+        (path = "/api/export", api = routes::export::ExportApi, tags = ["export"]),
+        (path = "/api/imports", api = routes::imports::ImportsApi, tags = ["imports"]),
+        (path = "/api/claims", api = routes::claims::ClaimsApi, tags = ["claims"]),
+        (path = "/api/events", api = routes::events::EventsApi, tags = ["events"]),
+        (path = "/api/node-claims", api = routes::node_claims::NodeClaimsApi, tags = ["node-claims"]),
+        (path = "/api/webhooks", api = routes::webhooks::WebhooksApi, tags = ["webhooks"]),
+    ),
+    modifiers(
+        &NestedRoots,
+        &MetricsPath,
+        &BearerAuth,
+        &IdempotencyKey,
+        &ConditionalGet
+    )
+)]
+pub struct ApiDoc;
+
+/// utoipa joins `nest` prefixes by concatenation, so a router's `/` route
+/// comes out as `/api/nodes/`; axum serves it as `/api/nodes`.
+struct NestedRoots;
+
+impl Modify for NestedRoots {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let paths = std::mem::take(&mut openapi.paths.paths);
+        openapi.paths.paths = paths
+            .into_iter()
+            .map(|(path, item)| match path.strip_suffix('/') {
+                Some(trimmed) if !trimmed.is_empty() => (trimmed.to_string(), item),
+                _ => (path, item),
+            })
+            .collect();
+    }
+}
+
+/// `/api/metrics` is a closure over the Prometheus handle in `main`.
+struct MetricsPath;
+
+impl Modify for MetricsPath {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let op = OperationBuilder::new()
+            .tag("metrics")
+            .summary(Some("Prometheus-metrics"))
+            .response(
+                "200",
+                ResponseBuilder::new()
+                    .description("Prometheus text format")
+                    .content("text/plain", ContentBuilder::new().build())
+                    .build(),
+            )
+            .build();
+        openapi
+            .paths
+            .paths
+            .insert("/api/metrics".to_string(), PathItem::new(HttpMethod::Get, op));
+    }
+}
+
+/// Mirrors `auth::require_auth_for_writes`: every non-GET operation (and
+/// the reads `auth::is_private_read_path` names) requires a bearer token.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+
+        let requirement = SecurityRequirement::new("bearer_auth", Vec::<String>::new());
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            if path.starts_with("/api/auth") {
+                continue;
+            }
+            let private_reads = path
+                .strip_prefix("/api")
+                .is_some_and(crate::auth::is_private_read_path);
+            let ops = [
+                (&mut item.get, private_reads),
+                (&mut item.head, private_reads),
+                (&mut item.post, true),
+                (&mut item.put, true),
+                (&mut item.patch, true),
+                (&mut item.delete, true),
+            ];
+            for (op, secured) in ops {
+                if let (Some(op), true) = (op.as_mut(), secured) {
+                    op.security = Some(vec![requirement.clone()]);
+                }
+            }
+        }
+    }
+}
+
+/// Mirrors `idempotency::idempotent_posts`: every authenticated POST takes
+/// an optional `Idempotency-Key`.
+struct IdempotencyKey;
+
+impl Modify for IdempotencyKey {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let param = ParameterBuilder::new()
+            .name("Idempotency-Key")
+            .parameter_in(ParameterIn::Header)
+            .required(utoipa::openapi::Required::False)
+            .description(Some(
+                "Samma nyckel inom giltighetstiden ger samma svar (header Idempotent-Replayed: true); annan body ger 409.",
+            ))
+            .schema(Some(ObjectBuilder::new().schema_type(Type::String).max_length(Some(255))))
+            .build();
+
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            if path.starts_with("/api/auth") {
+                continue;
+            }
+            if let Some(op) = item.post.as_mut() {
+                op.parameters
+                    .get_or_insert_with(Vec::new)
+                    .push(param.clone());
+            }
+        }
+    }
+}
+
+/// Mirrors `routes::conditional`: GETs that send an `ETag` (single entities,
+/// `/api/graph`, `/api/export`) honour `If-None-Match` with a 304.
+struct ConditionalGet;
+
+impl Modify for ConditionalGet {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let param = ParameterBuilder::new()
+            .name("If-None-Match")
+            .parameter_in(ParameterIn::Header)
+            .required(utoipa::openapi::Required::False)
+            .description(Some("ETag från ett tidigare svar; oförändrad resurs ger 304 utan body."))
+            .schema(Some(ObjectBuilder::new().schema_type(Type::String)))
+            .build();
+        let not_modified = ResponseBuilder::new()
+            .description("Oförändrad sedan If-None-Match")
+            .build();
+
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            let Some(op) = item.get.as_mut() else {
+                continue;
+            };
+            let collection = path.starts_with("/api/graph") || path.starts_with("/api/export");
+            let entity = matches!(
+                op.responses.responses.get("200"),
+                Some(RefOr::T(res)) if res.headers.contains_key("ETag")
+            );
+            if !(collection || entity) {
+                continue;
+            }
+            op.parameters
+                .get_or_insert_with(Vec::new)
+                .push(param.clone());
+            op.responses
+                .responses
+                .insert("304".to_string(), RefOr::T(not_modified.clone()));
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/openapi.json",
+    tag = "meta",
+    responses((status = 200, description = "Detta dokument", content_type = "application/json"))
+)]
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::time::Duration;
+
+    use axum::body::Body;
+    use axum::http::{header, Method, Request, StatusCode};
+    use axum::{middleware, Router};
+    use axum_prometheus::metrics_exporter_prometheus::PrometheusBuilder;
+    use sqlx::postgres::PgPoolOptions;
+    use tower::Service;
+
+    use super::*;
+    use crate::auth::{self, AuthActor, Role};
+    use crate::events::EventBus;
+    use crate::routes::{AppState, AuthState};
+
+    const METHODS: [Method; 5] = [
+        Method::GET,
+        Method::POST,
+        Method::PUT,
+        Method::PATCH,
+        Method::DELETE,
+    ];
+
+    /// The real `/api` router over a pool that never connects: handlers that
+    /// reach the database fail with 500, which is fine here. Only the router's
+    /// own 404/405 matter.
+    fn test_app() -> (Router, String) {
+        let pool = PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgres://drift@127.0.0.1:1/drift")
+            .expect("lazy pool");
+
+        let state = AppState {
+            pool,
+            auth: AuthState {
+                local_admin_username: "admin".into(),
+                local_admin_password: "admin".into(),
+                jwt_secret: "openapi-drift-test-secret-0123456789".into(),
+                token_ttl_seconds: 300,
+            },
+            events: EventBus::new(),
+            idempotency_ttl_seconds: 60,
+            webhook_targets: Default::default(),
+        };
+
+        let token = auth::issue_token(
+            &state,
+            &AuthActor {
+                username: "admin".into(),
+                role: Role::Admin,
+            },
+        )
+        .expect("token");
+
+        let handle = PrometheusBuilder::new().build_recorder().handle();
+        let app = Router::new()
+            .nest("/api", crate::api_router(state, handle))
+            .layer(middleware::from_fn(crate::attach_request_id));
+
+        (app, token)
+    }
+
+    /// A handler that panics has been routed, so that counts as a 500.
+    async fn status(app: &Router, token: &str, method: Method, path: &str) -> StatusCode {
+        let req = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::empty())
+            .unwrap();
+        let mut app = app.clone();
+        match tokio::spawn(async move { app.call(req).await }).await {
+            Ok(res) => res.unwrap().status(),
+            Err(e) if e.is_panic() => StatusCode::INTERNAL_SERVER_ERROR,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// `/api/nodes/{id}/claims` -> `/api/nodes/00000000-.../claims`
+    fn concrete(path: &str) -> String {
+        path.split('/')
+            .map(|seg| {
+                if seg.starts_with('{') && seg.ends_with('}') {
+                    uuid::Uuid::nil().to_string()
+                } else {
+                    seg.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    fn documented(item: &PathItem) -> Vec<Method> {
+        let ops = [
+            (Method::GET, &item.get),
+            (Method::POST, &item.post),
+            (Method::PUT, &item.put),
+            (Method::PATCH, &item.patch),
+            (Method::DELETE, &item.delete),
+        ];
+        ops.into_iter()
+            .filter(|(_, op)| op.is_some())
+            .map(|(m, _)| m)
+            .collect()
+    }
+
+    /// First string literal argument of every `<call>(` in `src`.
+    fn literal_args<'a>(src: &'a str, call: &str) -> Vec<(&'a str, &'a str)> {
+        let mut out = Vec::new();
+        for (i, _) in src.match_indices(call) {
+            let rest = src[i + call.len()..].trim_start();
+            let Some(rest) = rest.strip_prefix('"') else {
+                continue;
+            };
+            if let Some(end) = rest.find('"') {
+                out.push((&rest[..end], &rest[end + 1..]));
+            }
+        }
+        out
+    }
+
+    /// Every `.route("...")` registered in the source, with its `/api` prefix.
+    fn source_routes() -> BTreeSet<String> {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let main = std::fs::read_to_string(root.join("main.rs")).unwrap();
+
+        let mut routers = vec![("/api".to_string(), main.clone())];
+        for (prefix, rest) in literal_args(&main, ".nest(") {
+            let Some(module) = rest
+                .trim_start_matches(|c: char| c == ',' || c.is_whitespace())
+                .strip_prefix("routes::")
+                .and_then(|r| r.split("::").next())
+            else {
+                continue;
+            };
+            let dir = root.join("routes");
+            let src = std::fs::read_to_string(dir.join(format!("{module}.rs")))
+                .or_else(|_| std::fs::read_to_string(dir.join(module).join("mod.rs")))
+                .unwrap_or_else(|_| panic!("router source for routes::{module}"));
+            routers.push((format!("/api{prefix}"), src));
+        }
+
+        let mut out = BTreeSet::new();
+        for (prefix, src) in &routers {
+            for (route, _) in literal_args(src, ".route(") {
+                let path = route
+                    .split('/')
+                    .map(|seg| match seg.strip_prefix(':') {
+                        Some(name) => format!("{{{name}}}"),
+                        None => seg.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let full = format!("{prefix}{path}");
+                out.insert(full.trim_end_matches('/').to_string());
+            }
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn spec_matches_router() {
+        let spec = ApiDoc::openapi();
+        let (app, token) = test_app();
+
+        for (path, item) in &spec.paths.paths {
+            let uri = concrete(path);
+            let ops = documented(item);
+            assert!(!ops.is_empty(), "{path}: no operations");
+
+            for method in METHODS {
+                let got = status(&app, &token, method.clone(), &uri).await;
+                if ops.contains(&method) {
+                    assert!(
+                        got != StatusCode::NOT_FOUND && got != StatusCode::METHOD_NOT_ALLOWED,
+                        "{method} {path} is documented but the router answers {got}"
+                    );
+                } else {
+                    assert_eq!(
+                        got,
+                        StatusCode::METHOD_NOT_ALLOWED,
+                        "{method} {path} is routed but not documented"
+                    );
+                }
+            }
+        }
+
+        let missing: Vec<_> = source_routes()
+            .into_iter()
+            .filter(|p| !spec.paths.paths.contains_key(p))
+            .collect();
+        assert!(missing.is_empty(), "routes missing from the spec: {missing:?}");
+    }
+}
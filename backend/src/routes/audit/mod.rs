@@ -1,26 +1,29 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
     routing::get,
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
+use utoipa::{IntoParams, OpenApi, ToSchema};
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::routes::edges::helpers::internal_error;
 use crate::routes::AppState;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct AuditQuery {
     pub limit: Option<u32>,
     pub before: Option<String>,
 }
 
-#[derive(Serialize, sqlx::FromRow)]
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
 pub struct AuditLogEntry {
     pub id: Uuid,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub at: OffsetDateTime,
     pub actor_type: String,
     pub actor_id: Option<Uuid>,
@@ -35,25 +38,47 @@ pub struct AuditLogEntry {
     pub correlation_id: Option<Uuid>,
 }
 
+#[derive(OpenApi)]
+#[openapi(paths(get_node_audit, get_edge_audit))]
+pub struct AuditApi;
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/node/:id", get(get_node_audit))
         .route("/edge/:id", get(get_edge_audit))
 }
 
+#[utoipa::path(
+    get,
+    path = "/node/{id}",
+    params(("id" = Uuid, Path), AuditQuery),
+    responses(
+        (status = 200, body = Vec<AuditLogEntry>),
+        (status = 400, response = crate::openapi::ErrorResponse)
+    )
+)]
 async fn get_node_audit(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
     Query(q): Query<AuditQuery>,
-) -> Result<Json<Vec<AuditLogEntry>>, (StatusCode, String)> {
+) -> Result<Json<Vec<AuditLogEntry>>, ApiError> {
     get_audit_for(&state, "node", id, q).await
 }
 
+#[utoipa::path(
+    get,
+    path = "/edge/{id}",
+    params(("id" = Uuid, Path), AuditQuery),
+    responses(
+        (status = 200, body = Vec<AuditLogEntry>),
+        (status = 400, response = crate::openapi::ErrorResponse)
+    )
+)]
 async fn get_edge_audit(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
     Query(q): Query<AuditQuery>,
-) -> Result<Json<Vec<AuditLogEntry>>, (StatusCode, String)> {
+) -> Result<Json<Vec<AuditLogEntry>>, ApiError> {
     get_audit_for(&state, "edge", id, q).await
 }
 
@@ -62,7 +87,7 @@ async fn get_audit_for(
     entity_type: &str,
     entity_id: Uuid,
     q: AuditQuery,
-) -> Result<Json<Vec<AuditLogEntry>>, (StatusCode, String)> {
+) -> Result<Json<Vec<AuditLogEntry>>, ApiError> {
     let mut limit = q.limit.unwrap_or(200) as i64;
     if limit < 1 {
         limit = 1;
@@ -80,7 +105,7 @@ async fn get_audit_for(
                     None
                 } else {
                     Some(OffsetDateTime::parse(s, &Rfc3339).map_err(|_| {
-                        (StatusCode::BAD_REQUEST, "Ogiltig 'before' (RFC3339)".into())
+                        ApiError::bad_request("Ogiltig 'before' (RFC3339)")
                     })?)
                 }
             }
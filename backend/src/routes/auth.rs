@@ -6,23 +6,25 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 use subtle::ConstantTimeEq;
+use utoipa::{OpenApi, ToSchema};
 
+use crate::error::ApiError;
 use crate::auth::{issue_token, verify_token, AuthActor, Role};
 use crate::routes::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct LoginRequest {
     pub username: String,
     pub password: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct LoginResponse {
     pub token: String,
     pub user: UserInfo,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct UserInfo {
     pub username: String,
     pub role: String,
@@ -32,18 +34,24 @@ fn ct_eq(a: &str, b: &str) -> bool {
     a.as_bytes().ct_eq(b.as_bytes()).into()
 }
 
+#[utoipa::path(
+    post,
+    path = "/login",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, body = LoginResponse),
+        (status = 401, response = crate::openapi::ErrorResponse)
+    )
+)]
 async fn login(
     State(state): State<AppState>,
     Json(req): Json<LoginRequest>,
-) -> Result<Json<LoginResponse>, (StatusCode, String)> {
+) -> Result<Json<LoginResponse>, ApiError> {
     let ok_user = ct_eq(req.username.trim(), &state.auth.local_admin_username);
     let ok_pass = ct_eq(&req.password, &state.auth.local_admin_password);
 
     if !(ok_user && ok_pass) {
-        return Err((
-            StatusCode::UNAUTHORIZED,
-            "Fel användarnamn eller lösenord".into(),
-        ));
+        return Err(ApiError::unauthorized("Fel användarnamn eller lösenord"));
     }
 
     let actor = AuthActor {
@@ -51,12 +59,7 @@ async fn login(
         role: Role::Admin,
     };
 
-    let token = issue_token(&state, &actor).map_err(|_| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Kunde inte skapa token".into(),
-        )
-    })?;
+    let token = issue_token(&state, &actor).map_err(ApiError::internal)?;
 
     Ok(Json(LoginResponse {
         token,
@@ -67,10 +70,19 @@ async fn login(
     }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/me",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, body = UserInfo),
+        (status = 401, response = crate::openapi::ErrorResponse)
+    )
+)]
 async fn me(
     State(state): State<AppState>,
     headers: axum::http::HeaderMap,
-) -> Result<Json<UserInfo>, (StatusCode, String)> {
+) -> Result<Json<UserInfo>, ApiError> {
     let auth = headers
         .get(axum::http::header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok())
@@ -80,10 +92,10 @@ async fn me(
     let token = auth
         .strip_prefix("Bearer ")
         .or_else(|| auth.strip_prefix("bearer "))
-        .ok_or((StatusCode::UNAUTHORIZED, "Missing Authorization".into()))?;
+        .ok_or(ApiError::unauthorized("Missing Authorization"))?;
 
     let actor = verify_token(&state, token)
-        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token".into()))?;
+        .map_err(|_| ApiError::unauthorized("Invalid token"))?;
 
     Ok(Json(UserInfo {
         username: actor.username,
@@ -91,10 +103,15 @@ async fn me(
     }))
 }
 
+#[utoipa::path(post, path = "/logout", responses((status = 204)))]
 async fn logout() -> StatusCode {
     StatusCode::NO_CONTENT
 }
 
+#[derive(OpenApi)]
+#[openapi(paths(login, me, logout))]
+pub struct AuthApi;
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/login", post(login))
@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+
+use axum::{extract::State, routing::post, Extension, Json, Router};
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+use uuid::Uuid;
+
+use crate::audit::RequestContext;
+use crate::auth::AuthActor;
+use crate::error::ApiError;
+use crate::models::{Edge, EdgeKind, NewEdge, NewNode, Node, NodeKind, UpdateEdge, UpdateNode};
+use crate::routes::edges::helpers::internal_error;
+use crate::routes::edges::{
+    create::insert_edge, delete::delete_edge_row, update::apply_edge_update,
+};
+use crate::routes::nodes::crud::{apply_node_update, insert_node, soft_delete_node};
+use crate::routes::{etag_from_version, AppState};
+use crate::validation::ValidationResult;
+
+const MAX_OPERATIONS: usize = 500;
+
+#[derive(OpenApi)]
+#[openapi(paths(run_batch))]
+pub struct BatchApi;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/", post(run_batch))
+}
+
+/// An existing entity id, or the `temp_id` of an entity created earlier in
+/// the same batch.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum EntityRef {
+    Id(Uuid),
+    Temp(String),
+}
+
+/// `if_match` may be omitted for entities created or changed earlier in the
+/// same batch; the batch then uses the ETag it produced itself.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOperation {
+    CreateNode {
+        temp_id: Option<String>,
+        kind: NodeKind,
+        name: String,
+        #[serde(default)]
+        metadata: serde_json::Value,
+    },
+    UpdateNode {
+        id: EntityRef,
+        if_match: Option<String>,
+        kind: Option<NodeKind>,
+        name: Option<String>,
+        metadata: Option<serde_json::Value>,
+    },
+    DeleteNode {
+        id: EntityRef,
+        if_match: Option<String>,
+    },
+    CreateEdge {
+        temp_id: Option<String>,
+        from_id: EntityRef,
+        to_id: EntityRef,
+        kind: EdgeKind,
+        #[serde(default)]
+        metadata: serde_json::Value,
+    },
+    UpdateEdge {
+        id: EntityRef,
+        if_match: Option<String>,
+        kind: Option<EdgeKind>,
+        metadata: Option<serde_json::Value>,
+    },
+    DeleteEdge {
+        id: EntityRef,
+        if_match: Option<String>,
+    },
+}
+
+impl BatchOperation {
+    fn name(&self) -> &'static str {
+        match self {
+            BatchOperation::CreateNode { .. } => "create_node",
+            BatchOperation::UpdateNode { .. } => "update_node",
+            BatchOperation::DeleteNode { .. } => "delete_node",
+            BatchOperation::CreateEdge { .. } => "create_edge",
+            BatchOperation::UpdateEdge { .. } => "update_edge",
+            BatchOperation::DeleteEdge { .. } => "delete_edge",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchRequest {
+    /// Run every operation and roll back instead of committing.
+    #[serde(default)]
+    pub dry_run: bool,
+    pub operations: Vec<BatchOperation>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchResult {
+    pub index: usize,
+    pub op: String,
+    pub id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temp_id: Option<String>,
+    /// `None` once the entity is gone (deleted edge).
+    pub etag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node: Option<Node>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edge: Option<Edge>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation: Option<ValidationResult>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchResponse {
+    pub dry_run: bool,
+    /// `audit_log.correlation_id` of every row written by this batch.
+    pub correlation_id: Uuid,
+    pub results: Vec<BatchResult>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Node,
+    Edge,
+}
+
+/// What the batch knows about entities it has already touched.
+#[derive(Default)]
+struct BatchState {
+    temp_ids: HashMap<String, (Target, Uuid)>,
+    etags: HashMap<Uuid, String>,
+}
+
+impl BatchState {
+    fn resolve(&self, r: &EntityRef, target: Target, field: &str) -> Result<Uuid, ApiError> {
+        match r {
+            EntityRef::Id(id) => Ok(*id),
+            EntityRef::Temp(temp) => match self.temp_ids.get(temp) {
+                Some((t, id)) if *t == target => Ok(*id),
+                Some(_) => Err(ApiError::validation(
+                    field,
+                    format!("temp_id '{temp}' avser fel typ av objekt"),
+                )),
+                None => Err(ApiError::validation(
+                    field,
+                    format!("Okänt temp_id '{temp}' (måste skapas tidigare i samma batch)"),
+                )),
+            },
+        }
+    }
+
+    fn if_match(&self, id: Uuid, given: Option<String>) -> Result<String, ApiError> {
+        given
+            .or_else(|| self.etags.get(&id).cloned())
+            .ok_or_else(|| ApiError::if_match_required().with_field("if_match", "If-Match saknas"))
+    }
+
+    fn register_temp(
+        &mut self,
+        temp_id: Option<&String>,
+        target: Target,
+        id: Uuid,
+    ) -> Result<(), ApiError> {
+        let Some(temp) = temp_id else {
+            return Ok(());
+        };
+        if self.temp_ids.insert(temp.clone(), (target, id)).is_some() {
+            return Err(ApiError::validation(
+                "temp_id",
+                format!("temp_id '{temp}' används mer än en gång"),
+            ));
+        }
+        Ok(())
+    }
+
+    fn record_etag(&mut self, id: Uuid, version: i64) -> String {
+        let etag = etag_from_version(version)
+            .to_str()
+            .unwrap_or("")
+            .to_string();
+        self.etags.insert(id, etag.clone());
+        etag
+    }
+}
+
+/// Points every error at the operation that caused it:
+/// `name` -> `operations[3].name`, or `operations[3]` if none was set.
+fn at_operation(index: usize, mut err: ApiError) -> ApiError {
+    let prefix = format!("operations[{index}]");
+    if err.errors.is_empty() {
+        let message_sv = err.message_sv.clone();
+        err = err.with_field(prefix, message_sv);
+    } else {
+        for f in err.errors.iter_mut() {
+            f.field = format!("{prefix}.{}", f.field);
+        }
+    }
+    err
+}
+
+#[utoipa::path(
+    post,
+    path = "/",
+    request_body = BatchRequest,
+    responses(
+        (status = 200, body = BatchResponse),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse),
+        (status = 428, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn run_batch(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    Json(req): Json<BatchRequest>,
+) -> Result<Json<BatchResponse>, ApiError> {
+    if req.operations.is_empty() {
+        return Err(ApiError::validation(
+            "operations",
+            "Inga operationer angivna",
+        ));
+    }
+    if req.operations.len() > MAX_OPERATIONS {
+        return Err(ApiError::validation(
+            "operations",
+            format!("Högst {MAX_OPERATIONS} operationer per batch"),
+        ));
+    }
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let mut batch = BatchState::default();
+    let mut results = Vec::with_capacity(req.operations.len());
+
+    for (index, op) in req.operations.into_iter().enumerate() {
+        let op_name = op.name();
+        let result = async {
+            let mut out = BatchResult {
+                index,
+                op: op_name.to_string(),
+                id: Uuid::nil(),
+                temp_id: None,
+                etag: None,
+                node: None,
+                edge: None,
+                validation: None,
+            };
+
+            match op {
+                BatchOperation::CreateNode {
+                    temp_id,
+                    kind,
+                    name,
+                    metadata,
+                } => {
+                    let node = insert_node(
+                        &mut tx,
+                        ctx,
+                        &actor,
+                        NewNode {
+                            kind,
+                            name,
+                            metadata,
+                        },
+                    )
+                    .await?;
+                    batch.register_temp(temp_id.as_ref(), Target::Node, node.id)?;
+                    out.id = node.id;
+                    out.temp_id = temp_id;
+                    out.etag = Some(batch.record_etag(node.id, node.version));
+                    out.node = Some(node);
+                }
+                BatchOperation::UpdateNode {
+                    id,
+                    if_match,
+                    kind,
+                    name,
+                    metadata,
+                } => {
+                    let id = batch.resolve(&id, Target::Node, "id")?;
+                    let if_match = batch.if_match(id, if_match)?;
+                    let changes = UpdateNode {
+                        kind,
+                        name,
+                        metadata,
+                    };
+                    let node =
+                        apply_node_update(&mut tx, ctx, &actor, id, &if_match, changes).await?;
+                    out.id = id;
+                    out.etag = Some(batch.record_etag(id, node.version));
+                    out.node = Some(node);
+                }
+                BatchOperation::DeleteNode { id, if_match } => {
+                    let id = batch.resolve(&id, Target::Node, "id")?;
+                    let if_match = batch.if_match(id, if_match)?;
+                    let node = soft_delete_node(&mut tx, ctx, &actor, id, &if_match).await?;
+                    out.id = id;
+                    out.etag = Some(batch.record_etag(id, node.version));
+                    out.node = Some(node);
+                }
+                BatchOperation::CreateEdge {
+                    temp_id,
+                    from_id,
+                    to_id,
+                    kind,
+                    metadata,
+                } => {
+                    let from_id = batch.resolve(&from_id, Target::Node, "from_id")?;
+                    let to_id = batch.resolve(&to_id, Target::Node, "to_id")?;
+                    let new_edge = NewEdge {
+                        from_id,
+                        to_id,
+                        kind,
+                        metadata,
+                    };
+                    let edge = insert_edge(&mut tx, ctx, &actor, new_edge).await?;
+                    batch.register_temp(temp_id.as_ref(), Target::Edge, edge.id)?;
+                    out.id = edge.id;
+                    out.temp_id = temp_id;
+                    out.etag = Some(batch.record_etag(edge.id, edge.version));
+                    out.edge = Some(edge);
+                }
+                BatchOperation::UpdateEdge {
+                    id,
+                    if_match,
+                    kind,
+                    metadata,
+                } => {
+                    let id = batch.resolve(&id, Target::Edge, "id")?;
+                    let if_match = batch.if_match(id, if_match)?;
+                    let changes = UpdateEdge { kind, metadata };
+                    let written =
+                        apply_edge_update(&mut tx, ctx, &actor, id, &if_match, changes).await?;
+                    out.id = id;
+                    out.etag = Some(batch.record_etag(id, written.edge.version));
+                    out.validation = written.validation;
+                    out.edge = Some(written.edge);
+                }
+                BatchOperation::DeleteEdge { id, if_match } => {
+                    let id = batch.resolve(&id, Target::Edge, "id")?;
+                    let if_match = batch.if_match(id, if_match)?;
+                    delete_edge_row(&mut tx, ctx, &actor, id, &if_match).await?;
+                    batch.etags.remove(&id);
+                    out.id = id;
+                }
+            }
+
+            Ok::<_, ApiError>(out)
+        }
+        .await
+        .map_err(|e| at_operation(index, e))?;
+
+        results.push(result);
+    }
+
+    if req.dry_run {
+        tx.rollback().await.map_err(internal_error)?;
+    } else {
+        tx.commit().await.map_err(internal_error)?;
+    }
+
+    Ok(Json(BatchResponse {
+        dry_run: req.dry_run,
+        correlation_id: ctx.request_id,
+        results,
+    }))
+}
@@ -1,51 +1,76 @@
 use axum::{
     extract::{Path, State},
-    http::{HeaderMap, StatusCode},
+    http::HeaderMap,
     routing::post,
     Extension, Json, Router,
 };
 use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+use utoipa::{OpenApi, ToSchema};
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::{
     audit::{self, AuditAction, EntityType, RequestContext},
     auth::AuthActor,
     models::edge_claim::EdgeClaim,
-    routes::{etag_from_updated_at, is_match, require_if_match, AppState},
+    routes::{check_if_match, require_if_match, AppState},
 };
 
 use crate::routes::edges::helpers::{internal_error, map_sqlx_error};
 
+#[derive(OpenApi)]
+#[openapi(paths(approve_claim, reject_claim))]
+pub struct ClaimsApi;
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/:id/approve", post(approve_claim))
         .route("/:id/reject", post(reject_claim))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
+#[schema(as = EdgeClaimRejectBody)]
 pub struct RejectBody {
     #[serde(default)]
     pub reason: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
+#[schema(as = EdgeClaimApproveResponse)]
 pub struct ApproveResponse {
     pub active_claim: EdgeClaim,
     pub retired_proposal_id: Uuid,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
+#[schema(as = EdgeClaimRejectResponse)]
 pub struct RejectResponse {
     pub retired_claim: EdgeClaim,
 }
 
+#[utoipa::path(
+    post,
+    path = "/{id}/approve",
+    params(
+        ("id" = Uuid, Path, description = "Förslagets claim-id"),
+        ("If-Match" = String, Header, description = "ETag för claimet")
+    ),
+    responses(
+        (status = 200, body = ApproveResponse),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse),
+        (status = 428, response = crate::openapi::ErrorResponse)
+    )
+)]
 async fn approve_claim(
     State(state): State<AppState>,
     Extension(ctx): Extension<RequestContext>,
     Extension(actor): Extension<AuthActor>,
     headers: HeaderMap,
     Path(claim_id): Path<Uuid>,
-) -> Result<Json<ApproveResponse>, (StatusCode, String)> {
+) -> Result<Json<ApproveResponse>, ApiError> {
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
 
     let proposal: EdgeClaim = sqlx::query_as(
@@ -60,7 +85,8 @@ async fn approve_claim(
             created_by,
             created_at,
             updated_at,
-            last_verified_at
+            last_verified_at,
+            version
         FROM edge_claims
         WHERE id = $1
         "#,
@@ -71,28 +97,108 @@ async fn approve_claim(
     .map_err(map_sqlx_error)?;
 
     if proposal.status != "needs_review" && proposal.status != "rejected" {
-        return Err((
-            StatusCode::CONFLICT,
-            "Endast claims med status needs_review kan godkännas".into(),
-        ));
+        return Err(ApiError::conflict("Endast claims med status needs_review kan godkännas"));
     }
-    let proposal_updated_at = proposal.updated_at.ok_or((
-        StatusCode::INTERNAL_SERVER_ERROR,
-        "Claim saknar updated_at (migrations saknas?)".into(),
-    ))?;
-
     let if_match = require_if_match(&headers)?;
-    let expected = etag_from_updated_at(proposal_updated_at);
-    if !is_match(&expected, &if_match) {
-        return Err((StatusCode::BAD_REQUEST, "Ogiltig If-Match".into()));
+    check_if_match(
+        proposal.version,
+        proposal.updated_at.unwrap_or(proposal.created_at),
+        &if_match,
+    )?;
+
+    let (retired, active) = approve_edge_proposal(&mut tx, ctx, &actor, &proposal).await?;
+
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(Json(ApproveResponse {
+        active_claim: active,
+        retired_proposal_id: retired.id,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/{id}/reject",
+    params(
+        ("id" = Uuid, Path, description = "Förslagets claim-id"),
+        ("If-Match" = String, Header, description = "ETag för claimet")
+    ),
+    request_body = RejectBody,
+    responses(
+        (status = 200, body = RejectResponse),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse),
+        (status = 428, response = crate::openapi::ErrorResponse)
+    )
+)]
+async fn reject_claim(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    headers: HeaderMap,
+    Path(claim_id): Path<Uuid>,
+    Json(body): Json<RejectBody>,
+) -> Result<Json<RejectResponse>, ApiError> {
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+
+    let proposal: EdgeClaim = sqlx::query_as(
+        r#"
+        SELECT
+            id,
+            edge_id,
+            import_batch_id,
+            source,
+            confidence,
+            status,
+            created_by,
+            created_at,
+            updated_at,
+            last_verified_at,
+            version
+        FROM edge_claims
+        WHERE id = $1
+        "#,
+    )
+    .bind(claim_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    if proposal.status != "needs_review" && proposal.status != "rejected" {
+        return Err(ApiError::conflict("Endast claims med status needs_review eller rejected kan avslås"));
     }
+    let if_match = require_if_match(&headers)?;
+    check_if_match(
+        proposal.version,
+        proposal.updated_at.unwrap_or(proposal.created_at),
+        &if_match,
+    )?;
+
+    let retired = reject_edge_proposal(&mut tx, ctx, &actor, &proposal, body.reason).await?;
+
+    tx.commit().await.map_err(internal_error)?;
 
+    Ok(Json(RejectResponse {
+        retired_claim: retired,
+    }))
+}
+
+/// Retires `proposal` and puts an active claim with its source, confidence,
+/// evidence, flows and import batch in its place; the batch lets a rollback
+/// find it. The caller checks status and If-Match.
+pub(crate) async fn approve_edge_proposal(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    proposal: &EdgeClaim,
+) -> Result<(EdgeClaim, EdgeClaim), ApiError> {
     let retired: EdgeClaim = sqlx::query_as(
         r#"
         UPDATE edge_claims
         SET status = 'deprecated',
             updated_at = now()
-        WHERE id = $1
+        WHERE id = $1 AND version = $2
         RETURNING
             id,
             edge_id,
@@ -103,13 +209,16 @@ async fn approve_claim(
             created_by,
             created_at,
             updated_at,
-            last_verified_at
+            last_verified_at,
+            version
         "#,
     )
     .bind(proposal.id)
-    .fetch_one(&mut *tx)
+    .bind(proposal.version)
+    .fetch_optional(&mut **tx)
     .await
-    .map_err(map_sqlx_error)?;
+    .map_err(map_sqlx_error)?
+    .ok_or_else(ApiError::etag_mismatch)?;
 
     let active: EdgeClaim = sqlx::query_as(
         r#"
@@ -122,7 +231,7 @@ async fn approve_claim(
             created_by,
             last_verified_at
         )
-        VALUES ($1, NULL, $2, $3, 'active', $4, now())
+        VALUES ($1, $5, $2, $3, 'active', $4, now())
         RETURNING
             id,
             edge_id,
@@ -133,14 +242,16 @@ async fn approve_claim(
             created_by,
             created_at,
             updated_at,
-            last_verified_at
+            last_verified_at,
+            version
         "#,
     )
     .bind(proposal.edge_id)
     .bind(proposal.source.clone())
     .bind(proposal.confidence)
     .bind(actor.username.clone())
-    .fetch_one(&mut *tx)
+    .bind(proposal.import_batch_id)
+    .fetch_one(&mut **tx)
     .await
     .map_err(map_sqlx_error)?;
 
@@ -154,33 +265,36 @@ async fn approve_claim(
     )
     .bind(active.id)
     .bind(proposal.id)
-    .execute(&mut *tx)
+    .execute(&mut **tx)
     .await
     .map_err(internal_error)?;
 
     sqlx::query(
         r#"
-        INSERT INTO edge_claim_flows (claim_id, flow_type, data_category_id, protocol, frequency)
-        SELECT $1, flow_type, data_category_id, protocol, frequency
+        INSERT INTO edge_claim_flows
+            (claim_id, flow_type, direction, data_category_id, protocol, frequency)
+        SELECT $1, flow_type, direction, data_category_id, protocol, frequency
         FROM edge_claim_flows
         WHERE claim_id = $2
         "#,
     )
     .bind(active.id)
     .bind(proposal.id)
-    .execute(&mut *tx)
+    .execute(&mut **tx)
     .await
     .map_err(internal_error)?;
+
     audit::write_audit(
-        &mut *tx,
-        ctx.clone(),
-        Some(&actor),
+        tx,
+        ctx,
+        Some(actor),
         EntityType::Edge,
         proposal.edge_id,
         AuditAction::Patch,
         None,
         Some(serde_json::json!({
             "action": "claim_approved",
+            "import_batch_id": proposal.import_batch_id,
             "proposal_id": proposal.id,
             "active_id": active.id,
         })),
@@ -189,73 +303,27 @@ async fn approve_claim(
     .await
     .map_err(internal_error)?;
 
-    tx.commit().await.map_err(internal_error)?;
-
-    Ok(Json(ApproveResponse {
-        active_claim: active,
-        retired_proposal_id: retired.id,
-    }))
+    Ok((retired, active))
 }
 
-async fn reject_claim(
-    State(state): State<AppState>,
-    Extension(ctx): Extension<RequestContext>,
-    Extension(actor): Extension<AuthActor>,
-    headers: HeaderMap,
-    Path(claim_id): Path<Uuid>,
-    Json(body): Json<RejectBody>,
-) -> Result<Json<RejectResponse>, (StatusCode, String)> {
-    let mut tx = state.pool.begin().await.map_err(internal_error)?;
-
-    let proposal: EdgeClaim = sqlx::query_as(
-        r#"
-        SELECT
-            id,
-            edge_id,
-            import_batch_id,
-            source,
-            confidence,
-            status,
-            created_by,
-            created_at,
-            updated_at,
-            last_verified_at
-        FROM edge_claims
-        WHERE id = $1
-        "#,
-    )
-    .bind(claim_id)
-    .fetch_one(&mut *tx)
-    .await
-    .map_err(map_sqlx_error)?;
-
-    if proposal.status != "needs_review" && proposal.status != "rejected" {
-        return Err((
-            StatusCode::CONFLICT,
-            "Endast claims med status needs_review eller rejected kan avslås".into(),
-        ));
-    }
-    let proposal_updated_at = proposal.updated_at.ok_or((
-        StatusCode::INTERNAL_SERVER_ERROR,
-        "Claim saknar updated_at (migrations saknas?)".into(),
-    ))?;
-
-    let if_match = require_if_match(&headers)?;
-    let expected = etag_from_updated_at(proposal_updated_at);
-    if !is_match(&expected, &if_match) {
-        return Err((StatusCode::BAD_REQUEST, "Ogiltig If-Match".into()));
-    }
-
+/// Marks `proposal` rejected; a non-empty `reason` replaces its source.
+pub(crate) async fn reject_edge_proposal(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    proposal: &EdgeClaim,
+    reason: Option<String>,
+) -> Result<EdgeClaim, ApiError> {
     let retired: EdgeClaim = sqlx::query_as(
         r#"
         UPDATE edge_claims
         SET status = 'rejected',
             source = CASE
-              WHEN $2 IS NULL OR btrim($2) = '' THEN source
-              ELSE $2
+              WHEN $2::text IS NULL OR btrim($2::text) = '' THEN source
+              ELSE $2::text
             END,
             updated_at = now()
-        WHERE id = $1
+        WHERE id = $1 AND version = $3
         RETURNING
             id,
             edge_id,
@@ -266,35 +334,36 @@ async fn reject_claim(
             created_by,
             created_at,
             updated_at,
-            last_verified_at
+            last_verified_at,
+            version
         "#,
     )
     .bind(proposal.id)
-    .fetch_one(&mut *tx)
+    .bind(reason.clone())
+    .bind(proposal.version)
+    .fetch_optional(&mut **tx)
     .await
-    .map_err(map_sqlx_error)?;
+    .map_err(map_sqlx_error)?
+    .ok_or_else(ApiError::etag_mismatch)?;
 
     audit::write_audit(
-        &mut *tx,
-        ctx.clone(),
-        Some(&actor),
+        tx,
+        ctx,
+        Some(actor),
         EntityType::Edge,
         proposal.edge_id,
         AuditAction::Patch,
         None,
         Some(serde_json::json!({
             "action": "claim_rejected",
+            "import_batch_id": proposal.import_batch_id,
             "proposal_id": proposal.id,
-            "reason": body.reason,
+            "reason": reason,
         })),
         None,
     )
     .await
     .map_err(internal_error)?;
 
-    tx.commit().await.map_err(internal_error)?;
-
-    Ok(Json(RejectResponse {
-        retired_claim: retired,
-    }))
+    Ok(retired)
 }
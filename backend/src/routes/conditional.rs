@@ -0,0 +1,121 @@
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::error::ApiError;
+use crate::routes::{is_match, AppState};
+
+/// Tables whose changes show up in `/api/graph` and `/api/export`, with the
+/// column that moves on write. `audit_log.seq` is added on top and catches
+/// writes that do not touch a timestamp (claim status changes, hard deletes).
+const COLLECTION_TABLES: &[(&str, &str)] = &[
+    ("nodes", "updated_at"),
+    ("edges", "updated_at"),
+    ("edge_claims", "updated_at"),
+    ("edge_claim_flows", "created_at"),
+    ("edge_claim_evidence", "created_at"),
+    ("node_risk", "updated_at"),
+    ("node_software", "updated_at"),
+    ("node_owners", "created_at"),
+    ("node_suppliers", "created_at"),
+    ("node_supplier_types", "created_at"),
+    ("owners", "updated_at"),
+    ("suppliers", "updated_at"),
+    ("data_domains", "updated_at"),
+    ("edge_typed_flows", "updated_at"),
+    ("edge_typed_flow_domains", "created_at"),
+    // `/api/export?saved=<id>` filters by a stored FilterSpec.
+    ("saved_searches", "updated_at"),
+];
+
+/// `true` if the request's `If-None-Match` names `etag` (weak comparison).
+pub fn if_none_match(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| is_match(etag, v))
+}
+
+fn not_modified(etag: HeaderValue) -> Response {
+    let mut res = StatusCode::NOT_MODIFIED.into_response();
+    res.headers_mut().insert(header::ETAG, etag);
+    res
+}
+
+/// Weak ETag for the whole graph: max timestamp and row count per table plus
+/// the latest audit sequence number. Two index/heap scans per table, far
+/// cheaper than building the response.
+pub async fn collection_etag(pool: &PgPool) -> Result<HeaderValue, sqlx::Error> {
+    let parts: Vec<String> = COLLECTION_TABLES
+        .iter()
+        .map(|(table, col)| {
+            format!("(SELECT count(*)::text || '@' || coalesce(max({col})::text, '') FROM {table})")
+        })
+        .collect();
+    let sql = format!(
+        "SELECT concat_ws('|', (SELECT coalesce(max(seq), 0)::text FROM audit_log), {})",
+        parts.join(", ")
+    );
+
+    let (fingerprint,): (String,) = sqlx::query_as(&sql).fetch_one(pool).await?;
+    let digest = hex::encode(Sha256::digest(fingerprint.as_bytes()));
+
+    Ok(HeaderValue::from_str(&format!("W/\"g-{}\"", &digest[..32]))
+        .unwrap_or_else(|_| HeaderValue::from_static("W/\"invalid\"")))
+}
+
+/// Any GET whose 2xx response carries an ETag named by `If-None-Match`
+/// becomes a bodyless 304. Covers the single-entity GETs, which already
+/// set `ETag` from the row version.
+pub async fn conditional_get(req: Request<Body>, next: Next) -> Response {
+    if !matches!(*req.method(), Method::GET | Method::HEAD)
+        || !req.headers().contains_key(header::IF_NONE_MATCH)
+    {
+        return next.run(req).await;
+    }
+
+    let req_headers = req.headers().clone();
+    let res = next.run(req).await;
+    if !res.status().is_success() {
+        return res;
+    }
+
+    match res.headers().get(header::ETAG) {
+        Some(etag) if if_none_match(&req_headers, etag) => not_modified(etag.clone()),
+        _ => res,
+    }
+}
+
+/// For routers derived from the whole graph (`/api/graph`, `/api/export`):
+/// answers 304 before the handler runs and stamps the collection ETag on
+/// 2xx responses. `no-cache` lets browsers keep the body and revalidate
+/// with `If-None-Match` on their own.
+pub async fn collection_get(
+    State(state): State<AppState>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    if !matches!(*req.method(), Method::GET | Method::HEAD) {
+        return next.run(req).await;
+    }
+
+    let etag = match collection_etag(&state.pool).await {
+        Ok(etag) => etag,
+        Err(e) => return ApiError::internal(e).into_response(),
+    };
+    if if_none_match(req.headers(), &etag) {
+        return not_modified(etag);
+    }
+
+    let mut res = next.run(req).await;
+    if res.status().is_success() {
+        res.headers_mut().insert(header::ETAG, etag);
+        res.headers_mut()
+            .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    }
+    res
+}
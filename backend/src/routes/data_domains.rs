@@ -1,10 +1,12 @@
 use axum::{routing::get, Json, Router};
 use serde::Serialize;
+use utoipa::{OpenApi, ToSchema};
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::routes::AppState;
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct DataDomainRow {
     pub id: Uuid,
     pub name: String,
@@ -12,13 +14,25 @@ pub struct DataDomainRow {
     pub sort_order: i32,
 }
 
+#[derive(OpenApi)]
+#[openapi(paths(list_data_domains))]
+pub struct DataDomainsApi;
+
 pub fn router() -> Router<AppState> {
     Router::new().route("/", get(list_data_domains))
 }
 
+#[utoipa::path(
+    get,
+    path = "/",
+    responses(
+        (status = 200, body = Vec<DataDomainRow>),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
 async fn list_data_domains(
     axum::extract::State(state): axum::extract::State<AppState>,
-) -> Result<Json<Vec<DataDomainRow>>, (axum::http::StatusCode, String)> {
+) -> Result<Json<Vec<DataDomainRow>>, ApiError> {
     let rows = sqlx::query_as!(
         DataDomainRow,
         r#"
@@ -29,7 +43,7 @@ async fn list_data_domains(
     )
     .fetch_all(&state.pool)
     .await
-    .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    .map_err(ApiError::internal)?;
 
     Ok(Json(rows))
 }
@@ -1,10 +1,11 @@
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
     Extension, Json,
 };
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::{
     audit::{self, AuditAction, EntityType, RequestContext},
     auth::AuthActor,
@@ -23,7 +24,7 @@ use super::{
     helpers::{internal_error, map_sqlx_error},
 };
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ToSchema)]
 pub struct EdgeClaimWithDetails {
     #[serde(flatten)]
     pub claim: EdgeClaim,
@@ -31,7 +32,7 @@ pub struct EdgeClaimWithDetails {
     pub flows: Vec<EdgeClaimFlow>,
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, ToSchema)]
 pub struct CreateEdgeClaimRequest {
     #[serde(flatten)]
     pub claim: NewEdgeClaim,
@@ -43,13 +44,24 @@ pub struct CreateEdgeClaimRequest {
     pub flows: Vec<NewEdgeClaimFlow>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/{id}/claims",
+    params(("id" = Uuid, Path)),
+    request_body = CreateEdgeClaimRequest,
+    responses(
+        (status = 200, body = EdgeClaimWithDetails),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn create_edge_claim(
     State(state): State<AppState>,
     Extension(ctx): Extension<RequestContext>,
     Extension(actor): Extension<AuthActor>,
     Path(edge_id): Path<Uuid>,
     Json(payload): Json<CreateEdgeClaimRequest>,
-) -> Result<Json<EdgeClaimWithDetails>, (StatusCode, String)> {
+) -> Result<Json<EdgeClaimWithDetails>, ApiError> {
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
 
     let (exists,): (bool,) = sqlx::query_as("SELECT EXISTS(SELECT 1 FROM edges WHERE id = $1)")
@@ -59,7 +71,7 @@ pub async fn create_edge_claim(
         .map_err(internal_error)?;
 
     if !exists {
-        return Err((StatusCode::NOT_FOUND, "Relationen finns inte".into()));
+        return Err(ApiError::not_found("Relationen finns inte"));
     }
 
     sqlx::query(
@@ -91,7 +103,8 @@ pub async fn create_edge_claim(
             created_by,
             created_at,
             updated_at,
-            last_verified_at
+            last_verified_at,
+            version
         "#,
     )
     .bind(edge_id)
@@ -172,14 +185,18 @@ pub async fn create_edge_claim(
     };
 
     audit::write_audit(
-        &mut *tx,
+        &mut tx,
         ctx,
         Some(&actor),
         EntityType::Edge,
         edge_id,
         AuditAction::Create,
         None,
-        None,
+        Some(serde_json::json!({
+            "action": "claim_created",
+            "claim_id": out.claim.id,
+            "status": out.claim.status,
+        })),
         serde_json::to_value(&out).ok(),
     )
     .await
@@ -190,10 +207,19 @@ pub async fn create_edge_claim(
     Ok(Json(out))
 }
 
+#[utoipa::path(
+    get,
+    path = "/{id}/claims",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, body = Vec<EdgeClaimWithDetails>),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn list_edge_claims(
     State(state): State<AppState>,
     Path(edge_id): Path<Uuid>,
-) -> Result<Json<Vec<EdgeClaimWithDetails>>, (StatusCode, String)> {
+) -> Result<Json<Vec<EdgeClaimWithDetails>>, ApiError> {
     let claims = sqlx::query_as::<_, EdgeClaim>(
         r#"
         SELECT
@@ -206,7 +232,8 @@ pub async fn list_edge_claims(
             created_by,
             created_at,
             updated_at,
-            last_verified_at
+            last_verified_at,
+            version
         FROM edge_claims
         WHERE edge_id = $1
         ORDER BY created_at DESC
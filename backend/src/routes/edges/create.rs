@@ -3,55 +3,82 @@ use axum::{
     http::{HeaderMap, StatusCode},
     Extension, Json,
 };
+use sqlx::{Postgres, Transaction};
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::{
     audit::{self, AuditAction, EntityType, RequestContext},
     auth::AuthActor,
     models::{Edge, NewEdge},
-    routes::{etag_from_updated_at, AppState},
+    routes::{etag_from_version, AppState},
 };
 
 use super::helpers::{internal_error, map_sqlx_error};
 
+#[utoipa::path(
+    post,
+    path = "/",
+    request_body = NewEdge,
+    responses(
+        (status = 201, body = Edge, headers(("ETag" = String))),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn create_edge(
     State(state): State<AppState>,
     Extension(ctx): Extension<RequestContext>,
     Extension(actor): Extension<AuthActor>,
     Json(payload): Json<NewEdge>,
-) -> Result<(StatusCode, HeaderMap, Json<Edge>), (StatusCode, String)> {
+) -> Result<(StatusCode, HeaderMap, Json<Edge>), ApiError> {
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let edge = insert_edge(&mut tx, ctx, &actor, payload).await?;
+    tx.commit().await.map_err(internal_error)?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        axum::http::header::ETAG,
+        etag_from_version(edge.version),
+    );
+
+    Ok((StatusCode::CREATED, headers, Json(edge)))
+}
+
+/// Insert + audit inside the caller's transaction; shared with `/api/batch`.
+pub(crate) async fn insert_edge(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    payload: NewEdge,
+) -> Result<Edge, ApiError> {
     if payload.from_id == payload.to_id {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "Källa och mål måste vara olika".into(),
-        ));
+        return Err(ApiError::bad_request("Källa och mål måste vara olika"));
     }
 
-    let mut tx = state.pool.begin().await.map_err(internal_error)?;
-
     let (from_exists,): (bool,) =
         sqlx::query_as("SELECT EXISTS(SELECT 1 FROM nodes WHERE id = $1 AND deleted_at IS NULL)")
             .bind(payload.from_id)
-            .fetch_one(&mut *tx)
+            .fetch_one(&mut **tx)
             .await
             .map_err(internal_error)?;
 
     let (to_exists,): (bool,) =
         sqlx::query_as("SELECT EXISTS(SELECT 1 FROM nodes WHERE id = $1 AND deleted_at IS NULL)")
             .bind(payload.to_id)
-            .fetch_one(&mut *tx)
+            .fetch_one(&mut **tx)
             .await
             .map_err(internal_error)?;
 
     if !from_exists || !to_exists {
-        return Err((StatusCode::BAD_REQUEST, "Källa eller mål finns inte".into()));
+        return Err(ApiError::bad_request("Källa eller mål finns inte"));
     }
 
     let edge = sqlx::query_as::<_, Edge>(
         r#"
         INSERT INTO edges (id, from_id, to_id, kind, metadata)
         VALUES ($1, $2, $3, $4, $5)
-        RETURNING id, from_id, to_id, kind, metadata, created_at, updated_at
+        RETURNING id, from_id, to_id, kind, metadata, created_at, updated_at, version
         "#,
     )
     .bind(Uuid::new_v4())
@@ -59,14 +86,14 @@ pub async fn create_edge(
     .bind(payload.to_id)
     .bind(payload.kind.as_str())
     .bind(payload.metadata)
-    .fetch_one(&mut *tx)
+    .fetch_one(&mut **tx)
     .await
     .map_err(map_sqlx_error)?;
 
     audit::write_audit(
-        &mut *tx,
+        tx,
         ctx,
-        Some(&actor),
+        Some(actor),
         EntityType::Edge,
         edge.id,
         AuditAction::Create,
@@ -77,13 +104,5 @@ pub async fn create_edge(
     .await
     .map_err(internal_error)?;
 
-    tx.commit().await.map_err(internal_error)?;
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        axum::http::header::ETAG,
-        etag_from_updated_at(edge.updated_at),
-    );
-
-    Ok((StatusCode::CREATED, headers, Json(edge)))
+    Ok(edge)
 }
@@ -3,76 +3,91 @@ use axum::{
     http::{HeaderMap, StatusCode},
     Extension,
 };
-use time::format_description::well_known::Rfc3339;
-use time::OffsetDateTime;
+use sqlx::{Postgres, Transaction};
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::{
     audit::{self, AuditAction, EntityType, RequestContext},
     auth::AuthActor,
     models::Edge,
-    routes::{etag_from_updated_at, is_match, require_if_match, AppState},
+    routes::{check_if_match, require_if_match, AppState},
 };
 
 use super::helpers::internal_error;
 
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    params(
+        ("id" = Uuid, Path),
+        ("If-Match" = String, Header, description = "ETag för kopplingen")
+    ),
+    responses(
+        (status = 204),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse),
+        (status = 428, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn delete_edge(
     State(state): State<AppState>,
     Extension(ctx): Extension<RequestContext>,
     Extension(actor): Extension<AuthActor>,
     headers: HeaderMap,
     Path(id): Path<Uuid>,
-) -> Result<StatusCode, (StatusCode, String)> {
+) -> Result<StatusCode, ApiError> {
     let if_match = require_if_match(&headers)?;
-    let expected_updated_at = OffsetDateTime::parse(&if_match, &Rfc3339)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Ogiltig If-Match".into()))?;
 
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    delete_edge_row(&mut tx, ctx, &actor, id, &if_match).await?;
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
 
+pub(crate) async fn delete_edge_row(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    id: Uuid,
+    if_match: &str,
+) -> Result<Edge, ApiError> {
     let before = sqlx::query_as::<_, Edge>(
         r#"
-        SELECT id, from_id, to_id, kind, metadata, created_at, updated_at
+        SELECT id, from_id, to_id, kind, metadata, created_at, updated_at, version
         FROM edges
         WHERE id = $1
         "#,
     )
     .bind(id)
-    .fetch_optional(&mut *tx)
+    .fetch_optional(&mut **tx)
     .await
     .map_err(internal_error)?
-    .ok_or((StatusCode::NOT_FOUND, "Objektet finns inte längre".into()))?;
+    .ok_or_else(|| ApiError::not_found("Objektet finns inte längre"))?;
 
-    let current_etag = etag_from_updated_at(before.updated_at);
-    if !is_match(&current_etag, &if_match) {
-        return Err((
-            StatusCode::CONFLICT,
-            "Objektet har uppdaterats av någon annan".into(),
-        ));
-    }
+    check_if_match(before.version, before.updated_at, if_match)?;
 
     let deleted = sqlx::query(
         r#"
         DELETE FROM edges
-        WHERE id = $1 AND updated_at = $2
+        WHERE id = $1 AND version = $2
         "#,
     )
     .bind(id)
-    .bind(expected_updated_at)
-    .execute(&mut *tx)
+    .bind(before.version)
+    .execute(&mut **tx)
     .await
     .map_err(internal_error)?;
 
     if deleted.rows_affected() == 0 {
-        return Err((
-            StatusCode::CONFLICT,
-            "Objektet har uppdaterats av någon annan".into(),
-        ));
+        return Err(ApiError::etag_mismatch());
     }
 
     audit::write_audit(
-        &mut *tx,
+        tx,
         ctx,
-        Some(&actor),
+        Some(actor),
         EntityType::Edge,
         id,
         AuditAction::Delete,
@@ -83,7 +98,5 @@ pub async fn delete_edge(
     .await
     .map_err(internal_error)?;
 
-    tx.commit().await.map_err(internal_error)?;
-
-    Ok(StatusCode::NO_CONTENT)
+    Ok(before)
 }
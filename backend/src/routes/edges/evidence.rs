@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 use uuid::Uuid;
 
-use axum::http::StatusCode;
 
+use crate::error::ApiError;
 use crate::models::edge_claim_evidence::EdgeClaimEvidence;
 
 use super::helpers::internal_error;
@@ -10,7 +10,7 @@ use super::helpers::internal_error;
 pub async fn load_evidence_map_for_claim_ids(
     pool: &sqlx::PgPool,
     claim_ids: &[Uuid],
-) -> Result<HashMap<Uuid, Vec<EdgeClaimEvidence>>, (StatusCode, String)> {
+) -> Result<HashMap<Uuid, Vec<EdgeClaimEvidence>>, ApiError> {
     if claim_ids.is_empty() {
         return Ok(HashMap::new());
     }
@@ -39,7 +39,7 @@ pub async fn load_evidence_map_for_claim_ids(
 pub async fn load_evidence_for_claim_id(
     pool: &sqlx::PgPool,
     claim_id: Uuid,
-) -> Result<Vec<EdgeClaimEvidence>, (StatusCode, String)> {
+) -> Result<Vec<EdgeClaimEvidence>, ApiError> {
     let rows = sqlx::query_as::<_, EdgeClaimEvidence>(
         r#"
         SELECT id, claim_id, evidence_type, reference, note, created_at
@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
-use axum::http::StatusCode;
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::models::edge_claim_flow::EdgeClaimFlow;
 
 use super::helpers::internal_error;
@@ -10,7 +10,7 @@ use super::helpers::internal_error;
 pub async fn load_flow_map_for_claim_ids(
     pool: &sqlx::PgPool,
     claim_ids: &[Uuid],
-) -> Result<HashMap<Uuid, Vec<EdgeClaimFlow>>, (StatusCode, String)> {
+) -> Result<HashMap<Uuid, Vec<EdgeClaimFlow>>, ApiError> {
     if claim_ids.is_empty() {
         return Ok(HashMap::new());
     }
@@ -47,7 +47,7 @@ pub async fn load_flow_map_for_claim_ids(
 pub async fn load_flows_for_claim_id(
     pool: &sqlx::PgPool,
     claim_id: Uuid,
-) -> Result<Vec<EdgeClaimFlow>, (StatusCode, String)> {
+) -> Result<Vec<EdgeClaimFlow>, ApiError> {
     let rows: Vec<EdgeClaimFlow> = sqlx::query_as::<_, EdgeClaimFlow>(
         r#"
         SELECT
@@ -1,23 +1,25 @@
 use axum::{
     extract::{Path, State},
-    http::{HeaderMap, StatusCode},
+    http::HeaderMap,
     Json,
 };
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::{
     models::{
         edge::Edge, edge_claim::EdgeClaim, edge_claim_evidence::EdgeClaimEvidence,
         edge_claim_flow::EdgeClaimFlow,
     },
-    routes::{etag_from_updated_at, AppState},
+    routes::{etag_from_version, AppState},
 };
 
 use super::{
     evidence::load_evidence_for_claim_id, flows::load_flows_for_claim_id, helpers::internal_error,
 };
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ToSchema)]
 pub struct EdgeClaimWithDetails {
     #[serde(flatten)]
     pub claim: EdgeClaim,
@@ -25,17 +27,26 @@ pub struct EdgeClaimWithDetails {
     pub flows: Vec<EdgeClaimFlow>,
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, ToSchema)]
 pub struct EdgeWithClaim {
     #[serde(flatten)]
     pub edge: Edge,
     pub current_claim: Option<EdgeClaimWithDetails>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, body = EdgeWithClaim, headers(("ETag" = String))),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn get_edge(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-) -> Result<(HeaderMap, Json<EdgeWithClaim>), (StatusCode, String)> {
+) -> Result<(HeaderMap, Json<EdgeWithClaim>), ApiError> {
     let edge = sqlx::query_as::<_, Edge>(
         r#"
         SELECT
@@ -45,7 +56,8 @@ pub async fn get_edge(
             kind,
             metadata,
             created_at,
-            updated_at
+            updated_at,
+            version
         FROM edges
         WHERE id = $1
         "#,
@@ -54,7 +66,7 @@ pub async fn get_edge(
     .fetch_optional(&state.pool)
     .await
     .map_err(internal_error)?
-    .ok_or((StatusCode::NOT_FOUND, "Objektet finns inte längre".into()))?;
+    .ok_or_else(|| ApiError::not_found("Objektet finns inte längre"))?;
 
     let claim = sqlx::query_as::<_, EdgeClaim>(
         r#"
@@ -66,7 +78,8 @@ pub async fn get_edge(
             status,
             created_by,
             created_at,
-            last_verified_at
+            last_verified_at,
+            version
         FROM edge_claims
         WHERE edge_id = $1
           AND status IN ('active', 'needs_review')
@@ -107,7 +120,7 @@ pub async fn get_edge(
     let mut headers = HeaderMap::new();
     headers.insert(
         axum::http::header::ETAG,
-        etag_from_updated_at(edge.updated_at),
+        etag_from_version(edge.version),
     );
 
     Ok((
@@ -1,21 +1,9 @@
-use axum::http::StatusCode;
+use crate::error::ApiError;
 
-pub fn internal_error(err: impl std::fmt::Display) -> (StatusCode, String) {
-    (
-        StatusCode::INTERNAL_SERVER_ERROR,
-        format!("Internt fel: {err}"),
-    )
+pub fn internal_error(err: impl std::fmt::Display) -> ApiError {
+    ApiError::internal(err)
 }
 
-pub fn map_sqlx_error(err: sqlx::Error) -> (StatusCode, String) {
-    match err {
-        sqlx::Error::Database(db_err) => (
-            StatusCode::BAD_REQUEST,
-            format!("Ogiltig begäran: {}", db_err.message()),
-        ),
-        _ => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Internt fel: {err}"),
-        ),
-    }
+pub fn map_sqlx_error(err: sqlx::Error) -> ApiError {
+    ApiError::from_sqlx(err)
 }
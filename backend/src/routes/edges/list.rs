@@ -1,12 +1,21 @@
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{extract::State, Json};
 
+use crate::error::ApiError;
 use crate::{models::Edge, routes::AppState};
 
 use super::helpers::internal_error;
 
+#[utoipa::path(
+    get,
+    path = "/",
+    responses(
+        (status = 200, body = Vec<Edge>),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn list_edges(
     State(state): State<AppState>,
-) -> Result<Json<Vec<Edge>>, (StatusCode, String)> {
+) -> Result<Json<Vec<Edge>>, ApiError> {
     let rows = sqlx::query_as::<_, Edge>(
         r#"
         SELECT
@@ -16,7 +25,8 @@ pub async fn list_edges(
             kind,
             metadata,
             created_at,
-            updated_at
+            updated_at,
+            version
         FROM edges
         ORDER BY updated_at DESC
         "#,
@@ -2,6 +2,7 @@ use axum::{
     routing::{get, patch, post},
     Router,
 };
+use utoipa::OpenApi;
 
 use crate::routes::AppState;
 
@@ -17,6 +18,23 @@ pub mod needs_review;
 pub mod typed_flows;
 pub mod update;
 
+#[derive(OpenApi)]
+#[openapi(paths(
+    list::list_edges,
+    create::create_edge,
+    needs_review::list_edges_needing_review,
+    get::get_edge,
+    update::update_edge,
+    delete::delete_edge,
+    needs_review::mark_edge_needs_review,
+    update::patch_edge_metadata,
+    typed_flows::get_edge_typed_flows,
+    typed_flows::put_edge_typed_flows,
+    claims::list_edge_claims,
+    claims::create_edge_claim,
+))]
+pub struct EdgesApi;
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", get(list::list_edges).post(create::create_edge))
@@ -2,13 +2,15 @@
 
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
     Extension, Json,
 };
 use serde::Deserialize;
 use serde::Serialize;
+use sqlx::{Postgres, Transaction};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::{
     audit::{self, AuditAction, EntityType, RequestContext},
     auth::AuthActor,
@@ -18,7 +20,7 @@ use crate::{
 
 use crate::routes::edges::helpers::{internal_error, map_sqlx_error};
 
-#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
 pub struct NeedsReviewEdgeRow {
     pub edge_id: Uuid,
     pub kind: String,
@@ -27,17 +29,26 @@ pub struct NeedsReviewEdgeRow {
     pub proposals: i64,
     pub status: String,
     pub created_by: String,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub created_at: time::OffsetDateTime,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct MarkNeedsReviewBody {
     pub description: String,
 }
 
+#[utoipa::path(
+    get,
+    path = "/needs-review",
+    responses(
+        (status = 200, body = Vec<NeedsReviewEdgeRow>),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn list_edges_needing_review(
     State(state): State<AppState>,
-) -> Result<Json<Vec<NeedsReviewEdgeRow>>, (StatusCode, String)> {
+) -> Result<Json<Vec<NeedsReviewEdgeRow>>, ApiError> {
     let rows: Vec<NeedsReviewEdgeRow> = sqlx::query_as(
         r#"
         SELECT
@@ -71,20 +82,67 @@ pub async fn list_edges_needing_review(
     Ok(Json(rows))
 }
 
+#[utoipa::path(
+    post,
+    path = "/{id}/needs-review",
+    params(("id" = Uuid, Path)),
+    request_body = MarkNeedsReviewBody,
+    responses(
+        (status = 200, body = EdgeClaim),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn mark_edge_needs_review(
     State(state): State<AppState>,
     Extension(ctx): Extension<RequestContext>,
     Extension(actor): Extension<AuthActor>,
     Path(edge_id): Path<Uuid>,
     Json(body): Json<MarkNeedsReviewBody>,
-) -> Result<Json<EdgeClaim>, (StatusCode, String)> {
+) -> Result<Json<EdgeClaim>, ApiError> {
     let description = body.description.trim();
     if description.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "Beskrivning krävs".into()));
+        return Err(ApiError::bad_request("Beskrivning krävs"));
     }
 
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
 
+    let claim =
+        reopen_for_review(&mut tx, edge_id, description, 100, &actor.username, None).await?;
+
+    audit::write_audit(
+        &mut tx,
+        ctx,
+        Some(&actor),
+        EntityType::Edge,
+        edge_id,
+        AuditAction::Patch,
+        None,
+        Some(serde_json::json!({
+            "action": "edge_marked_needs_review",
+            "claim_id": claim.id
+        })),
+        None,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(Json(claim))
+}
+
+/// Deprecates the edge's current claims and puts a `needs_review` claim in
+/// their place. `carry_over` names a claim whose evidence and flows are
+/// copied onto the new one, so approving it restores the edge as it was.
+pub(crate) async fn reopen_for_review(
+    tx: &mut Transaction<'_, Postgres>,
+    edge_id: Uuid,
+    source: &str,
+    confidence: i16,
+    created_by: &str,
+    carry_over: Option<Uuid>,
+) -> Result<EdgeClaim, ApiError> {
     sqlx::query(
         r#"
         UPDATE edge_claims
@@ -94,7 +152,7 @@ pub async fn mark_edge_needs_review(
         "#,
     )
     .bind(edge_id)
-    .execute(&mut *tx)
+    .execute(&mut **tx)
     .await
     .map_err(map_sqlx_error)?;
 
@@ -119,35 +177,48 @@ pub async fn mark_edge_needs_review(
             created_by,
             created_at,
             updated_at,
-            last_verified_at
+            last_verified_at,
+            version
         "#,
     )
     .bind(edge_id)
-    .bind(description.to_string())
-    .bind(100i16)
-    .bind(actor.username.clone())
-    .fetch_one(&mut *tx)
+    .bind(source)
+    .bind(confidence)
+    .bind(created_by)
+    .fetch_one(&mut **tx)
     .await
     .map_err(map_sqlx_error)?;
 
-    audit::write_audit(
-        &mut *tx,
-        ctx.clone(),
-        Some(&actor),
-        EntityType::Edge,
-        edge_id,
-        AuditAction::Patch,
-        None,
-        Some(serde_json::json!({
-            "action": "edge_marked_needs_review",
-            "claim_id": claim.id
-        })),
-        None,
-    )
-    .await
-    .map_err(internal_error)?;
+    if let Some(previous_id) = carry_over {
+        sqlx::query(
+            r#"
+            INSERT INTO edge_claim_evidence (claim_id, evidence_type, reference, note)
+            SELECT $1, evidence_type, reference, note
+            FROM edge_claim_evidence
+            WHERE claim_id = $2
+            "#,
+        )
+        .bind(claim.id)
+        .bind(previous_id)
+        .execute(&mut **tx)
+        .await
+        .map_err(internal_error)?;
 
-    tx.commit().await.map_err(internal_error)?;
+        sqlx::query(
+            r#"
+            INSERT INTO edge_claim_flows
+                (claim_id, flow_type, direction, data_category_id, protocol, frequency)
+            SELECT $1, flow_type, direction, data_category_id, protocol, frequency
+            FROM edge_claim_flows
+            WHERE claim_id = $2
+            "#,
+        )
+        .bind(claim.id)
+        .bind(previous_id)
+        .execute(&mut **tx)
+        .await
+        .map_err(internal_error)?;
+    }
 
-    Ok(Json(claim))
+    Ok(claim)
 }
@@ -4,32 +4,43 @@ use axum::{
     Json,
 };
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use super::helpers::internal_error;
+use crate::error::ApiError;
 use crate::routes::AppState;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct EdgeTypedFlowResponse {
     pub direction: String,
     pub domain_ids: Vec<Uuid>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct UpsertEdgeTypedFlowsRequest {
     pub flows: Vec<UpsertEdgeTypedFlow>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct UpsertEdgeTypedFlow {
     pub direction: String,
     pub domain_ids: Vec<Uuid>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/{id}/typed-flows",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, body = Vec<EdgeTypedFlowResponse>),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn get_edge_typed_flows(
     State(state): State<AppState>,
     Path(edge_id): Path<Uuid>,
-) -> Result<Json<Vec<EdgeTypedFlowResponse>>, (StatusCode, String)> {
+) -> Result<Json<Vec<EdgeTypedFlowResponse>>, ApiError> {
     let rows = sqlx::query!(
         r#"
         SELECT
@@ -62,21 +73,28 @@ pub async fn get_edge_typed_flows(
     Ok(Json(out))
 }
 
+#[utoipa::path(
+    put,
+    path = "/{id}/typed-flows",
+    params(("id" = Uuid, Path)),
+    request_body = UpsertEdgeTypedFlowsRequest,
+    responses(
+        (status = 204),
+        (status = 400, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn put_edge_typed_flows(
     State(state): State<AppState>,
     Path(edge_id): Path<Uuid>,
     Json(payload): Json<UpsertEdgeTypedFlowsRequest>,
-) -> Result<StatusCode, (StatusCode, String)> {
+) -> Result<StatusCode, ApiError> {
     fn valid_direction(s: &str) -> bool {
         matches!(s, "fran" | "till" | "bidirectional")
     }
 
     for f in &payload.flows {
         if !valid_direction(f.direction.as_str()) {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                "Ogiltig riktning (måste vara fran, till eller bidirectional)".into(),
-            ));
+            return Err(ApiError::bad_request("Ogiltig riktning (måste vara fran, till eller bidirectional)"));
         }
     }
 
@@ -1,25 +1,28 @@
 use axum::{
     extract::{Path, State},
-    http::{HeaderMap, StatusCode},
+    http::HeaderMap,
     Extension, Json,
 };
 
 use serde::Serialize;
-use time::format_description::well_known::Rfc3339;
-use time::OffsetDateTime;
+use sqlx::{Postgres, Transaction};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::{
     audit::{self, AuditAction, EntityType, RequestContext},
     auth::AuthActor,
     models::{Edge, EdgeKind, MergePatch, UpdateEdge},
-    routes::{etag_from_updated_at, is_match, patch::merge_patch, require_if_match, AppState},
+    routes::{
+        check_if_match, etag_from_version, patch::merge_patch, require_if_match, AppState,
+    },
     validation::{validate_edge_metadata, ValidationResult},
 };
 
 use super::helpers::{internal_error, map_sqlx_error};
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct EdgeWriteResponse {
     pub edge: Edge,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -33,7 +36,7 @@ async fn ensure_needs_review_claim(
     actor: Option<&AuthActor>,
     edge_id: Uuid,
     reason: &str,
-) -> Result<bool, (StatusCode, String)> {
+) -> Result<bool, ApiError> {
     let exists: Option<(i64,)> = sqlx::query_as(
         r#"
         SELECT 1
@@ -86,7 +89,7 @@ async fn ensure_needs_review_claim(
     .map_err(map_sqlx_error)?;
 
     audit::write_audit(
-        &mut **tx,
+        tx,
         ctx.clone(),
         actor,
         EntityType::Edge,
@@ -105,6 +108,22 @@ async fn ensure_needs_review_claim(
     Ok(true)
 }
 
+#[utoipa::path(
+    put,
+    path = "/{id}",
+    params(
+        ("id" = Uuid, Path),
+        ("If-Match" = String, Header, description = "ETag för kopplingen")
+    ),
+    request_body = UpdateEdge,
+    responses(
+        (status = 200, body = EdgeWriteResponse, headers(("ETag" = String))),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse),
+        (status = 428, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn update_edge(
     State(state): State<AppState>,
     Extension(ctx): Extension<RequestContext>,
@@ -112,35 +131,46 @@ pub async fn update_edge(
     headers: HeaderMap,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateEdge>,
-) -> Result<(HeaderMap, Json<EdgeWriteResponse>), (StatusCode, String)> {
+) -> Result<(HeaderMap, Json<EdgeWriteResponse>), ApiError> {
     let if_match = require_if_match(&headers)?;
-    let expected_updated_at = OffsetDateTime::parse(&if_match, &Rfc3339)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Ogiltig If-Match".into()))?;
-
-    let kind_str = payload.kind.map(EdgeKind::as_str);
 
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let out = apply_edge_update(&mut tx, ctx, &actor, id, &if_match, payload).await?;
+    tx.commit().await.map_err(internal_error)?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        axum::http::header::ETAG,
+        etag_from_version(out.edge.version),
+    );
+
+    Ok((headers, Json(out)))
+}
+
+pub(crate) async fn apply_edge_update(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    id: Uuid,
+    if_match: &str,
+    payload: UpdateEdge,
+) -> Result<EdgeWriteResponse, ApiError> {
+    let kind_str = payload.kind.map(EdgeKind::as_str);
 
     let before = sqlx::query_as::<_, Edge>(
         r#"
-        SELECT id, from_id, to_id, kind, metadata, created_at, updated_at
+        SELECT id, from_id, to_id, kind, metadata, created_at, updated_at, version
         FROM edges
         WHERE id = $1
         "#,
     )
     .bind(id)
-    .fetch_optional(&mut *tx)
+    .fetch_optional(&mut **tx)
     .await
     .map_err(internal_error)?
-    .ok_or((StatusCode::NOT_FOUND, "Objektet finns inte längre".into()))?;
-
-    let current_etag = etag_from_updated_at(before.updated_at);
-    if !is_match(&current_etag, &if_match) {
-        return Err((
-            StatusCode::CONFLICT,
-            "Objektet har uppdaterats av någon annan".into(),
-        ));
-    }
+    .ok_or_else(|| ApiError::not_found("Objektet finns inte längre"))?;
+
+    check_if_match(before.version, before.updated_at, if_match)?;
 
     let updated = sqlx::query_as::<_, Edge>(
         r#"
@@ -149,26 +179,23 @@ pub async fn update_edge(
           kind = COALESCE($2, kind),
           metadata = COALESCE($3, metadata),
           updated_at = now()
-        WHERE id = $1 AND updated_at = $4
-        RETURNING id, from_id, to_id, kind, metadata, created_at, updated_at
+        WHERE id = $1 AND version = $4
+        RETURNING id, from_id, to_id, kind, metadata, created_at, updated_at, version
         "#,
     )
     .bind(id)
     .bind(kind_str)
     .bind(payload.metadata.clone())
-    .bind(expected_updated_at)
-    .fetch_optional(&mut *tx)
+    .bind(before.version)
+    .fetch_optional(&mut **tx)
     .await
     .map_err(map_sqlx_error)?
-    .ok_or((
-        StatusCode::CONFLICT,
-        "Objektet har uppdaterats av någon annan".into(),
-    ))?;
+    .ok_or_else(ApiError::etag_mismatch)?;
 
     audit::write_audit(
-        &mut *tx,
-        ctx.clone(),
-        Some(&actor),
+        tx,
+        ctx,
+        Some(actor),
         EntityType::Edge,
         id,
         AuditAction::Patch,
@@ -197,28 +224,33 @@ pub async fn update_edge(
                 .unwrap_or("metadata guidance");
 
             created_needs_review_claim =
-                ensure_needs_review_claim(&mut tx, &ctx, Some(&actor), id, reason).await?;
+                ensure_needs_review_claim(tx, &ctx, Some(actor), id, reason).await?;
         }
     }
 
-    tx.commit().await.map_err(internal_error)?;
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        axum::http::header::ETAG,
-        etag_from_updated_at(updated.updated_at),
-    );
-
-    Ok((
-        headers,
-        Json(EdgeWriteResponse {
-            edge: updated,
-            validation,
-            created_needs_review_claim,
-        }),
-    ))
+    Ok(EdgeWriteResponse {
+        edge: updated,
+        validation,
+        created_needs_review_claim,
+    })
 }
 
+#[utoipa::path(
+    patch,
+    path = "/{id}/metadata",
+    params(
+        ("id" = Uuid, Path),
+        ("If-Match" = String, Header, description = "ETag för kopplingen")
+    ),
+    request_body(content = MergePatch, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, body = EdgeWriteResponse, headers(("ETag" = String))),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse),
+        (status = 428, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn patch_edge_metadata(
     State(state): State<AppState>,
     Extension(ctx): Extension<RequestContext>,
@@ -226,16 +258,13 @@ pub async fn patch_edge_metadata(
     headers: HeaderMap,
     Path(id): Path<Uuid>,
     Json(MergePatch(patch)): Json<MergePatch>,
-) -> Result<(HeaderMap, Json<EdgeWriteResponse>), (StatusCode, String)> {
+) -> Result<(HeaderMap, Json<EdgeWriteResponse>), ApiError> {
     let if_match = require_if_match(&headers)?;
-    let expected_updated_at = OffsetDateTime::parse(&if_match, &Rfc3339)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Ogiltig If-Match".into()))?;
-
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
 
     let mut before = sqlx::query_as::<_, Edge>(
         r#"
-        SELECT id, from_id, to_id, kind, metadata, created_at, updated_at
+        SELECT id, from_id, to_id, kind, metadata, created_at, updated_at, version
         FROM edges
         WHERE id = $1
         "#,
@@ -244,15 +273,9 @@ pub async fn patch_edge_metadata(
     .fetch_optional(&mut *tx)
     .await
     .map_err(internal_error)?
-    .ok_or((StatusCode::NOT_FOUND, "Objektet finns inte längre".into()))?;
-
-    let current_etag = etag_from_updated_at(before.updated_at);
-    if !is_match(&current_etag, &if_match) {
-        return Err((
-            StatusCode::CONFLICT,
-            "Objektet har uppdaterats av någon annan".into(),
-        ));
-    }
+    .ok_or_else(|| ApiError::not_found("Objektet finns inte längre"))?;
+
+    check_if_match(before.version, before.updated_at, &if_match)?;
 
     let before_metadata = before.metadata.clone();
     merge_patch(&mut before.metadata, &patch);
@@ -261,23 +284,20 @@ pub async fn patch_edge_metadata(
         r#"
         UPDATE edges
         SET metadata = $2, updated_at = now()
-        WHERE id = $1 AND updated_at = $3
-        RETURNING id, from_id, to_id, kind, metadata, created_at, updated_at
+        WHERE id = $1 AND version = $3
+        RETURNING id, from_id, to_id, kind, metadata, created_at, updated_at, version
         "#,
     )
     .bind(id)
     .bind(before.metadata.clone())
-    .bind(expected_updated_at)
+    .bind(before.version)
     .fetch_optional(&mut *tx)
     .await
     .map_err(internal_error)?
-    .ok_or((
-        StatusCode::CONFLICT,
-        "Objektet har uppdaterats av någon annan".into(),
-    ))?;
+    .ok_or_else(ApiError::etag_mismatch)?;
 
     audit::write_audit(
-        &mut *tx,
+        &mut tx,
         ctx.clone(),
         Some(&actor),
         EntityType::Edge,
@@ -317,7 +337,7 @@ pub async fn patch_edge_metadata(
     let mut headers = HeaderMap::new();
     headers.insert(
         axum::http::header::ETAG,
-        etag_from_updated_at(updated.updated_at),
+        etag_from_version(updated.version),
     );
 
     Ok((
@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use utoipa::{IntoParams, OpenApi};
+
+use crate::error::ApiError;
+use crate::events::{AuditEventRow, EventEntity, GraphEvent, AUDIT_EVENT_COLUMNS};
+use crate::routes::edges::helpers::internal_error;
+use crate::routes::AppState;
+
+/// Max audit rows replayed on resume; beyond that the client gets `resync`.
+const REPLAY_LIMIT: i64 = 1000;
+
+/// `seq` is taken at insert, but transactions commit in any order: a row
+/// with a lower `seq` than the last one sent may become visible afterwards.
+/// Resume therefore replays this many sequence numbers before
+/// `Last-Event-ID` too, and clients drop ids they have already seen.
+const REPLAY_WINDOW: i64 = 200;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventsQuery {
+    /// Comma separated: node,edge,claim. Default: all.
+    pub types: Option<String>,
+    /// Fallback for clients that cannot set the `Last-Event-ID` header.
+    pub last_event_id: Option<i64>,
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(stream_events), components(schemas(GraphEvent)))]
+pub struct EventsApi;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/", get(stream_events))
+}
+
+fn parse_types(raw: Option<&str>) -> Result<Option<HashSet<EventEntity>>, ApiError> {
+    let Some(raw) = raw.map(str::trim).filter(|s| !s.is_empty()) else {
+        return Ok(None);
+    };
+
+    let mut out = HashSet::new();
+    for part in raw.split(',').filter(|p| !p.trim().is_empty()) {
+        let entity = EventEntity::parse(part).ok_or_else(|| {
+            ApiError::validation("types", format!("Ogiltig händelsetyp: {}", part.trim()))
+        })?;
+        out.insert(entity);
+    }
+
+    Ok(Some(out))
+}
+
+fn parse_last_event_id(
+    headers: &HeaderMap,
+    q: &EventsQuery,
+) -> Result<Option<i64>, ApiError> {
+    match headers.get("last-event-id") {
+        Some(v) => v
+            .to_str()
+            .ok()
+            .and_then(|s| s.trim().parse::<i64>().ok())
+            .map(Some)
+            .ok_or(ApiError::bad_request("Ogiltigt Last-Event-ID")),
+        None => Ok(q.last_event_id),
+    }
+}
+
+fn to_sse(ev: &GraphEvent) -> Event {
+    Event::default()
+        .id(ev.seq.to_string())
+        .event(ev.name())
+        .json_data(ev)
+        .unwrap_or_else(|_| Event::default().comment("serialization error"))
+}
+
+/// Tells the client it missed events and should refetch its state.
+fn resync_event(missed: u64) -> Event {
+    Event::default()
+        .event("resync")
+        .data(format!("{{\"missed\":{}}}", missed))
+}
+
+/// Server-sent events. Event name is `<entity>.<op>` (e.g. `node.created`),
+/// `id` is the audit sequence number and `data` a [`GraphEvent`] as JSON.
+/// On resume, events shortly before `Last-Event-ID` are sent again (see
+/// [`REPLAY_WINDOW`]); deduplicate on `id`.
+#[utoipa::path(
+    get,
+    path = "/",
+    params(
+        EventsQuery,
+        (
+            "Last-Event-ID" = Option<i64>,
+            Header,
+            description = "Återuppta efter detta event-id; några tidigare skickas igen, deduplicera på id"
+        )
+    ),
+    responses(
+        (status = 200, description = "SSE-ström", content_type = "text/event-stream", body = GraphEvent),
+        (status = 400, response = crate::openapi::ErrorResponse)
+    )
+)]
+async fn stream_events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(q): Query<EventsQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let types = parse_types(q.types.as_deref())?;
+    let last_event_id = parse_last_event_id(&headers, &q)?;
+
+    // Subscribe before replaying so nothing committed in between is lost;
+    // events present in both are dropped from the live side.
+    let rx = state.events.subscribe();
+
+    let mut replay: Vec<Event> = Vec::new();
+    let mut replayed: HashSet<i64> = HashSet::new();
+
+    if let Some(last) = last_event_id {
+        let after = last.saturating_sub(REPLAY_WINDOW);
+        let sql = format!(
+            "SELECT {AUDIT_EVENT_COLUMNS} FROM audit_log WHERE seq > $1 ORDER BY seq LIMIT $2"
+        );
+        let rows = sqlx::query_as::<_, AuditEventRow>(&sql)
+            .bind(after)
+            .bind(REPLAY_LIMIT + 1)
+            .fetch_all(&state.pool)
+            .await
+            .map_err(internal_error)?;
+
+        if rows.len() as i64 > REPLAY_LIMIT {
+            let (missed,): (i64,) =
+                sqlx::query_as("SELECT COUNT(*) FROM audit_log WHERE seq > $1")
+                    .bind(after)
+                    .fetch_one(&state.pool)
+                    .await
+                    .map_err(internal_error)?;
+            replay.push(resync_event(missed.max(0) as u64));
+        } else {
+            for ev in rows.into_iter().filter_map(AuditEventRow::into_event) {
+                replayed.insert(ev.seq);
+                if allowed(&types, &ev) {
+                    replay.push(to_sse(&ev));
+                }
+            }
+        }
+    }
+
+    let live = BroadcastStream::new(rx).filter_map(move |msg| match msg {
+        Ok(ev) if allowed(&types, &ev) && !replayed.contains(&ev.seq) => Some(to_sse(&ev)),
+        Ok(_) => None,
+        Err(BroadcastStreamRecvError::Lagged(n)) => Some(resync_event(n)),
+    });
+
+    let stream = tokio_stream::iter(replay).chain(live).map(Ok);
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+fn allowed(types: &Option<HashSet<EventEntity>>, ev: &GraphEvent) -> bool {
+    types.as_ref().map(|t| t.contains(&ev.entity)).unwrap_or(true)
+}
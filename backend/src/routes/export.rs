@@ -1,21 +1,47 @@
 use axum::{routing::get, Router};
+use utoipa::OpenApi;
 
 use crate::routes::AppState;
 
 mod csv;
-mod filter_spec;
+pub(crate) mod filter_spec;
+mod gdpr;
 mod json;
 mod util;
+mod vendors;
 mod xlsx;
 
+#[derive(OpenApi)]
+#[openapi(paths(
+    json::export_snapshot_json,
+    json::export_graph_json,
+    csv::export_snapshot_csv,
+    xlsx::export_snapshot_xlsx,
+    csv::export_nodes_csv,
+    csv::export_edges_csv,
+    csv::export_claims_current_csv,
+    csv::export_flows_current_csv,
+    gdpr::export_gdpr_json,
+    gdpr::export_gdpr_csv,
+    gdpr::export_gdpr_xlsx,
+    vendors::export_vendors_json,
+    vendors::export_vendors_xlsx
+))]
+pub struct ExportApi;
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/snapshot.json", get(json::export_snapshot_json))
-        .route("/graph.json", get(json::export_snapshot_json))
+        .route("/graph.json", get(json::export_graph_json))
         .route("/snapshot.csv", get(csv::export_snapshot_csv))
         .route("/snapshot.xlsx", get(xlsx::export_snapshot_xlsx))
         .route("/nodes.csv", get(csv::export_nodes_csv))
         .route("/edges.csv", get(csv::export_edges_csv))
         .route("/claims_current.csv", get(csv::export_claims_current_csv))
         .route("/flows_current.csv", get(csv::export_flows_current_csv))
+        .route("/gdpr.json", get(gdpr::export_gdpr_json))
+        .route("/gdpr.csv", get(gdpr::export_gdpr_csv))
+        .route("/gdpr.xlsx", get(gdpr::export_gdpr_xlsx))
+        .route("/vendors.json", get(vendors::export_vendors_json))
+        .route("/vendors.xlsx", get(vendors::export_vendors_xlsx))
 }
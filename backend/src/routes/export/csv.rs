@@ -1,87 +1,56 @@
-use crate::models::Node;
+use crate::auth::AuthActor;
+use crate::error::ApiError;
 use crate::routes::edges::flows::load_flow_map_for_claim_ids;
 use crate::routes::AppState;
 use axum::extract::{Query, State};
-use axum::http::{HeaderMap, StatusCode};
-use sqlx::Row;
+use axum::Extension;
+use axum::http::HeaderMap;
+use sqlx::{Postgres, QueryBuilder, Row};
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
 use super::util::{
-    csv_escape, csv_headers_csv, csv_opt, csv_opt_time, csv_opt_uuid, EdgeScope, ExportRequest,
+    csv_escape, csv_headers_csv, csv_opt, csv_opt_time, csv_opt_uuid, db_error,
+    load_filtered_nodes, push_edge_where, ExportRequest,
 };
 
+#[utoipa::path(
+    get,
+    path = "/snapshot.csv",
+    params(ExportRequest),
+    responses(
+        (status = 200, description = "CSV", content_type = "text/csv", body = String),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub(super) async fn export_snapshot_csv(
     State(state): State<AppState>,
+    actor: Option<Extension<AuthActor>>,
     Query(req): Query<ExportRequest>,
-) -> Result<(HeaderMap, String), (StatusCode, String)> {
-    let spec = req
-        .filter_spec()
-        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+) -> Result<(HeaderMap, String), ApiError> {
+    let spec = req.resolve_filter_spec(&state.pool, actor.as_deref()).await?;
 
     let include_edges = req.include_edges();
 
-    let all_nodes: Vec<Node> = sqlx::query_as::<_, Node>(
-        r#"
-        SELECT *
-        FROM nodes
-        WHERE deleted_at IS NULL
-        ORDER BY kind, name, id
-        "#,
-    )
-    .fetch_all(&state.pool)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let nodes: Vec<Node> = all_nodes
-        .into_iter()
-        .filter(|n| spec.matches_node(n))
-        .collect();
+    let nodes = load_filtered_nodes(&state.pool, &spec).await?;
 
-    let node_ids: Option<Vec<Uuid>> = if spec.is_empty() {
-        None
-    } else {
+    let node_ids: Option<Vec<Uuid>> = if spec.has_node_filter() {
         Some(nodes.iter().map(|n| n.id).collect())
+    } else {
+        None
     };
 
     let edges = if include_edges {
-        match (&node_ids, req.edge_scope()) {
-            (Some(ids), EdgeScope::Both) => sqlx::query(
-                r#"
-                    SELECT id, kind, from_id, to_id
-                    FROM edges
-                    WHERE from_id = ANY($1) AND to_id = ANY($1)
-                    ORDER BY kind, from_id, to_id, id
-                    "#,
-            )
-            .bind(ids)
-            .fetch_all(&state.pool)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
-            (Some(ids), EdgeScope::Any) => sqlx::query(
-                r#"
-                    SELECT id, kind, from_id, to_id
-                    FROM edges
-                    WHERE from_id = ANY($1) OR to_id = ANY($1)
-                    ORDER BY kind, from_id, to_id, id
-                    "#,
-            )
-            .bind(ids)
-            .fetch_all(&state.pool)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
-            (None, _) => sqlx::query(
-                r#"
-                    SELECT id, kind, from_id, to_id
-                    FROM edges
-                    ORDER BY kind, from_id, to_id, id
-                    "#,
-            )
+        let mut qb =
+            QueryBuilder::<Postgres>::new("SELECT e.id, e.kind, e.from_id, e.to_id FROM edges e");
+        push_edge_where(&mut qb, node_ids.as_deref(), req.edge_scope(), &spec)?;
+        qb.push(" ORDER BY e.kind, e.from_id, e.to_id, e.id");
+        qb.build()
             .fetch_all(&state.pool)
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
-        }
+            .map_err(ApiError::internal)?
     } else {
         vec![]
     };
@@ -118,7 +87,17 @@ pub(super) async fn export_snapshot_csv(
     Ok((csv_headers_csv(), out))
 }
 
-pub(super) async fn export_nodes_csv(State(state): State<AppState>) -> (HeaderMap, String) {
+#[utoipa::path(
+    get,
+    path = "/nodes.csv",
+    responses(
+        (status = 200, description = "CSV", content_type = "text/csv", body = String),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub(super) async fn export_nodes_csv(
+    State(state): State<AppState>,
+) -> Result<(HeaderMap, String), ApiError> {
     let rows = sqlx::query!(
         r#"
         SELECT id, name, kind
@@ -129,7 +108,7 @@ pub(super) async fn export_nodes_csv(State(state): State<AppState>) -> (HeaderMa
     )
     .fetch_all(&state.pool)
     .await
-    .unwrap();
+    .map_err(db_error)?;
 
     let mut out = String::new();
     out.push_str("id,name,kind\n");
@@ -142,10 +121,20 @@ pub(super) async fn export_nodes_csv(State(state): State<AppState>) -> (HeaderMa
         ));
     }
 
-    (csv_headers_csv(), out)
+    Ok((csv_headers_csv(), out))
 }
 
-pub(super) async fn export_edges_csv(State(state): State<AppState>) -> (HeaderMap, String) {
+#[utoipa::path(
+    get,
+    path = "/edges.csv",
+    responses(
+        (status = 200, description = "CSV", content_type = "text/csv", body = String),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub(super) async fn export_edges_csv(
+    State(state): State<AppState>,
+) -> Result<(HeaderMap, String), ApiError> {
     let rows = sqlx::query!(
         r#"
         SELECT id, from_id, to_id, kind
@@ -155,7 +144,7 @@ pub(super) async fn export_edges_csv(State(state): State<AppState>) -> (HeaderMa
     )
     .fetch_all(&state.pool)
     .await
-    .unwrap();
+    .map_err(db_error)?;
 
     let mut out = String::new();
     out.push_str("id,from_id,to_id,kind\n");
@@ -169,12 +158,20 @@ pub(super) async fn export_edges_csv(State(state): State<AppState>) -> (HeaderMa
         ));
     }
 
-    (csv_headers_csv(), out)
+    Ok((csv_headers_csv(), out))
 }
 
+#[utoipa::path(
+    get,
+    path = "/claims_current.csv",
+    responses(
+        (status = 200, description = "CSV", content_type = "text/csv", body = String),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub(super) async fn export_claims_current_csv(
     State(state): State<AppState>,
-) -> (HeaderMap, String) {
+) -> Result<(HeaderMap, String), ApiError> {
     let rows = sqlx::query(
         r#"
         SELECT
@@ -194,7 +191,7 @@ pub(super) async fn export_claims_current_csv(
     )
     .fetch_all(&state.pool)
     .await
-    .unwrap();
+    .map_err(db_error)?;
 
     let mut out = String::new();
     out.push_str("edge_id,claim_id,status,source,confidence,created_by,created_at,updated_at,last_verified_at,import_batch_id\n");
@@ -233,10 +230,20 @@ pub(super) async fn export_claims_current_csv(
         ));
     }
 
-    (csv_headers_csv(), out)
+    Ok((csv_headers_csv(), out))
 }
 
-pub(super) async fn export_flows_current_csv(State(state): State<AppState>) -> (HeaderMap, String) {
+#[utoipa::path(
+    get,
+    path = "/flows_current.csv",
+    responses(
+        (status = 200, description = "CSV", content_type = "text/csv", body = String),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub(super) async fn export_flows_current_csv(
+    State(state): State<AppState>,
+) -> Result<(HeaderMap, String), ApiError> {
     let edge_rows = sqlx::query(
         r#"
         SELECT
@@ -257,7 +264,7 @@ pub(super) async fn export_flows_current_csv(State(state): State<AppState>) -> (
     )
     .fetch_all(&state.pool)
     .await
-    .unwrap();
+    .map_err(db_error)?;
 
     let claim_ids: Vec<Uuid> = edge_rows
         .iter()
@@ -267,9 +274,7 @@ pub(super) async fn export_flows_current_csv(State(state): State<AppState>) -> (
     let flow_map = if claim_ids.is_empty() {
         std::collections::HashMap::new()
     } else {
-        load_flow_map_for_claim_ids(&state.pool, &claim_ids)
-            .await
-            .unwrap()
+        load_flow_map_for_claim_ids(&state.pool, &claim_ids).await?
     };
 
     let mut out = String::new();
@@ -341,5 +346,5 @@ pub(super) async fn export_flows_current_csv(State(state): State<AppState>) -> (
         }
     }
 
-    (csv_headers_csv(), out)
+    Ok((csv_headers_csv(), out))
 }
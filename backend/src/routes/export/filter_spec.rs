@@ -1,18 +1,69 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sqlx::{Postgres, QueryBuilder};
+use utoipa::ToSchema;
 
-use crate::models::Node;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// What a condition looks at. Node fields apply to `groups`; edge and claim
+/// fields apply to `edges`, and in `groups` mean "the node has an edge
+/// (or a claim on one) where this holds".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Field {
     Critical,
     Domain,
     Kind,
     Meta,
+    Name,
+    OwningDepartment,
+    Pii,
+    LegalRequirements,
+    FinancialValue,
+    BusinessCriticality,
+    InformationClass,
+    CriticalityScore,
+    Supplier,
+    SupplierType,
+    Owner,
+    /// Data domain of a typed flow on an edge; also matches parent domains.
+    DataDomain,
+    EdgeKind,
+    EdgeMeta,
+    ClaimStatus,
+    ClaimConfidence,
+    ClaimSource,
+}
+
+/// The wire name (`edge_kind`, `gte`, ...) for error messages.
+fn wire_name<T: Serialize>(v: &T) -> String {
+    serde_json::to_value(v)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+impl Field {
+    fn applies_to_edges(self) -> bool {
+        matches!(
+            self,
+            Field::DataDomain
+                | Field::EdgeKind
+                | Field::EdgeMeta
+                | Field::ClaimStatus
+                | Field::ClaimConfidence
+                | Field::ClaimSource
+        )
+    }
+
+    /// Fields holding a yes/no value, where `"ja"` or `"1"` means true.
+    fn is_boolean(self) -> bool {
+        matches!(
+            self,
+            Field::Critical | Field::Pii | Field::LegalRequirements | Field::FinancialValue
+        )
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Op {
     Eq,
@@ -24,13 +75,21 @@ pub enum Op {
     NotExists,
     In,
     NotIn,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Condition {
     pub field: Field,
+    /// Metadata key for `meta` / `edge_meta`; `a.b.c` walks nested objects.
     #[serde(default)]
     pub key: Option<String>,
+    /// Explicit nested path, for keys that themselves contain dots.
+    #[serde(default)]
+    pub path: Option<Vec<String>>,
     pub op: Op,
     #[serde(default)]
     pub value: Option<Value>,
@@ -38,48 +97,281 @@ pub struct Condition {
     pub values: Option<Vec<Value>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Group {
     #[serde(default)]
     pub conditions: Vec<Condition>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Groups are OR:ed, conditions within a group AND:ed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct FilterSpec {
     #[serde(default)]
     pub groups: Vec<Group>,
+    /// Narrows which edges are exported; only edge, claim and data domain
+    /// fields.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edges: Vec<Group>,
 }
 
-impl Default for FilterSpec {
-    fn default() -> Self {
-        Self { groups: vec![] }
-    }
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    /// Correlated on `n` (a row of `nodes`).
+    Node,
+    /// Correlated on `e` (a row of `edges`).
+    Edge,
+}
+
+const NUMERIC: &str = r"(CASE WHEN v.x ~ '^\s*-?[0-9]+(\.[0-9]+)?\s*$' THEN v.x::numeric END)";
+const TRUTHY: &str = "('true', '1', 'yes', 'ja')";
+const FALSY: &str = "('false', '0', 'no', 'nej')";
+
+fn has_conditions(groups: &[Group]) -> bool {
+    groups.iter().any(|g| !g.conditions.is_empty())
 }
 
 impl FilterSpec {
-    pub fn is_empty(&self) -> bool {
-        self.groups.is_empty() || self.groups.iter().all(|g| g.conditions.is_empty())
+    pub fn has_node_filter(&self) -> bool {
+        has_conditions(&self.groups)
+    }
+
+    pub fn has_edge_filter(&self) -> bool {
+        has_conditions(&self.edges)
+    }
+
+    /// Compiles the spec against a throwaway builder to surface errors early
+    /// (e.g. before storing it in a saved search).
+    pub fn validate(&self) -> Result<(), String> {
+        let mut qb = QueryBuilder::<Postgres>::new("");
+        self.push_node_filter(&mut qb)?;
+        self.push_edge_filter(&mut qb)
+    }
+
+    /// Boolean SQL over `nodes n`.
+    pub fn push_node_filter(&self, qb: &mut QueryBuilder<'_, Postgres>) -> Result<(), String> {
+        push_groups(qb, &self.groups, Scope::Node)
+    }
+
+    /// Boolean SQL over `edges e`.
+    pub fn push_edge_filter(&self, qb: &mut QueryBuilder<'_, Postgres>) -> Result<(), String> {
+        push_groups(qb, &self.edges, Scope::Edge)
+    }
+}
+
+fn push_groups(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    groups: &[Group],
+    scope: Scope,
+) -> Result<(), String> {
+    if !has_conditions(groups) {
+        qb.push("TRUE");
+        return Ok(());
     }
 
-    pub fn matches_node(&self, node: &Node) -> bool {
-        if self.is_empty() {
-            return true;
+    qb.push("(");
+    for (gi, g) in groups.iter().enumerate() {
+        if gi > 0 {
+            qb.push(" OR ");
+        }
+        if g.conditions.is_empty() {
+            qb.push("TRUE");
+            continue;
+        }
+        qb.push("(");
+        for (ci, c) in g.conditions.iter().enumerate() {
+            if ci > 0 {
+                qb.push(" AND ");
+            }
+            push_condition(qb, c, scope)?;
         }
+        qb.push(")");
+    }
+    qb.push(")");
+    Ok(())
+}
 
-        self.groups.iter().any(|g| {
-            if g.conditions.is_empty() {
-                return true;
+/// `[NOT] EXISTS (SELECT 1 FROM (<values of field>) v WHERE <op on v.x>)`.
+/// Negated ops are the negation of their positive op, so a missing value
+/// satisfies `neq` / `not_in` / `not_exists`.
+fn push_condition(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    c: &Condition,
+    scope: Scope,
+) -> Result<(), String> {
+    if scope == Scope::Edge && !c.field.applies_to_edges() {
+        return Err(format!(
+            "Fältet {} gäller noder och kan inte användas i edges",
+            wire_name(&c.field)
+        ));
+    }
+
+    let negated = matches!(c.op, Op::Neq | Op::NotIn | Op::NotExists);
+    if negated {
+        qb.push("NOT ");
+    }
+    qb.push("EXISTS (SELECT 1 FROM (");
+    push_source(qb, c, scope)?;
+    qb.push(") v WHERE v.x IS NOT NULL AND ");
+    push_predicate(qb, c)?;
+    qb.push(")");
+    Ok(())
+}
+
+/// A subquery yielding the field's values as text in column `x`.
+fn push_source(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    c: &Condition,
+    scope: Scope,
+) -> Result<(), String> {
+    let edges_of_node = "FROM edges e WHERE n.id IN (e.from_id, e.to_id)";
+    let claims = match scope {
+        Scope::Node => {
+            "FROM edges e JOIN edge_claims c ON c.edge_id = e.id \
+             WHERE n.id IN (e.from_id, e.to_id)"
+        }
+        Scope::Edge => "FROM edge_claims c WHERE c.edge_id = e.id",
+    };
+    let risk = |col: &str| {
+        format!("SELECT r.{col}::text AS x FROM node_risk r WHERE r.node_id = n.id")
+    };
+
+    match c.field {
+        Field::Kind => {
+            qb.push("SELECT n.kind AS x");
+        }
+        Field::Name => {
+            qb.push("SELECT n.name AS x");
+        }
+        Field::OwningDepartment => {
+            qb.push("SELECT n.owning_department::text AS x");
+        }
+        Field::Critical => {
+            qb.push("SELECT n.metadata ->> 'critical' AS x");
+        }
+        Field::Domain => {
+            qb.push("SELECT n.metadata ->> 'domain' AS x");
+        }
+        Field::Meta => {
+            qb.push("SELECT ");
+            push_json_value(qb, "n.metadata", c)?;
+            qb.push(" AS x");
+        }
+        Field::Pii => {
+            qb.push(risk("pii"));
+        }
+        Field::LegalRequirements => {
+            qb.push(risk("legal_requirements"));
+        }
+        Field::FinancialValue => {
+            qb.push(risk("financial_value"));
+        }
+        Field::BusinessCriticality => {
+            qb.push(risk("business_criticality"));
+        }
+        Field::InformationClass => {
+            qb.push(risk("information_class"));
+        }
+        Field::CriticalityScore => {
+            qb.push(risk("criticality_score"));
+        }
+        Field::Supplier => {
+            qb.push(
+                "SELECT s.name AS x FROM node_suppliers ns \
+                 JOIN suppliers s ON s.id = ns.supplier_id WHERE ns.node_id = n.id",
+            );
+        }
+        Field::SupplierType => {
+            qb.push(
+                "SELECT t.supplier_type::text AS x FROM node_supplier_types t \
+                 WHERE t.node_id = n.id",
+            );
+        }
+        Field::Owner => {
+            qb.push(
+                "SELECT o.name AS x FROM node_owners no \
+                 JOIN owners o ON o.id = no.owner_id WHERE no.node_id = n.id",
+            );
+        }
+        Field::DataDomain => {
+            qb.push(
+                "SELECT a.name AS x \
+                 FROM edge_typed_flows f \
+                 JOIN edge_typed_flow_domains fd ON fd.flow_id = f.id \
+                 JOIN LATERAL ( \
+                   WITH RECURSIVE up AS ( \
+                     SELECT d.name, d.parent_id FROM data_domains d WHERE d.id = fd.domain_id \
+                     UNION ALL \
+                     SELECT p.name, p.parent_id FROM data_domains p JOIN up ON p.id = up.parent_id \
+                   ) SELECT name FROM up \
+                 ) a ON TRUE ",
+            );
+            match scope {
+                Scope::Node => qb.push(
+                    "JOIN edges e ON e.id = f.edge_id WHERE n.id IN (e.from_id, e.to_id)",
+                ),
+                Scope::Edge => qb.push("WHERE f.edge_id = e.id"),
+            };
+        }
+        Field::EdgeKind => match scope {
+            Scope::Node => {
+                qb.push("SELECT e.kind AS x ").push(edges_of_node);
+            }
+            Scope::Edge => {
+                qb.push("SELECT e.kind AS x");
             }
-            g.conditions.iter().all(|c| matches_condition(node, c))
-        })
+        },
+        Field::EdgeMeta => {
+            qb.push("SELECT ");
+            push_json_value(qb, "e.metadata", c)?;
+            qb.push(" AS x");
+            if scope == Scope::Node {
+                qb.push(" ").push(edges_of_node);
+            }
+        }
+        Field::ClaimStatus => {
+            qb.push("SELECT c.status AS x ").push(claims);
+        }
+        Field::ClaimConfidence => {
+            qb.push("SELECT c.confidence::text AS x ").push(claims);
+        }
+        Field::ClaimSource => {
+            qb.push("SELECT c.source AS x ").push(claims);
+        }
     }
+    Ok(())
 }
 
-fn node_meta<'a>(node: &'a Node, key: &str) -> Option<&'a Value> {
-    match node.metadata.get(key) {
-        Some(v) if !v.is_null() => Some(v),
-        _ => None,
+/// `col ->> key`, or `col #>> path` for nested keys. A dotted `key` also
+/// tries the literal top-level key, which is what it meant before paths.
+fn push_json_value(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    col: &str,
+    c: &Condition,
+) -> Result<(), String> {
+    let key = c.key.as_deref().map(str::trim).unwrap_or("");
+    let path: Vec<String> = match &c.path {
+        Some(p) => p.iter().map(|s| s.trim().to_string()).collect(),
+        None => key.split('.').map(|s| s.trim().to_string()).collect(),
+    };
+    if path.is_empty() || path.iter().any(String::is_empty) {
+        return Err("key eller path saknas för metadatavillkor".to_string());
+    }
+
+    if path.len() == 1 {
+        qb.push(col).push(" ->> ").push_bind(path[0].clone());
+        return Ok(());
+    }
+
+    qb.push("COALESCE(")
+        .push(col)
+        .push(" #>> ")
+        .push_bind(path)
+        .push("::text[]");
+    if c.path.is_none() {
+        qb.push(", ").push(col).push(" ->> ").push_bind(key.to_string());
     }
+    qb.push(")");
+    Ok(())
 }
 
 fn as_string(v: &Value) -> Option<String> {
@@ -99,89 +391,121 @@ fn normalize_bool_str(s: &str) -> Option<bool> {
     }
 }
 
-fn equals_value(actual: &Value, expected: &Value) -> bool {
-    match (actual, expected) {
-        (Value::Bool(a), Value::Bool(e)) => a == e,
-        (Value::Number(a), Value::Number(e)) => a == e,
-        (Value::String(a), Value::String(e)) => a == e,
-        (Value::Bool(a), Value::String(e)) => {
-            normalize_bool_str(e).map(|b| b == *a).unwrap_or(false)
-        }
-        (Value::String(a), Value::Bool(e)) => {
-            normalize_bool_str(a).map(|b| b == *e).unwrap_or(false)
-        }
-        _ => as_string(actual)
-            .zip(as_string(expected))
-            .map(|(a, e)| a == e)
-            .unwrap_or(false),
+fn bool_list(b: bool) -> &'static str {
+    if b {
+        TRUTHY
+    } else {
+        FALSY
     }
 }
 
-fn matches_condition(node: &Node, c: &Condition) -> bool {
-    match c.field {
-        Field::Critical => {
-            let actual = node_meta(node, "critical");
-            matches_by_op(actual, c)
-        }
-        Field::Domain => {
-            let actual = node_meta(node, "domain");
-            matches_by_op(actual, c)
+/// Strings compare exactly (plus ja/nej-style booleans on boolean
+/// fields), numbers numerically.
+fn push_equals(qb: &mut QueryBuilder<'_, Postgres>, expected: &Value, boolean: bool) {
+    match expected {
+        Value::String(s) => match normalize_bool_str(s).filter(|_| boolean) {
+            Some(b) => {
+                qb.push("(v.x = ")
+                    .push_bind(s.clone())
+                    .push(" OR lower(v.x) IN ")
+                    .push(bool_list(b))
+                    .push(")");
+            }
+            None => {
+                qb.push("v.x = ").push_bind(s.clone());
+            }
+        },
+        Value::Bool(b) => {
+            qb.push("lower(v.x) IN ").push(bool_list(*b));
         }
-        Field::Kind => {
-            let actual = Value::String(node.kind.clone());
-            matches_value_by_op(Some(&actual), c)
+        Value::Number(n) => {
+            qb.push("(v.x = ")
+                .push_bind(n.to_string())
+                .push(" OR ")
+                .push(NUMERIC)
+                .push(" = ")
+                .push_bind(n.to_string())
+                .push("::numeric)");
         }
-        Field::Meta => {
-            let key = c.key.as_deref().unwrap_or("").trim();
-            if key.is_empty() {
-                return false;
-            }
-            let actual = node_meta(node, key);
-            matches_by_op(actual, c)
+        _ => {
+            qb.push("FALSE");
         }
     }
 }
 
-fn matches_by_op(actual: Option<&Value>, c: &Condition) -> bool {
-    matches_value_by_op(actual, c)
+fn numeric_operand(c: &Condition) -> Result<String, String> {
+    let v = c
+        .value
+        .as_ref()
+        .ok_or_else(|| format!("{} kräver value", wire_name(&c.op)))?;
+    let s = match v {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) if s.trim().parse::<f64>().is_ok() => s.trim().to_string(),
+        _ => return Err(format!("{} kräver ett numeriskt value", wire_name(&c.op))),
+    };
+    Ok(s)
 }
 
-fn matches_value_by_op(actual: Option<&Value>, c: &Condition) -> bool {
+fn push_predicate(qb: &mut QueryBuilder<'_, Postgres>, c: &Condition) -> Result<(), String> {
     match c.op {
-        Op::Exists => actual.is_some(),
-        Op::NotExists => actual.is_none(),
-        Op::Eq => match (actual, c.value.as_ref()) {
-            (Some(a), Some(e)) => equals_value(a, e),
-            _ => false,
-        },
-        Op::Neq => match (actual, c.value.as_ref()) {
-            (Some(a), Some(e)) => !equals_value(a, e),
-            (None, Some(_)) => true,
-            _ => false,
-        },
-        Op::In => match (actual, c.values.as_ref()) {
-            (Some(a), Some(vs)) => vs.iter().any(|e| equals_value(a, e)),
-            _ => false,
-        },
-        Op::NotIn => match (actual, c.values.as_ref()) {
-            (Some(a), Some(vs)) => !vs.iter().any(|e| equals_value(a, e)),
-            (None, Some(_)) => true,
-            _ => false,
-        },
+        Op::Exists | Op::NotExists => {
+            qb.push("TRUE");
+        }
+        // Negated, a predicate that is never true would match every row.
+        Op::Eq | Op::Neq => {
+            let v = c
+                .value
+                .as_ref()
+                .ok_or_else(|| format!("{} kräver value", wire_name(&c.op)))?;
+            push_equals(qb, v, c.field.is_boolean());
+        }
+        Op::In | Op::NotIn => {
+            let values = c.values.as_deref().unwrap_or(&[]);
+            if values.is_empty() {
+                return Err(format!(
+                    "{} kräver minst ett värde i values",
+                    wire_name(&c.op)
+                ));
+            }
+            qb.push("(");
+            for (i, v) in values.iter().enumerate() {
+                if i > 0 {
+                    qb.push(" OR ");
+                }
+                push_equals(qb, v, c.field.is_boolean());
+            }
+            qb.push(")");
+        }
         Op::Contains | Op::StartsWith | Op::EndsWith => {
-            let a = actual.and_then(as_string).unwrap_or_default();
             let e = c.value.as_ref().and_then(as_string).unwrap_or_default();
-            if a.is_empty() || e.is_empty() {
-                return false;
+            if e.is_empty() {
+                qb.push("FALSE");
+                return Ok(());
             }
-            let a_l = a.to_lowercase();
-            let e_l = e.to_lowercase();
+            let e = e.to_lowercase();
             match c.op {
-                Op::Contains => a_l.contains(&e_l),
-                Op::StartsWith => a_l.starts_with(&e_l),
-                Op::EndsWith => a_l.ends_with(&e_l),
-                _ => false,
-            }
+                Op::Contains => qb.push("strpos(lower(v.x), ").push_bind(e).push(") > 0"),
+                Op::StartsWith => qb.push("starts_with(lower(v.x), ").push_bind(e).push(")"),
+                _ => qb
+                    .push("right(lower(v.x), ")
+                    .push_bind(e.chars().count() as i32)
+                    .push(") = ")
+                    .push_bind(e),
+            };
+        }
+        Op::Gt | Op::Gte | Op::Lt | Op::Lte => {
+            let operand = numeric_operand(c)?;
+            let cmp = match c.op {
+                Op::Gt => " > ",
+                Op::Gte => " >= ",
+                Op::Lt => " < ",
+                _ => " <= ",
+            };
+            qb.push(NUMERIC)
+                .push(cmp)
+                .push_bind(operand)
+                .push("::numeric");
         }
     }
+    Ok(())
 }
@@ -0,0 +1,358 @@
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::response::Response;
+use axum::Json;
+use rust_xlsxwriter::{Color, Format, FormatAlign, Workbook};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::OffsetDateTime;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::routes::AppState;
+
+use super::util::{
+    csv_escape, csv_headers_csv, csv_opt, db_error, set_width_range, xlsx_error, xlsx_response,
+};
+
+/// Domain whose subtree is reported when no `root` is given.
+const DEFAULT_ROOT: &str = "Personaldata";
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GdprQuery {
+    /// Rotdomän för rapporten; standard är "Personaldata".
+    #[serde(default)]
+    pub root: Option<Uuid>,
+}
+
+/// One system handling one personal-data domain.
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct GdprRow {
+    pub domain_id: Uuid,
+    pub domain: String,
+    /// `Personaldata / Kansliga personuppgifter / ...`
+    pub domain_path: String,
+    pub node_id: Uuid,
+    pub system: String,
+    pub system_kind: String,
+    /// Data leaves this system on at least one flow.
+    pub sends: bool,
+    pub receives: bool,
+    pub sends_to: Vec<String>,
+    pub receives_from: Vec<String>,
+    pub suppliers: Vec<String>,
+    pub owners: Vec<String>,
+    pub owning_department: Option<String>,
+    pub information_class: Option<String>,
+    pub pii: Option<bool>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GdprRegister {
+    pub root_id: Uuid,
+    pub root: String,
+    pub rows: Vec<GdprRow>,
+}
+
+/// Typed flows tagged with a domain in the subtree, seen from each end of
+/// the edge. `fran` carries data from `from_id` to `to_id`, `till` the
+/// other way.
+const REGISTER_SQL: &str = r#"
+WITH RECURSIVE pd AS (
+    SELECT d.id, d.name, d.name AS path
+    FROM data_domains d
+    WHERE d.id = $1
+    UNION ALL
+    SELECT c.id, c.name, pd.path || ' / ' || c.name
+    FROM data_domains c
+    JOIN pd ON c.parent_id = pd.id
+),
+flows AS (
+    SELECT fd.domain_id, f.direction::text AS direction, e.from_id, e.to_id
+    FROM pd
+    JOIN edge_typed_flow_domains fd ON fd.domain_id = pd.id
+    JOIN edge_typed_flows f ON f.id = fd.flow_id
+    JOIN edges e ON e.id = f.edge_id
+),
+sides AS (
+    SELECT domain_id, from_id AS node_id, to_id AS other_id,
+           direction IN ('fran', 'bidirectional') AS sends,
+           direction IN ('till', 'bidirectional') AS receives
+    FROM flows
+    UNION ALL
+    SELECT domain_id, to_id, from_id,
+           direction IN ('till', 'bidirectional'),
+           direction IN ('fran', 'bidirectional')
+    FROM flows
+)
+SELECT
+    pd.id AS domain_id,
+    pd.name AS domain,
+    pd.path AS domain_path,
+    n.id AS node_id,
+    n.name AS system,
+    n.kind AS system_kind,
+    bool_or(s.sends) AS sends,
+    bool_or(s.receives) AS receives,
+    COALESCE(array_agg(DISTINCT o.name ORDER BY o.name) FILTER (WHERE s.sends), '{}') AS sends_to,
+    COALESCE(array_agg(DISTINCT o.name ORDER BY o.name) FILTER (WHERE s.receives), '{}')
+        AS receives_from,
+    ARRAY(
+        SELECT sp.name FROM node_suppliers ns
+        JOIN suppliers sp ON sp.id = ns.supplier_id
+        WHERE ns.node_id = n.id
+        ORDER BY sp.name
+    ) AS suppliers,
+    ARRAY(
+        SELECT ow.name FROM node_owners no
+        JOIN owners ow ON ow.id = no.owner_id
+        WHERE no.node_id = n.id
+        ORDER BY ow.name
+    ) AS owners,
+    n.owning_department::text AS owning_department,
+    r.information_class::text AS information_class,
+    r.pii
+FROM sides s
+JOIN pd ON pd.id = s.domain_id
+JOIN nodes n ON n.id = s.node_id AND n.deleted_at IS NULL
+JOIN nodes o ON o.id = s.other_id AND o.deleted_at IS NULL
+LEFT JOIN node_risk r ON r.node_id = n.id
+GROUP BY pd.id, pd.name, pd.path, n.id, r.information_class, r.pii
+ORDER BY pd.path, n.name, n.id
+"#;
+
+async fn load_register(pool: &PgPool, root: Option<Uuid>) -> Result<GdprRegister, ApiError> {
+    let root_row: Option<(Uuid, String)> = match root {
+        Some(id) => sqlx::query_as("SELECT id, name FROM data_domains WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .map_err(db_error)?,
+        None => sqlx::query_as(
+            "SELECT id, name FROM data_domains WHERE name = $1 ORDER BY sort_order, id LIMIT 1",
+        )
+        .bind(DEFAULT_ROOT)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_error)?,
+    };
+    let (root_id, root) = root_row.ok_or_else(|| ApiError::not_found("Datadomänen finns inte"))?;
+
+    let rows = sqlx::query_as::<_, GdprRow>(REGISTER_SQL)
+        .bind(root_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+    Ok(GdprRegister {
+        root_id,
+        root,
+        rows,
+    })
+}
+
+fn role_sv(r: &GdprRow) -> &'static str {
+    match (r.sends, r.receives) {
+        (true, true) => "Skickar och tar emot",
+        (true, false) => "Skickar",
+        (false, true) => "Tar emot",
+        (false, false) => "",
+    }
+}
+
+fn yes_no(b: Option<bool>) -> &'static str {
+    match b {
+        Some(true) => "Ja",
+        Some(false) => "Nej",
+        None => "",
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/gdpr.json",
+    params(GdprQuery),
+    responses(
+        (status = 200, body = GdprRegister),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub(super) async fn export_gdpr_json(
+    State(state): State<AppState>,
+    Query(q): Query<GdprQuery>,
+) -> Result<Json<GdprRegister>, ApiError> {
+    Ok(Json(load_register(&state.pool, q.root).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/gdpr.csv",
+    params(GdprQuery),
+    responses(
+        (status = 200, description = "CSV", content_type = "text/csv", body = String),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub(super) async fn export_gdpr_csv(
+    State(state): State<AppState>,
+    Query(q): Query<GdprQuery>,
+) -> Result<(HeaderMap, String), ApiError> {
+    let register = load_register(&state.pool, q.root).await?;
+
+    let mut out = String::new();
+    out.push_str("domain_id,domain,domain_path,node_id,system,system_kind,sends,receives,sends_to,receives_from,suppliers,owners,owning_department,information_class,pii\n");
+
+    for r in register.rows {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            r.domain_id,
+            csv_escape(&r.domain),
+            csv_escape(&r.domain_path),
+            r.node_id,
+            csv_escape(&r.system),
+            csv_escape(&r.system_kind),
+            r.sends,
+            r.receives,
+            csv_escape(&r.sends_to.join("; ")),
+            csv_escape(&r.receives_from.join("; ")),
+            csv_escape(&r.suppliers.join("; ")),
+            csv_escape(&r.owners.join("; ")),
+            csv_opt(&r.owning_department),
+            csv_opt(&r.information_class),
+            r.pii.map(|b| b.to_string()).unwrap_or_default(),
+        ));
+    }
+
+    Ok((csv_headers_csv(), out))
+}
+
+#[utoipa::path(
+    get,
+    path = "/gdpr.xlsx",
+    params(GdprQuery),
+    responses(
+        (
+            status = 200,
+            description = "Excel-arbetsbok med register över behandlingar (art. 30)",
+            content_type = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+        ),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub(super) async fn export_gdpr_xlsx(
+    State(state): State<AppState>,
+    Query(q): Query<GdprQuery>,
+) -> Result<Response, ApiError> {
+    let now = OffsetDateTime::now_utc();
+    let exported_at = now
+        .format(&Rfc3339)
+        .unwrap_or_else(|_| "1970-01-01T00:00:00Z".to_string());
+    let date_for_filename = now
+        .format(&format_description!("[day]_[month]_[year repr:last_two]"))
+        .unwrap_or_else(|_| "01_01_70".to_string());
+    let filename = format!("KEAB_SoR_GDPR_{}.xlsx", date_for_filename);
+
+    let register = load_register(&state.pool, q.root).await?;
+
+    let mut wb = Workbook::new();
+
+    let title_fmt = Format::new()
+        .set_bold()
+        .set_font_size(16.0)
+        .set_font_color(Color::White)
+        .set_background_color(Color::RGB(0x007A3D))
+        .set_align(FormatAlign::Center)
+        .set_align(FormatAlign::VerticalCenter);
+
+    let sub_fmt = Format::new()
+        .set_font_color(Color::RGB(0x005F2E))
+        .set_bold()
+        .set_align(FormatAlign::Left);
+
+    let header_fmt = Format::new()
+        .set_bold()
+        .set_background_color(Color::RGB(0xE6F4EA));
+
+    let headers = [
+        "kategori av personuppgifter",
+        "sökväg",
+        "system",
+        "typ",
+        "roll",
+        "skickar till",
+        "tar emot från",
+        "leverantörer (biträden)",
+        "ägare",
+        "ansvarig avdelning",
+        "informationsklass",
+        "personuppgifter",
+    ];
+
+    let ws = wb.add_worksheet();
+    ws.set_name("Register").map_err(xlsx_error)?;
+
+    ws.set_row_height(0, 24.0).map_err(xlsx_error)?;
+    ws.merge_range(
+        0,
+        0,
+        0,
+        (headers.len() - 1) as u16,
+        "KEAB SoR — Register över behandlingar av personuppgifter",
+        &title_fmt,
+    )
+    .map_err(xlsx_error)?;
+    ws.write_string_with_format(
+        1,
+        0,
+        format!("Exporterad: {} · Rotdomän: {}", exported_at, register.root),
+        &sub_fmt,
+    )
+    .map_err(xlsx_error)?;
+
+    let header_row: u32 = 3;
+    ws.set_freeze_panes(header_row + 1, 0).map_err(xlsx_error)?;
+
+    for (c, h) in headers.iter().enumerate() {
+        ws.write_string_with_format(header_row, c as u16, *h, &header_fmt)
+            .map_err(xlsx_error)?;
+    }
+
+    for (i, r) in register.rows.iter().enumerate() {
+        let row = header_row + 1 + i as u32;
+        let cells = [
+            r.domain.as_str(),
+            r.domain_path.as_str(),
+            r.system.as_str(),
+            r.system_kind.as_str(),
+            role_sv(r),
+            &r.sends_to.join(", "),
+            &r.receives_from.join(", "),
+            &r.suppliers.join(", "),
+            &r.owners.join(", "),
+            r.owning_department.as_deref().unwrap_or(""),
+            r.information_class.as_deref().unwrap_or(""),
+            yes_no(r.pii),
+        ];
+        for (c, v) in cells.iter().enumerate() {
+            ws.write_string(row, c as u16, *v).map_err(xlsx_error)?;
+        }
+    }
+
+    set_width_range(ws, 0, 0, 28.0).map_err(xlsx_error)?;
+    set_width_range(ws, 1, 1, 48.0).map_err(xlsx_error)?;
+    set_width_range(ws, 2, 2, 28.0).map_err(xlsx_error)?;
+    set_width_range(ws, 3, 4, 18.0).map_err(xlsx_error)?;
+    set_width_range(ws, 5, 8, 32.0).map_err(xlsx_error)?;
+    set_width_range(ws, 9, 11, 18.0).map_err(xlsx_error)?;
+
+    let last_row = header_row + register.rows.len() as u32;
+    ws.autofilter(header_row, 0, last_row, (headers.len() - 1) as u16)
+        .map_err(xlsx_error)?;
+
+    let bytes = wb.save_to_buffer().map_err(xlsx_error)?;
+    Ok(xlsx_response(bytes, &filename))
+}
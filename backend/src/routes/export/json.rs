@@ -1,18 +1,21 @@
+use crate::auth::AuthActor;
+use crate::error::ApiError;
 use crate::models::{Edge, EdgeClaim, Node};
 use crate::routes::edges::flows::load_flow_map_for_claim_ids;
 use crate::routes::AppState;
 use axum::extract::{Query, State};
-use axum::http::StatusCode;
+use axum::Extension;
 use axum::Json;
 use serde::Serialize;
-use sqlx::Row;
+use sqlx::{Postgres, QueryBuilder, Row};
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-use super::util::{EdgeScope, ExportRequest};
+use super::util::{load_filtered_nodes, push_edge_where, ExportRequest};
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct ExportSnapshot {
     version: i32,
     exported_at: String,
@@ -20,14 +23,14 @@ struct ExportSnapshot {
     edges: Vec<ExportEdgeRow>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct ExportEdgeRow {
     edge: Edge,
     current_claim: Option<EdgeClaim>,
     flows: Vec<ExportFlowRow>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct ExportFlowRow {
     flow_id: Option<Uuid>,
     claim_id: Option<Uuid>,
@@ -39,114 +42,65 @@ struct ExportFlowRow {
     created_at: String,
 }
 
+#[utoipa::path(
+    get,
+    path = "/snapshot.json",
+    params(ExportRequest),
+    responses(
+        (status = 200, body = ExportSnapshot),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub(super) async fn export_snapshot_json(
     State(state): State<AppState>,
+    actor: Option<Extension<AuthActor>>,
     Query(req): Query<ExportRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     let exported_at = OffsetDateTime::now_utc()
         .format(&Rfc3339)
         .unwrap_or_else(|_| "1970-01-01T00:00:00Z".to_string());
 
-    let spec = req
-        .filter_spec()
-        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let spec = req.resolve_filter_spec(&state.pool, actor.as_deref()).await?;
 
     let include_edges = req.include_edges();
     let include_claims = req.include_claims();
     let include_flows = req.include_flows() && include_claims;
 
-    let all_nodes: Vec<Node> = sqlx::query_as::<_, Node>(
-        r#"
-        SELECT *
-        FROM nodes
-        WHERE deleted_at IS NULL
-        ORDER BY kind, name, id
-        "#,
-    )
-    .fetch_all(&state.pool)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    let nodes: Vec<Node> = all_nodes
-        .into_iter()
-        .filter(|n| spec.matches_node(n))
-        .collect();
+    let nodes = load_filtered_nodes(&state.pool, &spec).await?;
 
-    let node_ids: Option<Vec<Uuid>> = if spec.is_empty() {
-        None
-    } else {
+    let node_ids: Option<Vec<Uuid>> = if spec.has_node_filter() {
         Some(nodes.iter().map(|n| n.id).collect())
+    } else {
+        None
     };
 
     let mut edges_out: Vec<ExportEdgeRow> = vec![];
 
     if include_edges {
-        let edge_rows = match (&node_ids, req.edge_scope()) {
-            (Some(ids), EdgeScope::Both) => sqlx::query(
-                r#"
-                    SELECT
-                      e.*,
-                      c.id AS current_claim_id
-                    FROM edges e
-                    LEFT JOIN LATERAL (
-                      SELECT id
-                      FROM edge_claims
-                      WHERE edge_id = e.id
-                        AND status IN ('active', 'needs_review')
-                      ORDER BY created_at DESC
-                      LIMIT 1
-                    ) c ON TRUE
-                    WHERE e.from_id = ANY($1) AND e.to_id = ANY($1)
-                    ORDER BY e.kind, e.from_id, e.to_id, e.id
-                    "#,
-            )
-            .bind(ids)
+        let mut qb = QueryBuilder::<Postgres>::new(
+            r#"
+            SELECT
+              e.*,
+              c.id AS current_claim_id
+            FROM edges e
+            LEFT JOIN LATERAL (
+              SELECT id
+              FROM edge_claims
+              WHERE edge_id = e.id
+                AND status IN ('active', 'needs_review')
+              ORDER BY created_at DESC
+              LIMIT 1
+            ) c ON TRUE
+            "#,
+        );
+        push_edge_where(&mut qb, node_ids.as_deref(), req.edge_scope(), &spec)?;
+        qb.push(" ORDER BY e.kind, e.from_id, e.to_id, e.id");
+        let edge_rows = qb
+            .build()
             .fetch_all(&state.pool)
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
-            (Some(ids), EdgeScope::Any) => sqlx::query(
-                r#"
-                    SELECT
-                      e.*,
-                      c.id AS current_claim_id
-                    FROM edges e
-                    LEFT JOIN LATERAL (
-                      SELECT id
-                      FROM edge_claims
-                      WHERE edge_id = e.id
-                        AND status IN ('active', 'needs_review')
-                      ORDER BY created_at DESC
-                      LIMIT 1
-                    ) c ON TRUE
-                    WHERE e.from_id = ANY($1) OR e.to_id = ANY($1)
-                    ORDER BY e.kind, e.from_id, e.to_id, e.id
-                    "#,
-            )
-            .bind(ids)
-            .fetch_all(&state.pool)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
-            (None, _) => sqlx::query(
-                r#"
-                    SELECT
-                      e.*,
-                      c.id AS current_claim_id
-                    FROM edges e
-                    LEFT JOIN LATERAL (
-                      SELECT id
-                      FROM edge_claims
-                      WHERE edge_id = e.id
-                        AND status IN ('active', 'needs_review')
-                      ORDER BY created_at DESC
-                      LIMIT 1
-                    ) c ON TRUE
-                    ORDER BY e.kind, e.from_id, e.to_id, e.id
-                    "#,
-            )
-            .fetch_all(&state.pool)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
-        };
+            .map_err(ApiError::internal)?;
 
         let claim_ids: Vec<Uuid> = if include_claims {
             edge_rows
@@ -176,7 +130,7 @@ pub(super) async fn export_snapshot_json(
             .bind(&claim_ids)
             .fetch_all(&state.pool)
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .map_err(ApiError::internal)?
         } else {
             vec![]
         };
@@ -197,6 +151,7 @@ pub(super) async fn export_snapshot_json(
                 metadata: r.get("metadata"),
                 created_at: r.get("created_at"),
                 updated_at: r.get("updated_at"),
+                version: r.get("version"),
             };
 
             let current_claim_id: Option<Uuid> = if include_claims {
@@ -285,5 +240,25 @@ pub(super) async fn export_snapshot_json(
         edges: edges_out,
     };
 
-    Ok(Json(serde_json::to_value(snapshot).unwrap()))
+    let body = serde_json::to_value(snapshot).map_err(ApiError::internal)?;
+    Ok(Json(body))
+}
+
+/// Same document as `/snapshot.json`; kept for older clients.
+#[utoipa::path(
+    get,
+    path = "/graph.json",
+    params(ExportRequest),
+    responses(
+        (status = 200, body = ExportSnapshot),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub(super) async fn export_graph_json(
+    state: State<AppState>,
+    actor: Option<Extension<AuthActor>>,
+    req: Query<ExportRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    export_snapshot_json(state, actor, req).await
 }
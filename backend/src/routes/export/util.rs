@@ -1,4 +1,7 @@
-use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue};
+use axum::response::Response;
+use bytes::Bytes;
 use rust_xlsxwriter::XlsxError;
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
@@ -43,15 +46,32 @@ pub fn csv_headers_csv() -> HeaderMap {
     h
 }
 
-pub fn xlsx_error(e: XlsxError) -> (StatusCode, String) {
-    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+pub fn xlsx_response(bytes: Vec<u8>, filename: &str) -> Response {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        ),
+    );
+
+    let cd = format!("attachment; filename=\"{}\"", filename);
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&cd).unwrap_or_else(|_| HeaderValue::from_static("attachment")),
+    );
+
+    let mut resp = Response::new(Body::from(Bytes::from(bytes)));
+    *resp.headers_mut() = headers;
+    resp
+}
+
+pub fn xlsx_error(e: XlsxError) -> ApiError {
+    ApiError::internal(e)
 }
 
-pub fn db_error<E: std::fmt::Display>(e: E) -> (StatusCode, String) {
-    (
-        StatusCode::INTERNAL_SERVER_ERROR,
-        format!("error returned from database: {e}"),
-    )
+pub fn db_error<E: std::fmt::Display>(e: E) -> ApiError {
+    ApiError::internal(format!("error returned from database: {e}"))
 }
 
 pub fn set_width_range(
@@ -116,10 +136,18 @@ pub fn meta_sla(meta: &serde_json::Value) -> String {
 
 use base64::Engine;
 use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use sqlx::{PgPool, Postgres, QueryBuilder};
 
 use super::filter_spec::FilterSpec;
 
-#[derive(Debug, Clone, Copy, Deserialize)]
+use crate::auth::AuthActor;
+use crate::error::ApiError;
+use crate::models::Node;
+use crate::routes::saved_searches::load_visible;
+
+#[derive(Debug, Clone, Copy, Deserialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum EdgeScope {
     Both,
@@ -132,10 +160,15 @@ impl Default for EdgeScope {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ExportRequest {
+    /// FilterSpec som JSON, base64url-kodad utan padding.
     #[serde(default)]
     pub f: Option<String>,
+    /// Sparad sökning vars filter används; kan inte kombineras med `f`.
+    #[serde(default)]
+    pub saved: Option<Uuid>,
 
     #[serde(default)]
     pub include_edges: Option<bool>,
@@ -177,11 +210,77 @@ impl ExportRequest {
     }
 }
 
+impl ExportRequest {
+    /// `f`, or the stored filter of the saved search `saved`.
+    pub async fn resolve_filter_spec(
+        &self,
+        pool: &PgPool,
+        actor: Option<&AuthActor>,
+    ) -> Result<FilterSpec, ApiError> {
+        let Some(id) = self.saved else {
+            return self.filter_spec().map_err(ApiError::bad_request);
+        };
+        if self.f.as_deref().is_some_and(|f| !f.trim().is_empty()) {
+            return Err(ApiError::validation(
+                "saved",
+                "Ange antingen f eller saved, inte båda",
+            ));
+        }
+
+        let saved = load_visible(pool, id, actor).await?;
+        saved.filter.map(|f| f.0).ok_or_else(|| {
+            ApiError::validation("saved", "Den sparade sökningen har inget filter")
+        })
+    }
+}
+
+/// Non-deleted nodes matching the spec's node groups, in export order.
+pub async fn load_filtered_nodes(pool: &PgPool, spec: &FilterSpec) -> Result<Vec<Node>, ApiError> {
+    let mut qb = QueryBuilder::<Postgres>::new(
+        "SELECT n.* FROM nodes n WHERE n.deleted_at IS NULL AND ",
+    );
+    spec.push_node_filter(&mut qb).map_err(ApiError::bad_request)?;
+    qb.push(" ORDER BY n.kind, n.name, n.id");
+
+    qb.build_query_as::<Node>()
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)
+}
+
+/// `WHERE` clause for an export query over `edges e`: edges touching the
+/// exported nodes (per `scope`), narrowed by the spec's edge groups.
+pub fn push_edge_where(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    node_ids: Option<&[Uuid]>,
+    scope: EdgeScope,
+    spec: &FilterSpec,
+) -> Result<(), ApiError> {
+    qb.push(" WHERE ");
+    if let Some(ids) = node_ids {
+        let join = match scope {
+            EdgeScope::Both => " AND ",
+            EdgeScope::Any => " OR ",
+        };
+        qb.push("(e.from_id = ANY(")
+            .push_bind(ids.to_vec())
+            .push(")")
+            .push(join)
+            .push("e.to_id = ANY(")
+            .push_bind(ids.to_vec())
+            .push(")) AND ");
+    }
+    spec.push_edge_filter(qb).map_err(ApiError::bad_request)
+}
+
 pub fn decode_filter_spec(encoded: &str) -> Result<FilterSpec, String> {
     let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
         .decode(encoded.as_bytes())
         .map_err(|e| format!("invalid filter encoding: {e}"))?;
 
     let s = String::from_utf8(bytes).map_err(|e| format!("invalid filter utf8: {e}"))?;
-    serde_json::from_str::<FilterSpec>(&s).map_err(|e| format!("invalid filter json: {e}"))
+    let spec =
+        serde_json::from_str::<FilterSpec>(&s).map_err(|e| format!("invalid filter json: {e}"))?;
+    spec.validate()?;
+    Ok(spec)
 }
@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+
+use axum::extract::{Query, State};
+use axum::response::Response;
+use axum::Json;
+use rust_xlsxwriter::{Color, Format, FormatAlign, Workbook};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::OffsetDateTime;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::routes::AppState;
+
+use super::util::{db_error, set_width_range, xlsx_error, xlsx_response};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct VendorReportQuery {
+    /// Hur många steg bakåt beroenden följs; standard 3, max 10.
+    #[serde(default)]
+    pub max_depth: Option<i32>,
+    #[serde(default)]
+    pub min_confidence: Option<i16>,
+    #[serde(default)]
+    pub include_needs_review: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+pub struct VendorSystem {
+    pub node_id: Uuid,
+    pub name: String,
+    pub kind: String,
+    /// 0 for systems delivered by the supplier, otherwise hops to one.
+    pub depth: i32,
+    pub business_criticality: Option<String>,
+    pub criticality_score: Option<f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VendorEntry {
+    /// `supplier` (row in `suppliers`) or `vendor_node` (node of kind vendor).
+    pub source: String,
+    pub id: Uuid,
+    pub name: String,
+    pub supplier_types: Vec<String>,
+    /// Data domains flowing into the supplier's systems.
+    pub data_domains: Vec<String>,
+    pub system_count: i64,
+    pub high_criticality_count: i64,
+    pub systems: Vec<VendorSystem>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VendorReport {
+    pub max_depth: i32,
+    pub min_confidence: i16,
+    pub include_needs_review: bool,
+    pub vendors: Vec<VendorEntry>,
+}
+
+#[derive(sqlx::FromRow)]
+struct VendorRow {
+    source: String,
+    id: Uuid,
+    name: String,
+    supplier_types: Vec<String>,
+    data_domains: Vec<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct DependentRow {
+    source: String,
+    vendor_id: Uuid,
+    #[sqlx(flatten)]
+    system: VendorSystem,
+}
+
+/// Where each vendor attaches to the graph: a supplier at the nodes it
+/// delivers, a vendor node at itself.
+const ANCHORS: &str = r#"
+anchors AS (
+    SELECT 'supplier' AS source, ns.supplier_id AS vendor_id, ns.node_id
+    FROM node_suppliers ns
+    JOIN nodes n ON n.id = ns.node_id AND n.deleted_at IS NULL
+    UNION ALL
+    SELECT 'vendor_node', n.id, n.id
+    FROM nodes n
+    WHERE n.kind = 'vendor' AND n.deleted_at IS NULL
+)
+"#;
+
+/// Edges with a claim the report counts (`$1` statuses, `$2` minimum
+/// confidence).
+const OK_EDGES: &str = r#"
+ok_edges AS (
+    SELECT e.id, e.from_id, e.to_id
+    FROM edges e
+    WHERE EXISTS (
+        SELECT 1 FROM edge_claims c
+        WHERE c.edge_id = e.id AND c.status = ANY($1) AND c.confidence >= $2
+    )
+)
+"#;
+
+async fn load_report(pool: &PgPool, q: &VendorReportQuery) -> Result<VendorReport, ApiError> {
+    let max_depth = q.max_depth.unwrap_or(3).clamp(0, 10);
+    let min_confidence = q.min_confidence.unwrap_or(0);
+    let include_needs_review = q.include_needs_review.unwrap_or(true);
+    let statuses: Vec<&str> = if include_needs_review {
+        vec!["active", "needs_review"]
+    } else {
+        vec!["active"]
+    };
+
+    let vendors_sql = format!(
+        r#"
+        WITH {ANCHORS}, {OK_EDGES}
+        SELECT
+            v.source,
+            v.id,
+            v.name,
+            ARRAY(
+                SELECT DISTINCT t.supplier_type::text
+                FROM anchors a
+                JOIN node_supplier_types t ON t.node_id = a.node_id
+                WHERE a.source = v.source AND a.vendor_id = v.id
+                ORDER BY 1
+            ) AS supplier_types,
+            ARRAY(
+                SELECT DISTINCT d.name
+                FROM anchors a
+                JOIN ok_edges e ON a.node_id IN (e.from_id, e.to_id)
+                JOIN nodes o
+                  ON o.id = CASE WHEN e.from_id = a.node_id THEN e.to_id ELSE e.from_id END
+                 AND o.deleted_at IS NULL
+                JOIN edge_typed_flows f ON f.edge_id = e.id
+                JOIN edge_typed_flow_domains fd ON fd.flow_id = f.id
+                JOIN data_domains d ON d.id = fd.domain_id
+                WHERE a.source = v.source AND a.vendor_id = v.id
+                  AND (
+                    (f.direction = 'fran' AND e.to_id = a.node_id)
+                    OR (f.direction = 'till' AND e.from_id = a.node_id)
+                    OR f.direction = 'bidirectional'
+                  )
+                ORDER BY 1
+            ) AS data_domains
+        FROM (
+            SELECT 'supplier' AS source, s.id, s.name FROM suppliers s
+            UNION ALL
+            SELECT 'vendor_node', n.id, n.name
+            FROM nodes n
+            WHERE n.kind = 'vendor' AND n.deleted_at IS NULL
+        ) v
+        "#
+    );
+
+    let vendor_rows = sqlx::query_as::<_, VendorRow>(&vendors_sql)
+        .bind(&statuses)
+        .bind(min_confidence)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+    // Walk upstream (`to_id` -> `from_id`) from the anchors: whatever
+    // reaches a supplier's system depends on the supplier. Deleted nodes
+    // end the walk rather than pass it on.
+    let dependents_sql = format!(
+        r#"
+        WITH RECURSIVE {ANCHORS}, {OK_EDGES},
+        walk AS (
+            SELECT source, vendor_id, node_id, 0 AS depth FROM anchors
+            UNION
+            SELECT w.source, w.vendor_id, e.from_id, w.depth + 1
+            FROM walk w
+            JOIN ok_edges e ON e.to_id = w.node_id
+            JOIN nodes f ON f.id = e.from_id AND f.deleted_at IS NULL
+            WHERE w.depth < $3
+        )
+        SELECT
+            w.source,
+            w.vendor_id,
+            n.id AS node_id,
+            n.name,
+            n.kind,
+            min(w.depth) AS depth,
+            r.business_criticality::text AS business_criticality,
+            r.criticality_score::float8 AS criticality_score
+        FROM walk w
+        JOIN nodes n ON n.id = w.node_id AND n.deleted_at IS NULL
+        LEFT JOIN node_risk r ON r.node_id = n.id
+        WHERE NOT (w.source = 'vendor_node' AND w.node_id = w.vendor_id)
+        GROUP BY w.source, w.vendor_id, n.id, r.business_criticality, r.criticality_score
+        ORDER BY min(w.depth), n.name, n.id
+        "#
+    );
+
+    let dependents = sqlx::query_as::<_, DependentRow>(&dependents_sql)
+        .bind(&statuses)
+        .bind(min_confidence)
+        .bind(max_depth)
+        .fetch_all(pool)
+        .await
+        .map_err(db_error)?;
+
+    let mut systems: HashMap<(String, Uuid), Vec<VendorSystem>> = HashMap::new();
+    for d in dependents {
+        systems
+            .entry((d.source, d.vendor_id))
+            .or_default()
+            .push(d.system);
+    }
+
+    let mut vendors: Vec<VendorEntry> = vendor_rows
+        .into_iter()
+        .map(|v| {
+            let systems = systems
+                .remove(&(v.source.clone(), v.id))
+                .unwrap_or_default();
+            VendorEntry {
+                system_count: systems.len() as i64,
+                high_criticality_count: systems
+                    .iter()
+                    .filter(|s| s.business_criticality.as_deref() == Some("high"))
+                    .count() as i64,
+                source: v.source,
+                id: v.id,
+                name: v.name,
+                supplier_types: v.supplier_types,
+                data_domains: v.data_domains,
+                systems,
+            }
+        })
+        .collect();
+
+    // Most concentrated first.
+    vendors.sort_by(|a, b| {
+        b.high_criticality_count
+            .cmp(&a.high_criticality_count)
+            .then(b.system_count.cmp(&a.system_count))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    Ok(VendorReport {
+        max_depth,
+        min_confidence,
+        include_needs_review,
+        vendors,
+    })
+}
+
+fn source_sv(source: &str) -> &'static str {
+    match source {
+        "supplier" => "Leverantör",
+        _ => "Leverantörsnod",
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/vendors.json",
+    params(VendorReportQuery),
+    responses(
+        (status = 200, body = VendorReport),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub(super) async fn export_vendors_json(
+    State(state): State<AppState>,
+    Query(q): Query<VendorReportQuery>,
+) -> Result<Json<VendorReport>, ApiError> {
+    Ok(Json(load_report(&state.pool, &q).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/vendors.xlsx",
+    params(VendorReportQuery),
+    responses(
+        (
+            status = 200,
+            description = "Excel-arbetsbok (Leverantörer, Beroenden)",
+            content_type = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+        ),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub(super) async fn export_vendors_xlsx(
+    State(state): State<AppState>,
+    Query(q): Query<VendorReportQuery>,
+) -> Result<Response, ApiError> {
+    let now = OffsetDateTime::now_utc();
+    let exported_at = now
+        .format(&Rfc3339)
+        .unwrap_or_else(|_| "1970-01-01T00:00:00Z".to_string());
+    let date_for_filename = now
+        .format(&format_description!("[day]_[month]_[year repr:last_two]"))
+        .unwrap_or_else(|_| "01_01_70".to_string());
+    let filename = format!("KEAB_SoR_Leverantorer_{}.xlsx", date_for_filename);
+
+    let report = load_report(&state.pool, &q).await?;
+
+    let mut wb = Workbook::new();
+
+    let title_fmt = Format::new()
+        .set_bold()
+        .set_font_size(16.0)
+        .set_font_color(Color::White)
+        .set_background_color(Color::RGB(0x007A3D))
+        .set_align(FormatAlign::Center)
+        .set_align(FormatAlign::VerticalCenter);
+
+    let sub_fmt = Format::new()
+        .set_font_color(Color::RGB(0x005F2E))
+        .set_bold()
+        .set_align(FormatAlign::Left);
+
+    let header_fmt = Format::new()
+        .set_bold()
+        .set_background_color(Color::RGB(0xE6F4EA));
+
+    let subtitle = format!(
+        "Exporterad: {} · Djup: {} · Min. konfidens: {}",
+        exported_at, report.max_depth, report.min_confidence
+    );
+
+    {
+        let ws = wb.add_worksheet();
+        ws.set_name("Leverantörer").map_err(xlsx_error)?;
+
+        let headers = [
+            "leverantör",
+            "källa",
+            "leveransform",
+            "antal system",
+            "varav hög kritikalitet",
+            "datadomäner",
+            "system",
+        ];
+
+        ws.set_row_height(0, 24.0).map_err(xlsx_error)?;
+        ws.merge_range(
+            0,
+            0,
+            0,
+            (headers.len() - 1) as u16,
+            "KEAB SoR — Leverantörskoncentration",
+            &title_fmt,
+        )
+        .map_err(xlsx_error)?;
+        ws.write_string_with_format(1, 0, &subtitle, &sub_fmt)
+            .map_err(xlsx_error)?;
+
+        let header_row: u32 = 3;
+        ws.set_freeze_panes(header_row + 1, 0).map_err(xlsx_error)?;
+        for (c, h) in headers.iter().enumerate() {
+            ws.write_string_with_format(header_row, c as u16, *h, &header_fmt)
+                .map_err(xlsx_error)?;
+        }
+
+        for (i, v) in report.vendors.iter().enumerate() {
+            let r = header_row + 1 + i as u32;
+            let names: Vec<&str> = v.systems.iter().map(|s| s.name.as_str()).collect();
+            ws.write_string(r, 0, &v.name).map_err(xlsx_error)?;
+            ws.write_string(r, 1, source_sv(&v.source))
+                .map_err(xlsx_error)?;
+            ws.write_string(r, 2, v.supplier_types.join(", "))
+                .map_err(xlsx_error)?;
+            ws.write_number(r, 3, v.system_count as f64)
+                .map_err(xlsx_error)?;
+            ws.write_number(r, 4, v.high_criticality_count as f64)
+                .map_err(xlsx_error)?;
+            ws.write_string(r, 5, v.data_domains.join(", "))
+                .map_err(xlsx_error)?;
+            ws.write_string(r, 6, names.join(", "))
+                .map_err(xlsx_error)?;
+        }
+
+        set_width_range(ws, 0, 0, 28.0).map_err(xlsx_error)?;
+        set_width_range(ws, 1, 2, 16.0).map_err(xlsx_error)?;
+        set_width_range(ws, 3, 4, 14.0).map_err(xlsx_error)?;
+        set_width_range(ws, 5, 6, 48.0).map_err(xlsx_error)?;
+
+        let last_row = header_row + report.vendors.len() as u32;
+        ws.autofilter(header_row, 0, last_row, (headers.len() - 1) as u16)
+            .map_err(xlsx_error)?;
+    }
+
+    {
+        let ws = wb.add_worksheet();
+        ws.set_name("Beroenden").map_err(xlsx_error)?;
+
+        let headers = [
+            "leverantör",
+            "leveransform",
+            "system",
+            "typ",
+            "avstånd",
+            "affärskritikalitet",
+            "kritikalitetspoäng",
+        ];
+
+        for (c, h) in headers.iter().enumerate() {
+            ws.write_string_with_format(0, c as u16, *h, &header_fmt)
+                .map_err(xlsx_error)?;
+        }
+        ws.set_freeze_panes(1, 0).map_err(xlsx_error)?;
+
+        let mut row: u32 = 1;
+        for v in &report.vendors {
+            let types = v.supplier_types.join(", ");
+            for s in &v.systems {
+                ws.write_string(row, 0, &v.name).map_err(xlsx_error)?;
+                ws.write_string(row, 1, &types).map_err(xlsx_error)?;
+                ws.write_string(row, 2, &s.name).map_err(xlsx_error)?;
+                ws.write_string(row, 3, &s.kind).map_err(xlsx_error)?;
+                ws.write_number(row, 4, s.depth as f64)
+                    .map_err(xlsx_error)?;
+                ws.write_string(row, 5, s.business_criticality.as_deref().unwrap_or(""))
+                    .map_err(xlsx_error)?;
+                if let Some(score) = s.criticality_score {
+                    ws.write_number(row, 6, score).map_err(xlsx_error)?;
+                }
+                row += 1;
+            }
+        }
+
+        set_width_range(ws, 0, 0, 28.0).map_err(xlsx_error)?;
+        set_width_range(ws, 1, 1, 16.0).map_err(xlsx_error)?;
+        set_width_range(ws, 2, 2, 28.0).map_err(xlsx_error)?;
+        set_width_range(ws, 3, 6, 18.0).map_err(xlsx_error)?;
+
+        ws.autofilter(
+            0,
+            0,
+            row.saturating_sub(1).max(1),
+            (headers.len() - 1) as u16,
+        )
+        .map_err(xlsx_error)?;
+    }
+
+    let bytes = wb.save_to_buffer().map_err(xlsx_error)?;
+    Ok(xlsx_response(bytes, &filename))
+}
@@ -1,14 +1,14 @@
-use crate::models::{Edge, Node};
+use crate::auth::AuthActor;
+use crate::error::ApiError;
+use crate::models::Edge;
 use crate::routes::edges::flows::load_flow_map_for_claim_ids;
 use crate::routes::AppState;
 use axum::{
-    body::Body,
     extract::{Query, State},
-    http::{header, HeaderMap, HeaderValue, StatusCode},
+    Extension,
     response::Response,
 };
-use bytes::Bytes;
-use sqlx::Row;
+use sqlx::{Postgres, QueryBuilder, Row};
 use time::format_description::well_known::Rfc3339;
 use time::macros::format_description;
 use time::OffsetDateTime;
@@ -17,14 +17,29 @@ use uuid::Uuid;
 use rust_xlsxwriter::{Color, Format, FormatAlign, Workbook};
 
 use super::util::{
-    db_error, meta_critical, meta_env, meta_os, meta_owner, meta_role, meta_sla, set_width_range,
-    xlsx_error, EdgeScope, ExportRequest,
+    db_error, load_filtered_nodes, meta_critical, meta_env, meta_os, meta_owner, meta_role,
+    meta_sla, push_edge_where, set_width_range, xlsx_error, xlsx_response, ExportRequest,
 };
 
+#[utoipa::path(
+    get,
+    path = "/snapshot.xlsx",
+    params(ExportRequest),
+    responses(
+        (
+            status = 200,
+            description = "Excel-arbetsbok (Noder, Kopplingar, ClaimsCurrent, FlowsCurrent)",
+            content_type = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+        ),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub(super) async fn export_snapshot_xlsx(
     State(state): State<AppState>,
+    actor: Option<Extension<AuthActor>>,
     Query(req): Query<ExportRequest>,
-) -> Result<Response, (StatusCode, String)> {
+) -> Result<Response, ApiError> {
     let now = OffsetDateTime::now_utc();
     let exported_at = now
         .format(&Rfc3339)
@@ -34,77 +49,34 @@ pub(super) async fn export_snapshot_xlsx(
         .unwrap_or_else(|_| "01_01_70".to_string());
     let filename = format!("KEAB_SoR_{}.xlsx", date_for_filename);
 
-    let spec = req
-        .filter_spec()
-        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let spec = req.resolve_filter_spec(&state.pool, actor.as_deref()).await?;
 
     let include_edges = req.include_edges();
     let include_claims = req.include_claims();
     let include_flows = req.include_flows() && include_claims;
 
-    let nodes: Vec<Node> = sqlx::query_as::<_, Node>(
-        r#"
-        SELECT *
-        FROM nodes
-        WHERE deleted_at IS NULL
-        ORDER BY kind, name, id
-        "#,
-    )
-    .fetch_all(&state.pool)
-    .await
-    .map_err(db_error)?;
-
-    let nodes: Vec<Node> = nodes.into_iter().filter(|n| spec.matches_node(n)).collect();
+    let nodes = load_filtered_nodes(&state.pool, &spec).await?;
 
-    let node_ids: Option<Vec<Uuid>> = if spec.is_empty() {
-        None
-    } else {
+    let node_ids: Option<Vec<Uuid>> = if spec.has_node_filter() {
         Some(nodes.iter().map(|n| n.id).collect())
+    } else {
+        None
     };
 
     let edges: Vec<Edge> = if include_edges {
-        match (&node_ids, req.edge_scope()) {
-            (Some(ids), EdgeScope::Both) => sqlx::query_as::<_, Edge>(
-                r#"
-                    SELECT *
-                    FROM edges
-                    WHERE from_id = ANY($1) AND to_id = ANY($1)
-                    ORDER BY kind, from_id, to_id, id
-                    "#,
-            )
-            .bind(ids)
-            .fetch_all(&state.pool)
-            .await
-            .map_err(db_error)?,
-            (Some(ids), EdgeScope::Any) => sqlx::query_as::<_, Edge>(
-                r#"
-                    SELECT *
-                    FROM edges
-                    WHERE from_id = ANY($1) OR to_id = ANY($1)
-                    ORDER BY kind, from_id, to_id, id
-                    "#,
-            )
-            .bind(ids)
-            .fetch_all(&state.pool)
-            .await
-            .map_err(db_error)?,
-            (None, _) => sqlx::query_as::<_, Edge>(
-                r#"
-                    SELECT *
-                    FROM edges
-                    ORDER BY kind, from_id, to_id, id
-                    "#,
-            )
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT e.* FROM edges e");
+        push_edge_where(&mut qb, node_ids.as_deref(), req.edge_scope(), &spec)?;
+        qb.push(" ORDER BY e.kind, e.from_id, e.to_id, e.id");
+        qb.build_query_as::<Edge>()
             .fetch_all(&state.pool)
             .await
-            .map_err(db_error)?,
-        }
+            .map_err(db_error)?
     } else {
         vec![]
     };
 
     let claim_rows = if include_edges && include_claims {
-        if node_ids.is_none() {
+        if node_ids.is_none() && !spec.has_edge_filter() {
             sqlx::query(
                 r#"
                 SELECT
@@ -386,12 +358,9 @@ pub(super) async fn export_snapshot_xlsx(
             for (i, r0) in claim_rows.iter().enumerate() {
                 let row = (i + 1) as u32;
 
-                let edge_id: Uuid = r0.try_get("edge_id").map_err(|e| {
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("decode edge_id: {e}"),
-                    )
-                })?;
+                let edge_id: Uuid = r0
+                    .try_get("edge_id")
+                    .map_err(|e| ApiError::internal(format!("decode edge_id: {e}")))?;
 
                 let claim_id: Option<Uuid> = r0.try_get("claim_id").ok();
                 let status: Option<String> = r0.try_get("claim_status").ok();
@@ -540,22 +509,5 @@ pub(super) async fn export_snapshot_xlsx(
     }
 
     let bytes = wb.save_to_buffer().map_err(xlsx_error)?;
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        header::CONTENT_TYPE,
-        HeaderValue::from_static(
-            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
-        ),
-    );
-
-    let cd = format!("attachment; filename=\"{}\"", filename);
-    headers.insert(
-        header::CONTENT_DISPOSITION,
-        HeaderValue::from_str(&cd).unwrap_or_else(|_| HeaderValue::from_static("attachment")),
-    );
-
-    let mut resp = Response::new(Body::from(Bytes::from(bytes)));
-    *resp.headers_mut() = headers;
-    Ok(resp)
+    Ok(xlsx_response(bytes, &filename))
 }
@@ -5,13 +5,19 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 use sqlx::Row;
+use utoipa::{IntoParams, OpenApi, ToSchema};
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::models::edge_claim_flow::EdgeClaimFlow;
-use crate::routes::{etag_from_updated_at, AppState};
+use crate::routes::{etag_from_version, AppState};
 
 use crate::routes::edges::flows::load_flow_map_for_claim_ids;
 
+#[derive(OpenApi)]
+#[openapi(paths(get_graph, blast_radius, reverse_deps))]
+pub struct GraphApi;
+
 pub fn router() -> Router<AppState> {
     Router::<AppState>::new()
         .route("/", get(get_graph))
@@ -19,13 +25,13 @@ pub fn router() -> Router<AppState> {
         .route("/reverse-deps/:id", get(reverse_deps))
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct GraphResponse {
     pub nodes: Vec<NodeRow>,
     pub links: Vec<EdgeRow>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct NodeRow {
     pub id: Uuid,
     pub kind: String,
@@ -34,7 +40,7 @@ pub struct NodeRow {
     pub etag: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct EdgeRow {
     pub id: Uuid,
     pub source: Uuid,
@@ -42,7 +48,9 @@ pub struct EdgeRow {
     pub kind: String,
     pub metadata: serde_json::Value,
     pub etag: String,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub created_at: time::OffsetDateTime,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub updated_at: time::OffsetDateTime,
     pub current_claim_id: Option<Uuid>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -52,7 +60,7 @@ pub struct EdgeRow {
     pub review_flows: Option<Vec<GraphEdgeFlow>>,
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Clone, ToSchema)]
 pub struct GraphEdgeFlow {
     pub id: Uuid,
     pub claim_id: Option<Uuid>,
@@ -62,22 +70,30 @@ pub struct GraphEdgeFlow {
     pub protocol: Option<String>,
     pub frequency: Option<String>,
     pub implicit: bool,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub created_at: time::OffsetDateTime,
 }
 
-#[derive(Deserialize, Default)]
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct GraphQuery {
     #[serde(default)]
     include_review: bool,
 }
 
+#[utoipa::path(
+    get,
+    path = "/",
+    params(GraphQuery),
+    responses((status = 200, body = GraphResponse), (status = 500, response = crate::openapi::ErrorResponse))
+)]
 async fn get_graph(
     State(state): State<AppState>,
     Query(q): Query<GraphQuery>,
-) -> Json<GraphResponse> {
+) -> Result<Json<GraphResponse>, ApiError> {
     let node_rows = sqlx::query(
         r#"
-        SELECT id, kind, name, metadata, updated_at
+        SELECT id, kind, name, metadata, version
         FROM nodes
         WHERE deleted_at IS NULL
         ORDER BY name
@@ -85,13 +101,12 @@ async fn get_graph(
     )
     .fetch_all(&state.pool)
     .await
-    .expect("fetch nodes failed");
+    .map_err(ApiError::internal)?;
 
     let nodes: Vec<NodeRow> = node_rows
         .into_iter()
         .map(|r| {
-            let updated_at: time::OffsetDateTime = r.get("updated_at");
-            let etag_hv = etag_from_updated_at(updated_at);
+            let etag_hv = etag_from_version(r.get("version"));
             let etag = etag_hv.to_str().unwrap_or("").to_string();
 
             NodeRow {
@@ -114,6 +129,7 @@ async fn get_graph(
           e.metadata,
           e.created_at,
           e.updated_at,
+          e.version,
           c_active.id AS current_claim_id,
           c_review.id AS review_claim_id
         FROM edges e
@@ -140,7 +156,7 @@ async fn get_graph(
     )
     .fetch_all(&state.pool)
     .await
-    .expect("fetch edges failed");
+    .map_err(ApiError::internal)?;
 
     let mut claim_ids: Vec<Uuid> = edge_rows
         .iter()
@@ -160,9 +176,7 @@ async fn get_graph(
     let flow_map = if claim_ids.is_empty() {
         std::collections::HashMap::<Uuid, Vec<EdgeClaimFlow>>::new()
     } else {
-        load_flow_map_for_claim_ids(&state.pool, &claim_ids)
-            .await
-            .expect("load flows failed")
+        load_flow_map_for_claim_ids(&state.pool, &claim_ids).await?
     };
 
     let links: Vec<EdgeRow> = edge_rows
@@ -170,7 +184,7 @@ async fn get_graph(
         .map(|r| {
             let updated_at: time::OffsetDateTime = r.get("updated_at");
             let created_at: time::OffsetDateTime = r.get("created_at");
-            let etag_hv = etag_from_updated_at(updated_at);
+            let etag_hv = etag_from_version(r.get("version"));
             let etag = etag_hv.to_str().unwrap_or("").to_string();
 
             let current_claim_id: Option<Uuid> = r.try_get("current_claim_id").ok();
@@ -207,7 +221,7 @@ async fn get_graph(
         })
         .collect();
 
-    Json(GraphResponse { nodes, links })
+    Ok(Json(GraphResponse { nodes, links }))
 }
 
 fn flows_for_claim_or_implicit(
@@ -259,16 +273,22 @@ fn flows_for_claim_or_implicit(
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct BlastRadiusResponse {
     pub node_ids: Vec<Uuid>,
     pub edge_ids: Vec<Uuid>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/blast-radius/{id}",
+    params(("id" = Uuid, Path, description = "Startnod")),
+    responses((status = 200, body = BlastRadiusResponse), (status = 500, response = crate::openapi::ErrorResponse))
+)]
 async fn blast_radius(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-) -> Json<BlastRadiusResponse> {
+) -> Result<Json<BlastRadiusResponse>, ApiError> {
     let rows = sqlx::query(
         r#"
         WITH RECURSIVE walk AS (
@@ -288,7 +308,7 @@ async fn blast_radius(
     .bind(id)
     .fetch_all(&state.pool)
     .await
-    .expect("blast radius failed");
+    .map_err(ApiError::internal)?;
 
     let mut node_ids: Vec<Uuid> = Vec::new();
     let mut edge_ids: Vec<Uuid> = Vec::new();
@@ -305,19 +325,25 @@ async fn blast_radius(
         }
     }
 
-    Json(BlastRadiusResponse { node_ids, edge_ids })
+    Ok(Json(BlastRadiusResponse { node_ids, edge_ids }))
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct ReverseDepsResponse {
     pub node_ids: Vec<Uuid>,
     pub edge_ids: Vec<Uuid>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/reverse-deps/{id}",
+    params(("id" = Uuid, Path, description = "Startnod")),
+    responses((status = 200, body = ReverseDepsResponse), (status = 500, response = crate::openapi::ErrorResponse))
+)]
 async fn reverse_deps(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
-) -> Json<ReverseDepsResponse> {
+) -> Result<Json<ReverseDepsResponse>, ApiError> {
     let rows = sqlx::query(
         r#"
         WITH RECURSIVE walk AS (
@@ -337,7 +363,7 @@ async fn reverse_deps(
     .bind(id)
     .fetch_all(&state.pool)
     .await
-    .expect("reverse deps failed");
+    .map_err(ApiError::internal)?;
 
     let mut node_ids: Vec<Uuid> = Vec::new();
     let mut edge_ids: Vec<Uuid> = Vec::new();
@@ -354,5 +380,5 @@ async fn reverse_deps(
         }
     }
 
-    Json(ReverseDepsResponse { node_ids, edge_ids })
+    Ok(Json(ReverseDepsResponse { node_ids, edge_ids }))
 }
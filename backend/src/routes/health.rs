@@ -1,6 +1,12 @@
 use axum::Json;
 use serde_json::json;
 
+#[utoipa::path(
+    get,
+    path = "/api/health",
+    tag = "meta",
+    responses((status = 200, description = "Tjänsten svarar", body = serde_json::Value))
+)]
 pub async fn health() -> Json<serde_json::Value> {
     Json(json!({"status": "ok"}))
 }
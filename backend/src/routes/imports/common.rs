@@ -0,0 +1,451 @@
+//! Helpers shared by the file importers: reading the multipart upload,
+//! writing the batch with its edge proposals aggregated per
+//! `(from, to, kind)`, lookups of existing hosts and deleted nodes, and
+//! reading container images and YAML scalars.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use axum::extract::Multipart;
+use serde::de::DeserializeOwned;
+use serde_yaml::Value;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::{
+    audit::RequestContext,
+    auth::AuthActor,
+    models::{
+        edge::{Edge, EdgeKind},
+        edge_claim::EdgeClaim,
+        edge_claim_evidence::NewEdgeClaimEvidence,
+        new_edge_claim_flow::NewEdgeClaimFlow,
+    },
+    routes::batch::EntityRef,
+};
+
+use super::reconcile::{plan_edge_proposal, Planned, ReconcileAction};
+use super::{
+    insert_edge_proposal, insert_import_batch, load_proposal_items, multipart_error, ImportBatch,
+    ProposalEdgeInput, ProposalItem,
+};
+use crate::routes::edges::helpers::internal_error;
+
+/// The parts of an import upload: one or more `file` parts, a JSON part
+/// with the importer's options and `source`.
+pub(super) struct Upload<T> {
+    /// `(filename, bytes)` in upload order.
+    files: Vec<(Option<String>, Vec<u8>)>,
+    pub options: Option<T>,
+    pub source: String,
+}
+
+impl<T: DeserializeOwned> Upload<T> {
+    /// Reads a body with its options in the `options` part.
+    pub async fn read(multipart: Multipart, default_source: &str) -> Result<Self, ApiError> {
+        Self::read_with(
+            multipart,
+            "options",
+            "Ogiltiga inställningar",
+            default_source,
+        )
+        .await
+    }
+
+    /// Reads a body with its options in the `part` part; `invalid` prefixes
+    /// the error when it does not parse.
+    pub async fn read_with(
+        mut multipart: Multipart,
+        part: &str,
+        invalid: &str,
+        default_source: &str,
+    ) -> Result<Self, ApiError> {
+        let mut upload = Upload {
+            files: Vec::new(),
+            options: None,
+            source: default_source.to_string(),
+        };
+
+        while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+            let name = field.name().unwrap_or_default().to_string();
+            if name == "file" {
+                let filename = field.file_name().map(str::to_string);
+                let bytes = field.bytes().await.map_err(multipart_error)?;
+                upload.files.push((filename, bytes.to_vec()));
+            } else if name == part {
+                let text = field.text().await.map_err(multipart_error)?;
+                if !text.trim().is_empty() {
+                    upload.options = Some(
+                        serde_json::from_str(&text)
+                            .map_err(|e| ApiError::validation(part, format!("{invalid}: {e}")))?,
+                    );
+                }
+            } else if name == "source" {
+                let text = field.text().await.map_err(multipart_error)?;
+                if !text.trim().is_empty() {
+                    upload.source = text.trim().to_string();
+                }
+            }
+        }
+
+        Ok(upload)
+    }
+
+    /// The uploaded file (the last one if several were sent), named
+    /// `default_name` when the part has no filename.
+    pub fn file(&mut self, default_name: &str) -> Result<(String, Vec<u8>), ApiError> {
+        let (filename, bytes) = self
+            .files
+            .pop()
+            .ok_or_else(|| ApiError::validation("file", "Fil saknas"))?;
+        Ok((filename.unwrap_or_else(|| default_name.to_string()), bytes))
+    }
+
+    /// Every uploaded file; a part without filename is named by
+    /// `default_name` from its position, counting from 1.
+    pub fn files(
+        &mut self,
+        default_name: impl Fn(usize) -> String,
+    ) -> Result<Vec<(String, Vec<u8>)>, ApiError> {
+        if self.files.is_empty() {
+            return Err(ApiError::validation("file", "Fil saknas"));
+        }
+        Ok(self
+            .files
+            .drain(..)
+            .enumerate()
+            .map(|(i, (filename, bytes))| (filename.unwrap_or_else(|| default_name(i + 1)), bytes))
+            .collect())
+    }
+}
+
+/// An import batch and the transaction its proposals are written in.
+pub(super) struct BatchWriter {
+    pub tx: Transaction<'static, Postgres>,
+    pub batch: ImportBatch,
+    ctx: RequestContext,
+    edges: Vec<Edge>,
+    claims: Vec<EdgeClaim>,
+}
+
+pub(super) struct WrittenBatch {
+    pub batch: ImportBatch,
+    pub proposals: Vec<ProposalItem>,
+    /// `(origin, message)` for edge proposals that clash with an open one.
+    pub conflicts: Vec<(String, String)>,
+}
+
+impl BatchWriter {
+    /// Opens the transaction and inserts the batch.
+    pub async fn begin(
+        pool: &PgPool,
+        ctx: RequestContext,
+        source: &str,
+        metadata: serde_json::Value,
+    ) -> Result<Self, ApiError> {
+        let mut tx = pool.begin().await.map_err(internal_error)?;
+        let batch = insert_import_batch(
+            &mut *tx,
+            source.to_string(),
+            ctx.request_id.to_string(),
+            Some(metadata),
+        )
+        .await?;
+        Ok(BatchWriter {
+            tx,
+            batch,
+            ctx,
+            edges: Vec::new(),
+            claims: Vec::new(),
+        })
+    }
+
+    /// Writes the edge proposals and returns `(origin, message)` for the
+    /// ones that clash with an open one.
+    pub async fn write_edges(
+        &mut self,
+        actor: &AuthActor,
+        proposed: EdgeMap,
+    ) -> Result<Vec<(String, String)>, ApiError> {
+        let written = write_edge_proposals(
+            &mut self.tx,
+            self.ctx,
+            actor,
+            self.batch.id,
+            &self.batch.source,
+            proposed,
+        )
+        .await?;
+        self.edges.extend(written.edges);
+        self.claims.extend(written.claims);
+        Ok(written.conflicts)
+    }
+
+    /// Adds keys to the batch metadata, for totals that are only known
+    /// once everything is written.
+    pub async fn merge_metadata(&mut self, patch: serde_json::Value) -> Result<(), ApiError> {
+        self.batch.metadata = sqlx::query_scalar(
+            r#"
+            UPDATE import_batches
+            SET metadata = COALESCE(metadata, '{}'::jsonb) || $2
+            WHERE id = $1
+            RETURNING metadata
+            "#,
+        )
+        .bind(self.batch.id)
+        .bind(patch)
+        .fetch_one(&mut *self.tx)
+        .await
+        .map_err(internal_error)?;
+        Ok(())
+    }
+
+    /// Commits and loads the written edge proposals for the response.
+    pub async fn commit(self, pool: &PgPool) -> Result<(ImportBatch, Vec<ProposalItem>), ApiError> {
+        self.tx.commit().await.map_err(internal_error)?;
+        let proposals = load_proposal_items(pool, self.edges, self.claims).await?;
+        Ok((self.batch, proposals))
+    }
+
+    /// [`write_edges`](Self::write_edges) followed by
+    /// [`commit`](Self::commit).
+    pub async fn finish(
+        mut self,
+        pool: &PgPool,
+        actor: &AuthActor,
+        proposed: EdgeMap,
+    ) -> Result<WrittenBatch, ApiError> {
+        let conflicts = self.write_edges(actor, proposed).await?;
+        let (batch, proposals) = self.commit(pool).await?;
+        Ok(WrittenBatch {
+            batch,
+            proposals,
+            conflicts,
+        })
+    }
+}
+
+/// Edge proposals keyed by `(from, to, kind)`.
+pub(super) type EdgeMap = BTreeMap<(Uuid, Uuid, &'static str), Proposed>;
+
+pub(super) struct Proposed {
+    /// First object that contributed; conflicts are reported against it.
+    pub origin: String,
+    pub confidence: i16,
+    pub evidence: Vec<NewEdgeClaimEvidence>,
+    /// Becomes one flow per entry, with the edge kind as flow type.
+    pub protocols: BTreeSet<String>,
+    /// Metadata for the edge if it is created.
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The aggregated proposal for `from -> to`; the highest confidence wins.
+pub(super) fn propose<'a>(
+    map: &'a mut EdgeMap,
+    from: Uuid,
+    to: Uuid,
+    kind: EdgeKind,
+    origin: &str,
+    confidence: i16,
+) -> &'a mut Proposed {
+    let p = map
+        .entry((from, to, kind.as_str()))
+        .or_insert_with(|| Proposed {
+            origin: origin.to_string(),
+            confidence,
+            evidence: Vec::new(),
+            protocols: BTreeSet::new(),
+            metadata: serde_json::Map::new(),
+        });
+    p.confidence = p.confidence.max(confidence);
+    p
+}
+
+struct WrittenEdges {
+    pub edges: Vec<Edge>,
+    pub claims: Vec<EdgeClaim>,
+    /// `(origin, message)` for proposals that clash with an open one.
+    pub conflicts: Vec<(String, String)>,
+}
+
+/// Reconciles each aggregated proposal against the graph and writes the
+/// ones that add something. No-ops are dropped silently.
+async fn write_edge_proposals(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    batch_id: Uuid,
+    source: &str,
+    proposed: EdgeMap,
+) -> Result<WrittenEdges, ApiError> {
+    let mut written = WrittenEdges {
+        edges: Vec::new(),
+        claims: Vec::new(),
+        conflicts: Vec::new(),
+    };
+
+    for (index, ((from_id, to_id, kind), p)) in proposed.into_iter().enumerate() {
+        let flows = (!p.protocols.is_empty()).then(|| {
+            p.protocols
+                .into_iter()
+                .map(|protocol| NewEdgeClaimFlow {
+                    flow_type: kind.to_string(),
+                    direction: None,
+                    data_category_id: None,
+                    protocol: Some(protocol),
+                    frequency: None,
+                })
+                .collect()
+        });
+        let item = ProposalEdgeInput {
+            from_id: EntityRef::Id(from_id),
+            to_id: EntityRef::Id(to_id),
+            kind: kind.to_string(),
+            source: source.to_string(),
+            confidence: Some(p.confidence),
+            evidence: Some(p.evidence),
+            flows,
+            metadata: (!p.metadata.is_empty()).then_some(serde_json::Value::Object(p.metadata)),
+        };
+
+        let plan = plan_edge_proposal(tx, index, from_id, to_id, &item, &Planned::new()).await?;
+        match plan.action {
+            ReconcileAction::Noop => continue,
+            ReconcileAction::Conflict => {
+                written
+                    .conflicts
+                    .push((p.origin, plan.message_sv.unwrap_or_default()));
+                continue;
+            }
+            ReconcileAction::CreateEdge | ReconcileAction::AddProposal => {}
+        }
+
+        let (edge, claim) =
+            insert_edge_proposal(tx, ctx, actor, batch_id, from_id, to_id, item).await?;
+        written.edges.push(edge);
+        written.claims.push(claim);
+    }
+
+    Ok(written)
+}
+
+/// Soft-deleted nodes among `names` as `(kind, name)`. They keep their
+/// `(kind, name)`, so an import cannot propose them until restored.
+pub(super) async fn deleted_nodes(
+    pool: &sqlx::PgPool,
+    names: Vec<String>,
+) -> Result<HashSet<(String, String)>, ApiError> {
+    let rows: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT kind, name
+        FROM nodes
+        WHERE deleted_at IS NOT NULL
+          AND name = ANY($1)
+        "#,
+    )
+    .bind(names)
+    .fetch_all(pool)
+    .await
+    .map_err(internal_error)?;
+
+    Ok(rows.into_iter().collect())
+}
+
+/// Splits `registry/repo:tag@digest` into repository and tag.
+pub(super) fn split_image(image: &str) -> (String, Option<String>) {
+    let image = image.split('@').next().unwrap_or(image);
+    let slash = image.rfind('/').map_or(0, |i| i + 1);
+    match image[slash..].rfind(':') {
+        Some(i) => (
+            image[..slash + i].to_string(),
+            Some(image[slash + i + 1..].to_string()),
+        ),
+        None => (image.to_string(), None),
+    }
+}
+
+pub(super) fn database_engine(repository: &str) -> Option<&'static str> {
+    let repository = repository.to_lowercase();
+    let last = repository.rsplit('/').next().unwrap_or_default();
+    let engine = match last {
+        "postgres" | "postgresql" | "postgis" | "timescaledb" | "timescaledb-ha" => "postgres",
+        "mysql" | "mysql-server" => "mysql",
+        "mariadb" => "mariadb",
+        "mongo" | "mongodb" | "mongodb-community-server" => "mongodb",
+        "redis" | "redis-stack-server" | "valkey" => "redis",
+        "influxdb" => "influxdb",
+        "elasticsearch" => "elasticsearch",
+        "opensearch" => "opensearch",
+        "couchdb" => "couchdb",
+        "cassandra" => "cassandra",
+        "neo4j" => "neo4j",
+        "clickhouse-server" => "clickhouse",
+        "server" if repository.contains("mssql") => "mssql",
+        _ => return None,
+    };
+    Some(engine)
+}
+
+/// Leading version number of an image tag: `16-alpine` gives `16`.
+pub(super) fn engine_version(tag: &str) -> Option<String> {
+    let version: String = tag
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    let version = version.trim_end_matches('.');
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+/// Entries of a list, or keys of a mapping (compose allows both for
+/// `depends_on`, `networks` and friends).
+pub(super) fn names(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::Sequence(items)) => items.iter().filter_map(scalar).collect(),
+        Some(Value::Mapping(map)) => map.keys().filter_map(scalar).collect(),
+        Some(v) => scalar(v).into_iter().collect(),
+        None => Vec::new(),
+    }
+}
+
+pub(super) fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+pub(super) struct HostIndex {
+    pub by_name: HashMap<String, Uuid>,
+    /// Hosts whose metadata says `role: swarm-manager`.
+    pub managers: Vec<Uuid>,
+}
+
+impl HostIndex {
+    pub async fn load(pool: &sqlx::PgPool) -> Result<Self, ApiError> {
+        let rows: Vec<(Uuid, String, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT id, name, metadata->>'role'
+            FROM nodes
+            WHERE deleted_at IS NULL
+              AND kind = 'host'
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(internal_error)?;
+
+        let mut index = HostIndex {
+            by_name: HashMap::new(),
+            managers: Vec::new(),
+        };
+        for (id, name, role) in rows {
+            index.by_name.insert(name.to_lowercase(), id);
+            if role.as_deref() == Some("swarm-manager") {
+                index.managers.push(id);
+            }
+        }
+        Ok(index)
+    }
+}
@@ -0,0 +1,583 @@
+//! Docker Compose / Swarm stack import.
+//!
+//! Reads a compose or stack file and proposes one node per service: a
+//! `database` node when the image belongs to a known database engine,
+//! otherwise a node of `options.service_kind` (`container` by default).
+//! Nodes are named like Swarm names the services, `<stack>_<service>`,
+//! unless the service sets `container_name`. Edges, all as proposals in the
+//! same batch:
+//!
+//! - `runs_on` to the hosts picked by `node.hostname` / `node.role ==
+//!   manager` placement constraints, otherwise to `options.hosts`;
+//! - `depends_on` from `depends_on` and `links`, and at lower confidence to
+//!   a database service on a shared network;
+//! - `exposes_port` to the same hosts for published ports, with the ports
+//!   as flows.
+//!
+//! Hosts are never created; they must already exist as `host` nodes.
+
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{
+    extract::{Multipart, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::{
+    audit::RequestContext,
+    auth::AuthActor,
+    models::{edge::EdgeKind, edge_claim_evidence::NewEdgeClaimEvidence, node::NodeKind},
+    routes::AppState,
+};
+
+use super::common::{
+    database_engine, deleted_nodes, engine_version, names, propose, scalar, split_image,
+    BatchWriter, EdgeMap, HostIndex, Upload,
+};
+use super::nodes::{propose_node, NodeProposalAction, ProposalNodeInput};
+use super::{ImportBatch, ProposalItem};
+
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct ComposeOptions {
+    /// Stackens namn; annars filens `name` eller filnamnet utan ändelse.
+    pub stack: Option<String>,
+    /// Värdar (nodnamn) för tjänster utan placeringsvillkor.
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// Nodtyp för tjänster som inte är databaser: `container` (standard),
+    /// `service` eller `app`.
+    pub service_kind: Option<NodeKind>,
+    /// Sätts som `env` i nodernas metadata.
+    pub env: Option<String>,
+    /// Confidence för förslagen; standard 70.
+    pub confidence: Option<i16>,
+}
+
+/// Shape of the multipart body; only used for the OpenAPI document.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct ComposeUpload {
+    /// Compose- eller stackfil (YAML).
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+    pub options: Option<ComposeOptions>,
+    /// Batchens källa; standard `compose`.
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ServiceIssue {
+    pub service: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ComposeNode {
+    pub service: String,
+    pub id: Uuid,
+    pub kind: String,
+    pub name: String,
+    /// `false` när noden redan fanns.
+    pub created: bool,
+    /// Förslagets claim; för befintliga noder bara när filen ändrar något.
+    pub claim_id: Option<Uuid>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ComposeImportResult {
+    /// Saknas när filen inte gav någon nod.
+    pub batch: Option<ImportBatch>,
+    pub stack: String,
+    pub services: usize,
+    pub nodes: Vec<ComposeNode>,
+    pub proposals: Vec<ProposalItem>,
+    pub issues: Vec<ServiceIssue>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/compose",
+    request_body(content = ComposeUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = ComposeImportResult),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 413, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn import_compose(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    multipart: Multipart,
+) -> Result<Json<ComposeImportResult>, ApiError> {
+    let mut upload = Upload::<ComposeOptions>::read(multipart, "compose").await?;
+    let (filename, bytes) = upload.file("docker-compose.yml")?;
+    let options = upload.options.unwrap_or_default();
+    let source = upload.source;
+    let service_kind = options.service_kind.unwrap_or(NodeKind::Container);
+    if !matches!(
+        service_kind,
+        NodeKind::Container | NodeKind::Service | NodeKind::App
+    ) {
+        return Err(ApiError::validation(
+            "options.service_kind",
+            "service_kind måste vara container, service eller app",
+        ));
+    }
+    let confidence = options.confidence.unwrap_or(70);
+    if !(0..=100).contains(&confidence) {
+        return Err(ApiError::validation(
+            "options.confidence",
+            "confidence måste vara mellan 0 och 100",
+        ));
+    }
+
+    let mut doc: Value = serde_yaml::from_slice(&bytes)
+        .map_err(|e| ApiError::validation("file", format!("Ogiltig YAML: {e}")))?;
+    doc.apply_merge()
+        .map_err(|e| ApiError::validation("file", format!("Ogiltig YAML: {e}")))?;
+
+    let stack = options
+        .stack
+        .clone()
+        .or_else(|| doc.get("name").and_then(scalar))
+        .unwrap_or_else(|| {
+            let stem = filename.rsplit(['/', '\\']).next().unwrap_or(&filename);
+            stem.split('.').next().unwrap_or(stem).to_string()
+        })
+        .trim()
+        .to_string();
+    if stack.is_empty() {
+        return Err(ApiError::validation("options.stack", "Stacknamn saknas"));
+    }
+
+    let services = read_services(&doc, &stack, service_kind, options.env.as_deref())
+        .map_err(|e| ApiError::validation("file", e))?;
+    let service_count = services.len();
+
+    let hosts = HostIndex::load(&state.pool).await?;
+    let mut default_hosts: Vec<Uuid> = Vec::new();
+    for name in &options.hosts {
+        let id = hosts
+            .by_name
+            .get(&name.trim().to_lowercase())
+            .ok_or_else(|| {
+                ApiError::validation("options.hosts", format!("Värden '{name}' finns inte"))
+            })?;
+        default_hosts.push(*id);
+    }
+
+    let mut issues: Vec<ServiceIssue> = Vec::new();
+    let mut issue = |service: &str, message: String| {
+        issues.push(ServiceIssue {
+            service: service.to_string(),
+            message,
+        })
+    };
+
+    let deleted = deleted_nodes(
+        &state.pool,
+        services.iter().map(|s| s.name.clone()).collect(),
+    )
+    .await?;
+
+    let services: Vec<Service> = services
+        .into_iter()
+        .filter(|s| {
+            let gone = deleted.contains(&(s.kind.as_str().to_string(), s.name.clone()));
+            if gone {
+                issue(
+                    &s.key,
+                    format!(
+                        "Noden '{}' ({}) är borttagen; återställ den innan import",
+                        s.name,
+                        s.kind.as_str()
+                    ),
+                );
+            }
+            !gone
+        })
+        .collect();
+
+    if services.is_empty() {
+        return Ok(Json(ComposeImportResult {
+            batch: None,
+            stack,
+            services: service_count,
+            nodes: Vec::new(),
+            proposals: Vec::new(),
+            issues,
+        }));
+    }
+
+    // Hosts per service, resolved before anything is written.
+    let mut placed: HashMap<&str, (Vec<Uuid>, String)> = HashMap::new();
+    for s in &services {
+        let (ids, reason) = match &s.placement {
+            Placement::Hostnames(names) => {
+                let mut ids = Vec::new();
+                for name in names {
+                    match hosts.by_name.get(&name.to_lowercase()) {
+                        Some(id) => ids.push(*id),
+                        None => issue(&s.key, format!("Värden '{name}' finns inte")),
+                    }
+                }
+                (ids, format!("node.hostname == {}", names.join(", ")))
+            }
+            Placement::Managers => {
+                if hosts.managers.is_empty() {
+                    issue(
+                        &s.key,
+                        "Inga värdar har role swarm-manager i metadata".to_string(),
+                    );
+                }
+                (hosts.managers.clone(), "node.role == manager".to_string())
+            }
+            Placement::Default => {
+                if default_hosts.is_empty() {
+                    issue(
+                        &s.key,
+                        "Ingen värd: ange placeringsvillkor eller options.hosts".to_string(),
+                    );
+                }
+                (default_hosts.clone(), "angivna värdar".to_string())
+            }
+        };
+        placed.insert(&s.key, (ids, reason));
+    }
+
+    let mut import = BatchWriter::begin(
+        &state.pool,
+        ctx,
+        &source,
+        serde_json::json!({
+            "filename": filename,
+            "stack": stack,
+            "services": service_count,
+        }),
+    )
+    .await?;
+
+    let mut ids: HashMap<&str, Uuid> = HashMap::new();
+    let mut nodes: Vec<ComposeNode> = Vec::new();
+
+    for (index, s) in services.iter().enumerate() {
+        let input = ProposalNodeInput {
+            id: None,
+            temp_id: None,
+            kind: Some(s.kind),
+            name: Some(s.name.clone()),
+            metadata: Some(serde_json::Value::Object(s.metadata.clone())),
+            details: None,
+            source: source.clone(),
+            confidence: Some(confidence),
+        };
+        let proposed =
+            propose_node(&mut import.tx, ctx, &actor, import.batch.id, index, &input).await?;
+        if proposed.action == NodeProposalAction::Conflict {
+            issue(&s.key, proposed.message_sv.clone().unwrap_or_default());
+        }
+        ids.insert(&s.key, proposed.node_id);
+        nodes.push(ComposeNode {
+            service: s.key.clone(),
+            id: proposed.node_id,
+            kind: proposed.kind,
+            name: proposed.name,
+            created: proposed.action == NodeProposalAction::CreateNode,
+            claim_id: proposed.claim_id,
+        });
+    }
+
+    let databases: Vec<&Service> = services
+        .iter()
+        .filter(|s| s.kind == NodeKind::Database)
+        .collect();
+
+    let mut proposed: EdgeMap = BTreeMap::new();
+    let evidence = |note: String| NewEdgeClaimEvidence {
+        evidence_type: "document".to_string(),
+        reference: filename.clone(),
+        note: Some(note),
+    };
+
+    for s in &services {
+        let from = ids[s.key.as_str()];
+        let (host_ids, reason) = &placed[s.key.as_str()];
+
+        for &host in host_ids {
+            propose(
+                &mut proposed,
+                from,
+                host,
+                EdgeKind::RunsOn,
+                &s.key,
+                confidence,
+            )
+            .evidence
+            .push(evidence(format!("{}: {reason}", s.key)));
+
+            if !s.ports.is_empty() {
+                let p = propose(
+                    &mut proposed,
+                    from,
+                    host,
+                    EdgeKind::ExposesPort,
+                    &s.key,
+                    confidence,
+                );
+                for (protocol, mapping) in &s.ports {
+                    p.protocols.insert(protocol.clone());
+                    p.evidence
+                        .push(evidence(format!("{}: ports {mapping}", s.key)));
+                }
+            }
+        }
+        if host_ids.is_empty() && !s.ports.is_empty() {
+            issue(
+                &s.key,
+                "Publicerade portar föreslås inte utan värd".to_string(),
+            );
+        }
+
+        for (target, how) in &s.depends {
+            let Some(&to) = ids.get(target.as_str()) else {
+                issue(
+                    &s.key,
+                    format!("Tjänsten '{target}' finns inte i filen eller hoppades över"),
+                );
+                continue;
+            };
+            propose(
+                &mut proposed,
+                from,
+                to,
+                EdgeKind::DependsOn,
+                &s.key,
+                confidence,
+            )
+            .evidence
+            .push(evidence(format!("{}: {how} {target}", s.key)));
+        }
+
+        // A shared network with a database is weaker evidence than an
+        // explicit `depends_on`.
+        if s.kind != NodeKind::Database {
+            for db in &databases {
+                if let Some(network) = s.networks.iter().find(|n| db.networks.contains(n)) {
+                    propose(
+                        &mut proposed,
+                        from,
+                        ids[db.key.as_str()],
+                        EdgeKind::DependsOn,
+                        &s.key,
+                        (confidence - 20).max(0),
+                    )
+                    .evidence
+                    .push(evidence(format!(
+                        "{}: delar nätverket '{network}' med {}",
+                        s.key, db.key
+                    )));
+                }
+            }
+        }
+    }
+
+    let written = import.finish(&state.pool, &actor, proposed).await?;
+    for (origin, message) in written.conflicts {
+        issue(&origin, message);
+    }
+
+    Ok(Json(ComposeImportResult {
+        batch: Some(written.batch),
+        stack,
+        services: service_count,
+        nodes,
+        proposals: written.proposals,
+        issues,
+    }))
+}
+
+/// Where a service's tasks may be scheduled.
+enum Placement {
+    Hostnames(Vec<String>),
+    Managers,
+    Default,
+}
+
+struct Service {
+    /// Key under `services`.
+    key: String,
+    /// Node name.
+    name: String,
+    kind: NodeKind,
+    metadata: serde_json::Map<String, serde_json::Value>,
+    /// Target service key and how the dependency was declared.
+    depends: Vec<(String, &'static str)>,
+    networks: Vec<String>,
+    /// Protocol (`tcp/8080`) and the mapping as written.
+    ports: Vec<(String, String)>,
+    placement: Placement,
+}
+
+fn read_services(
+    doc: &Value,
+    stack: &str,
+    service_kind: NodeKind,
+    env: Option<&str>,
+) -> Result<Vec<Service>, String> {
+    let Some(services) = doc.get("services").and_then(Value::as_mapping) else {
+        return Err("Filen saknar 'services'".to_string());
+    };
+
+    let mut out = Vec::new();
+    for (key, def) in services {
+        let Some(key) = scalar(key) else {
+            continue;
+        };
+        if !def.is_mapping() && !def.is_null() {
+            return Err(format!("Tjänsten '{key}' är inte ett objekt"));
+        }
+
+        let mut metadata = serde_json::Map::new();
+        metadata.insert("stack".into(), stack.into());
+        if let Some(env) = env {
+            metadata.insert("env".into(), env.into());
+        }
+
+        let mut kind = service_kind;
+        if let Some(image) = def.get("image").and_then(scalar) {
+            let (repository, tag) = split_image(&image);
+            if let Some(engine) = database_engine(&repository) {
+                kind = NodeKind::Database;
+                metadata.insert("engine".into(), engine.into());
+                if let Some(version) = tag.as_deref().and_then(engine_version) {
+                    metadata.insert("version".into(), version.into());
+                }
+            }
+            metadata.insert("image".into(), repository.into());
+            if let Some(tag) = tag {
+                metadata.insert("tag".into(), tag.into());
+            }
+        }
+        if let Some(replicas) = def
+            .get("deploy")
+            .and_then(|d| d.get("replicas"))
+            .and_then(Value::as_u64)
+        {
+            metadata.insert("replicas".into(), replicas.into());
+        }
+
+        let name = def
+            .get("container_name")
+            .and_then(scalar)
+            .unwrap_or_else(|| format!("{stack}_{key}"));
+
+        let mut depends: Vec<(String, &'static str)> = names(def.get("depends_on"))
+            .into_iter()
+            .map(|t| (t, "depends_on"))
+            .collect();
+        for link in names(def.get("links")) {
+            let target = link.split(':').next().unwrap_or(&link).trim().to_string();
+            depends.push((target, "links"));
+        }
+
+        let networks = match def.get("network_mode").and_then(scalar) {
+            Some(_) => Vec::new(),
+            None => match def.get("networks") {
+                Some(n) => names(Some(n)),
+                None => vec!["default".to_string()],
+            },
+        };
+
+        out.push(Service {
+            ports: published_ports(def.get("ports")),
+            placement: placement(def),
+            key,
+            name,
+            kind,
+            metadata,
+            depends,
+            networks,
+        });
+    }
+    Ok(out)
+}
+
+fn placement(def: &Value) -> Placement {
+    let constraints = names(
+        def.get("deploy")
+            .and_then(|d| d.get("placement"))
+            .and_then(|p| p.get("constraints")),
+    );
+
+    let mut hostnames = Vec::new();
+    let mut managers = false;
+    for c in &constraints {
+        let Some((lhs, rhs)) = c.split_once("==") else {
+            continue;
+        };
+        match lhs.trim() {
+            "node.hostname" => hostnames.push(rhs.trim().to_string()),
+            "node.role" if rhs.trim() == "manager" => managers = true,
+            _ => {}
+        }
+    }
+
+    if !hostnames.is_empty() {
+        Placement::Hostnames(hostnames)
+    } else if managers {
+        Placement::Managers
+    } else {
+        Placement::Default
+    }
+}
+
+/// Published ports in short (`[ip:]published:target[/proto]`) or long
+/// syntax. Ports without a published side are not reachable from outside
+/// and are skipped.
+fn published_ports(value: Option<&Value>) -> Vec<(String, String)> {
+    let mut out = Vec::new();
+    for port in value.and_then(Value::as_sequence).into_iter().flatten() {
+        if port.is_mapping() {
+            let published = port.get("published").and_then(scalar);
+            let target = port.get("target").and_then(scalar).unwrap_or_default();
+            let protocol = port
+                .get("protocol")
+                .and_then(scalar)
+                .unwrap_or_else(|| "tcp".to_string());
+            if let Some(published) = published.filter(|p| !p.is_empty()) {
+                out.push((
+                    format!("{protocol}/{published}"),
+                    format!("{published}:{target}/{protocol}"),
+                ));
+            }
+            continue;
+        }
+
+        let Some(spec) = scalar(port) else {
+            continue;
+        };
+        let (mapping, protocol) = match spec.rsplit_once('/') {
+            Some((m, p)) => (m.to_string(), p.to_string()),
+            None => (spec.clone(), "tcp".to_string()),
+        };
+        let parts: Vec<&str> = mapping.split(':').collect();
+        let (published, target) = match parts.as_slice() {
+            [published, target] => (*published, *target),
+            [_, published, target] => (*published, *target),
+            _ => continue,
+        };
+        if !published.is_empty() {
+            out.push((
+                format!("{protocol}/{published}"),
+                format!("{published}:{target}/{protocol}"),
+            ));
+        }
+    }
+    out
+}
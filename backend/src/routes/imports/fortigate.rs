@@ -0,0 +1,695 @@
+//! FortiGate configuration import.
+//!
+//! Reads a configuration export (`show full-configuration` or a backup
+//! file), resolves the addresses, address groups, services and service
+//! groups that accepting policies refer to, and proposes one edge per
+//! source/destination node pair. Addresses are matched against live host
+//! and service nodes: a single-IP address by the node's `ip` metadata,
+//! otherwise by FQDN or object name against the node name. Ports end up as
+//! flows on the claim and every contributing policy as `firewall_rule`
+//! evidence. VDOMs are read separately; object names are per VDOM.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use axum::{
+    extract::{Multipart, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::{
+    audit::RequestContext,
+    auth::AuthActor,
+    models::{edge::EdgeKind, edge_claim_evidence::NewEdgeClaimEvidence},
+    routes::AppState,
+};
+
+use super::common::{propose, BatchWriter, EdgeMap, Upload};
+use super::{ImportBatch, ProposalItem};
+use crate::routes::edges::helpers::internal_error;
+
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct FortigateOptions {
+    /// `flows_to` (standard) eller `depends_on`.
+    pub edge_kind: Option<EdgeKind>,
+    /// Confidence för förslagen; standard 60.
+    pub confidence: Option<i16>,
+}
+
+/// Shape of the multipart body; only used for the OpenAPI document.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct FortigateUpload {
+    /// Konfigurationsexport från FortiGate (text).
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+    pub options: Option<FortigateOptions>,
+    /// Batchens källa; standard `fortigate`.
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PolicyIssue {
+    pub vdom: Option<String>,
+    pub policy_id: String,
+    pub message: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct FortigateImportResult {
+    /// Saknas när ingen policy gav något förslag.
+    pub batch: Option<ImportBatch>,
+    /// Aktiva policyer med `action accept`.
+    pub policies: usize,
+    /// Policyer som nekar trafik eller är avstängda.
+    pub ignored_policies: usize,
+    pub proposals: Vec<ProposalItem>,
+    pub issues: Vec<PolicyIssue>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/fortigate",
+    request_body(content = FortigateUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = FortigateImportResult),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 413, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn import_fortigate(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    multipart: Multipart,
+) -> Result<Json<FortigateImportResult>, ApiError> {
+    let mut upload = Upload::<FortigateOptions>::read(multipart, "fortigate").await?;
+    let (filename, bytes) = upload.file("fortigate.conf")?;
+    let options = upload.options.unwrap_or_default();
+    let source = upload.source;
+    let edge_kind = options.edge_kind.unwrap_or(EdgeKind::FlowsTo);
+    if !matches!(edge_kind, EdgeKind::FlowsTo | EdgeKind::DependsOn) {
+        return Err(ApiError::validation(
+            "options.edge_kind",
+            "edge_kind måste vara flows_to eller depends_on",
+        ));
+    }
+    let confidence = options.confidence.unwrap_or(60);
+    if !(0..=100).contains(&confidence) {
+        return Err(ApiError::validation(
+            "options.confidence",
+            "confidence måste vara mellan 0 och 100",
+        ));
+    }
+
+    let root = parse_config(&String::from_utf8_lossy(&bytes))
+        .map_err(|e| ApiError::validation("file", e))?;
+    let hostname = root
+        .config("system global")
+        .and_then(|g| g.first("hostname"))
+        .map(str::to_string);
+
+    let mut scopes: Vec<(Option<String>, &Block)> = Vec::new();
+    collect_scopes(&root, None, &mut scopes);
+    if scopes.is_empty() {
+        return Err(ApiError::validation(
+            "file",
+            "Filen innehåller ingen 'config firewall policy'",
+        ));
+    }
+
+    let nodes = NodeIndex::load(&state.pool).await?;
+
+    let mut issues: Vec<PolicyIssue> = Vec::new();
+    let mut policies = 0;
+    let mut ignored_policies = 0;
+    let mut proposed = EdgeMap::new();
+    // Conflicts come back keyed by the first contributing policy.
+    let mut origins: HashMap<String, (Option<String>, String)> = HashMap::new();
+
+    for (vdom, block) in &scopes {
+        let objects = Objects::read(block);
+
+        for (policy_id, policy) in block
+            .config("firewall policy")
+            .map(|p| p.edits.as_slice())
+            .unwrap_or_default()
+        {
+            let accepts = policy.first("action") == Some("accept");
+            let enabled = policy.first("status") != Some("disable");
+            if !accepts || !enabled {
+                ignored_policies += 1;
+                continue;
+            }
+            policies += 1;
+
+            let mut issue = |message: String| {
+                issues.push(PolicyIssue {
+                    vdom: vdom.clone(),
+                    policy_id: policy_id.clone(),
+                    message,
+                })
+            };
+
+            let mut ends: [BTreeSet<Uuid>; 2] = Default::default();
+            for (side, key) in [(0, "srcaddr"), (1, "dstaddr")] {
+                for name in policy.values(key) {
+                    for target in objects.expand_address(name) {
+                        match nodes.resolve(&target) {
+                            Resolved::Node(id) => {
+                                ends[side].insert(id);
+                            }
+                            Resolved::Any => issue(format!(
+                                "Adressen '{}' omfattar allt och kopplas inte till någon nod",
+                                target.object
+                            )),
+                            Resolved::Missing => issue(format!(
+                                "Adressen '{}' ({}) matchar ingen nod",
+                                target.object,
+                                target.describe()
+                            )),
+                            Resolved::Ambiguous(n) => issue(format!(
+                                "Adressen '{}' ({}) matchar {n} noder",
+                                target.object,
+                                target.describe()
+                            )),
+                        }
+                    }
+                }
+            }
+            let [src, dst] = ends;
+            if src.is_empty() || dst.is_empty() {
+                continue;
+            }
+
+            let mut protocols: BTreeSet<String> = BTreeSet::new();
+            for name in policy.values("service") {
+                protocols.extend(objects.expand_service(name));
+            }
+
+            let name = policy.first("name").unwrap_or_default();
+            let comment = policy.first("comments").unwrap_or_default();
+            let note = [name, comment]
+                .into_iter()
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join(" – ");
+            let reference = match vdom {
+                Some(v) => format!("policy {policy_id} (vdom {v})"),
+                None => format!("policy {policy_id}"),
+            };
+            origins
+                .entry(reference.clone())
+                .or_insert_with(|| (vdom.clone(), policy_id.clone()));
+
+            for &from in &src {
+                for &to in &dst {
+                    if from == to {
+                        continue;
+                    }
+                    let p = propose(&mut proposed, from, to, edge_kind, &reference, confidence);
+                    p.protocols.extend(protocols.iter().cloned());
+                    p.evidence.push(NewEdgeClaimEvidence {
+                        evidence_type: "firewall_rule".to_string(),
+                        reference: reference.clone(),
+                        note: Some(if note.is_empty() {
+                            filename.clone()
+                        } else {
+                            format!("{note} ({filename})")
+                        }),
+                    });
+                }
+            }
+        }
+    }
+
+    if proposed.is_empty() {
+        return Ok(Json(FortigateImportResult {
+            batch: None,
+            policies,
+            ignored_policies,
+            proposals: Vec::new(),
+            issues,
+        }));
+    }
+
+    let import = BatchWriter::begin(
+        &state.pool,
+        ctx,
+        &source,
+        serde_json::json!({
+            "filename": filename,
+            "hostname": hostname,
+            "vdoms": scopes.iter().filter_map(|(v, _)| v.clone()).collect::<Vec<_>>(),
+            "policies": policies,
+        }),
+    )
+    .await?;
+
+    let written = import.finish(&state.pool, &actor, proposed).await?;
+    for (origin, message) in written.conflicts {
+        let (vdom, policy_id) = origins.remove(&origin).unwrap_or_default();
+        issues.push(PolicyIssue {
+            vdom,
+            policy_id,
+            message,
+        });
+    }
+
+    Ok(Json(FortigateImportResult {
+        batch: Some(written.batch),
+        policies,
+        ignored_policies,
+        proposals: written.proposals,
+        issues,
+    }))
+}
+
+/// One `config` or `edit` level of the configuration tree.
+#[derive(Debug, Default)]
+struct Block {
+    set: HashMap<String, Vec<String>>,
+    configs: Vec<(String, Block)>,
+    edits: Vec<(String, Block)>,
+}
+
+impl Block {
+    fn config(&self, path: &str) -> Option<&Block> {
+        self.configs.iter().find(|(p, _)| p == path).map(|(_, b)| b)
+    }
+
+    fn values(&self, key: &str) -> &[String] {
+        self.set.get(key).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    fn first(&self, key: &str) -> Option<&str> {
+        self.values(key).first().map(String::as_str)
+    }
+}
+
+fn parse_config(text: &str) -> Result<Block, String> {
+    enum Level {
+        Config(String),
+        Edit(String),
+    }
+
+    let mut stack: Vec<(Level, Block)> = Vec::new();
+    let mut root = Block::default();
+
+    for (i, line) in text.lines().enumerate() {
+        let line_no = i + 1;
+        let tokens = tokenize(line).map_err(|e| format!("Rad {line_no}: {e}"))?;
+        let Some((keyword, args)) = tokens.split_first() else {
+            continue;
+        };
+
+        match keyword.as_str() {
+            "config" => stack.push((Level::Config(args.join(" ")), Block::default())),
+            "edit" => {
+                let name = args
+                    .first()
+                    .cloned()
+                    .ok_or_else(|| format!("Rad {line_no}: 'edit' saknar namn"))?;
+                stack.push((Level::Edit(name), Block::default()));
+            }
+            "next" => match stack.pop() {
+                Some((Level::Edit(name), block)) => {
+                    current(&mut stack, &mut root).edits.push((name, block))
+                }
+                _ => return Err(format!("Rad {line_no}: 'next' utan 'edit'")),
+            },
+            "end" => {
+                // Some exports close the last `edit` of a table with `end`
+                // alone.
+                if let Some((Level::Edit(_), _)) = stack.last() {
+                    if let Some((Level::Edit(name), block)) = stack.pop() {
+                        current(&mut stack, &mut root).edits.push((name, block));
+                    }
+                }
+                match stack.pop() {
+                    Some((Level::Config(path), block)) => {
+                        current(&mut stack, &mut root).configs.push((path, block))
+                    }
+                    _ => return Err(format!("Rad {line_no}: 'end' utan 'config'")),
+                }
+            }
+            "set" | "append" => {
+                let Some((key, values)) = args.split_first() else {
+                    continue;
+                };
+                let block = current(&mut stack, &mut root);
+                let entry = block.set.entry(key.clone()).or_default();
+                if keyword == "set" {
+                    entry.clear();
+                }
+                entry.extend(values.iter().cloned());
+            }
+            "unset" => {
+                if let Some(key) = args.first() {
+                    current(&mut stack, &mut root).set.remove(key);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if !stack.is_empty() {
+        return Err("Filen slutar mitt i ett 'config'-block".to_string());
+    }
+    Ok(root)
+}
+
+fn current<'a>(stack: &'a mut [(impl Sized, Block)], root: &'a mut Block) -> &'a mut Block {
+    match stack.last_mut() {
+        Some((_, block)) => block,
+        None => root,
+    }
+}
+
+/// Splits a line into words; double-quoted words may contain spaces and
+/// `\"`. Lines starting with `#` are comments.
+fn tokenize(line: &str) -> Result<Vec<String>, &'static str> {
+    let line = line.trim();
+    if line.starts_with('#') {
+        return Ok(Vec::new());
+    }
+
+    let mut out = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut word = String::new();
+        if c == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('\\') => {
+                        if let Some(escaped) = chars.next() {
+                            word.push(escaped);
+                        }
+                    }
+                    Some('"') => break,
+                    Some(ch) => word.push(ch),
+                    None => return Err("citattecken saknar avslut"),
+                }
+            }
+        } else {
+            while let Some(&ch) = chars.peek() {
+                if ch.is_whitespace() {
+                    break;
+                }
+                word.push(ch);
+                chars.next();
+            }
+        }
+        out.push(word);
+    }
+    Ok(out)
+}
+
+/// Blocks holding firewall policies: the root, or each VDOM of a
+/// multi-VDOM configuration.
+fn collect_scopes<'a>(
+    block: &'a Block,
+    vdom: Option<String>,
+    out: &mut Vec<(Option<String>, &'a Block)>,
+) {
+    if block.config("firewall policy").is_some() {
+        out.push((vdom, block));
+    }
+    for (path, cfg) in &block.configs {
+        if path == "vdom" {
+            for (name, inner) in &cfg.edits {
+                collect_scopes(inner, Some(name.clone()), out);
+            }
+        }
+    }
+}
+
+/// An address object after group expansion.
+struct Target {
+    object: String,
+    ip: Option<String>,
+    fqdn: Option<String>,
+    /// Subnet or range that cannot be pinned to one node.
+    range: Option<String>,
+    any: bool,
+}
+
+impl Target {
+    fn describe(&self) -> String {
+        self.ip
+            .clone()
+            .or_else(|| self.fqdn.clone())
+            .or_else(|| self.range.clone())
+            .unwrap_or_else(|| "okänt objekt".to_string())
+    }
+}
+
+struct Objects<'a> {
+    addresses: HashMap<&'a str, &'a Block>,
+    address_groups: HashMap<&'a str, &'a Block>,
+    services: HashMap<&'a str, &'a Block>,
+    service_groups: HashMap<&'a str, &'a Block>,
+}
+
+impl<'a> Objects<'a> {
+    fn read(scope: &'a Block) -> Self {
+        let table = |path: &str| -> HashMap<&'a str, &'a Block> {
+            scope
+                .config(path)
+                .map(|c| c.edits.iter().map(|(n, b)| (n.as_str(), b)).collect())
+                .unwrap_or_default()
+        };
+        Self {
+            addresses: table("firewall address"),
+            address_groups: table("firewall addrgrp"),
+            services: table("firewall service custom"),
+            service_groups: table("firewall service group"),
+        }
+    }
+
+    fn expand_address(&self, name: &str) -> Vec<Target> {
+        let mut out = Vec::new();
+        self.expand_address_into(name, &mut HashSet::new(), &mut out);
+        out
+    }
+
+    fn expand_address_into(&self, name: &str, seen: &mut HashSet<String>, out: &mut Vec<Target>) {
+        if !seen.insert(name.to_string()) {
+            return;
+        }
+        if let Some(group) = self.address_groups.get(name) {
+            for member in group.values("member") {
+                self.expand_address_into(member, seen, out);
+            }
+            return;
+        }
+
+        let mut target = Target {
+            object: name.to_string(),
+            ip: None,
+            fqdn: None,
+            range: None,
+            any: name.eq_ignore_ascii_case("all"),
+        };
+        if let Some(addr) = self.addresses.get(name) {
+            match addr.first("type").unwrap_or("ipmask") {
+                "ipmask" => {
+                    let subnet = addr.values("subnet");
+                    if let Some(ip) = single_host(subnet) {
+                        target.ip = Some(ip);
+                    } else if subnet.first().map(String::as_str) == Some("0.0.0.0") {
+                        target.any = true;
+                    } else {
+                        target.range = Some(subnet.join(" "));
+                    }
+                }
+                "iprange" => {
+                    if let (Some(start), Some(end)) = (addr.first("start-ip"), addr.first("end-ip"))
+                    {
+                        if start == end {
+                            target.ip = Some(start.to_string());
+                        } else {
+                            target.range = Some(format!("{start}-{end}"));
+                        }
+                    }
+                }
+                "fqdn" => target.fqdn = addr.first("fqdn").map(str::to_lowercase),
+                _ => {}
+            }
+        }
+        out.push(target);
+    }
+
+    /// Protocol/port strings such as `tcp/443`, `udp/53` or `icmp`.
+    fn expand_service(&self, name: &str) -> Vec<String> {
+        let mut out = Vec::new();
+        self.expand_service_into(name, &mut HashSet::new(), &mut out);
+        out
+    }
+
+    fn expand_service_into(&self, name: &str, seen: &mut HashSet<String>, out: &mut Vec<String>) {
+        if !seen.insert(name.to_string()) {
+            return;
+        }
+        if let Some(group) = self.service_groups.get(name) {
+            for member in group.values("member") {
+                self.expand_service_into(member, seen, out);
+            }
+            return;
+        }
+
+        let Some(service) = self.services.get(name) else {
+            match predefined_service(name) {
+                Some(list) => out.extend(list.iter().map(|s| s.to_string())),
+                None => out.push(name.to_string()),
+            }
+            return;
+        };
+
+        match service
+            .first("protocol")
+            .unwrap_or("TCP/UDP/SCTP")
+            .to_ascii_uppercase()
+            .as_str()
+        {
+            "ICMP" | "ICMP6" => out.push("icmp".to_string()),
+            "IP" => out.push(match service.first("protocol-number") {
+                Some(n) if n != "0" => format!("ip/{n}"),
+                _ => "ip".to_string(),
+            }),
+            _ => {
+                for (key, proto) in [
+                    ("tcp-portrange", "tcp"),
+                    ("udp-portrange", "udp"),
+                    ("sctp-portrange", "sctp"),
+                ] {
+                    for range in service.values(key) {
+                        // `dst[:src]`; only the destination side matters here.
+                        let dst = range.split(':').next().unwrap_or(range);
+                        out.push(format!("{proto}/{dst}"));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// `set subnet 10.0.0.5 255.255.255.255` or `10.0.0.5/32`.
+fn single_host(subnet: &[String]) -> Option<String> {
+    match subnet {
+        [ip, mask] if mask == "255.255.255.255" => Some(ip.clone()),
+        [cidr] => cidr.strip_suffix("/32").map(str::to_string),
+        _ => None,
+    }
+}
+
+/// FortiGate's built-in service objects, used when the export does not
+/// define them.
+fn predefined_service(name: &str) -> Option<&'static [&'static str]> {
+    Some(match name.to_ascii_uppercase().as_str() {
+        "ALL" => &["any"],
+        "ALL_TCP" => &["tcp/1-65535"],
+        "ALL_UDP" => &["udp/1-65535"],
+        "ALL_ICMP" | "PING" => &["icmp"],
+        "HTTP" => &["tcp/80"],
+        "HTTPS" => &["tcp/443"],
+        "SSH" => &["tcp/22"],
+        "TELNET" => &["tcp/23"],
+        "FTP" => &["tcp/21"],
+        "SMTP" => &["tcp/25"],
+        "SMTPS" => &["tcp/465"],
+        "DNS" => &["tcp/53", "udp/53"],
+        "NTP" => &["tcp/123", "udp/123"],
+        "POP3" => &["tcp/110"],
+        "POP3S" => &["tcp/995"],
+        "IMAP" => &["tcp/143"],
+        "IMAPS" => &["tcp/993"],
+        "KERBEROS" => &["tcp/88", "udp/88"],
+        "LDAP" => &["tcp/389"],
+        "LDAP_UDP" => &["udp/389"],
+        "SAMBA" => &["tcp/139"],
+        "SMB" => &["tcp/445"],
+        "SNMP" => &["tcp/161-162", "udp/161-162"],
+        "SYSLOG" => &["udp/514"],
+        "RDP" => &["tcp/3389"],
+        "MYSQL" => &["tcp/3306"],
+        "MS-SQL" => &["tcp/1433", "tcp/1434"],
+        "DHCP" => &["udp/67-68"],
+        _ => return None,
+    })
+}
+
+enum Resolved {
+    Node(Uuid),
+    Any,
+    Missing,
+    Ambiguous(usize),
+}
+
+/// Live host and service nodes keyed by IP (`ip` metadata, comma or space
+/// separated) and by lower-cased name.
+struct NodeIndex {
+    by_ip: HashMap<String, Vec<Uuid>>,
+    by_name: HashMap<String, Vec<Uuid>>,
+}
+
+impl NodeIndex {
+    async fn load(pool: &sqlx::PgPool) -> Result<Self, ApiError> {
+        let rows: Vec<(Uuid, String, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT id, name, metadata->>'ip'
+            FROM nodes
+            WHERE deleted_at IS NULL
+              AND kind IN ('host', 'service')
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(internal_error)?;
+
+        let mut index = NodeIndex {
+            by_ip: HashMap::new(),
+            by_name: HashMap::new(),
+        };
+        for (id, name, ips) in rows {
+            index
+                .by_name
+                .entry(name.to_lowercase())
+                .or_default()
+                .push(id);
+            for ip in ips
+                .iter()
+                .flat_map(|s| s.split([',', ';', ' ']))
+                .map(|s| s.trim().trim_end_matches("/32"))
+                .filter(|s| !s.is_empty())
+            {
+                index.by_ip.entry(ip.to_string()).or_default().push(id);
+            }
+        }
+        Ok(index)
+    }
+
+    fn resolve(&self, target: &Target) -> Resolved {
+        if target.any {
+            return Resolved::Any;
+        }
+        let candidates = [
+            target.ip.as_ref().and_then(|ip| self.by_ip.get(ip)),
+            target.fqdn.as_ref().and_then(|f| self.by_name.get(f)),
+            self.by_name.get(&target.object.to_lowercase()),
+        ];
+        match candidates.into_iter().flatten().next() {
+            Some(ids) if ids.len() == 1 => Resolved::Node(ids[0]),
+            Some(ids) => Resolved::Ambiguous(ids.len()),
+            None => Resolved::Missing,
+        }
+    }
+}
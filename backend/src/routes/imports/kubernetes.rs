@@ -0,0 +1,883 @@
+//! Kubernetes manifest import.
+//!
+//! Takes one or more YAML files, one `file` part each; for a directory the
+//! client sends every file with its relative path as file name, which is
+//! kept as the manifest path. A file may hold any number of documents and
+//! `List` objects. Deployments, StatefulSets and DaemonSets become workload
+//! nodes (a `database` node when the first container's image is a known
+//! database engine); Services, Ingresses and PersistentVolumeClaims become
+//! `service` nodes. Nodes are named `<namespace>/<kind>/<name>` with the
+//! kind in lower case, the way `kubectl` prints them. Edges:
+//!
+//! - `runs_on` from workloads to the `nodeName` / `kubernetes.io/hostname`
+//!   host, otherwise to `options.hosts`;
+//! - `exposes` from a Service to the workloads its selector matches, with
+//!   the service ports as flows, and from an Ingress to its backends;
+//! - `stores_data` from workloads to the claims they mount, StatefulSet
+//!   volume claim templates included;
+//! - `depends_on` from workloads to Services whose DNS name shows up in a
+//!   container's env values, command or args.
+//!
+//! Every edge carries `document` evidence naming the manifest path, and
+//! every node has it as `manifest_path` in its metadata. Nothing is fetched
+//! from a cluster.
+
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{
+    extract::{Multipart, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::{
+    audit::RequestContext,
+    auth::AuthActor,
+    models::{edge::EdgeKind, edge_claim_evidence::NewEdgeClaimEvidence, node::NodeKind},
+    routes::AppState,
+};
+
+use super::common::{
+    database_engine, deleted_nodes, engine_version, names, propose, scalar, split_image,
+    BatchWriter, HostIndex, Upload,
+};
+use super::nodes::{propose_node, NodeProposalAction, ProposalNodeInput};
+use super::{ImportBatch, ProposalItem};
+
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct KubernetesOptions {
+    /// Namnrymd för objekt utan `metadata.namespace`; standard `default`.
+    pub namespace: Option<String>,
+    /// Värdar (klusternoder) för arbetslaster utan nodval.
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// Nodtyp för arbetslaster som inte är databaser: `container`
+    /// (standard), `service` eller `app`.
+    pub workload_kind: Option<NodeKind>,
+    /// Sätts som `env` i nodernas metadata.
+    pub env: Option<String>,
+    /// Confidence för förslagen; standard 70.
+    pub confidence: Option<i16>,
+}
+
+/// Shape of the multipart body; only used for the OpenAPI document.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct KubernetesUpload {
+    /// En eller flera manifestfiler (YAML); filnamnet blir manifestets sökväg.
+    #[schema(value_type = Vec<String>, format = Binary)]
+    pub file: Vec<Vec<u8>>,
+    pub options: Option<KubernetesOptions>,
+    /// Batchens källa; standard `kubernetes`.
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ManifestIssue {
+    pub path: String,
+    /// `Kind/name` när problemet gäller ett objekt.
+    pub object: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ManifestNode {
+    pub path: String,
+    /// `Kind/name` i manifestet.
+    pub object: String,
+    pub id: Uuid,
+    pub kind: String,
+    pub name: String,
+    /// `false` när noden redan fanns.
+    pub created: bool,
+    /// Förslagets claim; för befintliga noder bara när manifestet ändrar något.
+    pub claim_id: Option<Uuid>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct KubernetesImportResult {
+    /// Saknas när filerna inte gav någon nod.
+    pub batch: Option<ImportBatch>,
+    pub files: usize,
+    pub objects: usize,
+    /// Objekt av andra typer, t.ex. ConfigMap och Secret.
+    pub ignored_objects: usize,
+    pub nodes: Vec<ManifestNode>,
+    pub proposals: Vec<ProposalItem>,
+    pub issues: Vec<ManifestIssue>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/kubernetes",
+    request_body(content = KubernetesUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = KubernetesImportResult),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 413, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn import_kubernetes(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    multipart: Multipart,
+) -> Result<Json<KubernetesImportResult>, ApiError> {
+    let mut upload = Upload::<KubernetesOptions>::read(multipart, "kubernetes").await?;
+    let files = upload.files(|n| format!("manifest-{n}.yaml"))?;
+    let options = upload.options.unwrap_or_default();
+    let source = upload.source;
+    let workload_kind = options.workload_kind.unwrap_or(NodeKind::Container);
+    if !matches!(
+        workload_kind,
+        NodeKind::Container | NodeKind::Service | NodeKind::App
+    ) {
+        return Err(ApiError::validation(
+            "options.workload_kind",
+            "workload_kind måste vara container, service eller app",
+        ));
+    }
+    let confidence = options.confidence.unwrap_or(70);
+    if !(0..=100).contains(&confidence) {
+        return Err(ApiError::validation(
+            "options.confidence",
+            "confidence måste vara mellan 0 och 100",
+        ));
+    }
+    let default_namespace = options
+        .namespace
+        .clone()
+        .filter(|n| !n.trim().is_empty())
+        .unwrap_or_else(|| "default".to_string());
+
+    let mut issues: Vec<ManifestIssue> = Vec::new();
+    let mut objects: Vec<Object> = Vec::new();
+    let mut ignored_objects = 0;
+
+    for (path, bytes) in &files {
+        for document in serde_yaml::Deserializer::from_slice(bytes) {
+            let mut doc = Value::deserialize(document)
+                .and_then(|mut v| v.apply_merge().map(|_| v))
+                .map_err(|e| ApiError::validation("file", format!("{path}: Ogiltig YAML: {e}")))?;
+            let items = match doc.get("kind").and_then(Value::as_str) {
+                Some("List") => match doc.get_mut("items").map(std::mem::take) {
+                    Some(Value::Sequence(items)) => items,
+                    _ => Vec::new(),
+                },
+                _ => vec![doc],
+            };
+            for item in items {
+                match Object::read(path, item, &default_namespace) {
+                    Ok(Some(object)) => objects.push(object),
+                    Ok(None) => ignored_objects += 1,
+                    Err(message) => issues.push(ManifestIssue {
+                        path: path.clone(),
+                        object: None,
+                        message,
+                    }),
+                }
+            }
+        }
+    }
+
+    // StatefulSet volume claim templates stand in for the claims the
+    // controller creates per replica.
+    let templates: Vec<Object> = objects
+        .iter()
+        .filter(|o| o.kind == ObjectKind::StatefulSet)
+        .flat_map(|o| o.claim_templates())
+        .collect();
+    objects.extend(templates);
+
+    let mut index: HashMap<(ObjectKind, &str, &str), usize> = HashMap::new();
+    let mut skipped = vec![false; objects.len()];
+    for (i, o) in objects.iter().enumerate() {
+        if let Some(&first) = index.get(&(o.kind, o.namespace.as_str(), o.name.as_str())) {
+            skipped[i] = true;
+            issues.push(o.issue(format!("Objektet finns redan i {}", objects[first].path)));
+            continue;
+        }
+        index.insert((o.kind, &o.namespace, &o.name), i);
+    }
+
+    let object_count = objects.len();
+
+    let hosts = HostIndex::load(&state.pool).await?;
+    let mut default_hosts: Vec<Uuid> = Vec::new();
+    for name in &options.hosts {
+        let id = hosts
+            .by_name
+            .get(&name.trim().to_lowercase())
+            .ok_or_else(|| {
+                ApiError::validation("options.hosts", format!("Värden '{name}' finns inte"))
+            })?;
+        default_hosts.push(*id);
+    }
+
+    let planned: Vec<(NodeKind, String)> = objects
+        .iter()
+        .map(|o| (o.node_kind(workload_kind), o.node_name()))
+        .collect();
+    let deleted = deleted_nodes(
+        &state.pool,
+        planned.iter().map(|(_, name)| name.clone()).collect(),
+    )
+    .await?;
+    for (i, (kind, name)) in planned.iter().enumerate() {
+        if !skipped[i] && deleted.contains(&(kind.as_str().to_string(), name.clone())) {
+            skipped[i] = true;
+            issues.push(objects[i].issue(format!(
+                "Noden '{name}' ({}) är borttagen; återställ den innan import",
+                kind.as_str()
+            )));
+        }
+    }
+
+    if skipped.iter().all(|s| *s) {
+        return Ok(Json(KubernetesImportResult {
+            batch: None,
+            files: files.len(),
+            objects: object_count,
+            ignored_objects,
+            nodes: Vec::new(),
+            proposals: Vec::new(),
+            issues,
+        }));
+    }
+
+    let mut import = BatchWriter::begin(
+        &state.pool,
+        ctx,
+        &source,
+        serde_json::json!({
+            "files": files.iter().map(|(path, _)| path).collect::<Vec<_>>(),
+            "objects": object_count,
+        }),
+    )
+    .await?;
+
+    let mut ids: Vec<Option<Uuid>> = vec![None; objects.len()];
+    let mut nodes: Vec<ManifestNode> = Vec::new();
+
+    for (i, o) in objects.iter().enumerate() {
+        if skipped[i] {
+            continue;
+        }
+        let (kind, name) = &planned[i];
+        let input = ProposalNodeInput {
+            id: None,
+            temp_id: None,
+            kind: Some(*kind),
+            name: Some(name.clone()),
+            metadata: Some(serde_json::Value::Object(
+                o.metadata(*kind, options.env.as_deref()),
+            )),
+            details: None,
+            source: source.clone(),
+            confidence: Some(confidence),
+        };
+        let proposed =
+            propose_node(&mut import.tx, ctx, &actor, import.batch.id, i, &input).await?;
+        if proposed.action == NodeProposalAction::Conflict {
+            issues.push(o.issue(proposed.message_sv.clone().unwrap_or_default()));
+        }
+        ids[i] = Some(proposed.node_id);
+        nodes.push(ManifestNode {
+            path: o.path.clone(),
+            object: o.label(),
+            id: proposed.node_id,
+            kind: proposed.kind,
+            name: proposed.name,
+            created: proposed.action == NodeProposalAction::CreateNode,
+            claim_id: proposed.claim_id,
+        });
+    }
+
+    let lookup = |kind: ObjectKind, namespace: &str, name: &str| {
+        index
+            .get(&(kind, namespace, name))
+            .and_then(|&i| ids[i].map(|id| (i, id)))
+    };
+    let evidence = |o: &Object, note: String| NewEdgeClaimEvidence {
+        evidence_type: "document".to_string(),
+        reference: o.path.clone(),
+        note: Some(format!("{}: {note}", o.label())),
+    };
+
+    let mut proposed = BTreeMap::new();
+
+    for (i, o) in objects.iter().enumerate() {
+        let Some(from) = ids[i] else {
+            continue;
+        };
+
+        match o.kind {
+            ObjectKind::Deployment | ObjectKind::StatefulSet | ObjectKind::DaemonSet => {
+                let pod = o.pod();
+
+                let pinned: Vec<String> = [
+                    pod.and_then(|p| p.get("nodeName")),
+                    pod.and_then(|p| at(p, &["nodeSelector", "kubernetes.io/hostname"])),
+                ]
+                .into_iter()
+                .flatten()
+                .filter_map(scalar)
+                .take(1)
+                .collect();
+                let (hosts_for, reason) = if pinned.is_empty() {
+                    (default_hosts.clone(), "angivna värdar".to_string())
+                } else {
+                    let mut found = Vec::new();
+                    for name in &pinned {
+                        match hosts.by_name.get(&name.to_lowercase()) {
+                            Some(id) => found.push(*id),
+                            None => issues.push(o.issue(format!("Värden '{name}' finns inte"))),
+                        }
+                    }
+                    (found, format!("nodval {}", pinned.join(", ")))
+                };
+                if pinned.is_empty() && hosts_for.is_empty() {
+                    issues.push(o.issue(
+                        "Ingen värd: ange nodeName, nodeSelector eller options.hosts".into(),
+                    ));
+                }
+                for host in hosts_for {
+                    propose(
+                        &mut proposed,
+                        from,
+                        host,
+                        EdgeKind::RunsOn,
+                        &o.label(),
+                        confidence,
+                    )
+                    .evidence
+                    .push(evidence(o, reason.clone()));
+                }
+
+                for claim in o.claim_names() {
+                    let Some((_, to)) = lookup(ObjectKind::Claim, &o.namespace, &claim) else {
+                        issues.push(o.issue(format!(
+                            "PersistentVolumeClaim '{claim}' finns inte i filerna"
+                        )));
+                        continue;
+                    };
+                    propose(
+                        &mut proposed,
+                        from,
+                        to,
+                        EdgeKind::StoresData,
+                        &o.label(),
+                        confidence,
+                    )
+                    .evidence
+                    .push(evidence(
+                        o,
+                        format!("monterar PersistentVolumeClaim/{claim}"),
+                    ));
+                }
+
+                let texts = o.container_texts();
+                for (j, s) in objects.iter().enumerate() {
+                    if s.kind != ObjectKind::Service {
+                        continue;
+                    }
+                    let Some(to) = ids[j] else {
+                        continue;
+                    };
+                    let same_namespace = s.namespace == o.namespace;
+                    if let Some((var, _)) = texts.iter().find(|(var, text)| {
+                        addresses(text, var.as_deref(), &s.name, &s.namespace, same_namespace)
+                    }) {
+                        let how = match var {
+                            Some(var) => format!("env {var} pekar på Service/{}", s.name),
+                            None => format!("argument pekar på Service/{}", s.name),
+                        };
+                        propose(
+                            &mut proposed,
+                            from,
+                            to,
+                            EdgeKind::DependsOn,
+                            &o.label(),
+                            confidence,
+                        )
+                        .evidence
+                        .push(evidence(o, how));
+                    }
+                }
+            }
+            ObjectKind::Service => {
+                let selector = selector(&o.doc);
+                if selector.is_empty() {
+                    continue;
+                }
+                let ports = service_ports(&o.doc);
+                for (j, w) in objects.iter().enumerate() {
+                    if !w.kind.is_workload() || w.namespace != o.namespace {
+                        continue;
+                    }
+                    let Some(to) = ids[j] else {
+                        continue;
+                    };
+                    let labels = w.pod_labels();
+                    if selector.iter().all(|(k, v)| labels.get(k) == Some(v)) {
+                        let p = propose(
+                            &mut proposed,
+                            from,
+                            to,
+                            EdgeKind::Exposes,
+                            &o.label(),
+                            confidence,
+                        );
+                        p.protocols.extend(ports.iter().cloned());
+                        p.evidence
+                            .push(evidence(o, format!("selector matchar {}", w.label())));
+                    }
+                }
+            }
+            ObjectKind::Ingress => {
+                for (backend, rule) in ingress_backends(&o.doc) {
+                    let Some((_, to)) = lookup(ObjectKind::Service, &o.namespace, &backend) else {
+                        issues.push(o.issue(format!("Service '{backend}' finns inte i filerna")));
+                        continue;
+                    };
+                    propose(
+                        &mut proposed,
+                        from,
+                        to,
+                        EdgeKind::Exposes,
+                        &o.label(),
+                        confidence,
+                    )
+                    .evidence
+                    .push(evidence(o, format!("{rule} -> Service/{backend}")));
+                }
+            }
+            ObjectKind::Claim => {}
+        }
+    }
+
+    let written = import.finish(&state.pool, &actor, proposed).await?;
+    for (origin, message) in written.conflicts {
+        let path = objects
+            .iter()
+            .find(|o| o.label() == origin)
+            .map(|o| o.path.clone())
+            .unwrap_or_default();
+        issues.push(ManifestIssue {
+            path,
+            object: Some(origin),
+            message,
+        });
+    }
+
+    Ok(Json(KubernetesImportResult {
+        batch: Some(written.batch),
+        files: files.len(),
+        objects: object_count,
+        ignored_objects,
+        nodes,
+        proposals: written.proposals,
+        issues,
+    }))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ObjectKind {
+    Deployment,
+    StatefulSet,
+    DaemonSet,
+    Service,
+    Ingress,
+    Claim,
+}
+
+impl ObjectKind {
+    fn parse(kind: &str) -> Option<Self> {
+        Some(match kind {
+            "Deployment" => ObjectKind::Deployment,
+            "StatefulSet" => ObjectKind::StatefulSet,
+            "DaemonSet" => ObjectKind::DaemonSet,
+            "Service" => ObjectKind::Service,
+            "Ingress" => ObjectKind::Ingress,
+            "PersistentVolumeClaim" => ObjectKind::Claim,
+            _ => return None,
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            ObjectKind::Deployment => "Deployment",
+            ObjectKind::StatefulSet => "StatefulSet",
+            ObjectKind::DaemonSet => "DaemonSet",
+            ObjectKind::Service => "Service",
+            ObjectKind::Ingress => "Ingress",
+            ObjectKind::Claim => "PersistentVolumeClaim",
+        }
+    }
+
+    fn is_workload(self) -> bool {
+        matches!(
+            self,
+            ObjectKind::Deployment | ObjectKind::StatefulSet | ObjectKind::DaemonSet
+        )
+    }
+}
+
+struct Object {
+    path: String,
+    kind: ObjectKind,
+    namespace: String,
+    name: String,
+    doc: Value,
+}
+
+impl Object {
+    /// `Ok(None)` for kinds the import does not read.
+    fn read(path: &str, doc: Value, default_namespace: &str) -> Result<Option<Self>, String> {
+        if doc.is_null() {
+            return Ok(None);
+        }
+        let Some(kind) = doc.get("kind").and_then(Value::as_str).map(str::to_string) else {
+            return Err("Dokument utan 'kind'".to_string());
+        };
+        let Some(kind) = ObjectKind::parse(&kind) else {
+            return Ok(None);
+        };
+        let Some(name) = at(&doc, &["metadata", "name"]).and_then(scalar) else {
+            return Err(format!("{} utan metadata.name", kind.as_str()));
+        };
+        let namespace = at(&doc, &["metadata", "namespace"])
+            .and_then(scalar)
+            .unwrap_or_else(|| default_namespace.to_string());
+
+        Ok(Some(Object {
+            path: path.to_string(),
+            kind,
+            namespace,
+            name,
+            doc,
+        }))
+    }
+
+    fn label(&self) -> String {
+        format!("{}/{}", self.kind.as_str(), self.name)
+    }
+
+    fn node_name(&self) -> String {
+        format!(
+            "{}/{}/{}",
+            self.namespace,
+            self.kind.as_str().to_lowercase(),
+            self.name
+        )
+    }
+
+    fn issue(&self, message: String) -> ManifestIssue {
+        ManifestIssue {
+            path: self.path.clone(),
+            object: Some(self.label()),
+            message,
+        }
+    }
+
+    fn pod(&self) -> Option<&Value> {
+        at(&self.doc, &["spec", "template", "spec"])
+    }
+
+    fn pod_labels(&self) -> HashMap<String, String> {
+        string_map(at(&self.doc, &["spec", "template", "metadata", "labels"]))
+    }
+
+    fn containers(&self) -> impl Iterator<Item = &Value> {
+        let pod = self.pod();
+        ["initContainers", "containers"]
+            .into_iter()
+            .filter_map(move |key| pod.and_then(|p| p.get(key)).and_then(Value::as_sequence))
+            .flatten()
+    }
+
+    /// Image of the first regular container.
+    fn image(&self) -> Option<(String, Option<String>)> {
+        self.pod()
+            .and_then(|p| p.get("containers"))
+            .and_then(Value::as_sequence)
+            .and_then(|c| c.first())
+            .and_then(|c| c.get("image"))
+            .and_then(scalar)
+            .map(|image| split_image(&image))
+    }
+
+    fn node_kind(&self, workload_kind: NodeKind) -> NodeKind {
+        match self.kind {
+            ObjectKind::Service | ObjectKind::Ingress | ObjectKind::Claim => NodeKind::Service,
+            _ => match self.image() {
+                Some((repository, _)) if database_engine(&repository).is_some() => {
+                    NodeKind::Database
+                }
+                _ => workload_kind,
+            },
+        }
+    }
+
+    fn metadata(
+        &self,
+        kind: NodeKind,
+        env: Option<&str>,
+    ) -> serde_json::Map<String, serde_json::Value> {
+        let mut metadata = serde_json::Map::new();
+        metadata.insert("namespace".into(), self.namespace.clone().into());
+        metadata.insert("k8s_kind".into(), self.kind.as_str().into());
+        metadata.insert("manifest_path".into(), self.path.clone().into());
+        if let Some(env) = env {
+            metadata.insert("env".into(), env.into());
+        }
+
+        let spec = self.doc.get("spec");
+        match self.kind {
+            ObjectKind::Deployment | ObjectKind::StatefulSet | ObjectKind::DaemonSet => {
+                if let Some((repository, tag)) = self.image() {
+                    if kind == NodeKind::Database {
+                        if let Some(engine) = database_engine(&repository) {
+                            metadata.insert("engine".into(), engine.into());
+                        }
+                        if let Some(version) = tag.as_deref().and_then(engine_version) {
+                            metadata.insert("version".into(), version.into());
+                        }
+                    }
+                    metadata.insert("image".into(), repository.into());
+                    if let Some(tag) = tag {
+                        metadata.insert("tag".into(), tag.into());
+                    }
+                }
+                if let Some(replicas) = spec.and_then(|s| s.get("replicas")).and_then(Value::as_u64)
+                {
+                    metadata.insert("replicas".into(), replicas.into());
+                }
+            }
+            ObjectKind::Service => {
+                let service_type = spec
+                    .and_then(|s| s.get("type"))
+                    .and_then(scalar)
+                    .unwrap_or_else(|| "ClusterIP".to_string());
+                metadata.insert("service_type".into(), service_type.into());
+                let ports = service_ports(&self.doc);
+                if !ports.is_empty() {
+                    metadata.insert("ports".into(), ports.join(", ").into());
+                }
+            }
+            ObjectKind::Ingress => {
+                let hosts: Vec<String> = spec
+                    .and_then(|s| s.get("rules"))
+                    .and_then(Value::as_sequence)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|r| r.get("host").and_then(scalar))
+                    .collect();
+                if !hosts.is_empty() {
+                    metadata.insert("hosts".into(), hosts.join(", ").into());
+                }
+                if let Some(class) = spec
+                    .and_then(|s| s.get("ingressClassName"))
+                    .and_then(scalar)
+                {
+                    metadata.insert("ingress_class".into(), class.into());
+                }
+            }
+            ObjectKind::Claim => {
+                if let Some(class) = spec
+                    .and_then(|s| s.get("storageClassName"))
+                    .and_then(scalar)
+                {
+                    metadata.insert("storage_class".into(), class.into());
+                }
+                if let Some(size) = spec
+                    .and_then(|s| at(s, &["resources", "requests", "storage"]))
+                    .and_then(scalar)
+                {
+                    metadata.insert("size".into(), size.into());
+                }
+                let modes = names(spec.and_then(|s| s.get("accessModes")));
+                if !modes.is_empty() {
+                    metadata.insert("access_modes".into(), modes.join(", ").into());
+                }
+            }
+        }
+        metadata
+    }
+
+    /// Claims for a StatefulSet's `volumeClaimTemplates`, named
+    /// `<template>-<statefulset>` like the per-replica claims minus ordinal.
+    fn claim_templates(&self) -> Vec<Object> {
+        self.doc
+            .get("spec")
+            .and_then(|s| s.get("volumeClaimTemplates"))
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .filter_map(|t| {
+                let template = at(t, &["metadata", "name"]).and_then(scalar)?;
+                Some(Object {
+                    path: self.path.clone(),
+                    kind: ObjectKind::Claim,
+                    namespace: self.namespace.clone(),
+                    name: format!("{template}-{}", self.name),
+                    doc: t.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Claims the pod template mounts, templates included.
+    fn claim_names(&self) -> Vec<String> {
+        let mut claims: Vec<String> = self
+            .pod()
+            .and_then(|p| p.get("volumes"))
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten()
+            .filter_map(|v| at(v, &["persistentVolumeClaim", "claimName"]).and_then(scalar))
+            .collect();
+        if self.kind == ObjectKind::StatefulSet {
+            claims.extend(self.claim_templates().into_iter().map(|t| t.name));
+        }
+        claims
+    }
+
+    /// Env values (with the variable name), commands and args of every
+    /// container.
+    fn container_texts(&self) -> Vec<(Option<String>, String)> {
+        let mut texts = Vec::new();
+        for c in self.containers() {
+            for var in c
+                .get("env")
+                .and_then(Value::as_sequence)
+                .into_iter()
+                .flatten()
+            {
+                if let Some(value) = var.get("value").and_then(scalar) {
+                    texts.push((var.get("name").and_then(scalar), value));
+                }
+            }
+            for key in ["command", "args"] {
+                texts.extend(names(c.get(key)).into_iter().map(|t| (None, t)));
+            }
+        }
+        texts
+    }
+}
+
+fn at<'a>(value: &'a Value, path: &[&str]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |v, key| v.get(*key))
+}
+
+fn string_map(value: Option<&Value>) -> HashMap<String, String> {
+    value
+        .and_then(Value::as_mapping)
+        .into_iter()
+        .flatten()
+        .filter_map(|(k, v)| Some((scalar(k)?, scalar(v)?)))
+        .collect()
+}
+
+fn selector(service: &Value) -> HashMap<String, String> {
+    string_map(at(service, &["spec", "selector"]))
+}
+
+/// Service ports as `tcp/80`.
+fn service_ports(service: &Value) -> Vec<String> {
+    at(service, &["spec", "ports"])
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+        .filter_map(|p| {
+            let port = p.get("port").and_then(scalar)?;
+            let protocol = p
+                .get("protocol")
+                .and_then(scalar)
+                .unwrap_or_else(|| "TCP".to_string());
+            Some(format!("{}/{port}", protocol.to_lowercase()))
+        })
+        .collect()
+}
+
+/// Backend Service names with the rule that routes to them, for both
+/// `networking.k8s.io/v1` and the older `serviceName` form.
+fn ingress_backends(ingress: &Value) -> Vec<(String, String)> {
+    let backend_name = |b: &Value| {
+        at(b, &["service", "name"])
+            .or_else(|| b.get("serviceName"))
+            .and_then(scalar)
+    };
+
+    let mut out = Vec::new();
+    let spec = ingress.get("spec");
+    for key in ["defaultBackend", "backend"] {
+        if let Some(name) = spec.and_then(|s| s.get(key)).and_then(backend_name) {
+            out.push((name, "standardbackend".to_string()));
+        }
+    }
+    for rule in spec
+        .and_then(|s| s.get("rules"))
+        .and_then(Value::as_sequence)
+        .into_iter()
+        .flatten()
+    {
+        let host = rule.get("host").and_then(scalar).unwrap_or_default();
+        for path in at(rule, &["http", "paths"])
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(name) = path.get("backend").and_then(backend_name) {
+                let prefix = path.get("path").and_then(scalar).unwrap_or_default();
+                out.push((name, format!("{host}{prefix}")));
+            }
+        }
+    }
+    out
+}
+
+/// Whether `text` addresses the Service `name` in `namespace`: its cluster
+/// DNS name anywhere, or, within the same namespace, the short name as a URL
+/// host, as `name:port`, or as the whole value of a host-like variable.
+fn addresses(
+    text: &str,
+    var: Option<&str>,
+    name: &str,
+    namespace: &str,
+    same_namespace: bool,
+) -> bool {
+    let qualified = format!("{name}.{namespace}");
+    let tokens: Vec<&str> = text
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '-'))
+        .filter(|t| !t.is_empty())
+        .collect();
+    if tokens
+        .iter()
+        .any(|t| *t == qualified || t.starts_with(&format!("{qualified}.svc")))
+    {
+        return true;
+    }
+    if !same_namespace {
+        return false;
+    }
+
+    let host_like = var.is_some_and(|v| {
+        let v = v.to_uppercase();
+        [
+            "HOST", "ADDR", "SERVER", "URL", "URI", "ENDPOINT", "SERVICE",
+        ]
+        .iter()
+        .any(|s| v.contains(s))
+    });
+    text.contains(&format!("://{name}:"))
+        || text.contains(&format!("://{name}/"))
+        || text.ends_with(&format!("://{name}"))
+        || text.contains(&format!("@{name}:"))
+        || text.starts_with(&format!("{name}:"))
+        || (host_like && text == name)
+}
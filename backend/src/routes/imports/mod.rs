@@ -1,12 +1,17 @@
+use std::collections::HashMap;
+
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
-    routing::post,
+    routing::{get, post},
     Extension, Json, Router,
 };
 use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use utoipa::{IntoParams, OpenApi, ToSchema};
 use uuid::Uuid;
 
+use crate::error::{ApiError, ErrorCode};
 use crate::{
     audit::{self, AuditAction, EntityType, RequestContext},
     auth::AuthActor,
@@ -17,7 +22,7 @@ use crate::{
         edge_claim_flow::EdgeClaimFlow,
         new_edge_claim_flow::NewEdgeClaimFlow,
     },
-    routes::AppState,
+    routes::{batch::EntityRef, AppState},
 };
 
 use crate::routes::edges::{
@@ -26,64 +31,120 @@ use crate::routes::edges::{
     helpers::{internal_error, map_sqlx_error},
 };
 
+mod common;
+mod compose;
+mod fortigate;
+mod kubernetes;
+mod nmap;
+mod nodes;
+mod reconcile;
+mod review;
+mod spreadsheet;
+mod terraform;
+
+pub(crate) use nodes::{apply_node_proposal, restore_previous_claim};
+pub use nodes::{NodeProposalAction, NodeProposalPlan, ProposalNodeInput};
+pub use reconcile::{ProposalPlan, ReconcileAction};
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    create_import,
+    list_imports,
+    create_proposals,
+    list_proposals,
+    nodes::list_node_proposals,
+    review::approve_batch,
+    review::reject_batch,
+    review::finalize_batch,
+    review::rollback_batch,
+    spreadsheet::import_spreadsheet,
+    fortigate::import_fortigate,
+    compose::import_compose,
+    kubernetes::import_kubernetes,
+    nmap::import_nmap,
+    terraform::import_terraform
+))]
+pub struct ImportsApi;
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", post(create_import).get(list_imports))
+        .route("/spreadsheet", post(spreadsheet::import_spreadsheet))
+        .route("/fortigate", post(fortigate::import_fortigate))
+        .route("/compose", post(compose::import_compose))
+        .route("/kubernetes", post(kubernetes::import_kubernetes))
+        .route("/nmap", post(nmap::import_nmap))
+        .route("/terraform", post(terraform::import_terraform))
         .route("/:id/proposals", post(create_proposals).get(list_proposals))
+        .route("/:id/node-proposals", get(nodes::list_node_proposals))
+        .route("/:id/approve", post(review::approve_batch))
+        .route("/:id/reject", post(review::reject_batch))
+        .route("/:id/finalize", post(review::finalize_batch))
+        .route("/:id/rollback", post(review::rollback_batch))
 }
 
 type JsonObj = serde_json::Value;
 
-#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
 pub struct ImportBatch {
     pub id: Uuid,
     pub source: String,
     pub created_by: String,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub started_at: time::OffsetDateTime,
+    #[schema(value_type = Option<crate::openapi::Timestamp>)]
     pub finished_at: Option<time::OffsetDateTime>,
     pub metadata: Option<JsonObj>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct NewImportBatch {
     pub source: String,
     #[serde(default)]
     pub metadata: Option<JsonObj>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct ImportBatchSummary {
     pub id: Uuid,
     pub source: String,
     pub created_by: String,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub started_at: time::OffsetDateTime,
+    #[schema(value_type = Option<crate::openapi::Timestamp>)]
     pub finished_at: Option<time::OffsetDateTime>,
     pub metadata: Option<JsonObj>,
     pub open_proposals: i64,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
+#[schema(as = ProposalClaim)]
 pub struct EdgeClaimWithDetails {
     pub claim: EdgeClaim,
     pub evidence: Vec<EdgeClaimEvidence>,
     pub flows: Vec<EdgeClaimFlow>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct ProposalItem {
     pub edge: Edge,
     pub claim: EdgeClaimWithDetails,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateProposalsBody {
+    /// Behandlas före `edges`, så kanter kan peka på noder via `temp_id`.
+    #[serde(default)]
+    pub nodes: Vec<ProposalNodeInput>,
+    #[serde(default)]
     pub edges: Vec<ProposalEdgeInput>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct ProposalEdgeInput {
-    pub from_id: Uuid,
-    pub to_id: Uuid,
+    /// Nodens id, eller `temp_id` för en nod i samma anrop.
+    pub from_id: EntityRef,
+    pub to_id: EntityRef,
     pub kind: String,
 
     pub source: String,
@@ -91,6 +152,10 @@ pub struct ProposalEdgeInput {
 
     pub evidence: Option<Vec<NewEdgeClaimEvidence>>,
     pub flows: Option<Vec<NewEdgeClaimFlow>>,
+    /// Metadata för kanten när den skapas; en befintlig kants metadata
+    /// lämnas orörd.
+    #[serde(default)]
+    pub metadata: Option<JsonObj>,
 }
 
 #[derive(sqlx::FromRow)]
@@ -104,9 +169,14 @@ struct ImportBatchSummaryRow {
     pub open_proposals: i64,
 }
 
+#[utoipa::path(
+    get,
+    path = "/",
+    responses((status = 200, body = Vec<ImportBatchSummary>))
+)]
 pub async fn list_imports(
     State(state): State<AppState>,
-) -> Result<Json<Vec<ImportBatchSummary>>, (StatusCode, String)> {
+) -> Result<Json<Vec<ImportBatchSummary>>, ApiError> {
     let rows: Vec<ImportBatchSummaryRow> = sqlx::query_as(
         r#"
         SELECT
@@ -121,6 +191,11 @@ pub async fn list_imports(
                 FROM edge_claims c
                 WHERE c.import_batch_id = b.id
                   AND c.status = 'needs_review'
+            ), 0) + COALESCE((
+                SELECT COUNT(*)
+                FROM node_claims c
+                WHERE c.import_batch_id = b.id
+                  AND c.status = 'needs_review'
             ), 0) AS open_proposals
         FROM import_batches b
         ORDER BY b.started_at DESC
@@ -146,218 +221,181 @@ pub async fn list_imports(
     Ok(Json(out))
 }
 
+#[utoipa::path(
+    post,
+    path = "/",
+    request_body = NewImportBatch,
+    responses(
+        (status = 200, body = ImportBatch),
+        (status = 400, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn create_import(
     State(state): State<AppState>,
     Extension(ctx): Extension<RequestContext>,
     Json(body): Json<NewImportBatch>,
-) -> Result<Json<ImportBatch>, (StatusCode, String)> {
-    let batch_id = Uuid::new_v4();
-
-    let batch: ImportBatch = sqlx::query_as(
-        r#"
-        INSERT INTO import_batches (
-            id,
-            source,
-            created_by,
-            metadata
-        )
-        VALUES ($1, $2, $3, $4)
-        RETURNING
-            id,
-            source,
-            created_by,
-            started_at,
-            finished_at,
-            metadata
-        "#,
+) -> Result<Json<ImportBatch>, ApiError> {
+    let batch = insert_import_batch(
+        &state.pool,
+        body.source,
+        ctx.request_id.to_string(),
+        body.metadata,
     )
-    .bind(batch_id)
-    .bind(body.source)
-    .bind(ctx.request_id.to_string())
-    .bind(body.metadata)
-    .fetch_one(&state.pool)
-    .await
-    .map_err(map_sqlx_error)?;
+    .await?;
 
     Ok(Json(batch))
 }
 
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CreateProposalsQuery {
+    /// Planera utan att skriva något.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Node ids in `nodes` for `create_node` plans exist only within the dry
+/// run and change on the real call.
+#[derive(Serialize, ToSchema)]
+pub struct ProposalsDryRun {
+    pub dry_run: bool,
+    pub nodes: Vec<NodeProposalPlan>,
+    pub plans: Vec<ProposalPlan>,
+}
+
+/// `Vec<ProposalItem>` normally, `ProposalsDryRun` with `?dry_run=true`.
+/// Node proposals are listed by `GET /imports/{id}/node-proposals`.
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum CreateProposalsResponse {
+    Created(Vec<ProposalItem>),
+    DryRun(ProposalsDryRun),
+}
+
+#[utoipa::path(
+    post,
+    path = "/{id}/proposals",
+    params(("id" = Uuid, Path, description = "Importbatch"), CreateProposalsQuery),
+    request_body = CreateProposalsBody,
+    responses(
+        (status = 200, body = CreateProposalsResponse),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn create_proposals(
     State(state): State<AppState>,
     Extension(ctx): Extension<RequestContext>,
     Extension(actor): Extension<AuthActor>,
     Path(batch_id): Path<Uuid>,
+    Query(q): Query<CreateProposalsQuery>,
     Json(body): Json<CreateProposalsBody>,
-) -> Result<Json<Vec<ProposalItem>>, (StatusCode, String)> {
+) -> Result<Json<CreateProposalsResponse>, ApiError> {
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
 
-    let _: ImportBatch = sqlx::query_as(
-        r#"
-        SELECT
-            id, source, created_by, started_at, finished_at, metadata
-        FROM import_batches
-        WHERE id = $1
-        "#,
-    )
-    .bind(batch_id)
-    .fetch_one(&mut *tx)
-    .await
-    .map_err(map_sqlx_error)?;
-
-    let mut created_edges: Vec<Edge> = Vec::new();
-    let mut created_claims: Vec<EdgeClaim> = Vec::new();
+    let batch = lock_batch(&mut tx, batch_id).await?;
+    if batch.finished_at.is_some() {
+        return Err(ApiError::conflict(
+            "Importbatchen är avslutad; skapa en ny batch för nya förslag",
+        ));
+    }
 
-    for item in body.edges {
-        let edge: Edge = sqlx::query_as(
-            r#"
-            INSERT INTO edges (from_id, to_id, kind, metadata)
-            VALUES ($1, $2, $3, '{}'::jsonb)
-            ON CONFLICT (from_id, to_id, kind) DO UPDATE
-              SET updated_at = now()
-            RETURNING
-              id, from_id, to_id, kind, metadata, created_at, updated_at
-            "#,
-        )
-        .bind(item.from_id)
-        .bind(item.to_id)
-        .bind(item.kind)
-        .fetch_one(&mut *tx)
-        .await
-        .map_err(map_sqlx_error)?;
-
-        let claim: EdgeClaim = sqlx::query_as(
-            r#"
-            INSERT INTO edge_claims (
-              edge_id,
-              import_batch_id,
-              source,
-              confidence,
-              status,
-              created_by
-            )
-            VALUES ($1, $2, $3, $4, 'needs_review', $5)
-            RETURNING
-              id,
-              edge_id,
-              import_batch_id,
-              source,
-              confidence,
-              status,
-              created_by,
-              created_at,
-              updated_at,
-              last_verified_at
-            "#,
-        )
-        .bind(edge.id)
-        .bind(batch_id)
-        .bind(item.source)
-        .bind(item.confidence.unwrap_or(70))
-        .bind(ctx.request_id.to_string())
-        .fetch_one(&mut *tx)
-        .await
-        .map_err(map_sqlx_error)?;
-
-        if let Some(evs) = item.evidence {
-            for ev in evs {
-                let _e: EdgeClaimEvidence = sqlx::query_as(
-                    r#"
-                    INSERT INTO edge_claim_evidence (claim_id, evidence_type, reference, note)
-                    VALUES ($1, $2, $3, $4)
-                    RETURNING id, claim_id, evidence_type, reference, note, created_at
-                    "#,
-                )
-                .bind(claim.id)
-                .bind(ev.evidence_type)
-                .bind(ev.reference)
-                .bind(ev.note)
-                .fetch_one(&mut *tx)
-                .await
-                .map_err(map_sqlx_error)?;
+    // Nodes are written as they are planned so that edges further down can
+    // reference them; a dry run or a conflict rolls the whole call back.
+    let mut temp_ids: HashMap<String, Uuid> = HashMap::new();
+    let mut node_plans: Vec<NodeProposalPlan> = Vec::new();
+    for (index, item) in body.nodes.iter().enumerate() {
+        if let Some(temp) = &item.temp_id {
+            if temp_ids.contains_key(temp) {
+                return Err(ApiError::validation(
+                    format!("nodes[{index}].temp_id"),
+                    format!("temp_id '{temp}' används redan"),
+                ));
             }
         }
-
-        if let Some(flows) = item.flows {
-            for f in flows {
-                let _f: EdgeClaimFlow = sqlx::query_as(
-                    r#"
-                    INSERT INTO edge_claim_flows (claim_id, flow_type, data_category_id, protocol, frequency)
-                    VALUES ($1, $2, $3, $4, $5)
-                    RETURNING id, claim_id, flow_type, data_category_id, protocol, frequency, created_at
-                    "#,
-                )
-                .bind(claim.id)
-                .bind(f.flow_type)
-                .bind(f.data_category_id)
-                .bind(f.protocol)
-                .bind(f.frequency)
-                .fetch_one(&mut *tx)
-                .await
-                .map_err(map_sqlx_error)?;
-            }
+        let plan = nodes::propose_node(&mut tx, ctx, &actor, batch_id, index, item).await?;
+        if let Some(temp) = &item.temp_id {
+            temp_ids.insert(temp.clone(), plan.node_id);
         }
+        node_plans.push(plan);
+    }
 
-        audit::write_audit(
-            &mut *tx,
-            ctx.clone(),
-            Some(&actor),
-            EntityType::Edge,
-            edge.id,
-            AuditAction::Patch,
-            None,
-            Some(serde_json::json!({
-                "import_batch_id": batch_id,
-                "action": "proposal_created",
-                "claim_id": claim.id
-            })),
-            None,
-        )
-        .await
-        .map_err(internal_error)?;
+    let mut planned = reconcile::Planned::new();
+    let mut endpoints: Vec<(Uuid, Uuid)> = Vec::new();
+    let mut plans: Vec<ProposalPlan> = Vec::new();
+    for (index, item) in body.edges.iter().enumerate() {
+        let from_id = resolve_node_ref(&temp_ids, &item.from_id, index, "from_id")?;
+        let to_id = resolve_node_ref(&temp_ids, &item.to_id, index, "to_id")?;
+        let plan =
+            reconcile::plan_edge_proposal(&mut tx, index, from_id, to_id, item, &planned).await?;
+        if matches!(
+            plan.action,
+            ReconcileAction::CreateEdge | ReconcileAction::AddProposal
+        ) {
+            planned.insert(
+                (from_id, to_id, item.kind.clone()),
+                (item.source.clone(), item.confidence.unwrap_or(70)),
+            );
+        }
+        endpoints.push((from_id, to_id));
+        plans.push(plan);
+    }
 
-        created_edges.push(edge);
-        created_claims.push(claim);
+    if q.dry_run {
+        tx.rollback().await.map_err(internal_error)?;
+        return Ok(Json(CreateProposalsResponse::DryRun(ProposalsDryRun {
+            dry_run: true,
+            nodes: node_plans,
+            plans,
+        })));
     }
 
-    tx.commit().await.map_err(internal_error)?;
+    if let Some(conflict) = node_plans
+        .iter()
+        .find(|p| p.action == NodeProposalAction::Conflict)
+    {
+        let message = conflict.message_sv.clone().unwrap_or_default();
+        return Err(ApiError::conflict(message.clone())
+            .with_field(format!("nodes[{}]", conflict.index), message));
+    }
 
-    if created_claims.is_empty() {
-        return Ok(Json(Vec::new()));
+    if let Some(conflict) = plans.iter().find(|p| p.action == ReconcileAction::Conflict) {
+        let message = conflict.message_sv.clone().unwrap_or_default();
+        return Err(ApiError::conflict(message.clone())
+            .with_field(format!("edges[{}]", conflict.index), message));
     }
 
-    let claim_ids: Vec<Uuid> = created_claims.iter().map(|c| c.id).collect();
-    let evidence_map = load_evidence_map_for_claim_ids(&state.pool, &claim_ids).await?;
-    let flow_map = load_flow_map_for_claim_ids(&state.pool, &claim_ids).await?;
+    let mut created_edges: Vec<Edge> = Vec::new();
+    let mut created_claims: Vec<EdgeClaim> = Vec::new();
 
-    let mut edge_by_id: std::collections::HashMap<Uuid, Edge> = std::collections::HashMap::new();
-    for e in created_edges {
-        edge_by_id.insert(e.id, e);
+    for ((item, plan), (from_id, to_id)) in body.edges.into_iter().zip(&plans).zip(endpoints) {
+        if plan.action == ReconcileAction::Noop {
+            continue;
+        }
+        let (edge, claim) =
+            insert_edge_proposal(&mut tx, ctx, &actor, batch_id, from_id, to_id, item).await?;
+        created_edges.push(edge);
+        created_claims.push(claim);
     }
 
-    let mut out: Vec<ProposalItem> = Vec::new();
-    for c in created_claims {
-        let edge = edge_by_id
-            .get(&c.edge_id)
-            .cloned()
-            .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Edge saknas".into()))?;
+    tx.commit().await.map_err(internal_error)?;
 
-        out.push(ProposalItem {
-            edge,
-            claim: EdgeClaimWithDetails {
-                evidence: evidence_map.get(&c.id).cloned().unwrap_or_default(),
-                flows: flow_map.get(&c.id).cloned().unwrap_or_default(),
-                claim: c,
-            },
-        });
-    }
+    let out = load_proposal_items(&state.pool, created_edges, created_claims).await?;
 
-    Ok(Json(out))
+    Ok(Json(CreateProposalsResponse::Created(out)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/{id}/proposals",
+    params(("id" = Uuid, Path, description = "Importbatch")),
+    responses((status = 200, body = Vec<ProposalItem>))
+)]
 pub async fn list_proposals(
     State(state): State<AppState>,
     Path(batch_id): Path<Uuid>,
-) -> Result<Json<Vec<ProposalItem>>, (StatusCode, String)> {
+) -> Result<Json<Vec<ProposalItem>>, ApiError> {
     #[derive(sqlx::FromRow)]
     struct ProposalRow {
         e_id: Uuid,
@@ -367,6 +405,7 @@ pub async fn list_proposals(
         e_metadata: serde_json::Value,
         e_created_at: time::OffsetDateTime,
         e_updated_at: time::OffsetDateTime,
+        e_version: i64,
 
         c_id: Uuid,
         c_edge_id: Uuid,
@@ -378,6 +417,7 @@ pub async fn list_proposals(
         c_created_at: time::OffsetDateTime,
         c_updated_at: time::OffsetDateTime,
         c_last_verified_at: Option<time::OffsetDateTime>,
+        c_version: i64,
     }
 
     let rows: Vec<ProposalRow> = sqlx::query_as(
@@ -390,6 +430,7 @@ pub async fn list_proposals(
           e.metadata    AS e_metadata,
           e.created_at  AS e_created_at,
           e.updated_at  AS e_updated_at,
+          e.version     AS e_version,
 
           c.id               AS c_id,
           c.edge_id          AS c_edge_id,
@@ -400,7 +441,8 @@ pub async fn list_proposals(
           c.created_by       AS c_created_by,
           c.created_at       AS c_created_at,
           c.updated_at       AS c_updated_at,
-          c.last_verified_at AS c_last_verified_at
+          c.last_verified_at AS c_last_verified_at,
+          c.version          AS c_version
         FROM edge_claims c
         JOIN edges e ON e.id = c.edge_id
         WHERE c.import_batch_id = $1
@@ -428,6 +470,7 @@ pub async fn list_proposals(
             metadata: r.e_metadata,
             created_at: r.e_created_at,
             updated_at: r.e_updated_at,
+            version: r.e_version,
         };
 
         let claim = EdgeClaim {
@@ -441,6 +484,7 @@ pub async fn list_proposals(
             created_at: r.c_created_at,
             updated_at: Some(r.c_updated_at),
             last_verified_at: r.c_last_verified_at,
+            version: r.c_version,
         };
 
         out.push(ProposalItem {
@@ -455,3 +499,258 @@ pub async fn list_proposals(
 
     Ok(Json(out))
 }
+
+fn resolve_node_ref(
+    temp_ids: &HashMap<String, Uuid>,
+    r: &EntityRef,
+    index: usize,
+    field: &str,
+) -> Result<Uuid, ApiError> {
+    match r {
+        EntityRef::Id(id) => Ok(*id),
+        EntityRef::Temp(temp) => temp_ids.get(temp).copied().ok_or_else(|| {
+            ApiError::validation(
+                format!("edges[{index}].{field}"),
+                format!("Okänt temp_id '{temp}' (måste finnas i nodes)"),
+            )
+        }),
+    }
+}
+
+/// Maps upload errors for the file imports; the body limit gives 413.
+fn multipart_error(e: axum::extract::multipart::MultipartError) -> ApiError {
+    if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        ApiError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorCode::PayloadTooLarge,
+            "Filen är för stor (max 10 MB)",
+        )
+    } else {
+        ApiError::bad_request(format!("Ogiltig multipart-data: {}", e.body_text()))
+    }
+}
+
+/// Loads the batch and holds its row lock for the rest of the transaction.
+async fn lock_batch(
+    tx: &mut Transaction<'_, Postgres>,
+    batch_id: Uuid,
+) -> Result<ImportBatch, ApiError> {
+    sqlx::query_as(
+        r#"
+        SELECT
+            id, source, created_by, started_at, finished_at, metadata
+        FROM import_batches
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(batch_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(internal_error)?
+    .ok_or_else(|| ApiError::not_found("Importbatchen finns inte"))
+}
+
+async fn insert_import_batch<'e>(
+    executor: impl PgExecutor<'e>,
+    source: String,
+    created_by: String,
+    metadata: Option<JsonObj>,
+) -> Result<ImportBatch, ApiError> {
+    sqlx::query_as(
+        r#"
+        INSERT INTO import_batches (
+            id,
+            source,
+            created_by,
+            metadata
+        )
+        VALUES ($1, $2, $3, $4)
+        RETURNING
+            id,
+            source,
+            created_by,
+            started_at,
+            finished_at,
+            metadata
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(source)
+    .bind(created_by)
+    .bind(metadata)
+    .fetch_one(executor)
+    .await
+    .map_err(map_sqlx_error)
+}
+
+/// Upsert the edge and attach a `needs_review` claim (plus evidence and
+/// flows) to it inside the caller's transaction; shared by the JSON and
+/// spreadsheet imports.
+async fn insert_edge_proposal(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    batch_id: Uuid,
+    from_id: Uuid,
+    to_id: Uuid,
+    item: ProposalEdgeInput,
+) -> Result<(Edge, EdgeClaim), ApiError> {
+    let edge: Edge = sqlx::query_as(
+        r#"
+        INSERT INTO edges (id, from_id, to_id, kind, metadata, import_batch_id)
+        VALUES ($1, $2, $3, $4, COALESCE($6, '{}'::jsonb), $5)
+        ON CONFLICT (from_id, to_id, kind) DO UPDATE
+          SET updated_at = now()
+        RETURNING
+          id, from_id, to_id, kind, metadata, created_at, updated_at, version
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(from_id)
+    .bind(to_id)
+    .bind(item.kind)
+    .bind(batch_id)
+    .bind(item.metadata)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    let claim: EdgeClaim = sqlx::query_as(
+        r#"
+        INSERT INTO edge_claims (
+          edge_id,
+          import_batch_id,
+          source,
+          confidence,
+          status,
+          created_by
+        )
+        VALUES ($1, $2, $3, $4, 'needs_review', $5)
+        RETURNING
+          id,
+          edge_id,
+          import_batch_id,
+          source,
+          confidence,
+          status,
+          created_by,
+          created_at,
+          updated_at,
+          last_verified_at,
+          version
+        "#,
+    )
+    .bind(edge.id)
+    .bind(batch_id)
+    .bind(item.source)
+    .bind(item.confidence.unwrap_or(70))
+    .bind(ctx.request_id.to_string())
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    if let Some(evs) = item.evidence {
+        for ev in evs {
+            let _e: EdgeClaimEvidence = sqlx::query_as(
+                r#"
+                INSERT INTO edge_claim_evidence (claim_id, evidence_type, reference, note)
+                VALUES ($1, $2, $3, $4)
+                RETURNING id, claim_id, evidence_type, reference, note, created_at
+                "#,
+            )
+            .bind(claim.id)
+            .bind(ev.evidence_type)
+            .bind(ev.reference)
+            .bind(ev.note)
+            .fetch_one(&mut **tx)
+            .await
+            .map_err(map_sqlx_error)?;
+        }
+    }
+
+    if let Some(flows) = item.flows {
+        for f in flows {
+            let direction = f
+                .direction
+                .unwrap_or_else(|| "source_to_target".to_string());
+
+            let _f: EdgeClaimFlow = sqlx::query_as(
+                r#"
+                INSERT INTO edge_claim_flows
+                    (claim_id, flow_type, direction, data_category_id, protocol, frequency)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING
+                    id, claim_id, flow_type, direction, data_category_id, protocol, frequency, created_at
+                "#,
+            )
+            .bind(claim.id)
+            .bind(f.flow_type)
+            .bind(direction)
+            .bind(f.data_category_id)
+            .bind(f.protocol)
+            .bind(f.frequency)
+            .fetch_one(&mut **tx)
+            .await
+            .map_err(map_sqlx_error)?;
+        }
+    }
+
+    audit::write_audit(
+        tx,
+        ctx,
+        Some(actor),
+        EntityType::Edge,
+        edge.id,
+        AuditAction::Patch,
+        None,
+        Some(serde_json::json!({
+            "import_batch_id": batch_id,
+            "action": "proposal_created",
+            "claim_id": claim.id
+        })),
+        None,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    Ok((edge, claim))
+}
+
+async fn load_proposal_items(
+    pool: &PgPool,
+    created_edges: Vec<Edge>,
+    created_claims: Vec<EdgeClaim>,
+) -> Result<Vec<ProposalItem>, ApiError> {
+    if created_claims.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let claim_ids: Vec<Uuid> = created_claims.iter().map(|c| c.id).collect();
+    let evidence_map = load_evidence_map_for_claim_ids(pool, &claim_ids).await?;
+    let flow_map = load_flow_map_for_claim_ids(pool, &claim_ids).await?;
+
+    let mut edge_by_id: HashMap<Uuid, Edge> = HashMap::new();
+    for e in created_edges {
+        edge_by_id.insert(e.id, e);
+    }
+
+    let mut out: Vec<ProposalItem> = Vec::new();
+    for c in created_claims {
+        let edge = edge_by_id
+            .get(&c.edge_id)
+            .cloned()
+            .ok_or_else(|| ApiError::internal("Edge saknas"))?;
+
+        out.push(ProposalItem {
+            edge,
+            claim: EdgeClaimWithDetails {
+                evidence: evidence_map.get(&c.id).cloned().unwrap_or_default(),
+                flows: flow_map.get(&c.id).cloned().unwrap_or_default(),
+                claim: c,
+            },
+        });
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,717 @@
+//! Network scan import from nmap XML (`nmap -oX`).
+//!
+//! Every host reported up is matched against live `host` nodes, first by
+//! the node's `ip` metadata, then by hostname against the node name or its
+//! `hostname` metadata. Matched hosts get an update proposal when the scan
+//! adds an address or hostname the node lacks, or reports another OS or MAC
+//! address; unknown hosts are proposed as new nodes. Open ports become one
+//! `exposes_port` edge per host towards the node the scan ran from
+//! (`options.network`), with `tcp/22`-style flows and the detected service,
+//! product and version as `services` in the edge metadata. Without
+//! `options.network` the same list goes into the host's own proposal as
+//! `open_ports`.
+//!
+//! Hosts whose `ip` lies inside the scanned range but were not seen up are
+//! listed in `not_seen` and kept in the batch metadata. The range is read
+//! from the IPv4 targets in `nmaprun@args`, or given as `options.scope`.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::Ipv4Addr;
+
+use axum::{
+    extract::{Multipart, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::{
+    audit::RequestContext,
+    auth::AuthActor,
+    models::{edge::EdgeKind, edge_claim_evidence::NewEdgeClaimEvidence, node::NodeKind},
+    routes::AppState,
+};
+
+use super::common::{deleted_nodes, propose, BatchWriter, Upload};
+use super::nodes::{propose_node, NodeProposalAction, ProposalNodeInput};
+use super::{ImportBatch, ProposalItem};
+use crate::routes::edges::helpers::internal_error;
+
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct NmapOptions {
+    /// Nod som skanningen gjordes från (nät eller skanningsvärd); mål för
+    /// `exposes_port`. Utan den hamnar öppna portar som `open_ports` i
+    /// värdens metadata.
+    pub network: Option<String>,
+    /// IPv4-adresser, CIDR-block eller intervall (`10.0.0.1-50`) som
+    /// skannades; annars läses de ur nmap-argumenten.
+    #[serde(default)]
+    pub scope: Vec<String>,
+    /// Föreslå nya värdnoder för okända adresser; standard `true`.
+    pub create_hosts: Option<bool>,
+    /// Confidence för förslagen; standard 80.
+    pub confidence: Option<i16>,
+}
+
+/// Shape of the multipart body; only used for the OpenAPI document.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct NmapUpload {
+    /// XML från `nmap -oX`.
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+    pub options: Option<NmapOptions>,
+    /// Batchens källa; standard `nmap`.
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ScannedHost {
+    pub address: String,
+    pub hostname: Option<String>,
+    pub id: Uuid,
+    pub name: String,
+    /// `false` när noden redan fanns.
+    pub created: bool,
+    /// Förslagets claim; för befintliga noder bara när skanningen ändrar något.
+    pub claim_id: Option<Uuid>,
+    /// Öppna portar som `tcp/22`.
+    pub open_ports: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UnseenHost {
+    pub id: Uuid,
+    pub name: String,
+    pub ip: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ScanIssue {
+    pub address: String,
+    pub message: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct NmapImportResult {
+    /// Saknas när skanningen inte gav något att föreslå.
+    pub batch: Option<ImportBatch>,
+    /// `nmaprun@startstr`.
+    pub scanned_at: Option<String>,
+    pub hosts_up: usize,
+    pub hosts: Vec<ScannedHost>,
+    pub proposals: Vec<ProposalItem>,
+    /// Värdnoder inom det skannade intervallet som inte svarade.
+    pub not_seen: Vec<UnseenHost>,
+    pub issues: Vec<ScanIssue>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/nmap",
+    request_body(content = NmapUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = NmapImportResult),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 413, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn import_nmap(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    multipart: Multipart,
+) -> Result<Json<NmapImportResult>, ApiError> {
+    let mut upload = Upload::<NmapOptions>::read(multipart, "nmap").await?;
+    let (filename, bytes) = upload.file("nmap.xml")?;
+    let options = upload.options.unwrap_or_default();
+    let source = upload.source;
+    let confidence = options.confidence.unwrap_or(80);
+    if !(0..=100).contains(&confidence) {
+        return Err(ApiError::validation(
+            "options.confidence",
+            "confidence måste vara mellan 0 och 100",
+        ));
+    }
+    let create_hosts = options.create_hosts.unwrap_or(true);
+
+    let mut scope: Vec<Range> = Vec::new();
+    for s in &options.scope {
+        scope.push(Range::parse(s).ok_or_else(|| {
+            ApiError::validation("options.scope", format!("Ogiltigt intervall '{s}'"))
+        })?);
+    }
+
+    let text = String::from_utf8_lossy(&bytes);
+    let scan = Scan::parse(&text).map_err(|e| ApiError::validation("file", e))?;
+    if scope.is_empty() {
+        scope = scan.targets.clone();
+    }
+
+    let network = match options.network.as_deref().map(str::trim) {
+        Some(name) if !name.is_empty() => Some(
+            sqlx::query_scalar::<_, Uuid>(
+                r#"
+                SELECT id
+                FROM nodes
+                WHERE deleted_at IS NULL
+                  AND lower(name) = lower($1)
+                ORDER BY kind
+                LIMIT 1
+                "#,
+            )
+            .bind(name)
+            .fetch_optional(&state.pool)
+            .await
+            .map_err(internal_error)?
+            .ok_or_else(|| {
+                ApiError::validation("options.network", format!("Noden '{name}' finns inte"))
+            })?,
+        ),
+        _ => None,
+    };
+
+    let index = HostIndex::load(&state.pool).await?;
+    let mut issues: Vec<ScanIssue> = Vec::new();
+
+    // Each host up resolves to an existing node, a node to create, or
+    // nothing (ambiguous, deleted or creation turned off).
+    let mut targets: Vec<(&ScanHost, Target)> = Vec::new();
+    for host in scan.hosts.iter().filter(|h| h.up) {
+        match index.resolve(host) {
+            Ok(Some(id)) => targets.push((host, Target::Existing(id))),
+            Ok(None) if create_hosts => targets.push((host, Target::New(host.node_name()))),
+            Ok(None) => issues.push(ScanIssue {
+                address: host.address.clone(),
+                message: "Okänd värd; inga nya noder föreslås".to_string(),
+            }),
+            Err(message) => issues.push(ScanIssue {
+                address: host.address.clone(),
+                message,
+            }),
+        }
+    }
+
+    let new_names: Vec<String> = targets
+        .iter()
+        .filter_map(|(_, t)| match t {
+            Target::New(name) => Some(name.clone()),
+            Target::Existing(_) => None,
+        })
+        .collect();
+    let deleted = deleted_nodes(&state.pool, new_names).await?;
+    targets.retain(|(host, t)| match t {
+        Target::New(name) if deleted.contains(&("host".to_string(), name.clone())) => {
+            issues.push(ScanIssue {
+                address: host.address.clone(),
+                message: format!("Noden '{name}' (host) är borttagen; återställ den innan import"),
+            });
+            false
+        }
+        _ => true,
+    });
+
+    let seen: HashSet<Uuid> = targets
+        .iter()
+        .filter_map(|(_, t)| match t {
+            Target::Existing(id) => Some(*id),
+            Target::New(_) => None,
+        })
+        .collect();
+    let up: HashSet<Ipv4Addr> = scan
+        .hosts
+        .iter()
+        .filter(|h| h.up)
+        .filter_map(|h| h.address.parse().ok())
+        .collect();
+    let not_seen: Vec<UnseenHost> = index
+        .hosts
+        .iter()
+        .filter(|h| !seen.contains(&h.id))
+        .filter_map(|h| {
+            let ip = h
+                .ips
+                .iter()
+                .find(|ip| scope.iter().any(|r| r.contains(**ip)) && !up.contains(ip))?;
+            Some(UnseenHost {
+                id: h.id,
+                name: h.name.clone(),
+                ip: ip.to_string(),
+            })
+        })
+        .collect();
+
+    let hosts_up = scan.hosts.iter().filter(|h| h.up).count();
+
+    if targets.is_empty() && not_seen.is_empty() {
+        return Ok(Json(NmapImportResult {
+            batch: None,
+            scanned_at: scan.started,
+            hosts_up,
+            hosts: Vec::new(),
+            proposals: Vec::new(),
+            not_seen,
+            issues,
+        }));
+    }
+
+    let mut import = BatchWriter::begin(
+        &state.pool,
+        ctx,
+        &source,
+        serde_json::json!({
+            "filename": filename,
+            "scanned_at": scan.started,
+            "args": scan.args,
+            "scope": scope.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+            "hosts_up": hosts_up,
+            "not_seen": not_seen.iter().map(|h| h.id).collect::<Vec<_>>(),
+        }),
+    )
+    .await?;
+
+    let mut hosts: Vec<ScannedHost> = Vec::new();
+    let mut proposed = BTreeMap::new();
+    let when = scan
+        .started
+        .as_deref()
+        .map(|s| format!(" ({s})"))
+        .unwrap_or_default();
+
+    for (i, (host, target)) in targets.iter().enumerate() {
+        let input = match target {
+            Target::Existing(id) => ProposalNodeInput {
+                id: Some(*id),
+                temp_id: None,
+                kind: None,
+                name: None,
+                metadata: Some(serde_json::Value::Object(
+                    host.metadata(index.known(*id), network.is_none()),
+                )),
+                details: None,
+                source: source.clone(),
+                confidence: Some(confidence),
+            },
+            Target::New(name) => ProposalNodeInput {
+                id: None,
+                temp_id: None,
+                kind: Some(NodeKind::Host),
+                name: Some(name.clone()),
+                metadata: Some(serde_json::Value::Object(
+                    host.metadata(None, network.is_none()),
+                )),
+                details: None,
+                source: source.clone(),
+                confidence: Some(confidence),
+            },
+        };
+        let node = propose_node(&mut import.tx, ctx, &actor, import.batch.id, i, &input).await?;
+        if node.action == NodeProposalAction::Conflict {
+            issues.push(ScanIssue {
+                address: host.address.clone(),
+                message: node.message_sv.clone().unwrap_or_default(),
+            });
+        }
+
+        if let Some(network) = network.filter(|n| *n != node.node_id) {
+            if !host.ports.is_empty() {
+                let p = propose(
+                    &mut proposed,
+                    node.node_id,
+                    network,
+                    EdgeKind::ExposesPort,
+                    &host.address,
+                    confidence,
+                );
+                for port in &host.ports {
+                    p.protocols.insert(port.protocol());
+                    p.evidence.push(NewEdgeClaimEvidence {
+                        evidence_type: "document".to_string(),
+                        reference: filename.clone(),
+                        note: Some(format!("{} {}{when}", host.address, port.describe())),
+                    });
+                }
+                p.metadata.insert("services".into(), host.services().into());
+            }
+        }
+
+        hosts.push(ScannedHost {
+            address: host.address.clone(),
+            hostname: host.hostname.clone(),
+            id: node.node_id,
+            name: node.name,
+            created: node.action == NodeProposalAction::CreateNode,
+            claim_id: node.claim_id,
+            open_ports: host.ports.iter().map(Port::protocol).collect(),
+        });
+    }
+
+    let written = import.finish(&state.pool, &actor, proposed).await?;
+    for (address, message) in written.conflicts {
+        issues.push(ScanIssue { address, message });
+    }
+
+    Ok(Json(NmapImportResult {
+        batch: Some(written.batch),
+        scanned_at: scan.started,
+        hosts_up,
+        hosts,
+        proposals: written.proposals,
+        not_seen,
+        issues,
+    }))
+}
+
+enum Target {
+    Existing(Uuid),
+    New(String),
+}
+
+struct Scan {
+    args: Option<String>,
+    started: Option<String>,
+    /// IPv4 targets from the command line.
+    targets: Vec<Range>,
+    hosts: Vec<ScanHost>,
+}
+
+struct ScanHost {
+    up: bool,
+    /// IPv4 address, else IPv6, else MAC.
+    address: String,
+    mac: Option<String>,
+    mac_vendor: Option<String>,
+    /// First user-given hostname, else the first PTR name.
+    hostname: Option<String>,
+    os: Option<String>,
+    ports: Vec<Port>,
+}
+
+struct Port {
+    protocol: String,
+    port: String,
+    service: Option<String>,
+    product: Option<String>,
+    version: Option<String>,
+}
+
+impl Port {
+    fn protocol(&self) -> String {
+        format!("{}/{}", self.protocol, self.port)
+    }
+
+    /// `{"port": "tcp/22", "service": "ssh", "product": "OpenSSH", ...}`;
+    /// what nmap did not detect is left out.
+    fn to_json(&self) -> serde_json::Value {
+        let mut out = serde_json::Map::new();
+        out.insert("port".into(), self.protocol().into());
+        for (key, value) in [
+            ("service", &self.service),
+            ("product", &self.product),
+            ("version", &self.version),
+        ] {
+            if let Some(value) = value {
+                out.insert(key.into(), value.clone().into());
+            }
+        }
+        serde_json::Value::Object(out)
+    }
+
+    /// `22/tcp ssh OpenSSH 8.9p1`, like nmap prints it.
+    fn describe(&self) -> String {
+        [
+            Some(format!("{}/{}", self.port, self.protocol)),
+            self.service.clone(),
+            self.product.clone(),
+            self.version.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+}
+
+impl Scan {
+    fn parse(text: &str) -> Result<Self, String> {
+        let doc = roxmltree::Document::parse(text).map_err(|e| format!("Ogiltig XML: {e}"))?;
+        let root = doc.root_element();
+        if !root.has_tag_name("nmaprun") {
+            return Err("Filen är inte nmap-XML (saknar <nmaprun>)".to_string());
+        }
+
+        let args = root.attribute("args").map(str::to_string);
+        let targets = args
+            .as_deref()
+            .map(|a| a.split_whitespace().filter_map(Range::parse).collect())
+            .unwrap_or_default();
+
+        let hosts = root
+            .children()
+            .filter(|n| n.has_tag_name("host"))
+            .filter_map(ScanHost::read)
+            .collect();
+
+        Ok(Scan {
+            args,
+            started: root.attribute("startstr").map(str::to_string),
+            targets,
+            hosts,
+        })
+    }
+}
+
+impl ScanHost {
+    fn read(host: roxmltree::Node) -> Option<Self> {
+        let child = |name: &str| host.children().find(|n| n.has_tag_name(name));
+
+        let up = child("status").and_then(|s| s.attribute("state")) == Some("up");
+
+        let address = |kind: &str| {
+            host.children()
+                .filter(|n| n.has_tag_name("address"))
+                .find(|n| n.attribute("addrtype") == Some(kind))
+        };
+        let mac = address("mac");
+        let address = address("ipv4")
+            .or_else(|| address("ipv6"))
+            .or(mac)
+            .and_then(|a| a.attribute("addr"))?
+            .to_string();
+
+        let names: Vec<(&str, &str)> = child("hostnames")
+            .into_iter()
+            .flat_map(|h| h.children().filter(|n| n.has_tag_name("hostname")))
+            .filter_map(|n| Some((n.attribute("type").unwrap_or(""), n.attribute("name")?)))
+            .collect();
+        let hostname = names
+            .iter()
+            .find(|(t, _)| *t == "user")
+            .or_else(|| names.first())
+            .map(|(_, n)| n.to_string());
+
+        let os = child("os")
+            .and_then(|o| o.children().find(|n| n.has_tag_name("osmatch")))
+            .and_then(|m| m.attribute("name"))
+            .map(str::to_string);
+
+        let ports = child("ports")
+            .into_iter()
+            .flat_map(|p| p.children().filter(|n| n.has_tag_name("port")))
+            .filter(|p| {
+                p.children()
+                    .find(|n| n.has_tag_name("state"))
+                    .and_then(|s| s.attribute("state"))
+                    == Some("open")
+            })
+            .filter_map(|p| {
+                let service = p.children().find(|n| n.has_tag_name("service"));
+                let attr = |name: &str| {
+                    service
+                        .and_then(|s| s.attribute(name))
+                        .filter(|v| !v.is_empty())
+                        .map(str::to_string)
+                };
+                Some(Port {
+                    protocol: p.attribute("protocol").unwrap_or("tcp").to_string(),
+                    port: p.attribute("portid")?.to_string(),
+                    service: attr("name"),
+                    product: attr("product"),
+                    version: attr("version"),
+                })
+            })
+            .collect();
+
+        Some(ScanHost {
+            up,
+            address,
+            mac: mac.and_then(|m| m.attribute("addr")).map(str::to_string),
+            mac_vendor: mac.and_then(|m| m.attribute("vendor")).map(str::to_string),
+            hostname,
+            os,
+            ports,
+        })
+    }
+
+    fn node_name(&self) -> String {
+        self.hostname
+            .clone()
+            .unwrap_or_else(|| self.address.clone())
+    }
+
+    fn services(&self) -> Vec<serde_json::Value> {
+        self.ports.iter().map(Port::to_json).collect()
+    }
+
+    /// Metadata to propose. For a known host, `ip` and `hostname` are only
+    /// filled in when missing so a hand-kept address list is not replaced.
+    /// `open_ports` is the port list when there is no edge to carry it.
+    fn metadata(
+        &self,
+        known: Option<&KnownHost>,
+        open_ports: bool,
+    ) -> serde_json::Map<String, serde_json::Value> {
+        let mut metadata = serde_json::Map::new();
+        let missing_ip = known.is_none_or(|k| k.ips.is_empty());
+        let missing_hostname = known.is_none_or(|k| !k.has_hostname);
+
+        if missing_ip && self.address.parse::<std::net::IpAddr>().is_ok() {
+            metadata.insert("ip".into(), self.address.clone().into());
+        }
+        if let Some(hostname) = self.hostname.clone().filter(|_| missing_hostname) {
+            metadata.insert("hostname".into(), hostname.into());
+        }
+        if let Some(os) = &self.os {
+            metadata.insert("os".into(), os.clone().into());
+        }
+        if let Some(mac) = &self.mac {
+            metadata.insert("mac".into(), mac.clone().into());
+        }
+        if let Some(vendor) = self.mac_vendor.clone().filter(|_| known.is_none()) {
+            metadata.insert("mac_vendor".into(), vendor.into());
+        }
+        if open_ports && !self.ports.is_empty() {
+            metadata.insert("open_ports".into(), self.services().into());
+        }
+        metadata
+    }
+}
+
+/// An IPv4 address, CIDR block or last-octet range (`10.0.0.1-50`).
+#[derive(Debug, Clone, Copy)]
+struct Range {
+    first: u32,
+    last: u32,
+}
+
+impl Range {
+    fn parse(s: &str) -> Option<Self> {
+        if let Some((ip, bits)) = s.split_once('/') {
+            let ip = u32::from(ip.parse::<Ipv4Addr>().ok()?);
+            let bits: u32 = bits.parse().ok().filter(|b| *b <= 32)?;
+            let mask = u32::MAX.checked_shl(32 - bits).unwrap_or(0);
+            return Some(Range {
+                first: ip & mask,
+                last: (ip & mask) | !mask,
+            });
+        }
+        if let Some((ip, end)) = s.split_once('-') {
+            let first = u32::from(ip.parse::<Ipv4Addr>().ok()?);
+            let end: u8 = end.parse().ok()?;
+            let last = (first & !0xff) | u32::from(end);
+            return (last >= first).then_some(Range { first, last });
+        }
+        let ip = u32::from(s.parse::<Ipv4Addr>().ok()?);
+        Some(Range {
+            first: ip,
+            last: ip,
+        })
+    }
+
+    fn contains(&self, ip: Ipv4Addr) -> bool {
+        (self.first..=self.last).contains(&u32::from(ip))
+    }
+}
+
+impl std::fmt::Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (first, last) = (Ipv4Addr::from(self.first), Ipv4Addr::from(self.last));
+        if self.first == self.last {
+            write!(f, "{first}")
+        } else {
+            write!(f, "{first}-{last}")
+        }
+    }
+}
+
+struct KnownHost {
+    id: Uuid,
+    name: String,
+    ips: Vec<Ipv4Addr>,
+    has_hostname: bool,
+}
+
+struct HostIndex {
+    hosts: Vec<KnownHost>,
+    by_ip: HashMap<String, Vec<usize>>,
+    by_name: HashMap<String, Vec<usize>>,
+}
+
+impl HostIndex {
+    async fn load(pool: &sqlx::PgPool) -> Result<Self, ApiError> {
+        let rows: Vec<(Uuid, String, Option<String>, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT id, name, metadata->>'ip', metadata->>'hostname'
+            FROM nodes
+            WHERE deleted_at IS NULL
+              AND kind = 'host'
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(internal_error)?;
+
+        let mut index = HostIndex {
+            hosts: Vec::new(),
+            by_ip: HashMap::new(),
+            by_name: HashMap::new(),
+        };
+        for (i, (id, name, ips, hostname)) in rows.into_iter().enumerate() {
+            let ips: Vec<&str> = ips
+                .iter()
+                .flat_map(|s| s.split([',', ';', ' ']))
+                .map(|s| s.trim().trim_end_matches("/32"))
+                .filter(|s| !s.is_empty())
+                .collect();
+            for ip in &ips {
+                index.by_ip.entry(ip.to_string()).or_default().push(i);
+            }
+            for n in [Some(name.as_str()), hostname.as_deref()]
+                .into_iter()
+                .flatten()
+            {
+                index.by_name.entry(n.to_lowercase()).or_default().push(i);
+            }
+            index.hosts.push(KnownHost {
+                id,
+                ips: ips.iter().filter_map(|ip| ip.parse().ok()).collect(),
+                has_hostname: hostname.is_some(),
+                name,
+            });
+        }
+        Ok(index)
+    }
+
+    /// The node for a scanned host, by address first and hostname second
+    /// (full name, then the part before the first dot).
+    fn resolve(&self, host: &ScanHost) -> Result<Option<Uuid>, String> {
+        let hostname = host.hostname.as_deref().map(str::to_lowercase);
+        let short = hostname
+            .as_deref()
+            .and_then(|h| h.split_once('.'))
+            .map(|(s, _)| s.to_string());
+        let candidates = [
+            self.by_ip.get(&host.address),
+            hostname.as_ref().and_then(|h| self.by_name.get(h)),
+            short.as_ref().and_then(|h| self.by_name.get(h)),
+        ];
+
+        match candidates.into_iter().flatten().next() {
+            None => Ok(None),
+            Some(hits) => {
+                let ids: HashSet<Uuid> = hits.iter().map(|&i| self.hosts[i].id).collect();
+                if ids.len() == 1 {
+                    Ok(ids.into_iter().next())
+                } else {
+                    Err(format!("{} värdnoder matchar adressen", ids.len()))
+                }
+            }
+        }
+    }
+
+    fn known(&self, id: Uuid) -> Option<&KnownHost> {
+        self.hosts.iter().find(|h| h.id == id)
+    }
+}
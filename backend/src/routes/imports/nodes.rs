@@ -0,0 +1,564 @@
+//! Node proposals in import batches.
+//!
+//! A proposed node that does not exist yet is created right away (edges in
+//! the same payload need something to point at) and carries a
+//! `needs_review` claim with `{"op": "create_node"}`, listing existing nodes
+//! that look like the same thing under `similar`. For an existing node
+//! the import proposes only what differs; the change waits in the claim's
+//! `proposed_changes` and is applied by `/api/node-claims/:id/approve`.
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::{
+    audit::{self, AuditAction, EntityType, RequestContext},
+    auth::AuthActor,
+    models::{
+        node::{NewNode, Node, NodeKind},
+        node_claims::NodeClaim,
+    },
+    routes::{
+        nodes::{
+            crud::insert_node,
+            details::{apply_details_patch, diff_details_patch, NodeDetailsPatch},
+            matching::{find_matches, MatchQuery, NodeMatch, DEFAULT_MIN_SIMILARITY},
+        },
+        AppState,
+    },
+};
+
+use crate::routes::edges::helpers::{internal_error, map_sqlx_error};
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ProposalNodeInput {
+    /// Befintlig nod; annars matchas `(kind, name)` och noden skapas om den saknas.
+    pub id: Option<Uuid>,
+    /// Referens som kanter i samma anrop kan använda som `from_id`/`to_id`.
+    pub temp_id: Option<String>,
+    pub kind: Option<NodeKind>,
+    pub name: Option<String>,
+    /// Nycklar som slås ihop med nodens metadata.
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+    #[serde(default)]
+    pub details: Option<NodeDetailsPatch>,
+
+    pub source: String,
+    pub confidence: Option<i16>,
+}
+
+/// Stored in `node_claims.proposed_changes`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum NodeProposalChange {
+    CreateNode {
+        /// Befintliga noder som troligen är samma sak; granskaren avgör.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        similar: Vec<NodeMatch>,
+    },
+    UpdateNode {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[schema(value_type = Option<Object>)]
+        metadata: Option<serde_json::Map<String, serde_json::Value>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        details: Option<Box<NodeDetailsPatch>>,
+        /// Active claim set aside for the proposal; reinstated on reject.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        previous_claim_id: Option<Uuid>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeProposalAction {
+    /// Noden finns inte; den skapas med ett förslag.
+    CreateNode,
+    /// Noden finns; ändringarna läggs som förslag.
+    ProposeUpdate,
+    /// Noden har redan ett annat öppet förslag.
+    Conflict,
+    /// Inget skiljer sig från noden eller från ett väntande förslag.
+    Noop,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct NodeProposalPlan {
+    /// Position i `nodes` i anropet.
+    pub index: usize,
+    pub temp_id: Option<String>,
+    pub node_id: Uuid,
+    pub kind: String,
+    pub name: String,
+    pub action: NodeProposalAction,
+    /// Förslagets claim när ett sådant skapades.
+    pub claim_id: Option<Uuid>,
+    pub changes: Option<NodeProposalChange>,
+    pub current_claim: Option<NodeClaim>,
+    pub message_sv: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct NodeProposalItem {
+    pub node: Node,
+    pub claim: NodeClaim,
+}
+
+const NODE_CLAIM_COLUMNS: &str = r#"
+    id,
+    node_id,
+    source,
+    confidence,
+    status,
+    created_by,
+    created_at,
+    updated_at,
+    last_verified_at,
+    version,
+    import_batch_id,
+    proposed_changes
+"#;
+
+/// Creates the node or files an update proposal for it inside the caller's
+/// transaction. Conflicts and no-ops write nothing.
+pub(super) async fn propose_node(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    batch_id: Uuid,
+    index: usize,
+    input: &ProposalNodeInput,
+) -> Result<NodeProposalPlan, ApiError> {
+    let field = |name: &str| format!("nodes[{index}].{name}");
+
+    let confidence = input.confidence.unwrap_or(70);
+    if !(0..=100).contains(&confidence) {
+        return Err(ApiError::validation(
+            field("confidence"),
+            "confidence måste vara mellan 0 och 100",
+        ));
+    }
+
+    let metadata = match &input.metadata {
+        None | Some(serde_json::Value::Null) => serde_json::Map::new(),
+        Some(serde_json::Value::Object(m)) => m.clone(),
+        Some(_) => {
+            return Err(ApiError::validation(
+                field("metadata"),
+                "metadata måste vara ett objekt",
+            ))
+        }
+    };
+
+    let details = input.details.clone().unwrap_or_default();
+    details
+        .validate()
+        .map_err(|(f, msg)| ApiError::validation(field(&format!("details.{f}")), msg))?;
+
+    let existing: Option<(Uuid, String, String, serde_json::Value, bool)> = match input.id {
+        Some(id) => sqlx::query_as(
+            r#"
+            SELECT id, kind, name, metadata, deleted_at IS NOT NULL
+            FROM nodes
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(internal_error)?,
+        None => {
+            let (Some(kind), Some(name)) = (input.kind, input.name.as_deref()) else {
+                return Err(ApiError::validation(
+                    field("id"),
+                    "Ange id eller både kind och name",
+                ));
+            };
+            if name.trim().is_empty() {
+                return Err(ApiError::validation(
+                    field("name"),
+                    "Namn får inte vara tomt",
+                ));
+            }
+
+            sqlx::query_as(
+                r#"
+                SELECT id, kind, name, metadata, deleted_at IS NOT NULL
+                FROM nodes
+                WHERE kind = $1 AND name = $2
+                "#,
+            )
+            .bind(kind.as_str())
+            .bind(name.trim())
+            .fetch_optional(&mut **tx)
+            .await
+            .map_err(internal_error)?
+        }
+    };
+
+    let (node_id, kind, name, current_metadata) = match existing {
+        Some((_, _, _, _, true)) => {
+            return Err(ApiError::validation(
+                field(if input.id.is_some() { "id" } else { "name" }),
+                "Noden är borttagen; återställ den innan import",
+            ));
+        }
+        Some((id, kind, name, metadata, false)) => (id, kind, name, metadata),
+        None if input.id.is_some() => {
+            return Err(ApiError::validation(field("id"), "Noden finns inte"));
+        }
+        None => {
+            let kind = input.kind.unwrap_or(NodeKind::System);
+            let name = input.name.clone().unwrap_or_default().trim().to_string();
+            let metadata = serde_json::Value::Object(metadata);
+            let similar = find_matches(
+                tx,
+                &MatchQuery {
+                    kind: Some(kind.as_str()),
+                    name: &name,
+                    metadata: &metadata,
+                    exclude: None,
+                    min_similarity: DEFAULT_MIN_SIMILARITY,
+                    limit: 5,
+                },
+            )
+            .await?;
+            let message_sv = (!similar.is_empty()).then(|| {
+                let names: Vec<&str> = similar.iter().map(|m| m.name.as_str()).collect();
+                format!("Liknar befintliga noder: {}", names.join(", "))
+            });
+
+            let node = insert_node(
+                tx,
+                ctx,
+                actor,
+                NewNode {
+                    kind,
+                    name,
+                    metadata,
+                },
+            )
+            .await?;
+            apply_details_patch(tx, node.id, &details).await?;
+
+            let changes = NodeProposalChange::CreateNode { similar };
+            let claim =
+                insert_node_proposal(tx, node.id, batch_id, input, confidence, &changes, ctx)
+                    .await?;
+            write_proposal_audit(tx, ctx, actor, batch_id, node.id, claim.id).await?;
+
+            return Ok(NodeProposalPlan {
+                index,
+                temp_id: input.temp_id.clone(),
+                node_id: node.id,
+                kind: node.kind,
+                name: node.name,
+                action: NodeProposalAction::CreateNode,
+                claim_id: Some(claim.id),
+                changes: Some(changes),
+                current_claim: None,
+                message_sv,
+            });
+        }
+    };
+
+    let mut plan = NodeProposalPlan {
+        index,
+        temp_id: input.temp_id.clone(),
+        node_id,
+        kind,
+        name,
+        action: NodeProposalAction::Noop,
+        claim_id: None,
+        changes: None,
+        current_claim: None,
+        message_sv: None,
+    };
+
+    let metadata_diff: serde_json::Map<String, serde_json::Value> = metadata
+        .into_iter()
+        .filter(|(k, v)| current_metadata.get(k) != Some(v))
+        .collect();
+    let details_diff = diff_details_patch(tx, node_id, &details).await?;
+
+    if metadata_diff.is_empty() && details_diff.is_empty() {
+        plan.message_sv = Some("Inga ändringar mot noden".to_string());
+        return Ok(plan);
+    }
+
+    let current: Option<NodeClaim> = sqlx::query_as(&format!(
+        r#"
+        SELECT {NODE_CLAIM_COLUMNS}
+        FROM node_claims
+        WHERE node_id = $1
+          AND status IN ('active', 'needs_review')
+        "#
+    ))
+    .bind(node_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(internal_error)?;
+
+    let metadata_diff = (!metadata_diff.is_empty()).then_some(metadata_diff);
+    let details_diff = (!details_diff.is_empty()).then(|| Box::new(details_diff));
+
+    if let Some(c) = current.as_ref().filter(|c| c.status == "needs_review") {
+        let pending = c
+            .proposed_changes
+            .clone()
+            .and_then(|v| serde_json::from_value::<NodeProposalChange>(v).ok());
+        let same = matches!(
+            &pending,
+            Some(NodeProposalChange::UpdateNode { metadata, details, .. })
+                if *metadata == metadata_diff && *details == details_diff
+        );
+        if same && c.source == input.source {
+            plan.message_sv = Some("Samma förslag väntar redan på granskning".to_string());
+        } else {
+            plan.action = NodeProposalAction::Conflict;
+            plan.message_sv = Some(format!(
+                "Noden har redan ett öppet förslag från '{}'",
+                c.source
+            ));
+        }
+        plan.current_claim = current;
+        return Ok(plan);
+    }
+
+    // Same hand-over as `mark_node_needs_review`: the active claim steps
+    // aside while the proposal is reviewed, and comes back on reject.
+    let previous_claim_id = current.as_ref().map(|c| c.id);
+    if let Some(id) = previous_claim_id {
+        sqlx::query(
+            r#"
+            UPDATE node_claims
+            SET status = 'deprecated', updated_at = now()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(&mut **tx)
+        .await
+        .map_err(map_sqlx_error)?;
+    }
+
+    let changes = NodeProposalChange::UpdateNode {
+        metadata: metadata_diff,
+        details: details_diff,
+        previous_claim_id,
+    };
+    let claim =
+        insert_node_proposal(tx, node_id, batch_id, input, confidence, &changes, ctx).await?;
+    write_proposal_audit(tx, ctx, actor, batch_id, node_id, claim.id).await?;
+
+    plan.action = NodeProposalAction::ProposeUpdate;
+    plan.claim_id = Some(claim.id);
+    plan.changes = Some(changes);
+    plan.current_claim = current;
+    Ok(plan)
+}
+
+async fn insert_node_proposal(
+    tx: &mut Transaction<'_, Postgres>,
+    node_id: Uuid,
+    batch_id: Uuid,
+    input: &ProposalNodeInput,
+    confidence: i16,
+    changes: &NodeProposalChange,
+    ctx: RequestContext,
+) -> Result<NodeClaim, ApiError> {
+    sqlx::query_as(&format!(
+        r#"
+        INSERT INTO node_claims (
+            node_id,
+            import_batch_id,
+            source,
+            confidence,
+            status,
+            created_by,
+            proposed_changes
+        )
+        VALUES ($1, $2, $3, $4, 'needs_review', $5, $6)
+        RETURNING {NODE_CLAIM_COLUMNS}
+        "#
+    ))
+    .bind(node_id)
+    .bind(batch_id)
+    .bind(&input.source)
+    .bind(confidence)
+    .bind(ctx.request_id.to_string())
+    .bind(serde_json::to_value(changes).map_err(internal_error)?)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(map_sqlx_error)
+}
+
+async fn write_proposal_audit(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    batch_id: Uuid,
+    node_id: Uuid,
+    claim_id: Uuid,
+) -> Result<(), ApiError> {
+    audit::write_audit(
+        tx,
+        ctx,
+        Some(actor),
+        EntityType::Node,
+        node_id,
+        AuditAction::Patch,
+        None,
+        Some(serde_json::json!({
+            "import_batch_id": batch_id,
+            "action": "node_proposal_created",
+            "claim_id": claim_id
+        })),
+        None,
+    )
+    .await
+    .map_err(internal_error)
+}
+
+/// Applies an approved update proposal to its node. `create_node`
+/// proposals were applied when they were made.
+pub(crate) async fn apply_node_proposal(
+    tx: &mut Transaction<'_, Postgres>,
+    node_id: Uuid,
+    changes: &serde_json::Value,
+) -> Result<Option<serde_json::Value>, ApiError> {
+    let changes: NodeProposalChange =
+        serde_json::from_value(changes.clone()).map_err(internal_error)?;
+    let NodeProposalChange::UpdateNode {
+        metadata, details, ..
+    } = &changes
+    else {
+        return Ok(None);
+    };
+
+    let res = sqlx::query(
+        r#"
+        UPDATE nodes
+        SET metadata = CASE
+              WHEN jsonb_typeof(metadata) = 'object' THEN metadata
+              ELSE '{}'::jsonb
+            END || $2::jsonb,
+            updated_at = now()
+        WHERE id = $1 AND deleted_at IS NULL
+        "#,
+    )
+    .bind(node_id)
+    .bind(serde_json::Value::Object(
+        metadata.clone().unwrap_or_default(),
+    ))
+    .execute(&mut **tx)
+    .await
+    .map_err(map_sqlx_error)?;
+    if res.rows_affected() == 0 {
+        return Err(ApiError::conflict("Noden är borttagen"));
+    }
+
+    if let Some(details) = details {
+        apply_details_patch(tx, node_id, details).await?;
+    }
+
+    Ok(Some(
+        serde_json::to_value(&changes).map_err(internal_error)?,
+    ))
+}
+
+/// Puts back the active claim an update proposal set aside, unless the node
+/// has gained another current claim since.
+pub(crate) async fn restore_previous_claim(
+    tx: &mut Transaction<'_, Postgres>,
+    node_id: Uuid,
+    changes: &serde_json::Value,
+) -> Result<(), ApiError> {
+    let Ok(NodeProposalChange::UpdateNode {
+        previous_claim_id: Some(previous),
+        ..
+    }) = serde_json::from_value(changes.clone())
+    else {
+        return Ok(());
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE node_claims
+        SET status = 'active', updated_at = now()
+        WHERE id = $1
+          AND node_id = $2
+          AND status = 'deprecated'
+          AND NOT EXISTS (
+            SELECT 1
+            FROM node_claims
+            WHERE node_id = $2
+              AND status IN ('active', 'needs_review')
+          )
+        "#,
+    )
+    .bind(previous)
+    .bind(node_id)
+    .execute(&mut **tx)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}/node-proposals",
+    params(("id" = Uuid, Path, description = "Importbatch")),
+    responses((status = 200, body = Vec<NodeProposalItem>))
+)]
+pub async fn list_node_proposals(
+    State(state): State<AppState>,
+    Path(batch_id): Path<Uuid>,
+) -> Result<Json<Vec<NodeProposalItem>>, ApiError> {
+    let claims: Vec<NodeClaim> = sqlx::query_as(&format!(
+        r#"
+        SELECT {NODE_CLAIM_COLUMNS}
+        FROM node_claims
+        WHERE import_batch_id = $1
+          AND status = 'needs_review'
+        ORDER BY created_at DESC
+        "#
+    ))
+    .bind(batch_id)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let node_ids: Vec<Uuid> = claims.iter().map(|c| c.node_id).collect();
+    let nodes: Vec<Node> = sqlx::query_as(
+        r#"
+        SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
+        FROM nodes
+        WHERE id = ANY($1)
+        "#,
+    )
+    .bind(&node_ids)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    let mut by_id: std::collections::HashMap<Uuid, Node> =
+        nodes.into_iter().map(|n| (n.id, n)).collect();
+
+    let out = claims
+        .into_iter()
+        .filter_map(|claim| {
+            let node = by_id.remove(&claim.node_id)?;
+            Some(NodeProposalItem { node, claim })
+        })
+        .collect();
+
+    Ok(Json(out))
+}
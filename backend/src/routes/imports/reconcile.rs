@@ -0,0 +1,176 @@
+//! Human-truth-first reconciliation of proposed edges.
+//!
+//! An edge holds at most one current claim (`active` or `needs_review`),
+//! and every active claim was approved or written by a person. A proposal
+//! therefore only lands on an edge without a current claim; one that repeats
+//! the current claim (same source and confidence) is a no-op, anything else
+//! conflicts and is left for a person to settle.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use sqlx::{Postgres, Transaction};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::models::edge_claim::EdgeClaim;
+use crate::routes::edges::helpers::internal_error;
+
+use super::ProposalEdgeInput;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconcileAction {
+    /// Kanten finns inte; den skapas med förslaget.
+    CreateEdge,
+    /// Kanten finns utan aktuellt claim; förslaget läggs till.
+    AddProposal,
+    /// Kanten har redan ett aktuellt claim som förslaget skulle konkurrera med.
+    Conflict,
+    /// Samma källa och confidence finns redan.
+    Noop,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProposalPlan {
+    /// Position i `edges` i anropet.
+    pub index: usize,
+    pub from_id: Uuid,
+    pub to_id: Uuid,
+    pub kind: String,
+    pub action: ReconcileAction,
+    pub edge_id: Option<Uuid>,
+    /// Claimet som ger konflikt eller gör förslaget överflödigt.
+    pub current_claim: Option<EdgeClaim>,
+    pub message_sv: Option<String>,
+}
+
+/// Proposals already planned in the same request, keyed by
+/// `(from_id, to_id, kind)`; a dry run writes nothing, so repeats inside one
+/// payload are caught here instead of by the current-claim lookup.
+pub(super) type Planned = HashMap<(Uuid, Uuid, String), (String, i16)>;
+
+/// Decides what `insert_edge_proposal` would do with `item`. Read-only; the
+/// caller records the plan in `planned` once it is accepted.
+pub(super) async fn plan_edge_proposal(
+    tx: &mut Transaction<'_, Postgres>,
+    index: usize,
+    from_id: Uuid,
+    to_id: Uuid,
+    item: &ProposalEdgeInput,
+    planned: &Planned,
+) -> Result<ProposalPlan, ApiError> {
+    let confidence = item.confidence.unwrap_or(70);
+
+    let live: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT id
+        FROM nodes
+        WHERE id = ANY($1) AND deleted_at IS NULL
+        "#,
+    )
+    .bind(vec![from_id, to_id])
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(internal_error)?;
+
+    for (field, id) in [("from_id", from_id), ("to_id", to_id)] {
+        if !live.contains(&id) {
+            return Err(ApiError::validation(
+                format!("edges[{index}].{field}"),
+                "Noden finns inte",
+            ));
+        }
+    }
+
+    let mut plan = ProposalPlan {
+        index,
+        from_id,
+        to_id,
+        kind: item.kind.clone(),
+        action: ReconcileAction::CreateEdge,
+        edge_id: None,
+        current_claim: None,
+        message_sv: None,
+    };
+
+    let key = (from_id, to_id, item.kind.clone());
+    if let Some((source, conf)) = planned.get(&key) {
+        if *source == item.source && *conf == confidence {
+            plan.action = ReconcileAction::Noop;
+            plan.message_sv = Some("Samma förslag finns tidigare i anropet".to_string());
+        } else {
+            plan.action = ReconcileAction::Conflict;
+            plan.message_sv =
+                Some("Ett annat förslag för kanten finns tidigare i anropet".to_string());
+        }
+        return Ok(plan);
+    }
+
+    let edge_id: Option<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT id
+        FROM edges
+        WHERE from_id = $1 AND to_id = $2 AND kind = $3
+        "#,
+    )
+    .bind(from_id)
+    .bind(to_id)
+    .bind(&item.kind)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(internal_error)?;
+
+    let Some(edge_id) = edge_id else {
+        return Ok(plan);
+    };
+    plan.edge_id = Some(edge_id);
+
+    let current: Option<EdgeClaim> = sqlx::query_as(
+        r#"
+        SELECT
+            id,
+            edge_id,
+            import_batch_id,
+            source,
+            confidence,
+            status,
+            created_by,
+            created_at,
+            updated_at,
+            last_verified_at,
+            version
+        FROM edge_claims
+        WHERE edge_id = $1
+          AND status IN ('active', 'needs_review')
+        "#,
+    )
+    .bind(edge_id)
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(internal_error)?;
+
+    plan.action = match &current {
+        None => ReconcileAction::AddProposal,
+        Some(c) if c.source == item.source && c.confidence == confidence => {
+            plan.message_sv = Some(if c.status == "active" {
+                "Kanten är redan bekräftad med samma källa".to_string()
+            } else {
+                "Samma förslag väntar redan på granskning".to_string()
+            });
+            ReconcileAction::Noop
+        }
+        Some(c) => {
+            plan.message_sv = Some(if c.status == "active" {
+                format!("Kanten har ett aktivt claim från '{}'", c.source)
+            } else {
+                format!("Kanten har redan ett öppet förslag från '{}'", c.source)
+            });
+            ReconcileAction::Conflict
+        }
+    };
+    plan.current_claim = current;
+
+    Ok(plan)
+}
@@ -0,0 +1,644 @@
+//! Batch-level review of import proposals.
+//!
+//! Approve and reject go through the same helpers as the per-claim
+//! endpoints, so every claim still gets its own audit entry; the batch row
+//! is locked for the duration so two reviewers cannot interleave. An
+//! approved claim keeps its batch, so rollback retires it along with the
+//! open proposals; edges and nodes are only removed when no active or open
+//! claim from outside the batch is left on them.
+//! Finalize and rollback are also audited against the batch itself, and
+//! they are what sends `import.completed`: a batch is complete when nothing
+//! in it is left to review, not when its proposals were written.
+
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::{
+    audit::{self, AuditAction, EntityType, RequestContext},
+    auth::AuthActor,
+    models::{edge_claim::EdgeClaim, node_claims::NodeClaim},
+    routes::{
+        claims::{approve_edge_proposal, reject_edge_proposal},
+        edges::delete::delete_edge_row,
+        node_claims::{approve_node_proposal, reject_node_proposal},
+        nodes::crud::soft_delete_node,
+        AppState,
+    },
+    webhooks,
+};
+
+use super::nodes::restore_previous_claim;
+use super::{lock_batch, ImportBatch};
+use crate::routes::edges::helpers::{internal_error, map_sqlx_error};
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct ApproveBatchBody {
+    /// Godkänn bara förslag med minst denna confidence; övriga lämnas öppna.
+    #[serde(default)]
+    pub min_confidence: Option<i16>,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct RejectBatchBody {
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BatchReviewResult {
+    pub batch: ImportBatch,
+    /// Förslag (claim-id) som godkändes.
+    pub approved_edge_claims: Vec<Uuid>,
+    pub approved_node_claims: Vec<Uuid>,
+    /// Förslag (claim-id) som avslogs.
+    pub rejected_edge_claims: Vec<Uuid>,
+    pub rejected_node_claims: Vec<Uuid>,
+    /// Godkända anspråk (claim-id) från batchen som avvecklades vid rollback.
+    pub retired_edge_claims: Vec<Uuid>,
+    pub retired_node_claims: Vec<Uuid>,
+    /// Kanter och noder som batchen skapade och som togs bort vid rollback.
+    pub removed_edges: Vec<Uuid>,
+    pub removed_nodes: Vec<Uuid>,
+    /// Förslag som fortfarande väntar på granskning.
+    pub open_proposals: i64,
+}
+
+impl BatchReviewResult {
+    fn new(batch: ImportBatch) -> Self {
+        Self {
+            batch,
+            approved_edge_claims: Vec::new(),
+            approved_node_claims: Vec::new(),
+            rejected_edge_claims: Vec::new(),
+            rejected_node_claims: Vec::new(),
+            retired_edge_claims: Vec::new(),
+            retired_node_claims: Vec::new(),
+            removed_edges: Vec::new(),
+            removed_nodes: Vec::new(),
+            open_proposals: 0,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/{id}/approve",
+    params(("id" = Uuid, Path, description = "Importbatch")),
+    request_body = ApproveBatchBody,
+    responses(
+        (status = 200, body = BatchReviewResult),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn approve_batch(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    Path(batch_id): Path<Uuid>,
+    Json(body): Json<ApproveBatchBody>,
+) -> Result<Json<BatchReviewResult>, ApiError> {
+    if let Some(min) = body.min_confidence {
+        if !(0..=100).contains(&min) {
+            return Err(ApiError::validation(
+                "min_confidence",
+                "min_confidence måste vara mellan 0 och 100",
+            ));
+        }
+    }
+    let min = body.min_confidence.unwrap_or(0);
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let batch = lock_batch(&mut tx, batch_id).await?;
+    let mut out = BatchReviewResult::new(batch);
+
+    // Nodes first, so created nodes are confirmed before edges to them.
+    for proposal in open_node_proposals(&mut tx, batch_id, min).await? {
+        approve_node_proposal(&mut tx, ctx, &actor, &proposal).await?;
+        out.approved_node_claims.push(proposal.id);
+    }
+    for proposal in open_edge_proposals(&mut tx, batch_id, min).await? {
+        approve_edge_proposal(&mut tx, ctx, &actor, &proposal).await?;
+        out.approved_edge_claims.push(proposal.id);
+    }
+
+    out.open_proposals = count_open(&mut tx, batch_id).await?;
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(Json(out))
+}
+
+#[utoipa::path(
+    post,
+    path = "/{id}/reject",
+    params(("id" = Uuid, Path, description = "Importbatch")),
+    request_body = RejectBatchBody,
+    responses(
+        (status = 200, body = BatchReviewResult),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn reject_batch(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    Path(batch_id): Path<Uuid>,
+    Json(body): Json<RejectBatchBody>,
+) -> Result<Json<BatchReviewResult>, ApiError> {
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let batch = lock_batch(&mut tx, batch_id).await?;
+    let mut out = BatchReviewResult::new(batch);
+
+    reject_open(&mut tx, ctx, &actor, batch_id, body.reason, &mut out).await?;
+
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(Json(out))
+}
+
+#[utoipa::path(
+    post,
+    path = "/{id}/finalize",
+    params(("id" = Uuid, Path, description = "Importbatch")),
+    responses(
+        (status = 200, body = ImportBatch),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn finalize_batch(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    Path(batch_id): Path<Uuid>,
+) -> Result<Json<ImportBatch>, ApiError> {
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let before = lock_batch(&mut tx, batch_id).await?;
+
+    if before.finished_at.is_some() {
+        return Err(ApiError::conflict("Importbatchen är redan avslutad"));
+    }
+    let open = count_open(&mut tx, batch_id).await?;
+    if open > 0 {
+        return Err(ApiError::conflict(format!(
+            "{open} förslag väntar fortfarande på granskning; godkänn eller avslå dem först"
+        )));
+    }
+
+    let batch: ImportBatch = sqlx::query_as(
+        r#"
+        UPDATE import_batches
+        SET finished_at = now()
+        WHERE id = $1
+        RETURNING
+            id,
+            source,
+            created_by,
+            started_at,
+            finished_at,
+            metadata
+        "#,
+    )
+    .bind(batch_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    audit_batch(
+        &mut tx,
+        ctx,
+        &actor,
+        &before,
+        &batch,
+        serde_json::json!({ "action": "import_batch_finalized" }),
+    )
+    .await?;
+
+    enqueue_completed(
+        &mut tx,
+        ctx,
+        &actor,
+        &batch,
+        serde_json::json!({ "outcome": "finalized" }),
+    )
+    .await?;
+
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(Json(batch))
+}
+
+#[utoipa::path(
+    post,
+    path = "/{id}/rollback",
+    params(("id" = Uuid, Path, description = "Importbatch")),
+    request_body = RejectBatchBody,
+    responses(
+        (status = 200, body = BatchReviewResult),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn rollback_batch(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    Path(batch_id): Path<Uuid>,
+    Json(body): Json<RejectBatchBody>,
+) -> Result<Json<BatchReviewResult>, ApiError> {
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let before = lock_batch(&mut tx, batch_id).await?;
+    let mut out = BatchReviewResult::new(before.clone());
+
+    reject_open(
+        &mut tx,
+        ctx,
+        &actor,
+        batch_id,
+        body.reason.clone(),
+        &mut out,
+    )
+    .await?;
+    retire_approved(&mut tx, ctx, &actor, batch_id, &mut out).await?;
+
+    // Edges the batch created with no live claim from outside it.
+    let edge_ids: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT e.id
+        FROM edges e
+        WHERE e.import_batch_id = $1
+          AND NOT EXISTS (
+            SELECT 1
+            FROM edge_claims c
+            WHERE c.edge_id = e.id
+              AND c.import_batch_id IS DISTINCT FROM $1
+              AND c.status IN ('active', 'needs_review')
+          )
+        ORDER BY e.created_at
+        "#,
+    )
+    .bind(batch_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+
+    for id in edge_ids {
+        delete_edge_row(&mut tx, ctx, &actor, id, "*").await?;
+        out.removed_edges.push(id);
+    }
+
+    // Nodes the batch created, unless a live claim from outside it or a
+    // remaining edge keeps them.
+    let node_ids: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT DISTINCT n.id
+        FROM node_claims c
+        JOIN nodes n ON n.id = c.node_id
+        WHERE c.import_batch_id = $1
+          AND c.proposed_changes->>'op' = 'create_node'
+          AND n.deleted_at IS NULL
+          AND NOT EXISTS (
+            SELECT 1
+            FROM node_claims o
+            WHERE o.node_id = n.id
+              AND o.import_batch_id IS DISTINCT FROM $1
+              AND o.status IN ('active', 'needs_review')
+          )
+          AND NOT EXISTS (
+            SELECT 1
+            FROM edges e
+            WHERE e.from_id = n.id OR e.to_id = n.id
+          )
+        "#,
+    )
+    .bind(batch_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+
+    for id in node_ids {
+        soft_delete_node(&mut tx, ctx, &actor, id, "*").await?;
+        out.removed_nodes.push(id);
+    }
+
+    out.batch = sqlx::query_as(
+        r#"
+        UPDATE import_batches
+        SET finished_at = COALESCE(finished_at, now()),
+            metadata = COALESCE(metadata, '{}'::jsonb) || jsonb_build_object(
+              'rolled_back_at', now(),
+              'rolled_back_by', $2::text
+            )
+        WHERE id = $1
+        RETURNING
+            id,
+            source,
+            created_by,
+            started_at,
+            finished_at,
+            metadata
+        "#,
+    )
+    .bind(batch_id)
+    .bind(actor.username.clone())
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    audit_batch(
+        &mut tx,
+        ctx,
+        &actor,
+        &before,
+        &out.batch,
+        serde_json::json!({
+            "action": "import_batch_rolled_back",
+            "reason": body.reason,
+            "rejected_edge_claims": out.rejected_edge_claims.len(),
+            "rejected_node_claims": out.rejected_node_claims.len(),
+            "retired_edge_claims": out.retired_edge_claims.len(),
+            "retired_node_claims": out.retired_node_claims.len(),
+            "removed_edges": out.removed_edges,
+            "removed_nodes": out.removed_nodes,
+        }),
+    )
+    .await?;
+
+    enqueue_completed(
+        &mut tx,
+        ctx,
+        &actor,
+        &out.batch,
+        serde_json::json!({
+            "outcome": "rolled_back",
+            "rejected": out.rejected_edge_claims.len() + out.rejected_node_claims.len(),
+            "retired": out.retired_edge_claims.len() + out.retired_node_claims.len(),
+            "removed_edges": out.removed_edges.len(),
+            "removed_nodes": out.removed_nodes.len(),
+        }),
+    )
+    .await?;
+
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(Json(out))
+}
+
+async fn audit_batch(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    before: &ImportBatch,
+    after: &ImportBatch,
+    patch: serde_json::Value,
+) -> Result<(), ApiError> {
+    audit::write_audit(
+        tx,
+        ctx,
+        Some(actor),
+        EntityType::ImportBatch,
+        after.id,
+        AuditAction::Patch,
+        Some(serde_json::to_value(before).map_err(internal_error)?),
+        Some(patch),
+        Some(serde_json::to_value(after).map_err(internal_error)?),
+    )
+    .await
+    .map_err(internal_error)
+}
+
+async fn enqueue_completed(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    batch: &ImportBatch,
+    mut payload: serde_json::Value,
+) -> Result<(), ApiError> {
+    payload["import_batch_id"] = serde_json::json!(batch.id);
+    payload["source"] = serde_json::json!(batch.source);
+    payload["actor"] = serde_json::json!(actor.username);
+    payload["correlation_id"] = serde_json::json!(ctx.request_id);
+
+    webhooks::enqueue(tx, webhooks::EVENT_IMPORT_COMPLETED, payload)
+        .await
+        .map_err(internal_error)?;
+    Ok(())
+}
+
+async fn reject_open(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    batch_id: Uuid,
+    reason: Option<String>,
+    out: &mut BatchReviewResult,
+) -> Result<(), ApiError> {
+    for proposal in open_edge_proposals(tx, batch_id, 0).await? {
+        reject_edge_proposal(tx, ctx, actor, &proposal, reason.clone()).await?;
+        out.rejected_edge_claims.push(proposal.id);
+    }
+    for proposal in open_node_proposals(tx, batch_id, 0).await? {
+        reject_node_proposal(tx, ctx, actor, &proposal, reason.clone()).await?;
+        out.rejected_node_claims.push(proposal.id);
+    }
+    Ok(())
+}
+
+/// Deprecates the active claims that approving the batch's proposals put in
+/// place. A node whose approved proposal had set another claim aside gets
+/// that claim back.
+async fn retire_approved(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    batch_id: Uuid,
+    out: &mut BatchReviewResult,
+) -> Result<(), ApiError> {
+    let edge_claims: Vec<(Uuid, Uuid)> = sqlx::query_as(
+        r#"
+        UPDATE edge_claims
+        SET status = 'deprecated', updated_at = now()
+        WHERE import_batch_id = $1
+          AND status = 'active'
+        RETURNING id, edge_id
+        "#,
+    )
+    .bind(batch_id)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    for (claim_id, edge_id) in edge_claims {
+        audit::write_audit(
+            tx,
+            ctx,
+            Some(actor),
+            EntityType::Edge,
+            edge_id,
+            AuditAction::Patch,
+            None,
+            Some(serde_json::json!({
+                "action": "claim_retired",
+                "import_batch_id": batch_id,
+                "claim_id": claim_id,
+            })),
+            None,
+        )
+        .await
+        .map_err(internal_error)?;
+        out.retired_edge_claims.push(claim_id);
+    }
+
+    let node_claims: Vec<(Uuid, Uuid)> = sqlx::query_as(
+        r#"
+        UPDATE node_claims
+        SET status = 'deprecated', updated_at = now()
+        WHERE import_batch_id = $1
+          AND status = 'active'
+        RETURNING id, node_id
+        "#,
+    )
+    .bind(batch_id)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    for (claim_id, node_id) in node_claims {
+        // The approved proposal is the batch's deprecated claim that still
+        // carries the change.
+        let changes: Option<serde_json::Value> = sqlx::query_scalar(
+            r#"
+            SELECT proposed_changes
+            FROM node_claims
+            WHERE import_batch_id = $1
+              AND node_id = $2
+              AND status = 'deprecated'
+              AND proposed_changes IS NOT NULL
+            ORDER BY updated_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(batch_id)
+        .bind(node_id)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(internal_error)?
+        .flatten();
+        if let Some(changes) = &changes {
+            restore_previous_claim(tx, node_id, changes).await?;
+        }
+
+        audit::write_audit(
+            tx,
+            ctx,
+            Some(actor),
+            EntityType::Node,
+            node_id,
+            AuditAction::Patch,
+            None,
+            Some(serde_json::json!({
+                "action": "node_claim_retired",
+                "import_batch_id": batch_id,
+                "claim_id": claim_id,
+            })),
+            None,
+        )
+        .await
+        .map_err(internal_error)?;
+        out.retired_node_claims.push(claim_id);
+    }
+
+    Ok(())
+}
+
+async fn open_edge_proposals(
+    tx: &mut Transaction<'_, Postgres>,
+    batch_id: Uuid,
+    min_confidence: i16,
+) -> Result<Vec<EdgeClaim>, ApiError> {
+    sqlx::query_as(
+        r#"
+        SELECT
+            id,
+            edge_id,
+            import_batch_id,
+            source,
+            confidence,
+            status,
+            created_by,
+            created_at,
+            updated_at,
+            last_verified_at,
+            version
+        FROM edge_claims
+        WHERE import_batch_id = $1
+          AND status = 'needs_review'
+          AND confidence >= $2
+        ORDER BY created_at
+        FOR UPDATE
+        "#,
+    )
+    .bind(batch_id)
+    .bind(min_confidence)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(internal_error)
+}
+
+async fn open_node_proposals(
+    tx: &mut Transaction<'_, Postgres>,
+    batch_id: Uuid,
+    min_confidence: i16,
+) -> Result<Vec<NodeClaim>, ApiError> {
+    sqlx::query_as(
+        r#"
+        SELECT
+            id,
+            node_id,
+            source,
+            confidence,
+            status,
+            created_by,
+            created_at,
+            updated_at,
+            last_verified_at,
+            version,
+            import_batch_id,
+            proposed_changes
+        FROM node_claims
+        WHERE import_batch_id = $1
+          AND status = 'needs_review'
+          AND confidence >= $2
+        ORDER BY created_at
+        FOR UPDATE
+        "#,
+    )
+    .bind(batch_id)
+    .bind(min_confidence)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(internal_error)
+}
+
+async fn count_open(tx: &mut Transaction<'_, Postgres>, batch_id: Uuid) -> Result<i64, ApiError> {
+    sqlx::query_scalar(
+        r#"
+        SELECT
+          (SELECT COUNT(*) FROM edge_claims WHERE import_batch_id = $1 AND status = 'needs_review')
+          + (SELECT COUNT(*) FROM node_claims WHERE import_batch_id = $1 AND status = 'needs_review')
+        "#,
+    )
+    .bind(batch_id)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(internal_error)
+}
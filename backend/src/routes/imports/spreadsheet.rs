@@ -0,0 +1,997 @@
+//! CSV/XLSX inventory import.
+//!
+//! The upload is `multipart/form-data` with a `file` part and a `mapping`
+//! part (JSON, [`SpreadsheetMapping`]) that says which header maps to node
+//! kind, name, metadata keys and node details, and which headers describe
+//! an edge. Every row is validated before anything is written; rows with
+//! errors are skipped and reported, the rest land in one import batch as
+//! node and edge proposals, exactly as in `create_proposals`: unknown nodes
+//! are created with a `needs_review` node claim, changed metadata or details
+//! on known nodes become update proposals.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Cursor;
+
+use axum::{
+    extract::{Multipart, State},
+    Extension, Json,
+};
+use calamine::{open_workbook_auto_from_rs, Reader};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::{
+    audit::RequestContext,
+    auth::AuthActor,
+    models::{edge::EdgeKind, edge_claim_evidence::NewEdgeClaimEvidence, node::NodeKind},
+    routes::{
+        nodes::details::{
+            is_valid_business_criticality, is_valid_information_class, is_valid_supplier_type,
+            NodeDetailsPatch,
+        },
+        AppState,
+    },
+};
+
+use super::common::{propose, BatchWriter, EdgeMap, Upload};
+use super::nodes::{propose_node, NodeProposalAction, ProposalNodeInput};
+use super::{ImportBatch, ProposalItem};
+use crate::routes::edges::helpers::internal_error;
+
+/// Which spreadsheet headers feed which fields. Header names are matched
+/// case-insensitively after trimming.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct SpreadsheetMapping {
+    /// Blad att läsa i XLSX-filer; första bladet om det utelämnas.
+    pub sheet: Option<String>,
+    /// Radnummer för rubrikraden; annars första icke-tomma raden.
+    pub header_row: Option<usize>,
+    /// Confidence för nodanspråk och för kanter utan egen confidence-kolumn.
+    pub confidence: Option<i16>,
+    pub nodes: Option<NodeColumns>,
+    pub edges: Option<EdgeColumns>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct NodeColumns {
+    /// Kolumn med nodtyp; annars används `default_kind`.
+    pub kind: Option<String>,
+    pub default_kind: Option<NodeKind>,
+    pub name: String,
+    /// Kolumn -> metadatanyckel.
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    #[serde(default)]
+    pub details: DetailColumns,
+}
+
+/// Columns for the fields of `PUT /nodes/:id/details`. List columns
+/// (`supplier_types`, `suppliers`, `owners`) are split on `,` and `;`.
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct DetailColumns {
+    pub owning_department: Option<String>,
+    pub supplier_types: Option<String>,
+    pub suppliers: Option<String>,
+    pub owners: Option<String>,
+    pub software_name: Option<String>,
+    pub purpose: Option<String>,
+    pub description: Option<String>,
+    pub legal_requirements: Option<String>,
+    pub financial_value: Option<String>,
+    pub pii: Option<String>,
+    pub business_criticality: Option<String>,
+    pub information_class: Option<String>,
+    pub criticality_score: Option<String>,
+}
+
+/// Endpoints are resolved by `(kind, name)` against live nodes and nodes
+/// created by the same upload.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct EdgeColumns {
+    pub from_kind: Option<String>,
+    pub default_from_kind: Option<NodeKind>,
+    pub from_name: String,
+    pub to_kind: Option<String>,
+    pub default_to_kind: Option<NodeKind>,
+    pub to_name: String,
+    /// Kolumn med kanttyp; annars `default_kind` (standard `depends_on`).
+    pub kind: Option<String>,
+    pub default_kind: Option<EdgeKind>,
+    pub confidence: Option<String>,
+}
+
+/// Shape of the multipart body; only used for the OpenAPI document.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct SpreadsheetUpload {
+    /// CSV (`,` eller `;`, UTF-8) eller XLSX/XLS/ODS.
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+    pub mapping: SpreadsheetMapping,
+    /// Batchens källa; standard `excel`.
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RowError {
+    /// Radnummer i filen (rubrikraden är rad 1).
+    pub row: usize,
+    pub column: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ImportedNode {
+    pub row: usize,
+    pub id: Uuid,
+    pub kind: String,
+    pub name: String,
+    /// `false` när noden redan fanns.
+    pub created: bool,
+    /// Förslagets claim; för befintliga noder bara när raden ändrar något.
+    pub claim_id: Option<Uuid>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SpreadsheetImportResult {
+    /// Saknas när ingen rad gick att importera.
+    pub batch: Option<ImportBatch>,
+    pub rows: usize,
+    pub nodes: Vec<ImportedNode>,
+    pub proposals: Vec<ProposalItem>,
+    pub errors: Vec<RowError>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/spreadsheet",
+    request_body(content = SpreadsheetUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = SpreadsheetImportResult),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 413, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn import_spreadsheet(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    multipart: Multipart,
+) -> Result<Json<SpreadsheetImportResult>, ApiError> {
+    let mut upload =
+        Upload::<SpreadsheetMapping>::read_with(multipart, "mapping", "Ogiltig mappning", "excel")
+            .await?;
+    let (filename, bytes) = upload.file("upload")?;
+    let source = upload.source;
+    let mapping = upload
+        .options
+        .ok_or_else(|| ApiError::validation("mapping", "Mappning saknas"))?;
+    if mapping.nodes.is_none() && mapping.edges.is_none() {
+        return Err(ApiError::validation(
+            "mapping",
+            "Mappningen måste ange nodes, edges eller båda",
+        ));
+    }
+    let default_confidence = mapping.confidence.unwrap_or(70);
+    if !(0..=100).contains(&default_confidence) {
+        return Err(ApiError::validation(
+            "mapping.confidence",
+            "confidence måste vara mellan 0 och 100",
+        ));
+    }
+
+    let table = read_table(
+        &filename,
+        &bytes,
+        mapping.sheet.as_deref(),
+        mapping.header_row,
+    )?;
+    let columns = ColumnMap::resolve(&table.headers, &mapping)?;
+
+    let departments: HashSet<String> =
+        sqlx::query_scalar("SELECT unnest(enum_range(NULL::owning_department))::text")
+            .fetch_all(&state.pool)
+            .await
+            .map_err(internal_error)?
+            .into_iter()
+            .collect();
+
+    let mut errors: Vec<RowError> = Vec::new();
+    let mut plans: Vec<RowPlan> = Vec::new();
+    for (row, cells) in &table.rows {
+        match columns.plan_row(*row, cells, default_confidence, &departments) {
+            Ok(Some(plan)) => plans.push(plan),
+            Ok(None) => {}
+            Err(e) => errors.push(e),
+        }
+    }
+
+    let existing = load_existing(&state.pool, &plans).await?;
+
+    // Node rows pointing at a soft-deleted node cannot be created (the
+    // `(kind, name)` index still holds the row) and are rejected up front.
+    let mut failed: HashSet<usize> = HashSet::new();
+    for (i, plan) in plans.iter().enumerate() {
+        if let Some(node) = &plan.node {
+            if let Some(Existing { deleted: true, .. }) = existing.get(&node.key) {
+                failed.insert(i);
+                errors.push(RowError {
+                    row: plan.row,
+                    column: Some(columns.name_header.clone()),
+                    message: format!(
+                        "Noden '{}' ({}) är borttagen; återställ den innan import",
+                        node.key.1, node.key.0
+                    ),
+                });
+            }
+        }
+    }
+
+    // Skipping a row can take away a node that another row's edge points
+    // at, so edge resolution repeats until no further row drops out.
+    loop {
+        let mut planned: HashMap<(String, String), usize> = HashMap::new();
+        for (i, plan) in plans.iter().enumerate() {
+            if failed.contains(&i) {
+                continue;
+            }
+            if let Some(node) = &plan.node {
+                if !existing.contains_key(&node.key) {
+                    planned.entry(node.key.clone()).or_insert(i);
+                }
+            }
+        }
+
+        let mut dropped = false;
+        for (i, plan) in plans.iter().enumerate() {
+            if failed.contains(&i) {
+                continue;
+            }
+            let Some(edge) = &plan.edge else {
+                continue;
+            };
+            for (key, header) in [
+                (&edge.from, &columns.from_header),
+                (&edge.to, &columns.to_header),
+            ] {
+                let resolvable = match existing.get(key) {
+                    Some(e) => !e.deleted,
+                    None => planned.contains_key(key),
+                };
+                if !resolvable {
+                    failed.insert(i);
+                    dropped = true;
+                    errors.push(RowError {
+                        row: plan.row,
+                        column: Some(header.clone()),
+                        message: format!("Noden '{}' ({}) finns inte", key.1, key.0),
+                    });
+                    break;
+                }
+            }
+        }
+
+        if !dropped {
+            break;
+        }
+    }
+
+    errors.sort_by_key(|e| e.row);
+
+    let valid: Vec<(usize, &RowPlan)> = plans
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !failed.contains(i))
+        .collect();
+
+    if valid.is_empty() {
+        return Ok(Json(SpreadsheetImportResult {
+            batch: None,
+            rows: table.rows.len(),
+            nodes: Vec::new(),
+            proposals: Vec::new(),
+            errors,
+        }));
+    }
+
+    let mut import = BatchWriter::begin(
+        &state.pool,
+        ctx,
+        &source,
+        serde_json::json!({
+            "filename": filename,
+            "sheet": table.sheet,
+            "rows": table.rows.len(),
+        }),
+    )
+    .await?;
+
+    let mut ids: HashMap<(String, String), Uuid> = existing
+        .iter()
+        .filter(|(_, e)| !e.deleted)
+        .map(|(k, e)| (k.clone(), e.id))
+        .collect();
+    let mut nodes: Vec<ImportedNode> = Vec::new();
+
+    // Nodes first: an edge may point at a node introduced further down.
+    // Only the first row for a node is proposed.
+    let mut seen: HashSet<&NodeKey> = HashSet::new();
+    let mut conflicted: HashSet<&NodeKey> = HashSet::new();
+    for &(i, plan) in &valid {
+        let Some(node) = &plan.node else {
+            continue;
+        };
+        if !seen.insert(&node.key) {
+            continue;
+        }
+
+        let input = ProposalNodeInput {
+            id: None,
+            temp_id: None,
+            kind: Some(node.kind),
+            name: Some(node.key.1.clone()),
+            metadata: Some(serde_json::Value::Object(node.metadata.clone())),
+            details: Some(node.details.clone()),
+            source: source.clone(),
+            confidence: Some(default_confidence),
+        };
+        let proposed =
+            propose_node(&mut import.tx, ctx, &actor, import.batch.id, i, &input).await?;
+
+        // The row is skipped, and so is every edge row pointing at the
+        // node; nothing may be proposed against a node under review.
+        if proposed.action == NodeProposalAction::Conflict {
+            failed.insert(i);
+            conflicted.insert(&node.key);
+            ids.remove(&node.key);
+            errors.push(RowError {
+                row: plan.row,
+                column: Some(columns.name_header.clone()),
+                message: proposed.message_sv.unwrap_or_default(),
+            });
+            continue;
+        }
+
+        ids.insert(node.key.clone(), proposed.node_id);
+        nodes.push(ImportedNode {
+            row: plan.row,
+            id: proposed.node_id,
+            kind: proposed.kind,
+            name: proposed.name,
+            created: proposed.action == NodeProposalAction::CreateNode,
+            claim_id: proposed.claim_id,
+        });
+    }
+
+    // Rows repeating an edge add evidence to one proposal; a conflict is
+    // reported against the first of them.
+    let mut proposed = EdgeMap::new();
+    for &(i, plan) in &valid {
+        if failed.contains(&i) {
+            continue;
+        }
+        if let Some(edge) = &plan.edge {
+            if let Some((key, header)) = [
+                (&edge.from, &columns.from_header),
+                (&edge.to, &columns.to_header),
+            ]
+            .into_iter()
+            .find(|(key, _)| conflicted.contains(key))
+            {
+                failed.insert(i);
+                errors.push(RowError {
+                    row: plan.row,
+                    column: Some(header.clone()),
+                    message: format!(
+                        "Noden '{}' ({}) har ett öppet förslag och kan inte kopplas",
+                        key.1, key.0
+                    ),
+                });
+                continue;
+            }
+
+            let (Some(&from_id), Some(&to_id)) = (ids.get(&edge.from), ids.get(&edge.to)) else {
+                return Err(ApiError::internal("Kantändpunkt saknas efter validering"));
+            };
+
+            let row = plan.row.to_string();
+            propose(
+                &mut proposed,
+                from_id,
+                to_id,
+                edge.kind,
+                &row,
+                edge.confidence,
+            )
+            .evidence
+            .push(NewEdgeClaimEvidence {
+                evidence_type: "document".to_string(),
+                reference: filename.clone(),
+                note: Some(format!("Rad {row}")),
+            });
+        }
+    }
+
+    for (row, message) in import.write_edges(&actor, proposed).await? {
+        errors.push(RowError {
+            row: row.parse().unwrap_or_default(),
+            column: Some(columns.to_header.clone()),
+            message,
+        });
+    }
+    errors.sort_by_key(|e| e.row);
+
+    let skipped_rows: HashSet<usize> = errors.iter().map(|e| e.row).collect();
+    import
+        .merge_metadata(serde_json::json!({ "skipped_rows": skipped_rows.len() }))
+        .await?;
+    let (batch, proposals) = import.commit(&state.pool).await?;
+
+    Ok(Json(SpreadsheetImportResult {
+        batch: Some(batch),
+        rows: table.rows.len(),
+        nodes,
+        proposals,
+        errors,
+    }))
+}
+
+struct Table {
+    sheet: Option<String>,
+    headers: Vec<String>,
+    /// `(row number in the file, trimmed cells)`; blank rows are dropped.
+    rows: Vec<(usize, Vec<String>)>,
+}
+
+/// Row number in the file plus its trimmed cells.
+type Grid = Vec<(usize, Vec<String>)>;
+
+fn read_table(
+    filename: &str,
+    bytes: &[u8],
+    sheet: Option<&str>,
+    header_row: Option<usize>,
+) -> Result<Table, ApiError> {
+    let ext = filename
+        .rsplit_once('.')
+        .map(|(_, e)| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    let is_workbook = matches!(ext.as_str(), "xlsx" | "xlsm" | "xls" | "ods")
+        || (ext != "csv" && (bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"\xD0\xCF")));
+
+    let (sheet, grid) = if is_workbook {
+        let (name, grid) = read_workbook(bytes, sheet)?;
+        (Some(name), grid)
+    } else {
+        (None, read_csv(bytes, header_row.unwrap_or(1))?)
+    };
+
+    let mut grid = grid
+        .into_iter()
+        .filter(|(_, cells)| cells.iter().any(|c| !c.is_empty()));
+
+    // Without `header_row` the first non-blank row is the header, which
+    // skips empty rows above a table but not title rows.
+    let headers = match header_row {
+        None => grid.next().map(|(_, cells)| cells),
+        Some(wanted) => grid
+            .by_ref()
+            .find(|(row, _)| *row >= wanted)
+            .filter(|(row, _)| *row == wanted)
+            .map(|(_, cells)| cells),
+    }
+    .ok_or_else(|| ApiError::validation("file", "Rubrikraden saknas eller är tom"))?;
+
+    Ok(Table {
+        sheet,
+        headers,
+        rows: grid.collect(),
+    })
+}
+
+fn read_csv(bytes: &[u8], header_row: usize) -> Result<Grid, ApiError> {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+
+    // Excel with a Swedish locale exports `;`-separated files; the header
+    // line decides.
+    let header_line = bytes
+        .split(|b| *b == b'\n')
+        .nth(header_row.saturating_sub(1))
+        .unwrap_or_default();
+    let semicolons = header_line.iter().filter(|b| **b == b';').count();
+    let commas = header_line.iter().filter(|b| **b == b',').count();
+    let delimiter = if semicolons > commas { b';' } else { b',' };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(bytes);
+
+    let mut grid = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| {
+            ApiError::validation("file", format!("Kunde inte läsa CSV (UTF-8 krävs): {e}"))
+        })?;
+        let row = record.position().map(|p| p.line() as usize).unwrap_or(0);
+        grid.push((row, record.iter().map(|c| c.trim().to_string()).collect()));
+    }
+    Ok(grid)
+}
+
+fn read_workbook(bytes: &[u8], sheet: Option<&str>) -> Result<(String, Grid), ApiError> {
+    let mut workbook = open_workbook_auto_from_rs(Cursor::new(bytes))
+        .map_err(|e| ApiError::validation("file", format!("Kunde inte läsa arbetsboken: {e}")))?;
+
+    let names = workbook.sheet_names();
+    let name = match sheet {
+        Some(s) => names
+            .iter()
+            .find(|n| n.eq_ignore_ascii_case(s))
+            .cloned()
+            .ok_or_else(|| {
+                ApiError::validation("mapping.sheet", format!("Bladet '{s}' finns inte"))
+            })?,
+        None => names
+            .first()
+            .cloned()
+            .ok_or_else(|| ApiError::validation("file", "Arbetsboken saknar blad"))?,
+    };
+
+    let range = workbook
+        .worksheet_range(&name)
+        .map_err(|e| ApiError::validation("file", format!("Kunde inte läsa bladet: {e}")))?;
+
+    // The range starts at the first used cell, not necessarily row 1.
+    let first_row = range.start().map(|(r, _)| r as usize + 1).unwrap_or(1);
+    let grid = range
+        .rows()
+        .enumerate()
+        .map(|(i, row)| {
+            let cells = row
+                .iter()
+                .map(|c| c.to_string().trim().to_string())
+                .collect();
+            (first_row + i, cells)
+        })
+        .collect();
+
+    Ok((name, grid))
+}
+
+type NodeKey = (String, String);
+
+struct NodePlan {
+    kind: NodeKind,
+    key: NodeKey,
+    metadata: serde_json::Map<String, serde_json::Value>,
+    details: NodeDetailsPatch,
+}
+
+struct EdgePlan {
+    from: NodeKey,
+    to: NodeKey,
+    kind: EdgeKind,
+    confidence: i16,
+}
+
+struct RowPlan {
+    row: usize,
+    node: Option<NodePlan>,
+    edge: Option<EdgePlan>,
+}
+
+/// A mapped column: its index in the row and the header as written in the
+/// file (used in row errors).
+#[derive(Clone)]
+struct Col {
+    index: usize,
+    header: String,
+}
+
+struct NodeCols {
+    kind: Option<Col>,
+    default_kind: Option<NodeKind>,
+    name: Col,
+    metadata: Vec<(Col, String)>,
+    details: BTreeMap<&'static str, Col>,
+}
+
+struct EdgeCols {
+    from_kind: Option<Col>,
+    default_from_kind: Option<NodeKind>,
+    from_name: Col,
+    to_kind: Option<Col>,
+    default_to_kind: Option<NodeKind>,
+    to_name: Col,
+    kind: Option<Col>,
+    default_kind: EdgeKind,
+    confidence: Option<Col>,
+}
+
+struct ColumnMap {
+    nodes: Option<NodeCols>,
+    edges: Option<EdgeCols>,
+    name_header: String,
+    from_header: String,
+    to_header: String,
+}
+
+impl ColumnMap {
+    fn resolve(headers: &[String], mapping: &SpreadsheetMapping) -> Result<Self, ApiError> {
+        let find = |field: &str, header: &str| -> Result<Col, ApiError> {
+            let wanted = header.trim();
+            headers
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(wanted))
+                .map(|index| Col {
+                    index,
+                    header: headers[index].clone(),
+                })
+                .ok_or_else(|| {
+                    ApiError::validation(
+                        format!("mapping.{field}"),
+                        format!("Kolumnen '{wanted}' finns inte i filen"),
+                    )
+                })
+        };
+        let find_opt = |field: &str, header: &Option<String>| -> Result<Option<Col>, ApiError> {
+            header.as_deref().map(|h| find(field, h)).transpose()
+        };
+
+        let nodes = match &mapping.nodes {
+            None => None,
+            Some(n) => {
+                if n.kind.is_none() && n.default_kind.is_none() {
+                    return Err(ApiError::validation(
+                        "mapping.nodes.kind",
+                        "Ange kind eller default_kind",
+                    ));
+                }
+
+                let mut metadata = Vec::new();
+                for (header, key) in &n.metadata {
+                    if key.trim().is_empty() {
+                        return Err(ApiError::validation(
+                            "mapping.nodes.metadata",
+                            format!("Tom metadatanyckel för kolumnen '{header}'"),
+                        ));
+                    }
+                    metadata.push((find("nodes.metadata", header)?, key.trim().to_string()));
+                }
+
+                let d = &n.details;
+                let mut details = BTreeMap::new();
+                for (field, header) in [
+                    ("owning_department", &d.owning_department),
+                    ("supplier_types", &d.supplier_types),
+                    ("suppliers", &d.suppliers),
+                    ("owners", &d.owners),
+                    ("software_name", &d.software_name),
+                    ("purpose", &d.purpose),
+                    ("description", &d.description),
+                    ("legal_requirements", &d.legal_requirements),
+                    ("financial_value", &d.financial_value),
+                    ("pii", &d.pii),
+                    ("business_criticality", &d.business_criticality),
+                    ("information_class", &d.information_class),
+                    ("criticality_score", &d.criticality_score),
+                ] {
+                    if let Some(col) = find_opt(&format!("nodes.details.{field}"), header)? {
+                        details.insert(field, col);
+                    }
+                }
+
+                Some(NodeCols {
+                    kind: find_opt("nodes.kind", &n.kind)?,
+                    default_kind: n.default_kind,
+                    name: find("nodes.name", &n.name)?,
+                    metadata,
+                    details,
+                })
+            }
+        };
+
+        let edges = match &mapping.edges {
+            None => None,
+            Some(e) => {
+                if e.from_kind.is_none() && e.default_from_kind.is_none() {
+                    return Err(ApiError::validation(
+                        "mapping.edges.from_kind",
+                        "Ange from_kind eller default_from_kind",
+                    ));
+                }
+                if e.to_kind.is_none() && e.default_to_kind.is_none() {
+                    return Err(ApiError::validation(
+                        "mapping.edges.to_kind",
+                        "Ange to_kind eller default_to_kind",
+                    ));
+                }
+
+                Some(EdgeCols {
+                    from_kind: find_opt("edges.from_kind", &e.from_kind)?,
+                    default_from_kind: e.default_from_kind,
+                    from_name: find("edges.from_name", &e.from_name)?,
+                    to_kind: find_opt("edges.to_kind", &e.to_kind)?,
+                    default_to_kind: e.default_to_kind,
+                    to_name: find("edges.to_name", &e.to_name)?,
+                    kind: find_opt("edges.kind", &e.kind)?,
+                    default_kind: e.default_kind.unwrap_or(EdgeKind::DependsOn),
+                    confidence: find_opt("edges.confidence", &e.confidence)?,
+                })
+            }
+        };
+
+        Ok(ColumnMap {
+            name_header: nodes
+                .as_ref()
+                .map(|n| n.name.header.clone())
+                .unwrap_or_default(),
+            from_header: edges
+                .as_ref()
+                .map(|e| e.from_name.header.clone())
+                .unwrap_or_default(),
+            to_header: edges
+                .as_ref()
+                .map(|e| e.to_name.header.clone())
+                .unwrap_or_default(),
+            nodes,
+            edges,
+        })
+    }
+
+    /// Parses one row; `Ok(None)` when the row has neither a node name nor
+    /// an edge target.
+    fn plan_row(
+        &self,
+        row: usize,
+        cells: &[String],
+        default_confidence: i16,
+        departments: &HashSet<String>,
+    ) -> Result<Option<RowPlan>, RowError> {
+        let cell = |col: &Col| cells.get(col.index).map(String::as_str).unwrap_or("");
+        let err = |col: &Col, message: String| RowError {
+            row,
+            column: Some(col.header.clone()),
+            message,
+        };
+
+        let mut node = None;
+        if let Some(n) = &self.nodes {
+            let name = cell(&n.name);
+            if !name.is_empty() {
+                let kind = node_kind(n.kind.as_ref().map(cell), n.default_kind)
+                    .map_err(|m| err(n.kind.as_ref().unwrap_or(&n.name), m))?;
+
+                let mut metadata = serde_json::Map::new();
+                for (col, key) in &n.metadata {
+                    let v = cell(col);
+                    if !v.is_empty() {
+                        metadata.insert(key.clone(), serde_json::Value::String(v.to_string()));
+                    }
+                }
+
+                let mut d = NodeDetailsPatch::default();
+                for (field, col) in &n.details {
+                    let v = cell(col);
+                    if v.is_empty() {
+                        continue;
+                    }
+                    match *field {
+                        "owning_department" => {
+                            let dept = v.to_lowercase();
+                            if !departments.contains(&dept) {
+                                return Err(err(col, format!("Okänd förvaltning '{v}'")));
+                            }
+                            d.owning_department = Some(dept);
+                        }
+                        "supplier_types" => {
+                            for t in split_list(v) {
+                                if !is_valid_supplier_type(&t) {
+                                    return Err(err(
+                                        col,
+                                        format!("Ogiltig supplier_type '{t}' (intern, saas, paas)"),
+                                    ));
+                                }
+                                d.supplier_types
+                                    .get_or_insert_with(Vec::new)
+                                    .push(t.to_lowercase());
+                            }
+                        }
+                        "suppliers" => d.suppliers = Some(split_list(v)),
+                        "owners" => d.owners = Some(split_list(v)),
+                        "software_name" => d.software_name = Some(v.to_string()),
+                        "purpose" => d.purpose = Some(v.to_string()),
+                        "description" => d.description = Some(v.to_string()),
+                        "legal_requirements" | "financial_value" | "pii" => {
+                            let b = parse_bool(v)
+                                .ok_or_else(|| err(col, format!("Ogiltigt ja/nej-värde '{v}'")))?;
+                            match *field {
+                                "legal_requirements" => d.legal_requirements = Some(b),
+                                "financial_value" => d.financial_value = Some(b),
+                                _ => d.pii = Some(b),
+                            }
+                        }
+                        "business_criticality" => {
+                            if !is_valid_business_criticality(v) {
+                                return Err(err(
+                                    col,
+                                    format!(
+                                        "Ogiltig business_criticality '{v}' (low, medium, high)"
+                                    ),
+                                ));
+                            }
+                            d.business_criticality = Some(v.to_lowercase());
+                        }
+                        "information_class" => {
+                            if !is_valid_information_class(v) {
+                                return Err(err(
+                                    col,
+                                    format!(
+                                        "Ogiltig information_class '{v}' (intern, begransad, skyddad, oppen, konfidentiell)"
+                                    ),
+                                ));
+                            }
+                            d.information_class = Some(v.to_lowercase());
+                        }
+                        "criticality_score" => {
+                            let score: f64 = v.replace(',', ".").parse().map_err(|_| {
+                                err(col, format!("Ogiltigt criticality_score '{v}'"))
+                            })?;
+                            if !(0.0..=5.0).contains(&score)
+                                || ((score * 2.0).round() / 2.0 - score).abs() > 1e-9
+                            {
+                                return Err(err(
+                                    col,
+                                    "criticality_score måste vara 0–5 i 0.5-steg".to_string(),
+                                ));
+                            }
+                            d.criticality_score = Some(score);
+                        }
+                        _ => {}
+                    }
+                }
+
+                node = Some(NodePlan {
+                    kind,
+                    key: (kind.as_str().to_string(), name.to_string()),
+                    metadata,
+                    details: d,
+                });
+            }
+        }
+
+        let mut edge = None;
+        if let Some(e) = &self.edges {
+            let from_name = cell(&e.from_name);
+            let to_name = cell(&e.to_name);
+            match (from_name.is_empty(), to_name.is_empty()) {
+                // The from column often doubles as the node name column, so
+                // an empty target just means the row carries no edge.
+                (_, true) => {}
+                (true, false) => {
+                    return Err(err(&e.from_name, "Från-nod saknas".to_string()));
+                }
+                (false, false) => {
+                    let from_kind = node_kind(e.from_kind.as_ref().map(cell), e.default_from_kind)
+                        .map_err(|m| err(e.from_kind.as_ref().unwrap_or(&e.from_name), m))?;
+                    let to_kind = node_kind(e.to_kind.as_ref().map(cell), e.default_to_kind)
+                        .map_err(|m| err(e.to_kind.as_ref().unwrap_or(&e.to_name), m))?;
+
+                    let kind = match e.kind.as_ref().map(|c| (c, cell(c))) {
+                        Some((col, v)) if !v.is_empty() => parse_enum::<EdgeKind>(v)
+                            .ok_or_else(|| err(col, format!("Okänd kanttyp '{v}'")))?,
+                        _ => e.default_kind,
+                    };
+
+                    let confidence = match e.confidence.as_ref().map(|c| (c, cell(c))) {
+                        Some((col, v)) if !v.is_empty() => v
+                            .parse::<i16>()
+                            .ok()
+                            .filter(|c| (0..=100).contains(c))
+                            .ok_or_else(|| {
+                                err(col, format!("confidence måste vara 0–100, fick '{v}'"))
+                            })?,
+                        _ => default_confidence,
+                    };
+
+                    if from_kind == to_kind && from_name == to_name {
+                        return Err(err(
+                            &e.to_name,
+                            "En kant kan inte peka på sig själv".to_string(),
+                        ));
+                    }
+
+                    edge = Some(EdgePlan {
+                        from: (from_kind.as_str().to_string(), from_name.to_string()),
+                        to: (to_kind.as_str().to_string(), to_name.to_string()),
+                        kind,
+                        confidence,
+                    });
+                }
+            }
+        }
+
+        if node.is_none() && edge.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(RowPlan { row, node, edge }))
+    }
+}
+
+fn node_kind(cell: Option<&str>, default: Option<NodeKind>) -> Result<NodeKind, String> {
+    match cell {
+        Some(v) if !v.is_empty() => {
+            parse_enum::<NodeKind>(v).ok_or_else(|| format!("Okänd nodtyp '{v}'"))
+        }
+        _ => default.ok_or_else(|| "Nodtyp saknas".to_string()),
+    }
+}
+
+/// Parses a snake_case enum value, accepting `Data category`-style cells.
+fn parse_enum<T: serde::de::DeserializeOwned>(v: &str) -> Option<T> {
+    let v = v.trim().to_lowercase().replace([' ', '-'], "_");
+    serde_json::from_value(serde_json::Value::String(v)).ok()
+}
+
+fn parse_bool(v: &str) -> Option<bool> {
+    match v.trim().to_lowercase().as_str() {
+        "ja" | "j" | "yes" | "y" | "true" | "sant" | "1" | "x" => Some(true),
+        "nej" | "n" | "no" | "false" | "falskt" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn split_list(v: &str) -> Vec<String> {
+    v.split([',', ';', '\n'])
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+struct Existing {
+    id: Uuid,
+    deleted: bool,
+}
+
+async fn load_existing(
+    pool: &sqlx::PgPool,
+    plans: &[RowPlan],
+) -> Result<HashMap<NodeKey, Existing>, ApiError> {
+    let mut keys: HashSet<&NodeKey> = HashSet::new();
+    for plan in plans {
+        if let Some(n) = &plan.node {
+            keys.insert(&n.key);
+        }
+        if let Some(e) = &plan.edge {
+            keys.insert(&e.from);
+            keys.insert(&e.to);
+        }
+    }
+    if keys.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let (kinds, names): (Vec<String>, Vec<String>) = keys.into_iter().cloned().unzip();
+
+    let rows: Vec<(Uuid, String, String, bool)> = sqlx::query_as(
+        r#"
+        SELECT n.id, n.kind, n.name, n.deleted_at IS NOT NULL
+        FROM nodes n
+        JOIN UNNEST($1::text[], $2::text[]) AS k(kind, name)
+          ON k.kind = n.kind AND k.name = n.name
+        "#,
+    )
+    .bind(kinds)
+    .bind(names)
+    .fetch_all(pool)
+    .await
+    .map_err(internal_error)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, kind, name, deleted)| ((kind, name), Existing { id, deleted }))
+        .collect())
+}
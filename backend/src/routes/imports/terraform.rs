@@ -0,0 +1,677 @@
+//! Terraform state import (`terraform.tfstate`, format version 4).
+//!
+//! Managed resources whose type is known as compute, database, DNS or
+//! storage become nodes: compute as `host`, databases as `database`, DNS
+//! and storage as `service`. `options.types` adds or overrides types. Each
+//! instance keeps its Terraform address (`module.db.aws_db_instance.main`,
+//! `aws_instance.web[0]`) and the state lineage in metadata; a re-import
+//! matches on that address first, so renaming a resource's `Name` tag does
+//! not create a second node. Without a match the usual `(kind, name)`
+//! lookup applies.
+//!
+//! Edges come from the instances' `dependencies`. A dependency on a resource
+//! that is not imported (a subnet, a security group) is followed through to
+//! whatever imported resources it depends on. An edge to storage is
+//! `stores_data`, anything else `depends_on`.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use axum::{
+    extract::{Multipart, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::{
+    audit::RequestContext,
+    auth::AuthActor,
+    models::{edge::EdgeKind, edge_claim_evidence::NewEdgeClaimEvidence, node::NodeKind},
+    routes::AppState,
+};
+
+use super::common::{deleted_nodes, propose, BatchWriter, Upload};
+use super::nodes::{propose_node, NodeProposalAction, ProposalNodeInput};
+use super::{ImportBatch, ProposalItem};
+use crate::routes::edges::helpers::internal_error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceCategory {
+    Compute,
+    Database,
+    Dns,
+    Storage,
+}
+
+impl ResourceCategory {
+    fn as_str(self) -> &'static str {
+        match self {
+            ResourceCategory::Compute => "compute",
+            ResourceCategory::Database => "database",
+            ResourceCategory::Dns => "dns",
+            ResourceCategory::Storage => "storage",
+        }
+    }
+
+    fn node_kind(self) -> NodeKind {
+        match self {
+            ResourceCategory::Compute => NodeKind::Host,
+            ResourceCategory::Database => NodeKind::Database,
+            ResourceCategory::Dns | ResourceCategory::Storage => NodeKind::Service,
+        }
+    }
+
+    /// Attributes copied into node metadata, before the location ones;
+    /// first hit per key wins. Nothing outside these lists is read, so
+    /// credentials in the state stay out. Keyed by category because the
+    /// same attribute differs: `size` is a droplet size on compute but
+    /// gigabytes on a volume, `type` a record type on DNS but a disk type
+    /// on storage.
+    fn attributes(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            ResourceCategory::Compute => COMPUTE_ATTRIBUTES,
+            ResourceCategory::Database => DATABASE_ATTRIBUTES,
+            ResourceCategory::Dns => DNS_ATTRIBUTES,
+            ResourceCategory::Storage => STORAGE_ATTRIBUTES,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct TerraformOptions {
+    /// Resurstyp -> kategori; kompletterar eller ersätter den inbyggda tabellen.
+    #[serde(default)]
+    pub types: HashMap<String, ResourceCategory>,
+    /// Sätts som `env` i nodernas metadata.
+    pub env: Option<String>,
+    /// Confidence för förslagen; standard 80.
+    pub confidence: Option<i16>,
+}
+
+/// Shape of the multipart body; only used for the OpenAPI document.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct TerraformUpload {
+    /// `terraform.tfstate` (JSON, version 4).
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+    pub options: Option<TerraformOptions>,
+    /// Batchens källa; standard `terraform`.
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TerraformNode {
+    pub address: String,
+    pub category: ResourceCategory,
+    pub id: Uuid,
+    pub kind: String,
+    pub name: String,
+    /// `false` när noden redan fanns.
+    pub created: bool,
+    /// Förslagets claim; för befintliga noder bara när tillståndet ändrar något.
+    pub claim_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ResourceIssue {
+    pub address: String,
+    pub message: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TerraformImportResult {
+    /// Saknas när tillståndet inte innehöll någon känd resurs.
+    pub batch: Option<ImportBatch>,
+    pub terraform_version: Option<String>,
+    pub serial: Option<i64>,
+    /// Hanterade resursinstanser i filen.
+    pub resources: usize,
+    /// Instanser av typer utanför tabellen.
+    pub ignored_resources: usize,
+    pub nodes: Vec<TerraformNode>,
+    pub proposals: Vec<ProposalItem>,
+    pub issues: Vec<ResourceIssue>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/terraform",
+    request_body(content = TerraformUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, body = TerraformImportResult),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 413, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn import_terraform(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    multipart: Multipart,
+) -> Result<Json<TerraformImportResult>, ApiError> {
+    let mut upload = Upload::<TerraformOptions>::read(multipart, "terraform").await?;
+    let (filename, bytes) = upload.file("terraform.tfstate")?;
+    let options = upload.options.unwrap_or_default();
+    let source = upload.source;
+    let confidence = options.confidence.unwrap_or(80);
+    if !(0..=100).contains(&confidence) {
+        return Err(ApiError::validation(
+            "options.confidence",
+            "confidence måste vara mellan 0 och 100",
+        ));
+    }
+
+    let doc: Value = serde_json::from_slice(&bytes)
+        .map_err(|e| ApiError::validation("file", format!("Ogiltig JSON: {e}")))?;
+    if doc.get("version").and_then(Value::as_i64) != Some(4) {
+        return Err(ApiError::validation(
+            "file",
+            "Endast Terraform-tillstånd i format version 4 stöds",
+        ));
+    }
+    let lineage = doc
+        .get("lineage")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let terraform_version = doc
+        .get("terraform_version")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let serial = doc.get("serial").and_then(Value::as_i64);
+
+    let state_file = StateFile::read(&doc, &options.types);
+    let mut issues: Vec<ResourceIssue> = Vec::new();
+
+    // Re-imports match on the address; a node from another state (other
+    // lineage) with the same address is left alone.
+    let matched: HashMap<String, Uuid> = {
+        let rows: Vec<(Uuid, String, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT id, metadata->>'terraform_address', metadata->>'terraform_lineage'
+            FROM nodes
+            WHERE deleted_at IS NULL
+              AND metadata->>'terraform_address' = ANY($1)
+            "#,
+        )
+        .bind(
+            state_file
+                .instances
+                .iter()
+                .map(|i| i.address.clone())
+                .collect::<Vec<_>>(),
+        )
+        .fetch_all(&state.pool)
+        .await
+        .map_err(internal_error)?;
+
+        let mut matched = HashMap::new();
+        for (id, address, node_lineage) in rows {
+            if (node_lineage.is_none() || node_lineage == lineage)
+                && matched.insert(address.clone(), id).is_some()
+            {
+                issues.push(ResourceIssue {
+                    address: address.clone(),
+                    message: "Flera noder har samma Terraform-adress".to_string(),
+                });
+            }
+        }
+        matched
+    };
+
+    // Instances sharing a display name (count, for_each) are kept apart by
+    // address.
+    let mut taken: HashSet<(&str, String)> = HashSet::new();
+    let names: Vec<String> = state_file
+        .instances
+        .iter()
+        .map(|i| {
+            if taken.insert((i.category.node_kind().as_str(), i.name.clone())) {
+                i.name.clone()
+            } else {
+                format!("{} ({})", i.name, i.address)
+            }
+        })
+        .collect();
+
+    let deleted = deleted_nodes(&state.pool, names.clone()).await?;
+    let mut skipped = vec![false; state_file.instances.len()];
+    for (i, instance) in state_file.instances.iter().enumerate() {
+        let kind = instance.category.node_kind();
+        if !matched.contains_key(&instance.address)
+            && deleted.contains(&(kind.as_str().to_string(), names[i].clone()))
+        {
+            skipped[i] = true;
+            issues.push(ResourceIssue {
+                address: instance.address.clone(),
+                message: format!(
+                    "Noden '{}' ({}) är borttagen; återställ den innan import",
+                    names[i],
+                    kind.as_str()
+                ),
+            });
+        }
+    }
+
+    if skipped.iter().all(|s| *s) {
+        return Ok(Json(TerraformImportResult {
+            batch: None,
+            terraform_version,
+            serial,
+            resources: state_file.managed,
+            ignored_resources: state_file.ignored,
+            nodes: Vec::new(),
+            proposals: Vec::new(),
+            issues,
+        }));
+    }
+
+    let mut import = BatchWriter::begin(
+        &state.pool,
+        ctx,
+        &source,
+        serde_json::json!({
+            "filename": filename,
+            "terraform_version": terraform_version,
+            "lineage": lineage,
+            "serial": serial,
+            "resources": state_file.managed,
+        }),
+    )
+    .await?;
+
+    let mut ids: Vec<Option<Uuid>> = vec![None; state_file.instances.len()];
+    let mut nodes: Vec<TerraformNode> = Vec::new();
+
+    for (i, instance) in state_file.instances.iter().enumerate() {
+        if skipped[i] {
+            continue;
+        }
+        let mut metadata = instance.metadata.clone();
+        metadata.insert("terraform_address".into(), instance.address.clone().into());
+        if let Some(lineage) = &lineage {
+            metadata.insert("terraform_lineage".into(), lineage.clone().into());
+        }
+        if let Some(env) = &options.env {
+            metadata.insert("env".into(), env.clone().into());
+        }
+
+        let existing = matched.get(&instance.address).copied();
+        let input = ProposalNodeInput {
+            id: existing,
+            temp_id: None,
+            kind: existing.is_none().then(|| instance.category.node_kind()),
+            name: existing.is_none().then(|| names[i].clone()),
+            metadata: Some(Value::Object(metadata)),
+            details: None,
+            source: source.clone(),
+            confidence: Some(confidence),
+        };
+        let proposed =
+            propose_node(&mut import.tx, ctx, &actor, import.batch.id, i, &input).await?;
+        if proposed.action == NodeProposalAction::Conflict {
+            issues.push(ResourceIssue {
+                address: instance.address.clone(),
+                message: proposed.message_sv.clone().unwrap_or_default(),
+            });
+        }
+        ids[i] = Some(proposed.node_id);
+        nodes.push(TerraformNode {
+            address: instance.address.clone(),
+            category: instance.category,
+            id: proposed.node_id,
+            kind: proposed.kind,
+            name: proposed.name,
+            created: proposed.action == NodeProposalAction::CreateNode,
+            claim_id: proposed.claim_id,
+        });
+    }
+
+    // Imported instances per resource address, for dependency lookups.
+    let mut by_resource: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, instance) in state_file.instances.iter().enumerate() {
+        if ids[i].is_some() {
+            by_resource.entry(&instance.resource).or_default().push(i);
+        }
+    }
+
+    let mut proposed = BTreeMap::new();
+    for (i, instance) in state_file.instances.iter().enumerate() {
+        let Some(from) = ids[i] else {
+            continue;
+        };
+
+        let mut queue: VecDeque<(&str, Vec<&str>)> = instance
+            .dependencies
+            .iter()
+            .map(|d| (d.as_str(), Vec::new()))
+            .collect();
+        let mut visited: HashSet<&str> = HashSet::new();
+        while let Some((resource, via)) = queue.pop_front() {
+            if !visited.insert(resource) || resource == instance.resource {
+                continue;
+            }
+            let Some(targets) = by_resource.get(resource) else {
+                // Not imported: look through it to what it depends on.
+                let mut via = via.clone();
+                via.push(resource);
+                for next in state_file.dependencies.get(resource).into_iter().flatten() {
+                    queue.push_back((next.as_str(), via.clone()));
+                }
+                continue;
+            };
+            for &j in targets {
+                let Some(to) = ids[j].filter(|to| *to != from) else {
+                    continue;
+                };
+                let target = &state_file.instances[j];
+                let kind = match target.category {
+                    ResourceCategory::Storage => EdgeKind::StoresData,
+                    _ => EdgeKind::DependsOn,
+                };
+                let path = if via.is_empty() {
+                    String::new()
+                } else {
+                    format!(" via {}", via.join(", "))
+                };
+                propose(&mut proposed, from, to, kind, &instance.address, confidence)
+                    .evidence
+                    .push(NewEdgeClaimEvidence {
+                        evidence_type: "document".to_string(),
+                        reference: filename.clone(),
+                        note: Some(format!("{} -> {}{path}", instance.address, target.address)),
+                    });
+            }
+        }
+    }
+
+    let written = import.finish(&state.pool, &actor, proposed).await?;
+    for (address, message) in written.conflicts {
+        issues.push(ResourceIssue { address, message });
+    }
+
+    Ok(Json(TerraformImportResult {
+        batch: Some(written.batch),
+        terraform_version,
+        serial,
+        resources: state_file.managed,
+        ignored_resources: state_file.ignored,
+        nodes,
+        proposals: written.proposals,
+        issues,
+    }))
+}
+
+struct Instance {
+    /// Full address, index included.
+    address: String,
+    /// Address of the resource block.
+    resource: String,
+    category: ResourceCategory,
+    name: String,
+    metadata: serde_json::Map<String, Value>,
+    dependencies: Vec<String>,
+}
+
+struct StateFile {
+    instances: Vec<Instance>,
+    /// Dependencies per resource address, for every managed resource.
+    dependencies: HashMap<String, Vec<String>>,
+    managed: usize,
+    ignored: usize,
+}
+
+impl StateFile {
+    fn read(doc: &Value, extra: &HashMap<String, ResourceCategory>) -> Self {
+        let mut state = StateFile {
+            instances: Vec::new(),
+            dependencies: HashMap::new(),
+            managed: 0,
+            ignored: 0,
+        };
+
+        let resources = doc.get("resources").and_then(Value::as_array);
+        for resource in resources.into_iter().flatten() {
+            if resource.get("mode").and_then(Value::as_str) != Some("managed") {
+                continue;
+            }
+            let resource_type = str_at(resource, "type").unwrap_or_default();
+            let address = [
+                str_at(resource, "module"),
+                Some(resource_type.clone()),
+                str_at(resource, "name"),
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(".");
+            let category = extra
+                .get(&resource_type)
+                .copied()
+                .or_else(|| builtin_category(&resource_type));
+            let provider = str_at(resource, "provider").map(|p| provider_name(&p));
+
+            for instance in resource
+                .get("instances")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                state.managed += 1;
+
+                let dependencies: Vec<String> = instance
+                    .get("dependencies")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect();
+                state
+                    .dependencies
+                    .entry(address.clone())
+                    .or_default()
+                    .extend(dependencies.iter().cloned());
+
+                let Some(category) = category else {
+                    state.ignored += 1;
+                    continue;
+                };
+
+                let instance_address = match instance.get("index_key") {
+                    Some(Value::Number(n)) => format!("{address}[{n}]"),
+                    Some(Value::String(s)) => format!("{address}[\"{s}\"]"),
+                    _ => address.clone(),
+                };
+                let attributes = instance.get("attributes").unwrap_or(&Value::Null);
+
+                let mut metadata = serde_json::Map::new();
+                metadata.insert("terraform_type".into(), resource_type.clone().into());
+                metadata.insert("terraform_category".into(), category.as_str().into());
+                if let Some(provider) = &provider {
+                    metadata.insert("provider".into(), provider.clone().into());
+                }
+                for (attribute, key) in category.attributes().iter().chain(LOCATION_ATTRIBUTES) {
+                    if metadata.contains_key(*key) {
+                        continue;
+                    }
+                    if let Some(value) = scalar_at(attributes, attribute) {
+                        metadata.insert((*key).into(), value.into());
+                    }
+                }
+
+                state.instances.push(Instance {
+                    name: display_name(attributes).unwrap_or_else(|| instance_address.clone()),
+                    address: instance_address,
+                    resource: address.clone(),
+                    category,
+                    metadata,
+                    dependencies,
+                });
+            }
+        }
+        state
+    }
+}
+
+const COMPUTE_ATTRIBUTES: &[(&str, &str)] = &[
+    ("private_ip", "ip"),
+    ("ipv4_address", "ip"),
+    ("private_ip_address", "ip"),
+    ("network_interface.0.network_ip", "ip"),
+    ("public_ip", "public_ip"),
+    ("public_ip_address", "public_ip"),
+    ("instance_type", "instance_type"),
+    ("machine_type", "instance_type"),
+    ("server_type", "instance_type"),
+    ("size", "instance_type"),
+    ("hostname", "fqdn"),
+];
+
+const DATABASE_ATTRIBUTES: &[(&str, &str)] = &[
+    ("engine", "engine"),
+    ("database_version", "engine"),
+    ("engine_version", "version"),
+    ("version", "version"),
+    ("instance_class", "instance_type"),
+    ("settings.0.tier", "instance_type"),
+    ("size", "instance_type"),
+    ("fqdn", "fqdn"),
+    ("endpoint", "endpoint"),
+    ("address", "endpoint"),
+    ("host", "endpoint"),
+];
+
+const DNS_ATTRIBUTES: &[(&str, &str)] = &[
+    ("fqdn", "fqdn"),
+    ("hostname", "fqdn"),
+    ("type", "record_type"),
+];
+
+const STORAGE_ATTRIBUTES: &[(&str, &str)] = &[
+    ("size", "size_gb"),
+    ("disk_size_gb", "size_gb"),
+    ("type", "disk_type"),
+    ("storage_account_type", "disk_type"),
+    ("storage_class", "storage_class"),
+    ("endpoint", "endpoint"),
+];
+
+const LOCATION_ATTRIBUTES: &[(&str, &str)] = &[
+    ("region", "region"),
+    ("location", "region"),
+    ("availability_zone", "zone"),
+    ("zone", "zone"),
+];
+
+/// Best human name for the resource.
+fn display_name(attributes: &Value) -> Option<String> {
+    [
+        "tags.Name",
+        "identifier",
+        "bucket",
+        "fqdn",
+        "name",
+        "cluster_identifier",
+        "replication_group_id",
+    ]
+    .iter()
+    .find_map(|path| scalar_at(attributes, path))
+    .filter(|s| !s.is_empty())
+}
+
+fn builtin_category(resource_type: &str) -> Option<ResourceCategory> {
+    use ResourceCategory::*;
+    Some(match resource_type {
+        "aws_instance"
+        | "google_compute_instance"
+        | "azurerm_linux_virtual_machine"
+        | "azurerm_windows_virtual_machine"
+        | "azurerm_virtual_machine"
+        | "digitalocean_droplet"
+        | "hcloud_server"
+        | "linode_instance"
+        | "openstack_compute_instance_v2"
+        | "vsphere_virtual_machine"
+        | "proxmox_vm_qemu"
+        | "libvirt_domain" => Compute,
+        "aws_db_instance"
+        | "aws_rds_cluster"
+        | "aws_dynamodb_table"
+        | "aws_elasticache_cluster"
+        | "aws_elasticache_replication_group"
+        | "aws_docdb_cluster"
+        | "google_sql_database_instance"
+        | "google_redis_instance"
+        | "azurerm_postgresql_server"
+        | "azurerm_postgresql_flexible_server"
+        | "azurerm_mysql_server"
+        | "azurerm_mysql_flexible_server"
+        | "azurerm_mssql_server"
+        | "azurerm_mssql_database"
+        | "azurerm_cosmosdb_account"
+        | "azurerm_redis_cache"
+        | "digitalocean_database_cluster"
+        | "hcloud_database" => Database,
+        "aws_route53_zone"
+        | "aws_route53_record"
+        | "google_dns_managed_zone"
+        | "google_dns_record_set"
+        | "azurerm_dns_zone"
+        | "azurerm_dns_a_record"
+        | "azurerm_dns_cname_record"
+        | "azurerm_private_dns_zone"
+        | "cloudflare_zone"
+        | "cloudflare_record"
+        | "cloudflare_dns_record"
+        | "digitalocean_domain"
+        | "digitalocean_record" => Dns,
+        "aws_s3_bucket"
+        | "aws_ebs_volume"
+        | "aws_efs_file_system"
+        | "aws_fsx_windows_file_system"
+        | "google_storage_bucket"
+        | "google_compute_disk"
+        | "google_filestore_instance"
+        | "azurerm_storage_account"
+        | "azurerm_storage_container"
+        | "azurerm_storage_share"
+        | "azurerm_managed_disk"
+        | "digitalocean_volume"
+        | "digitalocean_spaces_bucket"
+        | "hcloud_volume" => Storage,
+        _ => return None,
+    })
+}
+
+/// `provider["registry.terraform.io/hashicorp/aws"]` gives `aws`.
+fn provider_name(provider: &str) -> String {
+    provider
+        .rsplit('/')
+        .next()
+        .unwrap_or(provider)
+        .trim_end_matches(['"', ']'])
+        .to_string()
+}
+
+fn str_at(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+/// A scalar at a dotted path; numeric segments index arrays.
+fn scalar_at(value: &Value, path: &str) -> Option<String> {
+    let value = path.split('.').try_fold(value, |v, key| match v {
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        _ => v.get(key),
+    })?;
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
@@ -1,13 +1,18 @@
-use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::http::{HeaderMap, HeaderValue};
 use sqlx::PgPool;
 use time::format_description::well_known::Rfc3339;
-use time::{OffsetDateTime, UtcOffset};
+use time::OffsetDateTime;
+
+use crate::error::ApiError;
 
 pub mod audit;
 pub mod auth;
+pub mod batch;
 pub mod claims;
+pub mod conditional;
 pub mod data_domains;
 pub mod edges;
+pub mod events;
 pub mod export;
 pub mod graph;
 pub mod health;
@@ -17,7 +22,9 @@ pub mod nodes;
 pub mod patch;
 pub mod query;
 pub mod schema;
+pub mod saved_searches;
 pub mod search;
+pub mod webhooks;
 
 #[derive(Clone)]
 pub struct AuthState {
@@ -31,35 +38,69 @@ pub struct AuthState {
 pub struct AppState {
     pub pool: PgPool,
     pub auth: AuthState,
+    pub events: crate::events::EventBus,
+    /// How long an `Idempotency-Key` response is replayed, see `crate::idempotency`.
+    pub idempotency_ttl_seconds: u64,
+    /// Which webhook URLs are acceptable, see `crate::webhooks::TargetPolicy`.
+    pub webhook_targets: crate::webhooks::TargetPolicy,
 }
 
-pub fn etag_from_updated_at(updated_at: OffsetDateTime) -> HeaderValue {
-    let s = updated_at
-        .to_offset(UtcOffset::UTC)
-        .format(&Rfc3339)
-        .unwrap_or_else(|_| "1970-01-01T00:00:00Z".to_string());
-    HeaderValue::from_str(&format!("\"{}\"", s))
+/// `"v<version>"`. `version` is bumped by a trigger on every UPDATE of
+/// nodes, edges and claims (migration 020).
+pub fn etag_from_version(version: i64) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"v{}\"", version))
         .unwrap_or_else(|_| HeaderValue::from_static("\"invalid\""))
 }
 
-pub fn require_if_match(headers: &HeaderMap) -> Result<String, (StatusCode, String)> {
+pub fn require_if_match(headers: &HeaderMap) -> Result<String, ApiError> {
     let raw = headers
         .get(axum::http::header::IF_MATCH)
-        .ok_or((
-            StatusCode::PRECONDITION_REQUIRED,
-            "If-Match saknas (optimistisk låsning)".into(),
-        ))?
+        .ok_or_else(ApiError::if_match_required)?
         .to_str()
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Ogiltig If-Match".into()))?
+        .map_err(|_| ApiError::invalid_if_match())?
         .trim();
 
     if raw.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "Ogiltig If-Match".into()));
+        return Err(ApiError::invalid_if_match());
     }
 
     Ok(raw.to_string())
 }
 
+/// Optimistic locking against a row's `version`. During the transition
+/// from timestamp ETags, an RFC 3339 token is still accepted if it equals
+/// `updated_at` exactly; clients get the new form on their next read.
+pub fn check_if_match(
+    version: i64,
+    updated_at: OffsetDateTime,
+    if_match: &str,
+) -> Result<(), ApiError> {
+    let mut understood = false;
+
+    for token in if_match.split(',').filter_map(normalize_etag_token) {
+        if token == "*" {
+            return Ok(());
+        }
+        if let Some(v) = token.strip_prefix('v').and_then(|v| v.parse::<i64>().ok()) {
+            understood = true;
+            if v == version {
+                return Ok(());
+            }
+        } else if let Ok(ts) = OffsetDateTime::parse(&token, &Rfc3339) {
+            understood = true;
+            if ts == updated_at {
+                return Ok(());
+            }
+        }
+    }
+
+    if understood {
+        Err(ApiError::etag_mismatch())
+    } else {
+        Err(ApiError::invalid_if_match())
+    }
+}
+
 fn normalize_etag_token(token: &str) -> Option<String> {
     let mut s = token.trim();
     if s.is_empty() {
@@ -88,11 +129,6 @@ fn normalize_etag_token(token: &str) -> Option<String> {
     }
 }
 
-fn parse_rfc3339_millis(s: &str) -> Option<i128> {
-    let dt = OffsetDateTime::parse(s, &Rfc3339).ok()?;
-    Some(dt.unix_timestamp_nanos() / 1_000_000)
-}
-
 pub fn is_match(current_etag: &HeaderValue, if_match: &str) -> bool {
     let current_raw = match current_etag.to_str() {
         Ok(v) => v,
@@ -118,7 +154,10 @@ pub fn is_match(current_etag: &HeaderValue, if_match: &str) -> bool {
             return true;
         }
 
-        if let (Some(a), Some(b)) = (parse_rfc3339_millis(&token), parse_rfc3339_millis(&current)) {
+        if let (Ok(a), Ok(b)) = (
+            OffsetDateTime::parse(&token, &Rfc3339),
+            OffsetDateTime::parse(&current, &Rfc3339),
+        ) {
             if a == b {
                 return true;
             }
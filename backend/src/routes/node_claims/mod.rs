@@ -1,51 +1,80 @@
 use axum::{
     extract::{Path, State},
-    http::{HeaderMap, StatusCode},
+    http::HeaderMap,
     routing::post,
     Extension, Json, Router,
 };
 use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+use utoipa::{OpenApi, ToSchema};
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::{
     audit::{self, AuditAction, EntityType, RequestContext},
     auth::AuthActor,
     models::node_claims::NodeClaim,
-    routes::{etag_from_updated_at, is_match, require_if_match, AppState},
+    routes::{
+        check_if_match,
+        imports::{apply_node_proposal, restore_previous_claim},
+        require_if_match, AppState,
+    },
 };
 
 use crate::routes::edges::helpers::{internal_error, map_sqlx_error};
 
+#[derive(OpenApi)]
+#[openapi(paths(approve_claim, reject_claim))]
+pub struct NodeClaimsApi;
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/:id/approve", post(approve_claim))
         .route("/:id/reject", post(reject_claim))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
+#[schema(as = NodeClaimRejectBody)]
 pub struct RejectBody {
     #[serde(default)]
     pub reason: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
+#[schema(as = NodeClaimApproveResponse)]
 pub struct ApproveResponse {
     pub active_claim: NodeClaim,
     pub retired_proposal_id: Uuid,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
+#[schema(as = NodeClaimRejectResponse)]
 pub struct RejectResponse {
     pub retired_claim: NodeClaim,
 }
 
+#[utoipa::path(
+    post,
+    path = "/{id}/approve",
+    params(
+        ("id" = Uuid, Path, description = "Förslagets claim-id"),
+        ("If-Match" = String, Header, description = "ETag för claimet")
+    ),
+    responses(
+        (status = 200, body = ApproveResponse),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse),
+        (status = 428, response = crate::openapi::ErrorResponse)
+    )
+)]
 async fn approve_claim(
     State(state): State<AppState>,
     Extension(ctx): Extension<RequestContext>,
     Extension(actor): Extension<AuthActor>,
     headers: HeaderMap,
     Path(claim_id): Path<Uuid>,
-) -> Result<Json<ApproveResponse>, (StatusCode, String)> {
+) -> Result<Json<ApproveResponse>, ApiError> {
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
 
     let proposal: NodeClaim = sqlx::query_as(
@@ -59,7 +88,10 @@ async fn approve_claim(
             created_by,
             created_at,
             updated_at,
-            last_verified_at
+            last_verified_at,
+            version,
+            import_batch_id,
+            proposed_changes
         FROM node_claims
         WHERE id = $1
         "#,
@@ -70,23 +102,101 @@ async fn approve_claim(
     .map_err(map_sqlx_error)?;
 
     if proposal.status != "needs_review" && proposal.status != "rejected" {
-        return Err((
-            StatusCode::CONFLICT,
-            "Endast claims med status needs_review kan godkännas".into(),
-        ));
+        return Err(ApiError::conflict("Endast claims med status needs_review kan godkännas"));
     }
     let if_match = require_if_match(&headers)?;
-    let expected = etag_from_updated_at(proposal.updated_at);
-    if !is_match(&expected, &if_match) {
-        return Err((StatusCode::BAD_REQUEST, "Ogiltig If-Match".into()));
+    check_if_match(proposal.version, proposal.updated_at, &if_match)?;
+
+    let (retired, active) = approve_node_proposal(&mut tx, ctx, &actor, &proposal).await?;
+
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(Json(ApproveResponse {
+        active_claim: active,
+        retired_proposal_id: retired.id,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/{id}/reject",
+    params(
+        ("id" = Uuid, Path, description = "Förslagets claim-id"),
+        ("If-Match" = String, Header, description = "ETag för claimet")
+    ),
+    request_body = RejectBody,
+    responses(
+        (status = 200, body = RejectResponse),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse),
+        (status = 428, response = crate::openapi::ErrorResponse)
+    )
+)]
+async fn reject_claim(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    headers: HeaderMap,
+    Path(claim_id): Path<Uuid>,
+    Json(body): Json<RejectBody>,
+) -> Result<Json<RejectResponse>, ApiError> {
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+
+    let proposal: NodeClaim = sqlx::query_as(
+        r#"
+        SELECT
+            id,
+            node_id,
+            source,
+            confidence,
+            status,
+            created_by,
+            created_at,
+            updated_at,
+            last_verified_at,
+            version,
+            import_batch_id,
+            proposed_changes
+        FROM node_claims
+        WHERE id = $1
+        "#,
+    )
+    .bind(claim_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    if proposal.status != "needs_review" && proposal.status != "rejected" {
+        return Err(ApiError::conflict("Endast claims med status needs_review eller rejected kan avslås"));
     }
+    let if_match = require_if_match(&headers)?;
+    check_if_match(proposal.version, proposal.updated_at, &if_match)?;
+
+    let retired = reject_node_proposal(&mut tx, ctx, &actor, &proposal, body.reason).await?;
 
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(Json(RejectResponse {
+        retired_claim: retired,
+    }))
+}
+
+/// Retires `proposal`, applies any changes it carries to the node and puts
+/// an active claim from the same import batch in its place. The caller
+/// checks status and If-Match.
+pub(crate) async fn approve_node_proposal(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    proposal: &NodeClaim,
+) -> Result<(NodeClaim, NodeClaim), ApiError> {
     let retired: NodeClaim = sqlx::query_as(
         r#"
         UPDATE node_claims
         SET status = 'deprecated',
             updated_at = now()
-        WHERE id = $1
+        WHERE id = $1 AND version = $2
         RETURNING
             id,
             node_id,
@@ -96,13 +206,18 @@ async fn approve_claim(
             created_by,
             created_at,
             updated_at,
-            last_verified_at
+            last_verified_at,
+            version,
+            import_batch_id,
+            proposed_changes
         "#,
     )
     .bind(proposal.id)
-    .fetch_one(&mut *tx)
+    .bind(proposal.version)
+    .fetch_optional(&mut **tx)
     .await
-    .map_err(map_sqlx_error)?;
+    .map_err(map_sqlx_error)?
+    .ok_or_else(ApiError::etag_mismatch)?;
 
     let active: NodeClaim = sqlx::query_as(
         r#"
@@ -112,9 +227,10 @@ async fn approve_claim(
             confidence,
             status,
             created_by,
-            last_verified_at
+            last_verified_at,
+            import_batch_id
         )
-        VALUES ($1, $2, $3, 'active', $4, now())
+        VALUES ($1, $2, $3, 'active', $4, now(), $5)
         RETURNING
             id,
             node_id,
@@ -124,86 +240,59 @@ async fn approve_claim(
             created_by,
             created_at,
             updated_at,
-            last_verified_at
+            last_verified_at,
+            version,
+            import_batch_id,
+            proposed_changes
         "#,
     )
     .bind(proposal.node_id)
     .bind(proposal.source.clone())
     .bind(proposal.confidence)
     .bind(actor.username.clone())
-    .fetch_one(&mut *tx)
+    .bind(proposal.import_batch_id)
+    .fetch_one(&mut **tx)
     .await
     .map_err(map_sqlx_error)?;
 
+    // Import proposals may carry metadata/details changes for the node.
+    let applied_changes = match &proposal.proposed_changes {
+        Some(changes) => apply_node_proposal(tx, proposal.node_id, changes).await?,
+        None => None,
+    };
+
     audit::write_audit(
-        &mut *tx,
-        ctx.clone(),
-        Some(&actor),
+        tx,
+        ctx,
+        Some(actor),
         EntityType::Node,
         proposal.node_id,
         AuditAction::Patch,
         None,
         Some(serde_json::json!({
             "action": "node_claim_approved",
+            "import_batch_id": proposal.import_batch_id,
             "proposal_id": proposal.id,
             "active_id": active.id,
+            "applied_changes": applied_changes,
         })),
         None,
     )
     .await
     .map_err(internal_error)?;
 
-    tx.commit().await.map_err(internal_error)?;
-
-    Ok(Json(ApproveResponse {
-        active_claim: active,
-        retired_proposal_id: retired.id,
-    }))
+    Ok((retired, active))
 }
 
-async fn reject_claim(
-    State(state): State<AppState>,
-    Extension(ctx): Extension<RequestContext>,
-    Extension(actor): Extension<AuthActor>,
-    headers: HeaderMap,
-    Path(claim_id): Path<Uuid>,
-    Json(body): Json<RejectBody>,
-) -> Result<Json<RejectResponse>, (StatusCode, String)> {
-    let mut tx = state.pool.begin().await.map_err(internal_error)?;
-
-    let proposal: NodeClaim = sqlx::query_as(
-        r#"
-        SELECT
-            id,
-            node_id,
-            source,
-            confidence,
-            status,
-            created_by,
-            created_at,
-            updated_at,
-            last_verified_at
-        FROM node_claims
-        WHERE id = $1
-        "#,
-    )
-    .bind(claim_id)
-    .fetch_one(&mut *tx)
-    .await
-    .map_err(map_sqlx_error)?;
-
-    if proposal.status != "needs_review" && proposal.status != "rejected" {
-        return Err((
-            StatusCode::CONFLICT,
-            "Endast claims med status needs_review eller rejected kan avslås".into(),
-        ));
-    }
-    let if_match = require_if_match(&headers)?;
-    let expected = etag_from_updated_at(proposal.updated_at);
-    if !is_match(&expected, &if_match) {
-        return Err((StatusCode::BAD_REQUEST, "Ogiltig If-Match".into()));
-    }
-
+/// Marks `proposal` rejected and reinstates the claim an update proposal
+/// set aside; a non-empty `reason` replaces its source.
+pub(crate) async fn reject_node_proposal(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    proposal: &NodeClaim,
+    reason: Option<String>,
+) -> Result<NodeClaim, ApiError> {
     let retired: NodeClaim = sqlx::query_as(
         r#"
         UPDATE node_claims
@@ -213,7 +302,7 @@ async fn reject_claim(
               ELSE $2::text
             END,
             updated_at = now()
-        WHERE id = $1
+        WHERE id = $1 AND version = $3
         RETURNING
             id,
             node_id,
@@ -223,36 +312,42 @@ async fn reject_claim(
             created_by,
             created_at,
             updated_at,
-            last_verified_at
+            last_verified_at,
+            version,
+            import_batch_id,
+            proposed_changes
         "#,
     )
     .bind(proposal.id)
-    .bind(body.reason.clone())
-    .fetch_one(&mut *tx)
+    .bind(reason.clone())
+    .bind(proposal.version)
+    .fetch_optional(&mut **tx)
     .await
-    .map_err(map_sqlx_error)?;
+    .map_err(map_sqlx_error)?
+    .ok_or_else(ApiError::etag_mismatch)?;
+
+    if let Some(changes) = &proposal.proposed_changes {
+        restore_previous_claim(tx, proposal.node_id, changes).await?;
+    }
 
     audit::write_audit(
-        &mut *tx,
-        ctx.clone(),
-        Some(&actor),
+        tx,
+        ctx,
+        Some(actor),
         EntityType::Node,
         proposal.node_id,
         AuditAction::Patch,
         None,
         Some(serde_json::json!({
             "action": "node_claim_rejected",
+            "import_batch_id": proposal.import_batch_id,
             "proposal_id": proposal.id,
-            "reason": body.reason,
+            "reason": reason,
         })),
         None,
     )
     .await
     .map_err(internal_error)?;
 
-    tx.commit().await.map_err(internal_error)?;
-
-    Ok(Json(RejectResponse {
-        retired_claim: retired,
-    }))
+    Ok(retired)
 }
@@ -0,0 +1,224 @@
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::{
+    audit::{self, AuditAction, EntityType, RequestContext},
+    auth::AuthActor,
+    routes::AppState,
+};
+
+use crate::routes::edges::helpers::{internal_error, map_sqlx_error};
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+pub struct NodeAlias {
+    pub alias: String,
+    pub created_by: String,
+    #[schema(value_type = crate::openapi::Timestamp)]
+    pub created_at: time::OffsetDateTime,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct PutNodeAliasesBody {
+    /// Hela listan; alias som inte finns med tas bort.
+    pub aliases: Vec<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}/aliases",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, body = Vec<NodeAlias>),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn list_node_aliases(
+    State(state): State<AppState>,
+    Path(node_id): Path<Uuid>,
+) -> Result<Json<Vec<NodeAlias>>, ApiError> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM nodes WHERE id = $1)")
+        .bind(node_id)
+        .fetch_one(&state.pool)
+        .await
+        .map_err(internal_error)?;
+    if !exists {
+        return Err(ApiError::not_found("Noden finns inte"));
+    }
+
+    Ok(Json(load_aliases(&state.pool, node_id).await?))
+}
+
+/// Replaces the node's aliases. An alias may not normalize to the node's
+/// own name, nor to the name or an alias of another node of the same kind.
+#[utoipa::path(
+    put,
+    path = "/{id}/aliases",
+    params(("id" = Uuid, Path)),
+    request_body = PutNodeAliasesBody,
+    responses(
+        (status = 200, body = Vec<NodeAlias>),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn put_node_aliases(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    Path(node_id): Path<Uuid>,
+    Json(body): Json<PutNodeAliasesBody>,
+) -> Result<Json<Vec<NodeAlias>>, ApiError> {
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+
+    let node: Option<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT kind, normalize_node_name(name)
+        FROM nodes
+        WHERE id = $1 AND deleted_at IS NULL
+        FOR UPDATE
+        "#,
+    )
+    .bind(node_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+    let Some((kind, own_name)) = node else {
+        return Err(ApiError::not_found("Noden finns inte"));
+    };
+
+    let mut aliases: Vec<String> = Vec::new();
+    let mut seen: Vec<String> = Vec::new();
+    for (i, alias) in body.aliases.iter().enumerate() {
+        let field = format!("aliases[{i}]");
+        let alias = alias.trim();
+        if alias.is_empty() {
+            return Err(ApiError::validation(field, "Alias får inte vara tomt"));
+        }
+        let normalized: String = sqlx::query_scalar("SELECT normalize_node_name($1)")
+            .bind(alias)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+        if normalized.is_empty() {
+            return Err(ApiError::validation(
+                field,
+                "Alias måste innehålla bokstäver eller siffror",
+            ));
+        }
+        if normalized == own_name {
+            return Err(ApiError::validation(
+                field,
+                "Aliaset är samma som nodens namn",
+            ));
+        }
+        if seen.contains(&normalized) {
+            return Err(ApiError::validation(field, "Aliaset finns redan i listan"));
+        }
+
+        let taken: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT n.name
+            FROM nodes n
+            WHERE n.kind = $1
+              AND n.id <> $2
+              AND n.deleted_at IS NULL
+              AND (
+                normalize_node_name(n.name) = $3
+                OR EXISTS (
+                    SELECT 1 FROM node_aliases a
+                    WHERE a.node_id = n.id AND normalize_node_name(a.alias) = $3
+                )
+              )
+            LIMIT 1
+            "#,
+        )
+        .bind(&kind)
+        .bind(node_id)
+        .bind(&normalized)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+        if let Some(other) = taken {
+            return Err(ApiError::conflict(format!(
+                "Aliaset '{alias}' används redan av noden '{other}'"
+            )));
+        }
+
+        seen.push(normalized);
+        aliases.push(alias.to_string());
+    }
+
+    let mut before: Vec<String> =
+        sqlx::query_scalar("SELECT alias FROM node_aliases WHERE node_id = $1")
+            .bind(node_id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+    before.sort();
+
+    sqlx::query("DELETE FROM node_aliases WHERE node_id = $1 AND alias <> ALL($2)")
+        .bind(node_id)
+        .bind(&aliases)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_sqlx_error)?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO node_aliases (node_id, alias, created_by)
+        SELECT $1, a, $3
+        FROM unnest($2::text[]) AS a
+        ON CONFLICT (node_id, alias) DO NOTHING
+        "#,
+    )
+    .bind(node_id)
+    .bind(&aliases)
+    .bind(actor.username.clone())
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    let mut after = aliases.clone();
+    after.sort();
+    if before != after {
+        audit::write_audit(
+            &mut tx,
+            ctx,
+            Some(&actor),
+            EntityType::Node,
+            node_id,
+            AuditAction::Patch,
+            Some(serde_json::json!({ "aliases": before })),
+            None,
+            Some(serde_json::json!({ "aliases": after })),
+        )
+        .await
+        .map_err(internal_error)?;
+    }
+
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(Json(load_aliases(&state.pool, node_id).await?))
+}
+
+async fn load_aliases(pool: &sqlx::PgPool, node_id: Uuid) -> Result<Vec<NodeAlias>, ApiError> {
+    sqlx::query_as(
+        r#"
+        SELECT alias, created_by, created_at
+        FROM node_aliases
+        WHERE node_id = $1
+        ORDER BY alias
+        "#,
+    )
+    .bind(node_id)
+    .fetch_all(pool)
+    .await
+    .map_err(internal_error)
+}
@@ -1,10 +1,10 @@
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
     Extension, Json,
 };
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::{
     audit::{self, AuditAction, EntityType, RequestContext},
     auth::AuthActor,
@@ -14,10 +14,19 @@ use crate::{
 
 use crate::routes::edges::helpers::{internal_error, map_sqlx_error};
 
+#[utoipa::path(
+    get,
+    path = "/{id}/claims",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, body = Vec<NodeClaim>),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn list_node_claims(
     State(state): State<AppState>,
     Path(node_id): Path<Uuid>,
-) -> Result<Json<Vec<NodeClaim>>, (StatusCode, String)> {
+) -> Result<Json<Vec<NodeClaim>>, ApiError> {
     let rows: Vec<NodeClaim> = sqlx::query_as(
         r#"
         SELECT
@@ -29,7 +38,10 @@ pub async fn list_node_claims(
             created_by,
             created_at,
             updated_at,
-            last_verified_at
+            last_verified_at,
+            version,
+            import_batch_id,
+            proposed_changes
         FROM node_claims
         WHERE node_id = $1
         ORDER BY created_at DESC
@@ -43,13 +55,24 @@ pub async fn list_node_claims(
     Ok(Json(rows))
 }
 
+#[utoipa::path(
+    post,
+    path = "/{id}/claims",
+    params(("id" = Uuid, Path)),
+    request_body = NewNodeClaim,
+    responses(
+        (status = 200, body = NodeClaim),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn create_node_claim(
     State(state): State<AppState>,
     Extension(ctx): Extension<RequestContext>,
     Extension(actor): Extension<AuthActor>,
     Path(node_id): Path<Uuid>,
     Json(body): Json<NewNodeClaim>,
-) -> Result<Json<NodeClaim>, (StatusCode, String)> {
+) -> Result<Json<NodeClaim>, ApiError> {
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
 
     let claim: NodeClaim = sqlx::query_as(
@@ -71,7 +94,10 @@ pub async fn create_node_claim(
             created_by,
             created_at,
             updated_at,
-            last_verified_at
+            last_verified_at,
+            version,
+            import_batch_id,
+            proposed_changes
         "#,
     )
     .bind(node_id)
@@ -84,7 +110,7 @@ pub async fn create_node_claim(
     .map_err(map_sqlx_error)?;
 
     audit::write_audit(
-        &mut *tx,
+        &mut tx,
         ctx.clone(),
         Some(&actor),
         EntityType::Node,
@@ -4,39 +4,53 @@ use axum::{
     Json,
 };
 use serde::Deserialize;
-use time::format_description::well_known::Rfc3339;
-use time::OffsetDateTime;
+use sqlx::{Postgres, Transaction};
+use utoipa::IntoParams;
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::{
     audit::{self, AuditAction, EntityType, RequestContext},
     auth::AuthActor,
     models::{MergePatch, NewNode, Node, UpdateNode},
-    routes::{etag_from_updated_at, is_match, patch::merge_patch, require_if_match, AppState},
+    routes::{
+        check_if_match, etag_from_version, patch::merge_patch, require_if_match, AppState,
+    },
 };
 
 use crate::routes::edges::helpers::{internal_error, map_sqlx_error};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct NodesQuery {
     #[serde(default)]
     pub include_deleted: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct NodeQuery {
     #[serde(default)]
     pub include_deleted: bool,
 }
 
+#[utoipa::path(
+    get,
+    path = "/",
+    params(NodesQuery),
+    responses(
+        (status = 200, body = Vec<Node>),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn list_nodes(
     State(state): State<AppState>,
     Query(q): Query<NodesQuery>,
-) -> Result<Json<Vec<Node>>, (StatusCode, String)> {
+) -> Result<Json<Vec<Node>>, ApiError> {
     let nodes: Vec<Node> = if q.include_deleted {
         sqlx::query_as(
             r#"
-            SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+            SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
             FROM nodes
             ORDER BY created_at DESC
             "#,
@@ -47,7 +61,7 @@ pub async fn list_nodes(
     } else {
         sqlx::query_as(
             r#"
-            SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+            SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
             FROM nodes
             WHERE deleted_at IS NULL
             ORDER BY created_at DESC
@@ -61,15 +75,24 @@ pub async fn list_nodes(
     Ok(Json(nodes))
 }
 
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    params(("id" = Uuid, Path), NodeQuery),
+    responses(
+        (status = 200, body = Node, headers(("ETag" = String))),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn get_node(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
     Query(q): Query<NodeQuery>,
-) -> Result<(HeaderMap, Json<Node>), (StatusCode, String)> {
+) -> Result<(HeaderMap, Json<Node>), ApiError> {
     let node: Option<Node> = if q.include_deleted {
         sqlx::query_as(
             r#"
-            SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+            SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
             FROM nodes
             WHERE id = $1
             "#,
@@ -81,7 +104,7 @@ pub async fn get_node(
     } else {
         sqlx::query_as(
             r#"
-            SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+            SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
             FROM nodes
             WHERE id = $1 AND deleted_at IS NULL
             "#,
@@ -92,49 +115,78 @@ pub async fn get_node(
         .map_err(internal_error)?
     };
 
-    let node = node.ok_or((StatusCode::NOT_FOUND, "Objektet finns inte längre".into()))?;
+    let node = node.ok_or_else(|| ApiError::not_found("Objektet finns inte längre"))?;
 
     let mut headers = HeaderMap::new();
     headers.insert(
         axum::http::header::ETAG,
-        etag_from_updated_at(node.updated_at),
+        etag_from_version(node.version),
     );
 
     Ok((headers, Json(node)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/",
+    request_body = NewNode,
+    responses(
+        (status = 201, body = Node, headers(("ETag" = String))),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn create_node(
     State(state): State<AppState>,
     axum::Extension(ctx): axum::Extension<RequestContext>,
     axum::Extension(actor): axum::Extension<AuthActor>,
     Json(payload): Json<NewNode>,
-) -> Result<(StatusCode, HeaderMap, Json<Node>), (StatusCode, String)> {
+) -> Result<(StatusCode, HeaderMap, Json<Node>), ApiError> {
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let node = insert_node(&mut tx, ctx, &actor, payload).await?;
+    tx.commit().await.map_err(internal_error)?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        axum::http::header::ETAG,
+        etag_from_version(node.version),
+    );
+
+    Ok((StatusCode::CREATED, headers, Json(node)))
+}
+
+/// Insert + audit inside the caller's transaction; shared with `/api/batch`.
+pub(crate) async fn insert_node(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    payload: NewNode,
+) -> Result<Node, ApiError> {
     if payload.name.trim().is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "Namn får inte vara tomt".into()));
+        return Err(ApiError::validation("name", "Namn får inte vara tomt"));
     }
 
     let id = Uuid::new_v4();
-    let mut tx = state.pool.begin().await.map_err(internal_error)?;
 
     let node = sqlx::query_as::<_, Node>(
         r#"
         INSERT INTO nodes (id, kind, name, metadata)
         VALUES ($1, $2, $3, $4)
-        RETURNING id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+        RETURNING id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
         "#,
     )
     .bind(id)
     .bind(payload.kind.as_str())
     .bind(payload.name)
     .bind(payload.metadata)
-    .fetch_one(&mut *tx)
+    .fetch_one(&mut **tx)
     .await
     .map_err(map_sqlx_error)?;
 
     audit::write_audit(
-        &mut *tx,
+        tx,
         ctx,
-        Some(&actor),
+        Some(actor),
         EntityType::Node,
         node.id,
         AuditAction::Create,
@@ -145,17 +197,22 @@ pub async fn create_node(
     .await
     .map_err(internal_error)?;
 
-    tx.commit().await.map_err(internal_error)?;
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        axum::http::header::ETAG,
-        etag_from_updated_at(node.updated_at),
-    );
-
-    Ok((StatusCode::CREATED, headers, Json(node)))
+    Ok(node)
 }
 
+#[utoipa::path(
+    put,
+    path = "/{id}",
+    params(("id" = Uuid, Path), ("If-Match" = String, Header, description = "ETag för noden")),
+    request_body = UpdateNode,
+    responses(
+        (status = 200, body = Node, headers(("ETag" = String))),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse),
+        (status = 428, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn update_node(
     State(state): State<AppState>,
     axum::Extension(ctx): axum::Extension<RequestContext>,
@@ -163,33 +220,44 @@ pub async fn update_node(
     headers: HeaderMap,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateNode>,
-) -> Result<(HeaderMap, Json<Node>), (StatusCode, String)> {
+) -> Result<(HeaderMap, Json<Node>), ApiError> {
     let if_match = require_if_match(&headers)?;
-    let expected_updated_at = OffsetDateTime::parse(&if_match, &Rfc3339)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Ogiltig If-Match".into()))?;
 
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let updated = apply_node_update(&mut tx, ctx, &actor, id, &if_match, payload).await?;
+    tx.commit().await.map_err(internal_error)?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        axum::http::header::ETAG,
+        etag_from_version(updated.version),
+    );
+
+    Ok((headers, Json(updated)))
+}
 
+pub(crate) async fn apply_node_update(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    id: Uuid,
+    if_match: &str,
+    payload: UpdateNode,
+) -> Result<Node, ApiError> {
     let before = sqlx::query_as::<_, Node>(
         r#"
-        SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+        SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
         FROM nodes
         WHERE id = $1 AND deleted_at IS NULL
         "#,
     )
     .bind(id)
-    .fetch_optional(&mut *tx)
+    .fetch_optional(&mut **tx)
     .await
     .map_err(internal_error)?
-    .ok_or((StatusCode::NOT_FOUND, "Objektet finns inte längre".into()))?;
-
-    let current_etag = etag_from_updated_at(before.updated_at);
-    if !is_match(&current_etag, &if_match) {
-        return Err((
-            StatusCode::CONFLICT,
-            "Objektet har uppdaterats av någon annan".into(),
-        ));
-    }
+    .ok_or_else(|| ApiError::not_found("Objektet finns inte längre"))?;
+
+    check_if_match(before.version, before.updated_at, if_match)?;
 
     let kind_str = payload.kind.map(|k| k.as_str());
 
@@ -201,27 +269,24 @@ pub async fn update_node(
           name = COALESCE($3, name),
           metadata = COALESCE($4, metadata),
           updated_at = now()
-        WHERE id = $1 AND updated_at = $5 AND deleted_at IS NULL
-        RETURNING id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+        WHERE id = $1 AND version = $5 AND deleted_at IS NULL
+        RETURNING id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
         "#,
     )
     .bind(id)
     .bind(kind_str)
     .bind(payload.name.clone())
     .bind(payload.metadata.clone())
-    .bind(expected_updated_at)
-    .fetch_optional(&mut *tx)
+    .bind(before.version)
+    .fetch_optional(&mut **tx)
     .await
     .map_err(map_sqlx_error)?
-    .ok_or((
-        StatusCode::CONFLICT,
-        "Objektet har uppdaterats av någon annan".into(),
-    ))?;
+    .ok_or_else(ApiError::etag_mismatch)?;
 
     audit::write_audit(
-        &mut *tx,
+        tx,
         ctx,
-        Some(&actor),
+        Some(actor),
         EntityType::Node,
         id,
         AuditAction::Patch,
@@ -232,17 +297,22 @@ pub async fn update_node(
     .await
     .map_err(internal_error)?;
 
-    tx.commit().await.map_err(internal_error)?;
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        axum::http::header::ETAG,
-        etag_from_updated_at(updated.updated_at),
-    );
-
-    Ok((headers, Json(updated)))
+    Ok(updated)
 }
 
+#[utoipa::path(
+    patch,
+    path = "/{id}/metadata",
+    params(("id" = Uuid, Path), ("If-Match" = String, Header, description = "ETag för noden")),
+    request_body(content = MergePatch, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, body = Node, headers(("ETag" = String))),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse),
+        (status = 428, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn patch_node_metadata(
     State(state): State<AppState>,
     axum::Extension(ctx): axum::Extension<RequestContext>,
@@ -250,16 +320,13 @@ pub async fn patch_node_metadata(
     headers: HeaderMap,
     Path(id): Path<Uuid>,
     Json(MergePatch(patch)): Json<MergePatch>,
-) -> Result<(HeaderMap, Json<Node>), (StatusCode, String)> {
+) -> Result<(HeaderMap, Json<Node>), ApiError> {
     let if_match = require_if_match(&headers)?;
-    let expected_updated_at = OffsetDateTime::parse(&if_match, &Rfc3339)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Ogiltig If-Match".into()))?;
-
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
 
     let mut before = sqlx::query_as::<_, Node>(
         r#"
-        SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+        SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
         FROM nodes
         WHERE id = $1 AND deleted_at IS NULL
         "#,
@@ -268,15 +335,9 @@ pub async fn patch_node_metadata(
     .fetch_optional(&mut *tx)
     .await
     .map_err(internal_error)?
-    .ok_or((StatusCode::NOT_FOUND, "Objektet finns inte längre".into()))?;
-
-    let current_etag = etag_from_updated_at(before.updated_at);
-    if !is_match(&current_etag, &if_match) {
-        return Err((
-            StatusCode::CONFLICT,
-            "Objektet har uppdaterats av någon annan".into(),
-        ));
-    }
+    .ok_or_else(|| ApiError::not_found("Objektet finns inte längre"))?;
+
+    check_if_match(before.version, before.updated_at, &if_match)?;
 
     let before_metadata = before.metadata.clone();
     merge_patch(&mut before.metadata, &patch);
@@ -285,23 +346,20 @@ pub async fn patch_node_metadata(
         r#"
         UPDATE nodes
         SET metadata = $2, updated_at = now()
-        WHERE id = $1 AND updated_at = $3 AND deleted_at IS NULL
-        RETURNING id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+        WHERE id = $1 AND version = $3 AND deleted_at IS NULL
+        RETURNING id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
         "#,
     )
     .bind(id)
     .bind(before.metadata.clone())
-    .bind(expected_updated_at)
+    .bind(before.version)
     .fetch_optional(&mut *tx)
     .await
     .map_err(internal_error)?
-    .ok_or((
-        StatusCode::CONFLICT,
-        "Objektet har uppdaterats av någon annan".into(),
-    ))?;
+    .ok_or_else(ApiError::etag_mismatch)?;
 
     audit::write_audit(
-        &mut *tx,
+        &mut tx,
         ctx,
         Some(&actor),
         EntityType::Node,
@@ -319,45 +377,60 @@ pub async fn patch_node_metadata(
     let mut headers = HeaderMap::new();
     headers.insert(
         axum::http::header::ETAG,
-        etag_from_updated_at(updated.updated_at),
+        etag_from_version(updated.version),
     );
 
     Ok((headers, Json(updated)))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    params(("id" = Uuid, Path), ("If-Match" = String, Header, description = "ETag för noden")),
+    responses(
+        (status = 204),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse),
+        (status = 428, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn delete_node(
     State(state): State<AppState>,
     axum::Extension(ctx): axum::Extension<RequestContext>,
     axum::Extension(actor): axum::Extension<AuthActor>,
     headers: HeaderMap,
     Path(id): Path<Uuid>,
-) -> Result<StatusCode, (StatusCode, String)> {
+) -> Result<StatusCode, ApiError> {
     let if_match = require_if_match(&headers)?;
-    let expected_updated_at = OffsetDateTime::parse(&if_match, &Rfc3339)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Ogiltig If-Match".into()))?;
 
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    soft_delete_node(&mut tx, ctx, &actor, id, &if_match).await?;
+    tx.commit().await.map_err(internal_error)?;
 
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(crate) async fn soft_delete_node(
+    tx: &mut Transaction<'_, Postgres>,
+    ctx: RequestContext,
+    actor: &AuthActor,
+    id: Uuid,
+    if_match: &str,
+) -> Result<Node, ApiError> {
     let before = sqlx::query_as::<_, Node>(
         r#"
-        SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+        SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
         FROM nodes
         WHERE id = $1 AND deleted_at IS NULL
         "#,
     )
     .bind(id)
-    .fetch_optional(&mut *tx)
+    .fetch_optional(&mut **tx)
     .await
     .map_err(internal_error)?
-    .ok_or((StatusCode::NOT_FOUND, "Objektet finns inte längre".into()))?;
-
-    let current_etag = etag_from_updated_at(before.updated_at);
-    if !is_match(&current_etag, &if_match) {
-        return Err((
-            StatusCode::CONFLICT,
-            "Objektet har uppdaterats av någon annan".into(),
-        ));
-    }
+    .ok_or_else(|| ApiError::not_found("Objektet finns inte längre"))?;
+
+    check_if_match(before.version, before.updated_at, if_match)?;
 
     let deleted = sqlx::query_as::<_, Node>(
         r#"
@@ -365,25 +438,22 @@ pub async fn delete_node(
         SET deleted_at = now(),
             deleted_by = $3,
             updated_at = now()
-        WHERE id = $1 AND updated_at = $2 AND deleted_at IS NULL
-        RETURNING id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+        WHERE id = $1 AND version = $2 AND deleted_at IS NULL
+        RETURNING id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
         "#,
     )
     .bind(id)
-    .bind(expected_updated_at)
+    .bind(before.version)
     .bind(actor.username.clone())
-    .fetch_optional(&mut *tx)
+    .fetch_optional(&mut **tx)
     .await
     .map_err(map_sqlx_error)?
-    .ok_or((
-        StatusCode::CONFLICT,
-        "Objektet har uppdaterats av någon annan".into(),
-    ))?;
+    .ok_or_else(ApiError::etag_mismatch)?;
 
     audit::write_audit(
-        &mut *tx,
+        tx,
         ctx,
-        Some(&actor),
+        Some(actor),
         EntityType::Node,
         id,
         AuditAction::Delete,
@@ -394,27 +464,33 @@ pub async fn delete_node(
     .await
     .map_err(internal_error)?;
 
-    tx.commit().await.map_err(internal_error)?;
-
-    Ok(StatusCode::NO_CONTENT)
+    Ok(deleted)
 }
 
+#[utoipa::path(
+    post,
+    path = "/{id}/restore",
+    params(("id" = Uuid, Path), ("If-Match" = String, Header, description = "ETag för noden")),
+    responses(
+        (status = 200, body = Node, headers(("ETag" = String))),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse),
+        (status = 428, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn restore_node(
     State(state): State<AppState>,
     axum::Extension(ctx): axum::Extension<RequestContext>,
     axum::Extension(actor): axum::Extension<AuthActor>,
     headers: HeaderMap,
     Path(id): Path<Uuid>,
-) -> Result<(HeaderMap, Json<Node>), (StatusCode, String)> {
+) -> Result<(HeaderMap, Json<Node>), ApiError> {
     let if_match = require_if_match(&headers)?;
-    let expected_updated_at = OffsetDateTime::parse(&if_match, &Rfc3339)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Ogiltig If-Match".into()))?;
-
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
 
     let before = sqlx::query_as::<_, Node>(
         r#"
-        SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+        SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
         FROM nodes
         WHERE id = $1 AND deleted_at IS NOT NULL
         "#,
@@ -423,15 +499,9 @@ pub async fn restore_node(
     .fetch_optional(&mut *tx)
     .await
     .map_err(internal_error)?
-    .ok_or((StatusCode::NOT_FOUND, "Objektet är inte raderat".into()))?;
-
-    let current_etag = etag_from_updated_at(before.updated_at);
-    if !is_match(&current_etag, &if_match) {
-        return Err((
-            StatusCode::CONFLICT,
-            "Objektet har uppdaterats av någon annan".into(),
-        ));
-    }
+    .ok_or_else(|| ApiError::not_found("Objektet är inte raderat"))?;
+
+    check_if_match(before.version, before.updated_at, &if_match)?;
 
     let restored = sqlx::query_as::<_, Node>(
         r#"
@@ -439,22 +509,19 @@ pub async fn restore_node(
         SET deleted_at = NULL,
             deleted_by = NULL,
             updated_at = now()
-        WHERE id = $1 AND updated_at = $2 AND deleted_at IS NOT NULL
-        RETURNING id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+        WHERE id = $1 AND version = $2 AND deleted_at IS NOT NULL
+        RETURNING id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
         "#,
     )
     .bind(id)
-    .bind(expected_updated_at)
+    .bind(before.version)
     .fetch_optional(&mut *tx)
     .await
     .map_err(map_sqlx_error)?
-    .ok_or((
-        StatusCode::CONFLICT,
-        "Objektet har uppdaterats av någon annan".into(),
-    ))?;
+    .ok_or_else(ApiError::etag_mismatch)?;
 
     audit::write_audit(
-        &mut *tx,
+        &mut tx,
         ctx,
         Some(&actor),
         EntityType::Node,
@@ -472,23 +539,32 @@ pub async fn restore_node(
     let mut headers = HeaderMap::new();
     headers.insert(
         axum::http::header::ETAG,
-        etag_from_updated_at(restored.updated_at),
+        etag_from_version(restored.version),
     );
 
     Ok((headers, Json(restored)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/{id}/duplicate",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 201, body = Node, headers(("ETag" = String))),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn duplicate_node(
     State(state): State<AppState>,
     axum::Extension(ctx): axum::Extension<RequestContext>,
     axum::Extension(actor): axum::Extension<AuthActor>,
     Path(id): Path<Uuid>,
-) -> Result<(StatusCode, HeaderMap, Json<Node>), (StatusCode, String)> {
+) -> Result<(StatusCode, HeaderMap, Json<Node>), ApiError> {
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
 
     let original = sqlx::query_as::<_, Node>(
         r#"
-        SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+        SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
         FROM nodes
         WHERE id = $1 AND deleted_at IS NULL
         "#,
@@ -497,7 +573,7 @@ pub async fn duplicate_node(
     .fetch_optional(&mut *tx)
     .await
     .map_err(internal_error)?
-    .ok_or((StatusCode::NOT_FOUND, "Objektet finns inte längre".into()))?;
+    .ok_or_else(|| ApiError::not_found("Objektet finns inte längre"))?;
 
     let new_id = Uuid::new_v4();
     let new_name = format!("{} (kopia)", original.name);
@@ -506,7 +582,7 @@ pub async fn duplicate_node(
         r#"
         INSERT INTO nodes (id, kind, name, metadata)
         VALUES ($1, $2, $3, $4)
-        RETURNING id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+        RETURNING id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
         "#,
     )
     .bind(new_id)
@@ -518,7 +594,7 @@ pub async fn duplicate_node(
     .map_err(map_sqlx_error)?;
 
     audit::write_audit(
-        &mut *tx,
+        &mut tx,
         ctx,
         Some(&actor),
         EntityType::Node,
@@ -536,7 +612,7 @@ pub async fn duplicate_node(
     let mut headers = HeaderMap::new();
     headers.insert(
         axum::http::header::ETAG,
-        etag_from_updated_at(new_node.updated_at),
+        etag_from_version(new_node.version),
     );
 
     Ok((StatusCode::CREATED, headers, Json(new_node)))
@@ -3,11 +3,11 @@ use axum::{
     http::{HeaderMap, StatusCode},
     Json,
 };
-use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
-use crate::routes::{etag_from_updated_at, is_match, require_if_match, AppState};
+use crate::error::{ApiError, ErrorCode};
+use crate::routes::{check_if_match, etag_from_version, require_if_match, AppState};
 
 use super::types::*;
 use super::util::{
@@ -15,10 +15,19 @@ use super::util::{
     is_valid_supplier_type, trim_opt,
 };
 
+#[utoipa::path(
+    get,
+    path = "/{id}/details",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, body = NodeDetailsResponse, headers(("ETag" = String))),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn get_node_details(
     State(state): State<AppState>,
     Path(node_id): Path<Uuid>,
-) -> Result<(HeaderMap, Json<NodeDetailsResponse>), (StatusCode, String)> {
+) -> Result<(HeaderMap, Json<NodeDetailsResponse>), ApiError> {
     let node = sqlx::query_as::<_, NodeCore>(
         r#"
         SELECT
@@ -28,7 +37,8 @@ pub async fn get_node_details(
           metadata,
           owning_department::text AS owning_department,
           created_at,
-          updated_at
+          updated_at,
+          version
         FROM nodes
         WHERE id = $1 AND deleted_at IS NULL
         "#,
@@ -37,7 +47,7 @@ pub async fn get_node_details(
     .fetch_optional(&state.pool)
     .await
     .map_err(internal_error)?
-    .ok_or((StatusCode::NOT_FOUND, "Noden finns inte".into()))?;
+    .ok_or_else(|| ApiError::not_found("Noden finns inte"))?;
 
     let suppliers = sqlx::query_as::<_, Party>(
         r#"
@@ -114,7 +124,7 @@ pub async fn get_node_details(
     .map_err(internal_error)?;
 
     let mut headers = HeaderMap::new();
-    let etag = etag_from_updated_at(node.updated_at);
+    let etag = etag_from_version(node.version);
     headers.insert("etag", etag);
 
     Ok((
@@ -130,17 +140,31 @@ pub async fn get_node_details(
     ))
 }
 
+#[utoipa::path(
+    put,
+    path = "/{id}/details",
+    params(("id" = Uuid, Path), ("If-Match" = String, Header, description = "ETag för noden")),
+    request_body = PutNodeDetailsRequest,
+    responses(
+        (status = 200, body = NodeDetailsResponse, headers(("ETag" = String))),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse),
+        (status = 412, response = crate::openapi::ErrorResponse),
+        (status = 428, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn put_node_details(
     State(state): State<AppState>,
     Path(node_id): Path<Uuid>,
     headers: HeaderMap,
     Json(payload): Json<PutNodeDetailsRequest>,
-) -> Result<(HeaderMap, Json<NodeDetailsResponse>), (StatusCode, String)> {
+) -> Result<(HeaderMap, Json<NodeDetailsResponse>), ApiError> {
     let if_match = require_if_match(&headers)?;
 
-    let node_updated_at: OffsetDateTime = sqlx::query_scalar(
+    let (node_version, node_updated_at): (i64, OffsetDateTime) = sqlx::query_as(
         r#"
-        SELECT updated_at
+        SELECT version, updated_at
         FROM nodes
         WHERE id = $1 AND deleted_at IS NULL
         "#,
@@ -149,19 +173,23 @@ pub async fn put_node_details(
     .fetch_optional(&state.pool)
     .await
     .map_err(internal_error)?
-    .ok_or((StatusCode::NOT_FOUND, "Noden finns inte".into()))?;
-
-    let current_etag = etag_from_updated_at(node_updated_at);
-    if !is_match(&current_etag, &if_match) {
-        return Err((
-            StatusCode::PRECONDITION_FAILED,
-            "Objektet har ändrats. Ladda om och försök igen.".into(),
-        ));
-    }
+    .ok_or_else(|| ApiError::not_found("Noden finns inte"))?;
+
+    check_if_match(node_version, node_updated_at, &if_match).map_err(|e| {
+        if e.code == ErrorCode::EtagMismatch {
+            ApiError::new(
+                StatusCode::PRECONDITION_FAILED,
+                ErrorCode::EtagMismatch,
+                "Objektet har ändrats. Ladda om och försök igen.",
+            )
+        } else {
+            e
+        }
+    })?;
 
-    let if_match_clean = if_match.trim().trim_matches('"');
-    let expected_updated_at =
-        OffsetDateTime::parse(if_match_clean, &Rfc3339).unwrap_or(node_updated_at);
+    // Each guarded UPDATE below bumps `version` by one (trigger), so the
+    // next one expects the bumped value.
+    let mut expected_version = node_version;
 
     let mut touched = false;
 
@@ -176,12 +204,12 @@ pub async fn put_node_details(
                 r#"
                 UPDATE nodes
                 SET owning_department = $2::owning_department
-                WHERE id = $1 AND updated_at = $3
+                WHERE id = $1 AND version = $3
                 "#,
             )
             .bind(node_id)
             .bind(dept)
-            .bind(expected_updated_at)
+            .bind(expected_version)
             .execute(&mut *tx)
             .await
             .map_err(internal_error)?
@@ -190,23 +218,25 @@ pub async fn put_node_details(
                 r#"
                 UPDATE nodes
                 SET owning_department = NULL
-                WHERE id = $1 AND updated_at = $2
+                WHERE id = $1 AND version = $2
                 "#,
             )
             .bind(node_id)
-            .bind(expected_updated_at)
+            .bind(expected_version)
             .execute(&mut *tx)
             .await
             .map_err(internal_error)?
         };
 
         if res.rows_affected() == 0 {
-            return Err((
+            return Err(ApiError::new(
                 StatusCode::CONFLICT,
-                "Objektet har uppdaterats av någon annan. Ladda om och försök igen.".into(),
+                ErrorCode::EtagMismatch,
+                "Objektet har uppdaterats av någon annan. Ladda om och försök igen.",
             ));
         }
 
+        expected_version += 1;
         touched = true;
     }
 
@@ -237,23 +267,25 @@ pub async fn put_node_details(
                 r#"
                 UPDATE nodes
                 SET metadata = COALESCE(metadata, '{}'::jsonb) || $2::jsonb
-                WHERE id = $1 AND updated_at = $3
+                WHERE id = $1 AND version = $3
                 "#,
             )
             .bind(node_id)
             .bind(patch)
-            .bind(expected_updated_at)
+            .bind(expected_version)
             .execute(&mut *tx)
             .await
             .map_err(internal_error)?;
 
             if res.rows_affected() == 0 {
-                return Err((
-                    StatusCode::PRECONDITION_FAILED,
-                    "Objektet har ändrats. Ladda om och försök igen.".into(),
-                ));
+                return Err(ApiError::new(
+            StatusCode::PRECONDITION_FAILED,
+            ErrorCode::EtagMismatch,
+            "Objektet har ändrats. Ladda om och försök igen.",
+        ));
             }
 
+            expected_version += 1;
             touched = true;
         }
     }
@@ -261,10 +293,7 @@ pub async fn put_node_details(
     if let Some(types) = payload.supplier_types {
         for t in &types {
             if !is_valid_supplier_type(t) {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    "Ogiltig supplier_type (måste vara intern, saas eller paas)".into(),
-                ));
+                return Err(ApiError::bad_request("Ogiltig supplier_type (måste vara intern, saas eller paas)"));
             }
         }
 
@@ -426,36 +455,23 @@ pub async fn put_node_details(
     if let Some(risk) = payload.risk {
         if let Some(score) = risk.criticality_score {
             if score < 0.0 || score > 5.0 {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    "criticality_score måste vara mellan 0 och 5".into(),
-                ));
+                return Err(ApiError::bad_request("criticality_score måste vara mellan 0 och 5"));
             }
             let step = (score * 2.0).round() / 2.0;
             if (step - score).abs() > 1e-9 {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    "criticality_score måste vara i 0.5-steg (0, 0.5, 1.0 ... 5.0)".into(),
-                ));
+                return Err(ApiError::bad_request("criticality_score måste vara i 0.5-steg (0, 0.5, 1.0 ... 5.0)"));
             }
         }
 
         if let Some(ref bc) = risk.business_criticality {
             if !is_valid_business_criticality(bc) {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    "Ogiltig business_criticality (low, medium, high)".into(),
-                ));
+                return Err(ApiError::bad_request("Ogiltig business_criticality (low, medium, high)"));
             }
         }
 
         if let Some(ref ic) = risk.information_class {
             if !is_valid_information_class(ic) {
-                return Err((
-                    StatusCode::BAD_REQUEST,
-                    "Ogiltig information_class (intern, begransad, skyddad, oppen, konfidentiell)"
-                        .into(),
-                ));
+                return Err(ApiError::bad_request("Ogiltig information_class (intern, begransad, skyddad, oppen, konfidentiell)"));
             }
         }
 
@@ -502,19 +518,20 @@ pub async fn put_node_details(
             r#"
             UPDATE nodes
             SET updated_at = now()
-            WHERE id = $1 AND updated_at = $2
+            WHERE id = $1 AND version = $2
             "#,
         )
         .bind(node_id)
-        .bind(expected_updated_at)
+        .bind(expected_version)
         .execute(&mut *tx)
         .await
         .map_err(internal_error)?;
 
         if res.rows_affected() == 0 {
-            return Err((
+            return Err(ApiError::new(
                 StatusCode::CONFLICT,
-                "Objektet har uppdaterats av någon annan. Ladda om och försök igen.".into(),
+                ErrorCode::EtagMismatch,
+                "Objektet har uppdaterats av någon annan. Ladda om och försök igen.",
             ));
         }
     }
@@ -1,18 +1,27 @@
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
     Json,
 };
 
+use crate::error::ApiError;
 use crate::routes::AppState;
 
 use super::types::{LookupItem, LookupQuery};
 use super::util::internal_error;
 
+#[utoipa::path(
+    get,
+    path = "/lookups/suppliers",
+    params(LookupQuery),
+    responses(
+        (status = 200, body = Vec<LookupItem>),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn lookup_suppliers(
     State(state): State<AppState>,
     Query(q): Query<LookupQuery>,
-) -> Result<Json<Vec<LookupItem>>, (StatusCode, String)> {
+) -> Result<Json<Vec<LookupItem>>, ApiError> {
     let q_str = q.q.unwrap_or_default().trim().to_string();
     let limit = q.limit.unwrap_or(20).clamp(1, 50);
 
@@ -50,10 +59,19 @@ pub async fn lookup_suppliers(
     Ok(Json(rows))
 }
 
+#[utoipa::path(
+    get,
+    path = "/lookups/owners",
+    params(LookupQuery),
+    responses(
+        (status = 200, body = Vec<LookupItem>),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn lookup_owners(
     State(state): State<AppState>,
     Query(q): Query<LookupQuery>,
-) -> Result<Json<Vec<LookupItem>>, (StatusCode, String)> {
+) -> Result<Json<Vec<LookupItem>>, ApiError> {
     let q_str = q.q.unwrap_or_default().trim().to_string();
     let limit = q.limit.unwrap_or(20).clamp(1, 50);
 
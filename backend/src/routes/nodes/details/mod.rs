@@ -1,9 +1,28 @@
+use utoipa::OpenApi;
+
 mod get_put;
 mod lookups;
+mod patch;
 mod types;
 mod util;
 
 pub use get_put::{get_node_details, put_node_details};
 pub use lookups::{lookup_owners, lookup_suppliers};
+pub(crate) use patch::{apply_details_patch, diff_details_patch};
+pub use patch::NodeDetailsPatch;
 #[allow(unused_imports)]
 pub use types::*;
+pub(crate) use util::{
+    is_valid_business_criticality, is_valid_information_class, is_valid_supplier_type,
+};
+
+/// Nested next to `NodesApi` under `/api/nodes`; the handler modules are
+/// private to `details`.
+#[derive(OpenApi)]
+#[openapi(paths(
+    lookups::lookup_suppliers,
+    lookups::lookup_owners,
+    get_put::get_node_details,
+    get_put::put_node_details,
+))]
+pub struct DetailsApi;
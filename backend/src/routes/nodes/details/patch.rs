@@ -0,0 +1,397 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::routes::edges::helpers::map_sqlx_error;
+
+use super::util::{
+    is_valid_business_criticality, is_valid_information_class, is_valid_supplier_type, trim_opt,
+};
+
+/// Partial node details as proposed by imports. Unlike
+/// `PutNodeDetailsRequest`, only the fields that are set are written;
+/// lists replace the current set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct NodeDetailsPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owning_department: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supplier_types: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suppliers: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owners: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub software_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legal_requirements: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub financial_value: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pii: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub business_criticality: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub information_class: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub criticality_score: Option<f64>,
+}
+
+impl NodeDetailsPatch {
+    pub fn is_empty(&self) -> bool {
+        *self == NodeDetailsPatch::default()
+    }
+
+    /// Same rules as `PUT /nodes/:id/details`; returns the offending field.
+    /// `owning_department` is left to the enum cast.
+    pub fn validate(&self) -> Result<(), (&'static str, String)> {
+        for t in self.supplier_types.iter().flatten() {
+            if !is_valid_supplier_type(t) {
+                return Err((
+                    "supplier_types",
+                    format!("Ogiltig supplier_type '{t}' (intern, saas, paas)"),
+                ));
+            }
+        }
+        if let Some(bc) = &self.business_criticality {
+            if !is_valid_business_criticality(bc) {
+                return Err((
+                    "business_criticality",
+                    format!("Ogiltig business_criticality '{bc}' (low, medium, high)"),
+                ));
+            }
+        }
+        if let Some(ic) = &self.information_class {
+            if !is_valid_information_class(ic) {
+                return Err((
+                    "information_class",
+                    format!(
+                        "Ogiltig information_class '{ic}' (intern, begransad, skyddad, oppen, konfidentiell)"
+                    ),
+                ));
+            }
+        }
+        if let Some(score) = self.criticality_score {
+            if !(0.0..=5.0).contains(&score) || ((score * 2.0).round() / 2.0 - score).abs() > 1e-9 {
+                return Err((
+                    "criticality_score",
+                    "criticality_score måste vara 0–5 i 0.5-steg".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn has_software(&self) -> bool {
+        self.software_name.is_some() || self.purpose.is_some() || self.description.is_some()
+    }
+
+    fn has_risk(&self) -> bool {
+        self.legal_requirements.is_some()
+            || self.financial_value.is_some()
+            || self.pii.is_some()
+            || self.business_criticality.is_some()
+            || self.information_class.is_some()
+            || self.criticality_score.is_some()
+    }
+}
+
+/// Writes `patch` inside the caller's transaction. Does not touch
+/// `nodes.updated_at`/`version` beyond what `owning_department` implies;
+/// callers that need a fresh ETag bump the node themselves.
+pub(crate) async fn apply_details_patch(
+    tx: &mut Transaction<'_, Postgres>,
+    node_id: Uuid,
+    patch: &NodeDetailsPatch,
+) -> Result<(), ApiError> {
+    if let Some(dept) = trim_opt(patch.owning_department.clone()) {
+        sqlx::query(
+            r#"
+            UPDATE nodes
+            SET owning_department = lower($2)::owning_department
+            WHERE id = $1
+            "#,
+        )
+        .bind(node_id)
+        .bind(dept)
+        .execute(&mut **tx)
+        .await
+        .map_err(map_sqlx_error)?;
+    }
+
+    if let Some(types) = &patch.supplier_types {
+        sqlx::query("DELETE FROM node_supplier_types WHERE node_id = $1")
+            .bind(node_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(map_sqlx_error)?;
+
+        for t in types {
+            let t = t.trim().to_lowercase();
+            if t.is_empty() {
+                continue;
+            }
+            sqlx::query(
+                r#"
+                INSERT INTO node_supplier_types (node_id, supplier_type)
+                VALUES ($1, $2::supplier_type)
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(node_id)
+            .bind(t)
+            .execute(&mut **tx)
+            .await
+            .map_err(map_sqlx_error)?;
+        }
+    }
+
+    if let Some(suppliers) = &patch.suppliers {
+        sqlx::query("DELETE FROM node_suppliers WHERE node_id = $1")
+            .bind(node_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(map_sqlx_error)?;
+
+        for name in suppliers {
+            let name = name.trim();
+            if name.is_empty() {
+                continue;
+            }
+            sqlx::query(
+                r#"
+                WITH s AS (
+                  INSERT INTO suppliers (name, updated_at)
+                  VALUES ($2, now())
+                  ON CONFLICT (name) DO UPDATE
+                  SET updated_at = EXCLUDED.updated_at
+                  RETURNING id
+                )
+                INSERT INTO node_suppliers (node_id, supplier_id)
+                SELECT $1, id FROM s
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(node_id)
+            .bind(name)
+            .execute(&mut **tx)
+            .await
+            .map_err(map_sqlx_error)?;
+        }
+    }
+
+    if let Some(owners) = &patch.owners {
+        sqlx::query("DELETE FROM node_owners WHERE node_id = $1")
+            .bind(node_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(map_sqlx_error)?;
+
+        for name in owners {
+            let name = name.trim();
+            if name.is_empty() {
+                continue;
+            }
+            sqlx::query(
+                r#"
+                WITH o AS (
+                  INSERT INTO owners (name, updated_at)
+                  VALUES ($2, now())
+                  ON CONFLICT (name) DO UPDATE
+                  SET updated_at = EXCLUDED.updated_at
+                  RETURNING id
+                )
+                INSERT INTO node_owners (node_id, owner_id)
+                SELECT $1, id FROM o
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(node_id)
+            .bind(name)
+            .execute(&mut **tx)
+            .await
+            .map_err(map_sqlx_error)?;
+        }
+    }
+
+    if patch.has_software() {
+        sqlx::query(
+            r#"
+            INSERT INTO node_software (node_id, software_name, purpose, description, updated_at)
+            VALUES ($1, $2, $3, $4, now())
+            ON CONFLICT (node_id) DO UPDATE
+            SET
+              software_name = COALESCE(EXCLUDED.software_name, node_software.software_name),
+              purpose = COALESCE(EXCLUDED.purpose, node_software.purpose),
+              description = COALESCE(EXCLUDED.description, node_software.description),
+              updated_at = now()
+            "#,
+        )
+        .bind(node_id)
+        .bind(trim_opt(patch.software_name.clone()))
+        .bind(trim_opt(patch.purpose.clone()))
+        .bind(trim_opt(patch.description.clone()))
+        .execute(&mut **tx)
+        .await
+        .map_err(map_sqlx_error)?;
+    }
+
+    if patch.has_risk() {
+        sqlx::query(
+            r#"
+            INSERT INTO node_risk (
+              node_id,
+              legal_requirements,
+              financial_value,
+              pii,
+              business_criticality,
+              information_class,
+              criticality_score,
+              updated_at
+            )
+            VALUES (
+              $1, $2, $3, $4,
+              lower($5)::business_criticality,
+              lower($6)::information_class,
+              $7, now()
+            )
+            ON CONFLICT (node_id) DO UPDATE
+            SET
+              legal_requirements = COALESCE(EXCLUDED.legal_requirements, node_risk.legal_requirements),
+              financial_value = COALESCE(EXCLUDED.financial_value, node_risk.financial_value),
+              pii = COALESCE(EXCLUDED.pii, node_risk.pii),
+              business_criticality = COALESCE(EXCLUDED.business_criticality, node_risk.business_criticality),
+              information_class = COALESCE(EXCLUDED.information_class, node_risk.information_class),
+              criticality_score = COALESCE(EXCLUDED.criticality_score, node_risk.criticality_score),
+              updated_at = now()
+            "#,
+        )
+        .bind(node_id)
+        .bind(patch.legal_requirements)
+        .bind(patch.financial_value)
+        .bind(patch.pii)
+        .bind(trim_opt(patch.business_criticality.clone()))
+        .bind(trim_opt(patch.information_class.clone()))
+        .bind(patch.criticality_score)
+        .execute(&mut **tx)
+        .await
+        .map_err(map_sqlx_error)?;
+    }
+
+    Ok(())
+}
+
+/// Drops every field of `patch` that already matches the node, leaving what
+/// an approval would actually change.
+pub(crate) async fn diff_details_patch(
+    tx: &mut Transaction<'_, Postgres>,
+    node_id: Uuid,
+    patch: &NodeDetailsPatch,
+) -> Result<NodeDetailsPatch, ApiError> {
+    #[derive(sqlx::FromRow)]
+    struct Current {
+        owning_department: Option<String>,
+        supplier_types: Vec<String>,
+        suppliers: Vec<String>,
+        owners: Vec<String>,
+        software_name: Option<String>,
+        purpose: Option<String>,
+        description: Option<String>,
+        legal_requirements: Option<bool>,
+        financial_value: Option<bool>,
+        pii: Option<bool>,
+        business_criticality: Option<String>,
+        information_class: Option<String>,
+        criticality_score: Option<f64>,
+    }
+
+    let cur: Current = sqlx::query_as(
+        r#"
+        SELECT
+          n.owning_department::text AS owning_department,
+          ARRAY(
+            SELECT supplier_type::text FROM node_supplier_types WHERE node_id = n.id
+          ) AS supplier_types,
+          ARRAY(
+            SELECT s.name
+            FROM node_suppliers ns
+            JOIN suppliers s ON s.id = ns.supplier_id
+            WHERE ns.node_id = n.id
+          ) AS suppliers,
+          ARRAY(
+            SELECT o.name
+            FROM node_owners no
+            JOIN owners o ON o.id = no.owner_id
+            WHERE no.node_id = n.id
+          ) AS owners,
+          sw.software_name,
+          sw.purpose,
+          sw.description,
+          r.legal_requirements,
+          r.financial_value,
+          r.pii,
+          r.business_criticality::text AS business_criticality,
+          r.information_class::text AS information_class,
+          r.criticality_score::float8 AS criticality_score
+        FROM nodes n
+        LEFT JOIN node_software sw ON sw.node_id = n.id
+        LEFT JOIN node_risk r ON r.node_id = n.id
+        WHERE n.id = $1
+        "#,
+    )
+    .bind(node_id)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(map_sqlx_error)?;
+
+    fn changed<T: PartialEq + Clone>(new: &Option<T>, cur: &Option<T>) -> Option<T> {
+        new.as_ref().filter(|v| Some(*v) != cur.as_ref()).cloned()
+    }
+    fn changed_text(new: &Option<String>, cur: &Option<String>) -> Option<String> {
+        let new = trim_opt(new.clone())?;
+        let same = cur.as_deref().is_some_and(|c| c.eq_ignore_ascii_case(&new));
+        (!same).then_some(new)
+    }
+    fn changed_set(new: &Option<Vec<String>>, cur: &[String]) -> Option<Vec<String>> {
+        let new = new.as_ref()?;
+        let norm = |v: &[String]| {
+            let mut v: Vec<String> = v
+                .iter()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+            v.sort();
+            v.dedup();
+            v
+        };
+        (norm(new) != norm(cur)).then(|| new.clone())
+    }
+
+    Ok(NodeDetailsPatch {
+        owning_department: changed_text(&patch.owning_department, &cur.owning_department),
+        supplier_types: changed_set(
+            &patch
+                .supplier_types
+                .as_ref()
+                .map(|v| v.iter().map(|t| t.trim().to_lowercase()).collect()),
+            &cur.supplier_types,
+        ),
+        suppliers: changed_set(&patch.suppliers, &cur.suppliers),
+        owners: changed_set(&patch.owners, &cur.owners),
+        software_name: changed(&trim_opt(patch.software_name.clone()), &cur.software_name),
+        purpose: changed(&trim_opt(patch.purpose.clone()), &cur.purpose),
+        description: changed(&trim_opt(patch.description.clone()), &cur.description),
+        legal_requirements: changed(&patch.legal_requirements, &cur.legal_requirements),
+        financial_value: changed(&patch.financial_value, &cur.financial_value),
+        pii: changed(&patch.pii, &cur.pii),
+        business_criticality: changed_text(&patch.business_criticality, &cur.business_criticality),
+        information_class: changed_text(&patch.information_class, &cur.information_class),
+        criticality_score: changed(&patch.criticality_score, &cur.criticality_score),
+    })
+}
@@ -1,20 +1,22 @@
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct LookupQuery {
     pub q: Option<String>,
     pub limit: Option<i64>,
 }
 
-#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
 pub struct LookupItem {
     pub id: Uuid,
     pub name: String,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct NodeDetailsResponse {
     pub node: NodeCore,
     pub suppliers: Vec<Party>,
@@ -24,31 +26,34 @@ pub struct NodeDetailsResponse {
     pub risk: Option<NodeRisk>,
 }
 
-#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
 pub struct NodeCore {
     pub id: Uuid,
     pub kind: String,
     pub name: String,
     pub metadata: serde_json::Value,
     pub owning_department: Option<String>,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub created_at: OffsetDateTime,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub updated_at: OffsetDateTime,
+    pub version: i64,
 }
 
-#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
 pub struct Party {
     pub id: Uuid,
     pub name: String,
 }
 
-#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
 pub struct NodeSoftware {
     pub software_name: Option<String>,
     pub purpose: Option<String>,
     pub description: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
 pub struct NodeRisk {
     pub legal_requirements: Option<bool>,
     pub financial_value: Option<bool>,
@@ -58,7 +63,7 @@ pub struct NodeRisk {
     pub criticality_score: Option<f64>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct PutNodeDetailsRequest {
     pub owning_department: Option<String>,
     pub supplier_types: Option<Vec<String>>,
@@ -69,14 +74,14 @@ pub struct PutNodeDetailsRequest {
     pub risk: Option<PutNodeRisk>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct PutNodeSoftware {
     pub software_name: Option<String>,
     pub purpose: Option<String>,
     pub description: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct PutNodeRisk {
     pub legal_requirements: Option<bool>,
     pub financial_value: Option<bool>,
@@ -1,4 +1,5 @@
-use axum::http::StatusCode;
+
+use crate::error::ApiError;
 
 pub fn is_valid_supplier_type(v: &str) -> bool {
     matches!(v.trim().to_lowercase().as_str(), "intern" | "saas" | "paas")
@@ -24,6 +25,6 @@ pub fn trim_opt(v: Option<String>) -> Option<String> {
     }
 }
 
-pub fn internal_error<E: std::fmt::Display>(e: E) -> (StatusCode, String) {
-    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+pub fn internal_error<E: std::fmt::Display>(e: E) -> ApiError {
+    ApiError::internal(e)
 }
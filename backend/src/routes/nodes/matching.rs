@@ -0,0 +1,562 @@
+//! Entity resolution. Names are compared through `normalize_node_name()`
+//! (migration 025): "Postgres16", "postgres-16" and "PostgreSQL 16" are the
+//! same name, "PG16 prod" a similar one. Aliases count as names, and `ip` /
+//! `hostname` / `fqdn` metadata are compared as sets.
+//!
+//! `find_matches` serves imports (a node about to be created is checked
+//! against existing ones) and `GET /nodes/matches` (manual entry). The
+//! duplicate report compares existing nodes of the same kind pairwise.
+//!
+//! Both only score candidates found through the indexes of migration 025:
+//! the trigram `%` operator on normalized names and aliases, and overlap on
+//! addresses and host names. `%` uses `pg_trgm.similarity_threshold`, which
+//! is set to the requested minimum for the current transaction.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::{
+    audit::{self, AuditAction, EntityType, RequestContext},
+    auth::AuthActor,
+    models::node::NodeKind,
+    routes::AppState,
+};
+
+use crate::routes::edges::helpers::{internal_error, map_sqlx_error};
+
+/// Similarity below which a name alone is not a match.
+pub const DEFAULT_MIN_SIMILARITY: f32 = 0.5;
+const DEFAULT_LIMIT: i64 = 10;
+const MAX_LIMIT: i64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchReason {
+    /// Samma normaliserade namn.
+    Name,
+    /// Namnet är ett alias för den andra noden (eller tvärtom).
+    Alias,
+    /// Minst en gemensam IP-adress.
+    Ip,
+    /// Gemensamt värdnamn, med eller utan domän.
+    Hostname,
+    /// Namnen liknar varandra (trigram).
+    SimilarName,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct NodeMatch {
+    pub node_id: Uuid,
+    pub kind: String,
+    pub name: String,
+    /// 0–1; exakt namn 1, alias 0.95, IP 0.9, värdnamn 0.85, annars likheten.
+    pub score: f64,
+    pub reasons: Vec<MatchReason>,
+}
+
+/// What to look for; `metadata` is read for `ip`, `hostname` and `fqdn`.
+pub struct MatchQuery<'a> {
+    pub kind: Option<&'a str>,
+    pub name: &'a str,
+    pub metadata: &'a serde_json::Value,
+    pub exclude: Option<Uuid>,
+    pub min_similarity: f32,
+    pub limit: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct Signals {
+    same_name: bool,
+    alias: bool,
+    ip: bool,
+    hostname: bool,
+    similarity: f32,
+}
+
+impl Signals {
+    fn score(&self, min_similarity: f32) -> (f64, Vec<MatchReason>) {
+        let mut score: f64 = 0.0;
+        let mut reasons = Vec::new();
+        for (hit, reason, weight) in [
+            (self.same_name, MatchReason::Name, 1.0),
+            (self.alias, MatchReason::Alias, 0.95),
+            (self.ip, MatchReason::Ip, 0.9),
+            (self.hostname, MatchReason::Hostname, 0.85),
+        ] {
+            if hit {
+                score = score.max(weight);
+                reasons.push(reason);
+            }
+        }
+        // An exact name or alias already explains the similarity.
+        if !self.same_name && !self.alias && self.similarity >= min_similarity {
+            // Rounded: f32 noise would otherwise end up in stored proposals.
+            score = score.max((f64::from(self.similarity) * 1000.0).round() / 1000.0);
+            reasons.push(MatchReason::SimilarName);
+        }
+        (score, reasons)
+    }
+}
+
+/// Existing, non-deleted nodes that are probably the one described,
+/// best first. `conn` should be inside a transaction, or the similarity
+/// threshold falls back to the pg_trgm default.
+pub async fn find_matches(
+    conn: &mut PgConnection,
+    query: &MatchQuery<'_>,
+) -> Result<Vec<NodeMatch>, ApiError> {
+    #[derive(sqlx::FromRow)]
+    struct Row {
+        id: Uuid,
+        kind: String,
+        name: String,
+        #[sqlx(flatten)]
+        signals: Signals,
+    }
+
+    set_similarity_threshold(&mut *conn, query.min_similarity).await?;
+
+    let rows: Vec<Row> = sqlx::query_as(
+        r#"
+        WITH q AS (
+            SELECT
+                normalize_node_name($1) AS norm,
+                node_ips($2) AS ips,
+                node_hostnames($2) || lower(btrim($1)) AS hostnames
+        ),
+        cand AS (
+            SELECT n.id
+            FROM nodes n
+            WHERE normalize_node_name(n.name) % normalize_node_name($1)
+            UNION
+            SELECT a.node_id
+            FROM node_aliases a
+            WHERE normalize_node_name(a.alias) % normalize_node_name($1)
+            UNION
+            SELECT n.id
+            FROM nodes n
+            WHERE node_ips(n.metadata) && node_ips($2)
+            UNION
+            SELECT n.id
+            FROM nodes n
+            WHERE (node_hostnames(n.metadata) || lower(n.name))
+                  && (node_hostnames($2) || lower(btrim($1)))
+        ),
+        c AS (
+            SELECT
+                n.id,
+                n.kind,
+                n.name,
+                q.norm <> '' AND normalize_node_name(n.name) = q.norm AS same_name,
+                q.norm <> '' AND EXISTS (
+                    SELECT 1 FROM node_aliases a
+                    WHERE a.node_id = n.id AND normalize_node_name(a.alias) = q.norm
+                ) AS alias,
+                node_ips(n.metadata) && q.ips AS ip,
+                (node_hostnames(n.metadata) || lower(n.name)) && q.hostnames AS hostname,
+                GREATEST(
+                    similarity(normalize_node_name(n.name), q.norm),
+                    COALESCE((
+                        SELECT max(similarity(normalize_node_name(a.alias), q.norm))
+                        FROM node_aliases a
+                        WHERE a.node_id = n.id
+                    ), 0)
+                )::float4 AS similarity
+            FROM nodes n
+            JOIN cand ON cand.id = n.id
+            CROSS JOIN q
+            WHERE n.deleted_at IS NULL
+              AND ($3::text IS NULL OR n.kind = $3)
+              AND ($4::uuid IS NULL OR n.id <> $4)
+        )
+        SELECT * FROM c
+        WHERE same_name OR alias OR ip OR hostname OR similarity >= $5
+        "#,
+    )
+    .bind(query.name)
+    .bind(query.metadata)
+    .bind(query.kind)
+    .bind(query.exclude)
+    .bind(query.min_similarity)
+    .fetch_all(conn)
+    .await
+    .map_err(internal_error)?;
+
+    let mut matches: Vec<NodeMatch> = rows
+        .into_iter()
+        .map(|row| {
+            let (score, reasons) = row.signals.score(query.min_similarity);
+            NodeMatch {
+                node_id: row.id,
+                kind: row.kind,
+                name: row.name,
+                score,
+                reasons,
+            }
+        })
+        .filter(|m| !m.reasons.is_empty())
+        .collect();
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.name.cmp(&b.name)));
+    matches.truncate(query.limit.max(0) as usize);
+    Ok(matches)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MatchesQuery {
+    #[serde(default)]
+    pub name: String,
+    pub kind: Option<NodeKind>,
+    /// En eller flera adresser, kommaseparerade.
+    pub ip: Option<String>,
+    pub hostname: Option<String>,
+    /// Nod som inte ska räknas, t.ex. den som redigeras.
+    pub exclude: Option<Uuid>,
+    /// Lägsta namnlikhet (0–1); standard 0.5.
+    pub min_similarity: Option<f32>,
+    pub limit: Option<i64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/matches",
+    params(MatchesQuery),
+    responses(
+        (status = 200, body = Vec<NodeMatch>),
+        (status = 400, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn list_matches(
+    State(state): State<AppState>,
+    Query(q): Query<MatchesQuery>,
+) -> Result<Json<Vec<NodeMatch>>, ApiError> {
+    if q.name.trim().is_empty() && q.ip.is_none() && q.hostname.is_none() {
+        return Err(ApiError::validation("name", "Ange name, ip eller hostname"));
+    }
+    let min_similarity = min_similarity(q.min_similarity, DEFAULT_MIN_SIMILARITY)?;
+
+    let mut metadata = serde_json::Map::new();
+    if let Some(ip) = &q.ip {
+        metadata.insert("ip".into(), ip.clone().into());
+    }
+    if let Some(hostname) = &q.hostname {
+        metadata.insert("hostname".into(), hostname.clone().into());
+    }
+    let metadata = serde_json::Value::Object(metadata);
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let matches = find_matches(
+        &mut tx,
+        &MatchQuery {
+            kind: q.kind.map(NodeKind::as_str),
+            name: q.name.trim(),
+            metadata: &metadata,
+            exclude: q.exclude,
+            min_similarity,
+            limit: q.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        },
+    )
+    .await?;
+
+    Ok(Json(matches))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DuplicatesQuery {
+    pub kind: Option<NodeKind>,
+    /// Lägsta namnlikhet (0–1); standard 0.6.
+    pub min_similarity: Option<f32>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DuplicateNode {
+    pub id: Uuid,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DuplicatePair {
+    pub kind: String,
+    /// Noden med lägst id.
+    pub a: DuplicateNode,
+    pub b: DuplicateNode,
+    pub score: f64,
+    pub reasons: Vec<MatchReason>,
+}
+
+/// Likely duplicate pairs among nodes of the same kind, best first. Pairs
+/// dismissed through `POST /nodes/duplicates/dismiss` are left out. Needs a
+/// token even though it is a read: the report walks every node of a kind.
+#[utoipa::path(
+    get,
+    path = "/duplicates",
+    params(DuplicatesQuery),
+    responses(
+        (status = 200, body = Vec<DuplicatePair>),
+        (status = 400, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn list_duplicates(
+    State(state): State<AppState>,
+    Query(q): Query<DuplicatesQuery>,
+) -> Result<Json<Vec<DuplicatePair>>, ApiError> {
+    let min_similarity = min_similarity(q.min_similarity, 0.6)?;
+
+    #[derive(sqlx::FromRow)]
+    struct Row {
+        kind: String,
+        a_id: Uuid,
+        a_name: String,
+        b_id: Uuid,
+        b_name: String,
+        #[sqlx(flatten)]
+        signals: Signals,
+    }
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    set_similarity_threshold(&mut tx, min_similarity).await?;
+
+    let rows: Vec<Row> = sqlx::query_as(
+        r#"
+        WITH live AS (
+            SELECT id, kind, name, metadata
+            FROM nodes
+            WHERE deleted_at IS NULL
+              AND ($1::text IS NULL OR kind = $1)
+        ),
+        cand AS (
+            SELECT a.id AS a_id, b.id AS b_id
+            FROM live a
+            JOIN nodes b
+              ON b.kind = a.kind
+             AND b.id > a.id
+             AND b.deleted_at IS NULL
+             AND normalize_node_name(b.name) % normalize_node_name(a.name)
+            UNION
+            SELECT LEAST(a.id, b.id), GREATEST(a.id, b.id)
+            FROM live a
+            JOIN node_aliases al
+              ON normalize_node_name(al.alias) = normalize_node_name(a.name)
+            JOIN nodes b
+              ON b.id = al.node_id
+             AND b.kind = a.kind
+             AND b.id <> a.id
+             AND b.deleted_at IS NULL
+            UNION
+            SELECT LEAST(x.node_id, y.node_id), GREATEST(x.node_id, y.node_id)
+            FROM node_aliases x
+            JOIN live a ON a.id = x.node_id
+            JOIN node_aliases y
+              ON normalize_node_name(y.alias) = normalize_node_name(x.alias)
+             AND y.node_id <> x.node_id
+            JOIN nodes b
+              ON b.id = y.node_id
+             AND b.kind = a.kind
+             AND b.deleted_at IS NULL
+            UNION
+            SELECT a.id, b.id
+            FROM live a
+            JOIN nodes b
+              ON b.kind = a.kind
+             AND b.id > a.id
+             AND b.deleted_at IS NULL
+             AND node_ips(b.metadata) && node_ips(a.metadata)
+            UNION
+            SELECT a.id, b.id
+            FROM live a
+            JOIN nodes b
+              ON b.kind = a.kind
+             AND b.id > a.id
+             AND b.deleted_at IS NULL
+             AND (node_hostnames(b.metadata) || lower(b.name))
+                 && (node_hostnames(a.metadata) || lower(a.name))
+        ),
+        k AS (
+            SELECT
+                n.id,
+                n.kind,
+                n.name,
+                normalize_node_name(n.name) AS norm,
+                COALESCE(
+                    array_agg(normalize_node_name(a.alias)) FILTER (WHERE a.alias IS NOT NULL),
+                    '{}'
+                ) AS aliases,
+                node_ips(n.metadata) AS ips,
+                node_hostnames(n.metadata) || lower(n.name) AS hostnames
+            FROM nodes n
+            LEFT JOIN node_aliases a ON a.node_id = n.id
+            WHERE n.id IN (SELECT a_id FROM cand UNION SELECT b_id FROM cand)
+            GROUP BY n.id
+        ),
+        p AS (
+            SELECT
+                a.kind,
+                a.id AS a_id,
+                a.name AS a_name,
+                b.id AS b_id,
+                b.name AS b_name,
+                a.norm <> '' AND a.norm = b.norm AS same_name,
+                a.norm = ANY(b.aliases) OR b.norm = ANY(a.aliases) OR a.aliases && b.aliases
+                    AS alias,
+                a.ips && b.ips AS ip,
+                a.hostnames && b.hostnames AS hostname,
+                similarity(a.norm, b.norm)::float4 AS similarity
+            FROM cand
+            JOIN k a ON a.id = cand.a_id
+            JOIN k b ON b.id = cand.b_id
+        )
+        SELECT * FROM p
+        WHERE (same_name OR alias OR ip OR hostname OR similarity >= $2)
+          AND NOT EXISTS (
+              SELECT 1 FROM node_duplicate_dismissals d
+              WHERE d.node_a = p.a_id AND d.node_b = p.b_id
+          )
+        "#,
+    )
+    .bind(q.kind.map(NodeKind::as_str))
+    .bind(min_similarity)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(internal_error)?;
+
+    let mut pairs: Vec<DuplicatePair> = rows
+        .into_iter()
+        .map(|row| {
+            let (score, reasons) = row.signals.score(min_similarity);
+            DuplicatePair {
+                kind: row.kind,
+                a: DuplicateNode {
+                    id: row.a_id,
+                    name: row.a_name,
+                },
+                b: DuplicateNode {
+                    id: row.b_id,
+                    name: row.b_name,
+                },
+                score,
+                reasons,
+            }
+        })
+        .filter(|p| !p.reasons.is_empty())
+        .collect();
+    pairs.sort_by(|x, y| {
+        y.score
+            .total_cmp(&x.score)
+            .then(x.kind.cmp(&y.kind))
+            .then(x.a.name.cmp(&y.a.name))
+    });
+    pairs.truncate(q.limit.unwrap_or(100).clamp(1, MAX_LIMIT) as usize);
+
+    Ok(Json(pairs))
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct DismissDuplicateBody {
+    pub a: Uuid,
+    pub b: Uuid,
+}
+
+/// Marks a pair as not duplicates; it no longer shows up in the report.
+#[utoipa::path(
+    post,
+    path = "/duplicates/dismiss",
+    request_body = DismissDuplicateBody,
+    responses(
+        (status = 204),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub async fn dismiss_duplicate(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    Json(body): Json<DismissDuplicateBody>,
+) -> Result<StatusCode, ApiError> {
+    let (a, b) = match body.a.cmp(&body.b) {
+        std::cmp::Ordering::Less => (body.a, body.b),
+        std::cmp::Ordering::Greater => (body.b, body.a),
+        std::cmp::Ordering::Equal => {
+            return Err(ApiError::validation("b", "Ange två olika noder"));
+        }
+    };
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+
+    let found: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM nodes WHERE id = ANY($1)")
+        .bind(vec![a, b])
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+    if found != 2 {
+        return Err(ApiError::not_found("Noden finns inte"));
+    }
+
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO node_duplicate_dismissals (node_a, node_b, dismissed_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(a)
+    .bind(b)
+    .bind(actor.username.clone())
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_error)?
+    .rows_affected();
+
+    if inserted > 0 {
+        audit::write_audit(
+            &mut tx,
+            ctx,
+            Some(&actor),
+            EntityType::Node,
+            a,
+            AuditAction::Patch,
+            None,
+            Some(serde_json::json!({
+                "action": "duplicate_dismissed",
+                "other_node_id": b
+            })),
+            None,
+        )
+        .await
+        .map_err(internal_error)?;
+    }
+
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn set_similarity_threshold(conn: &mut PgConnection, value: f32) -> Result<(), ApiError> {
+    sqlx::query("SELECT set_config('pg_trgm.similarity_threshold', $1, true)")
+        .bind(value.to_string())
+        .execute(conn)
+        .await
+        .map_err(internal_error)?;
+    Ok(())
+}
+
+fn min_similarity(value: Option<f32>, default: f32) -> Result<f32, ApiError> {
+    let value = value.unwrap_or(default);
+    if !(0.0..=1.0).contains(&value) {
+        return Err(ApiError::validation(
+            "min_similarity",
+            "min_similarity måste vara mellan 0 och 1",
+        ));
+    }
+    Ok(value)
+}
@@ -2,20 +2,52 @@ use axum::{
     routing::{get, patch, post},
     Router,
 };
+use utoipa::OpenApi;
 
 use crate::routes::AppState;
 
+pub mod aliases;
 pub mod claims;
 pub mod crud;
 pub mod details;
+pub mod matching;
 pub mod needs_review;
 pub mod queries;
 pub mod walk;
 
+#[derive(OpenApi)]
+#[openapi(paths(
+    crud::list_nodes,
+    crud::create_node,
+    crud::get_node,
+    crud::update_node,
+    crud::delete_node,
+    crud::patch_node_metadata,
+    crud::restore_node,
+    crud::duplicate_node,
+    queries::blast_radius::get_blast_radius,
+    queries::dependents::get_dependents,
+    queries::needs_review::get_needs_review_in_blast_radius,
+    queries::vendor_exposure::get_vendor_exposure,
+    needs_review::list_nodes_needing_review,
+    needs_review::mark_node_needs_review,
+    claims::list_node_claims,
+    claims::create_node_claim,
+    aliases::list_node_aliases,
+    aliases::put_node_aliases,
+    matching::list_matches,
+    matching::list_duplicates,
+    matching::dismiss_duplicate,
+))]
+pub struct NodesApi;
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", get(crud::list_nodes).post(crud::create_node))
         .route("/lookups/suppliers", get(details::lookup_suppliers))
+        .route("/matches", get(matching::list_matches))
+        .route("/duplicates", get(matching::list_duplicates))
+        .route("/duplicates/dismiss", post(matching::dismiss_duplicate))
         .route("/lookups/owners", get(details::lookup_owners))
         .route(
             "/:id/details",
@@ -49,4 +81,8 @@ pub fn router() -> Router<AppState> {
             "/:id/claims",
             get(claims::list_node_claims).post(claims::create_node_claim),
         )
+        .route(
+            "/:id/aliases",
+            get(aliases::list_node_aliases).put(aliases::put_node_aliases),
+        )
 }
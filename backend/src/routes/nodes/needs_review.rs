@@ -2,13 +2,14 @@
 
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
     Extension, Json,
 };
 use serde::Deserialize;
 use serde::Serialize;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::{
     audit::{self, AuditAction, EntityType, RequestContext},
     auth::AuthActor,
@@ -18,7 +19,7 @@ use crate::{
 
 use crate::routes::edges::helpers::{internal_error, map_sqlx_error};
 
-#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
 pub struct NeedsReviewNodeRow {
     pub node_id: Uuid,
     pub kind: String,
@@ -27,17 +28,26 @@ pub struct NeedsReviewNodeRow {
     pub status: String,
 
     pub created_by: String,
+    #[schema(value_type = crate::openapi::Timestamp)]
     pub created_at: time::OffsetDateTime,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct MarkNeedsReviewBody {
     pub description: String,
 }
 
+#[utoipa::path(
+    get,
+    path = "/needs-review",
+    responses(
+        (status = 200, body = Vec<NeedsReviewNodeRow>),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn list_nodes_needing_review(
     State(state): State<AppState>,
-) -> Result<Json<Vec<NeedsReviewNodeRow>>, (StatusCode, String)> {
+) -> Result<Json<Vec<NeedsReviewNodeRow>>, ApiError> {
     let rows: Vec<NeedsReviewNodeRow> = sqlx::query_as(
         r#"
         SELECT
@@ -70,16 +80,27 @@ pub async fn list_nodes_needing_review(
     Ok(Json(rows))
 }
 
+#[utoipa::path(
+    post,
+    path = "/{id}/needs-review",
+    params(("id" = Uuid, Path)),
+    request_body = MarkNeedsReviewBody,
+    responses(
+        (status = 200, body = NodeClaim),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn mark_node_needs_review(
     State(state): State<AppState>,
     Extension(ctx): Extension<RequestContext>,
     Extension(actor): Extension<AuthActor>,
     Path(node_id): Path<Uuid>,
     Json(body): Json<MarkNeedsReviewBody>,
-) -> Result<Json<NodeClaim>, (StatusCode, String)> {
+) -> Result<Json<NodeClaim>, ApiError> {
     let description = body.description.trim();
     if description.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "Beskrivning krävs".into()));
+        return Err(ApiError::bad_request("Beskrivning krävs"));
     }
     let mut tx = state.pool.begin().await.map_err(internal_error)?;
 
@@ -115,7 +136,10 @@ pub async fn mark_node_needs_review(
             created_by,
             created_at,
             updated_at,
-            last_verified_at
+            last_verified_at,
+            version,
+            import_batch_id,
+            proposed_changes
         "#,
     )
     .bind(node_id)
@@ -127,7 +151,7 @@ pub async fn mark_node_needs_review(
     .map_err(map_sqlx_error)?;
 
     audit::write_audit(
-        &mut *tx,
+        &mut tx,
         ctx.clone(),
         Some(&actor),
         EntityType::Node,
@@ -1,10 +1,11 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
     Json,
 };
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::models::Edge;
 use crate::routes::AppState;
 
@@ -12,7 +13,8 @@ use crate::routes::nodes::walk::{materialize_walk, BlastRadiusNode, WalkRow};
 
 use super::internal_error;
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct BlastRadiusQuery {
     pub direction: Option<String>,
     pub max_depth: Option<i32>,
@@ -20,7 +22,8 @@ pub struct BlastRadiusQuery {
     pub include_needs_review: Option<bool>,
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, ToSchema)]
+#[schema(as = NodeBlastRadiusResponse)]
 pub struct BlastRadiusResponse {
     pub root_node_id: Uuid,
     pub direction: String,
@@ -31,11 +34,21 @@ pub struct BlastRadiusResponse {
     pub edges: Vec<Edge>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/{id}/blast-radius",
+    params(("id" = Uuid, Path), BlastRadiusQuery),
+    responses(
+        (status = 200, body = BlastRadiusResponse),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn get_blast_radius(
     State(state): State<AppState>,
     Path(root_id): Path<Uuid>,
     Query(q): Query<BlastRadiusQuery>,
-) -> Result<Json<BlastRadiusResponse>, (StatusCode, String)> {
+) -> Result<Json<BlastRadiusResponse>, ApiError> {
     let exists: (bool,) =
         sqlx::query_as("SELECT EXISTS(SELECT 1 FROM nodes WHERE id = $1 AND deleted_at IS NULL)")
             .bind(root_id)
@@ -44,15 +57,12 @@ pub async fn get_blast_radius(
             .map_err(internal_error)?;
 
     if !exists.0 {
-        return Err((StatusCode::NOT_FOUND, "Noden finns inte".into()));
+        return Err(ApiError::not_found("Noden finns inte"));
     }
 
     let direction = q.direction.unwrap_or_else(|| "downstream".to_string());
     if direction != "downstream" && direction != "upstream" {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "direction måste vara 'downstream' eller 'upstream'".into(),
-        ));
+        return Err(ApiError::bad_request("direction måste vara 'downstream' eller 'upstream'"));
     }
 
     let mut max_depth = q.max_depth.unwrap_or(5);
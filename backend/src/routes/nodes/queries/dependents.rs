@@ -1,10 +1,11 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
     Json,
 };
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::models::Edge;
 use crate::routes::AppState;
 
@@ -12,14 +13,15 @@ use crate::routes::nodes::walk::{materialize_walk, BlastRadiusNode, WalkRow};
 
 use super::internal_error;
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct DependentsQuery {
     pub max_depth: Option<i32>,
     pub min_confidence: Option<i16>,
     pub include_needs_review: Option<bool>,
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, ToSchema)]
 pub struct DependentsResponse {
     pub root_node_id: Uuid,
     pub max_depth: i32,
@@ -29,11 +31,20 @@ pub struct DependentsResponse {
     pub edges: Vec<Edge>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/{id}/dependents",
+    params(("id" = Uuid, Path), DependentsQuery),
+    responses(
+        (status = 200, body = DependentsResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn get_dependents(
     State(state): State<AppState>,
     Path(root_id): Path<Uuid>,
     Query(q): Query<DependentsQuery>,
-) -> Result<Json<DependentsResponse>, (StatusCode, String)> {
+) -> Result<Json<DependentsResponse>, ApiError> {
     let exists: (bool,) =
         sqlx::query_as("SELECT EXISTS(SELECT 1 FROM nodes WHERE id = $1 AND deleted_at IS NULL)")
             .bind(root_id)
@@ -42,7 +53,7 @@ pub async fn get_dependents(
             .map_err(internal_error)?;
 
     if !exists.0 {
-        return Err((StatusCode::NOT_FOUND, "Noden finns inte".into()));
+        return Err(ApiError::not_found("Noden finns inte"));
     }
 
     let mut max_depth = q.max_depth.unwrap_or(1);
@@ -1,10 +1,11 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
     Json,
 };
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::models::Edge;
 use crate::routes::AppState;
 
@@ -12,14 +13,15 @@ use crate::routes::nodes::walk::{materialize_walk, BlastRadiusNode, WalkRow};
 
 use super::internal_error;
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct NeedsReviewInRadiusQuery {
     pub direction: Option<String>,
     pub max_depth: Option<i32>,
     pub confidence_threshold: Option<i16>,
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, ToSchema)]
 pub struct FlaggedEdge {
     pub edge: Edge,
     pub reason: String,
@@ -27,11 +29,12 @@ pub struct FlaggedEdge {
     pub claim_id: Uuid,
     pub status: String,
     pub confidence: i16,
+    #[schema(value_type = Option<crate::openapi::Timestamp>)]
     pub last_verified_at: Option<time::OffsetDateTime>,
     pub source: String,
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, ToSchema)]
 pub struct NeedsReviewInRadiusResponse {
     pub root_node_id: Uuid,
     pub direction: String,
@@ -42,11 +45,21 @@ pub struct NeedsReviewInRadiusResponse {
     pub flagged_edges: Vec<FlaggedEdge>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/{id}/blast-radius/needs-review",
+    params(("id" = Uuid, Path), NeedsReviewInRadiusQuery),
+    responses(
+        (status = 200, body = NeedsReviewInRadiusResponse),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn get_needs_review_in_blast_radius(
     State(state): State<AppState>,
     Path(root_id): Path<Uuid>,
     Query(q): Query<NeedsReviewInRadiusQuery>,
-) -> Result<Json<NeedsReviewInRadiusResponse>, (StatusCode, String)> {
+) -> Result<Json<NeedsReviewInRadiusResponse>, ApiError> {
     let exists: (bool,) =
         sqlx::query_as("SELECT EXISTS(SELECT 1 FROM nodes WHERE id = $1 AND deleted_at IS NULL)")
             .bind(root_id)
@@ -55,15 +68,12 @@ pub async fn get_needs_review_in_blast_radius(
             .map_err(internal_error)?;
 
     if !exists.0 {
-        return Err((StatusCode::NOT_FOUND, "Noden finns inte".into()));
+        return Err(ApiError::not_found("Noden finns inte"));
     }
 
     let direction = q.direction.unwrap_or_else(|| "downstream".to_string());
     if direction != "downstream" && direction != "upstream" {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "direction måste vara 'downstream' eller 'upstream'".into(),
-        ));
+        return Err(ApiError::bad_request("direction måste vara 'downstream' eller 'upstream'"));
     }
 
     let mut max_depth = q.max_depth.unwrap_or(5);
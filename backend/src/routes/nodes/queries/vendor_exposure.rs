@@ -1,10 +1,11 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
     Json,
 };
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::models::Edge;
 use crate::routes::AppState;
 
@@ -12,7 +13,8 @@ use crate::routes::nodes::walk::{materialize_walk, BlastRadiusNode, WalkRow};
 
 use super::internal_error;
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct VendorExposureQuery {
     pub direction: Option<String>,
     pub max_depth: Option<i32>,
@@ -20,7 +22,7 @@ pub struct VendorExposureQuery {
     pub include_needs_review: Option<bool>,
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, ToSchema)]
 pub struct VendorExposureResponse {
     pub root_node_id: Uuid,
     pub direction: String,
@@ -34,11 +36,21 @@ pub struct VendorExposureResponse {
     pub vendors: Vec<BlastRadiusNode>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/{id}/vendor-exposure",
+    params(("id" = Uuid, Path), VendorExposureQuery),
+    responses(
+        (status = 200, body = VendorExposureResponse),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
 pub async fn get_vendor_exposure(
     State(state): State<AppState>,
     Path(root_id): Path<Uuid>,
     Query(q): Query<VendorExposureQuery>,
-) -> Result<Json<VendorExposureResponse>, (StatusCode, String)> {
+) -> Result<Json<VendorExposureResponse>, ApiError> {
     let exists: (bool,) =
         sqlx::query_as("SELECT EXISTS(SELECT 1 FROM nodes WHERE id = $1 AND deleted_at IS NULL)")
             .bind(root_id)
@@ -47,15 +59,12 @@ pub async fn get_vendor_exposure(
             .map_err(internal_error)?;
 
     if !exists.0 {
-        return Err((StatusCode::NOT_FOUND, "Noden finns inte".into()));
+        return Err(ApiError::not_found("Noden finns inte"));
     }
 
     let direction = q.direction.unwrap_or_else(|| "downstream".to_string());
     if direction != "downstream" && direction != "upstream" {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "direction måste vara 'downstream' eller 'upstream'".into(),
-        ));
+        return Err(ApiError::bad_request("direction måste vara 'downstream' eller 'upstream'"));
     }
 
     let mut max_depth = q.max_depth.unwrap_or(5);
@@ -1,7 +1,8 @@
-use axum::http::StatusCode;
 use std::collections::{HashMap, HashSet};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::models::{Edge, Node};
 
 #[derive(sqlx::FromRow)]
@@ -11,7 +12,7 @@ pub struct WalkRow {
     pub edge_id: Option<Uuid>,
 }
 
-#[derive(Debug, serde::Serialize, Clone)]
+#[derive(Debug, serde::Serialize, Clone, ToSchema)]
 pub struct BlastRadiusNode {
     pub node: Node,
     pub depth: i32,
@@ -20,7 +21,7 @@ pub struct BlastRadiusNode {
 pub async fn materialize_walk(
     pool: &sqlx::PgPool,
     walked: Vec<WalkRow>,
-) -> Result<(Vec<BlastRadiusNode>, Vec<Edge>), (StatusCode, String)> {
+) -> Result<(Vec<BlastRadiusNode>, Vec<Edge>), ApiError> {
     let mut node_depth: HashMap<Uuid, i32> = HashMap::new();
     let mut edge_ids: HashSet<Uuid> = HashSet::new();
 
@@ -44,7 +45,7 @@ pub async fn materialize_walk(
 
     let nodes = sqlx::query_as::<_, Node>(
         r#"
-        SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by
+        SELECT id, kind, name, metadata, created_at, updated_at, deleted_at, deleted_by, version
         FROM nodes
         WHERE id = ANY($1) AND deleted_at IS NULL
         "#,
@@ -59,7 +60,7 @@ pub async fn materialize_walk(
     } else {
         sqlx::query_as::<_, Edge>(
             r#"
-            SELECT id, from_id, to_id, kind, metadata, created_at, updated_at
+            SELECT id, from_id, to_id, kind, metadata, created_at, updated_at, version
             FROM edges
             WHERE id = ANY($1)
             "#,
@@ -87,6 +88,6 @@ pub async fn materialize_walk(
     Ok((nodes_with_depth, edges))
 }
 
-fn internal_error<E: std::fmt::Display>(e: E) -> (StatusCode, String) {
-    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+fn internal_error<E: std::fmt::Display>(e: E) -> ApiError {
+    ApiError::internal(e)
 }
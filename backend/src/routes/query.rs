@@ -1,22 +1,46 @@
 use axum::{
     extract::{Query, State},
-    routing::get,
+    routing::{get, post},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use sqlx::Row;
+use std::collections::{HashMap, HashSet, VecDeque};
+use utoipa::{IntoParams, OpenApi, ToSchema};
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::routes::AppState;
 
+mod information_class;
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    shortest_path,
+    paths,
+    pii_flows,
+    information_class::check_information_class,
+    information_class::raise_information_class_reviews
+))]
+pub struct QueryApi;
+
 pub fn router() -> Router<AppState> {
     Router::<AppState>::new()
         .route("/path", get(shortest_path))
         .route("/paths", get(paths))
         .route("/compliance/pii", get(pii_flows))
+        .route(
+            "/compliance/information-class",
+            get(information_class::check_information_class),
+        )
+        .route(
+            "/compliance/information-class/reviews",
+            post(information_class::raise_information_class_reviews),
+        )
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct PathQuery {
     from: Uuid,
     to: Uuid,
@@ -28,16 +52,22 @@ fn default_depth() -> i32 {
     12
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct PathResult {
     pub node_ids: Vec<Uuid>,
     pub edge_ids: Vec<Uuid>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/path",
+    params(PathQuery),
+    responses((status = 200, body = PathResult), (status = 500, response = crate::openapi::ErrorResponse))
+)]
 async fn shortest_path(
     State(state): State<AppState>,
     Query(q): Query<PathQuery>,
-) -> Json<PathResult> {
+) -> Result<Json<PathResult>, ApiError> {
     let row = sqlx::query(
         r#"
         WITH RECURSIVE walk AS (
@@ -77,21 +107,22 @@ async fn shortest_path(
     .bind(q.max_depth)
     .fetch_optional(&state.pool)
     .await
-    .expect("path query failed");
+    .map_err(ApiError::internal)?;
 
     if let Some(r) = row {
         let node_ids: Vec<Uuid> = r.try_get("node_path").unwrap_or_default();
         let edge_ids: Vec<Uuid> = r.try_get("edge_path").unwrap_or_default();
-        Json(PathResult { node_ids, edge_ids })
+        Ok(Json(PathResult { node_ids, edge_ids }))
     } else {
-        Json(PathResult {
+        Ok(Json(PathResult {
             node_ids: vec![],
             edge_ids: vec![],
-        })
+        }))
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct PathsQuery {
     from: Uuid,
     to: Uuid,
@@ -124,7 +155,7 @@ fn default_paths_limit() -> i32 {
     20
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct PathsResponse {
     pub from: Uuid,
     pub to: Uuid,
@@ -134,7 +165,16 @@ pub struct PathsResponse {
     pub paths: Vec<PathResult>,
 }
 
-async fn paths(State(state): State<AppState>, Query(q): Query<PathsQuery>) -> Json<PathsResponse> {
+#[utoipa::path(
+    get,
+    path = "/paths",
+    params(PathsQuery),
+    responses((status = 200, body = PathsResponse), (status = 500, response = crate::openapi::ErrorResponse))
+)]
+async fn paths(
+    State(state): State<AppState>,
+    Query(q): Query<PathsQuery>,
+) -> Result<Json<PathsResponse>, ApiError> {
     let mut max_depth = q.max_depth;
     if max_depth < 1 {
         max_depth = 1;
@@ -251,7 +291,7 @@ async fn paths(State(state): State<AppState>, Query(q): Query<PathsQuery>) -> Js
         .bind(limit)
         .fetch_all(&state.pool)
         .await
-        .expect("paths query failed");
+        .map_err(ApiError::internal)?;
 
     let mut out: Vec<PathResult> = Vec::with_capacity(rows.len());
 
@@ -261,93 +301,289 @@ async fn paths(State(state): State<AppState>, Query(q): Query<PathsQuery>) -> Js
         out.push(PathResult { node_ids, edge_ids });
     }
 
-    Json(PathsResponse {
+    Ok(Json(PathsResponse {
         from: q.from,
         to: q.to,
         max_depth,
         min_confidence: q.min_confidence,
         include_needs_review: q.include_needs_review,
         paths: out,
-    })
+    }))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WalkDirection {
+    /// Along edges, `from_id` -> `to_id`.
+    Downstream,
+    Upstream,
+    #[default]
+    Both,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct ComplianceQuery {
     #[serde(default = "default_depth")]
     depth: i32,
+
+    #[serde(default)]
+    direction: WalkDirection,
+
+    #[serde(default = "default_min_confidence")]
+    min_confidence: i16,
+
+    #[serde(default = "default_include_needs_review")]
+    include_needs_review: bool,
 }
 
-#[derive(Serialize)]
+/// Why a node is considered PII-touching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiSignal {
+    /// `node_risk.pii` is set.
+    NodeRisk,
+    /// Metadata `classification = pii` or `pii = true`.
+    NodeMetadata,
+    /// An incident edge has metadata `contains_pii = true`.
+    EdgeMetadata,
+    /// A claim flow on an incident edge has a PII data category.
+    ClaimFlowCategory,
+    /// A typed flow on an incident edge carries a personal-data domain.
+    TypedFlowDomain,
+    /// Reached from a PII node by walking edges.
+    Lineage,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PiiReason {
+    pub signal: PiiSignal,
+    pub edge_id: Option<Uuid>,
+    /// Previous node on the walk, for `lineage`.
+    pub via_node_id: Option<Uuid>,
+    /// Metadata key, data category or domain name behind the signal.
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PiiNode {
+    pub node_id: Uuid,
+    pub name: String,
+    pub kind: String,
+    /// Hops from the nearest node with a direct signal; 0 for those.
+    pub depth: i32,
+    pub reasons: Vec<PiiReason>,
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct ComplianceResult {
+    /// Nodes with a direct signal.
     pub matched_pii_nodes: Vec<Uuid>,
     pub node_ids: Vec<Uuid>,
+    /// Edges carrying PII plus the edges walked.
     pub edge_ids: Vec<Uuid>,
+    pub nodes: Vec<PiiNode>,
+}
+
+/// Direct PII signals as `(node_id, signal, edge_id, detail)`. Edge-borne
+/// signals mark both endpoints. Only edges with a claim passing the filter
+/// ($1 statuses, $2 min confidence) count.
+const PII_SIGNALS_SQL: &str = r#"
+WITH RECURSIVE pd AS (
+    SELECT d.id, d.name FROM data_domains d WHERE d.name = 'Personaldata'
+    UNION ALL
+    SELECT c.id, c.name FROM data_domains c JOIN pd ON c.parent_id = pd.id
+),
+ok_edges AS (
+    SELECT e.*
+    FROM edges e
+    WHERE EXISTS (
+        SELECT 1 FROM edge_claims c
+        WHERE c.edge_id = e.id AND c.status = ANY($1) AND c.confidence >= $2
+    )
+),
+pii_nodes AS (
+    SELECT n.id, 'classification' AS detail
+    FROM nodes n
+    WHERE lower(n.metadata->>'classification') = 'pii'
+    UNION ALL
+    SELECT n.id, 'pii'
+    FROM nodes n
+    WHERE lower(n.metadata->>'pii') IN ('true', '1', 'yes', 'ja')
+),
+signals AS (
+    SELECT r.node_id, 'node_risk' AS signal, NULL::uuid AS edge_id, NULL::text AS detail
+    FROM node_risk r
+    WHERE r.pii
+
+    UNION ALL
+    SELECT p.id, 'node_metadata', NULL, p.detail
+    FROM pii_nodes p
+
+    UNION ALL
+    SELECT x.node_id, 'edge_metadata', e.id, 'contains_pii'
+    FROM ok_edges e
+    CROSS JOIN LATERAL (VALUES (e.from_id), (e.to_id)) x(node_id)
+    WHERE lower(e.metadata->>'contains_pii') IN ('true', '1', 'yes', 'ja')
+
+    UNION ALL
+    SELECT DISTINCT x.node_id, 'claim_flow_category', e.id, cat.name
+    FROM ok_edges e
+    JOIN edge_claims c ON c.edge_id = e.id AND c.status = ANY($1) AND c.confidence >= $2
+    JOIN edge_claim_flows f ON f.claim_id = c.id
+    JOIN nodes cat ON cat.id = f.data_category_id
+    LEFT JOIN node_risk cr ON cr.node_id = cat.id
+    CROSS JOIN LATERAL (VALUES (e.from_id), (e.to_id)) x(node_id)
+    WHERE cr.pii OR cat.id IN (SELECT id FROM pii_nodes)
+
+    UNION ALL
+    SELECT DISTINCT x.node_id, 'typed_flow_domain', e.id, pd.name
+    FROM pd
+    JOIN edge_typed_flow_domains fd ON fd.domain_id = pd.id
+    JOIN edge_typed_flows tf ON tf.id = fd.flow_id
+    JOIN ok_edges e ON e.id = tf.edge_id
+    CROSS JOIN LATERAL (VALUES (e.from_id), (e.to_id)) x(node_id)
+)
+SELECT s.node_id, s.signal, s.edge_id, s.detail
+FROM signals s
+JOIN nodes n ON n.id = s.node_id AND n.deleted_at IS NULL
+ORDER BY s.node_id, s.signal, s.detail
+"#;
+
+fn parse_signal(s: &str) -> PiiSignal {
+    match s {
+        "node_risk" => PiiSignal::NodeRisk,
+        "node_metadata" => PiiSignal::NodeMetadata,
+        "edge_metadata" => PiiSignal::EdgeMetadata,
+        "claim_flow_category" => PiiSignal::ClaimFlowCategory,
+        _ => PiiSignal::TypedFlowDomain,
+    }
 }
 
+#[utoipa::path(
+    get,
+    path = "/compliance/pii",
+    params(ComplianceQuery),
+    responses((status = 200, body = ComplianceResult), (status = 500, response = crate::openapi::ErrorResponse))
+)]
 async fn pii_flows(
     State(state): State<AppState>,
     Query(q): Query<ComplianceQuery>,
-) -> Json<ComplianceResult> {
-    let row = sqlx::query(
-        r#"
-        WITH pii AS (
-            SELECT id
-            FROM nodes
-            WHERE deleted_at IS NULL
-              AND LOWER(metadata->>'classification') = 'pii'
-        ),
-        RECURSIVE flow AS (
-            SELECT
-                e.id AS edge_id,
-                e.from_id AS source,
-                e.to_id AS target,
-                ARRAY[e.id] AS edge_path,
-                ARRAY[e.from_id, e.to_id] AS node_path,
-                1 AS depth
-            FROM edges e
-            JOIN pii p ON e.from_id = p.id
+) -> Result<Json<ComplianceResult>, ApiError> {
+    let depth = q.depth.clamp(0, 20);
+    let statuses: Vec<&str> = if q.include_needs_review {
+        vec!["active", "needs_review"]
+    } else {
+        vec!["active"]
+    };
 
-            UNION ALL
+    let signal_rows = sqlx::query(PII_SIGNALS_SQL)
+        .bind(&statuses)
+        .bind(q.min_confidence)
+        .fetch_all(&state.pool)
+        .await
+        .map_err(ApiError::internal)?;
+
+    let mut reasons: HashMap<Uuid, Vec<PiiReason>> = HashMap::new();
+    let mut edge_ids: HashSet<Uuid> = HashSet::new();
+    for r in signal_rows {
+        let node_id: Uuid = r.get("node_id");
+        let signal: String = r.get("signal");
+        let edge_id: Option<Uuid> = r.get("edge_id");
+        edge_ids.extend(edge_id);
+        reasons.entry(node_id).or_default().push(PiiReason {
+            signal: parse_signal(&signal),
+            edge_id,
+            via_node_id: None,
+            detail: r.get("detail"),
+        });
+    }
 
-            SELECT
-                e.id,
-                f.source,
-                e.to_id,
-                f.edge_path || e.id,
-                f.node_path || e.to_id,
-                f.depth + 1
-            FROM flow f
-            JOIN edges e ON e.from_id = f.target
-            WHERE f.depth < $1
-              AND NOT e.id = ANY(f.edge_path)
+    let mut matched_pii_nodes: Vec<Uuid> = reasons.keys().copied().collect();
+    matched_pii_nodes.sort();
+
+    let edge_rows = sqlx::query(
+        r#"
+        SELECT e.id, e.from_id, e.to_id
+        FROM edges e
+        JOIN nodes a ON a.id = e.from_id AND a.deleted_at IS NULL
+        JOIN nodes b ON b.id = e.to_id AND b.deleted_at IS NULL
+        WHERE EXISTS (
+            SELECT 1 FROM edge_claims c
+            WHERE c.edge_id = e.id AND c.status = ANY($1) AND c.confidence >= $2
         )
-        SELECT
-            COALESCE((SELECT array_agg(id) FROM pii), ARRAY[]::uuid[]) AS pii_nodes,
-            COALESCE(ARRAY(SELECT DISTINCT unnest(node_path)), ARRAY[]::uuid[]) AS node_ids,
-            COALESCE(ARRAY(SELECT DISTINCT unnest(edge_path)), ARRAY[]::uuid[]) AS edge_ids
-        FROM flow
-        LIMIT 1
         "#,
     )
-    .bind(q.depth)
-    .fetch_optional(&state.pool)
+    .bind(&statuses)
+    .bind(q.min_confidence)
+    .fetch_all(&state.pool)
     .await
-    .expect("compliance query failed");
+    .map_err(ApiError::internal)?;
+
+    let mut adjacency: HashMap<Uuid, Vec<(Uuid, Uuid)>> = HashMap::new();
+    for r in edge_rows {
+        let (id, from, to): (Uuid, Uuid, Uuid) = (r.get("id"), r.get("from_id"), r.get("to_id"));
+        if q.direction != WalkDirection::Upstream {
+            adjacency.entry(from).or_default().push((id, to));
+        }
+        if q.direction != WalkDirection::Downstream {
+            adjacency.entry(to).or_default().push((id, from));
+        }
+    }
 
-    if let Some(r) = row {
-        let matched_pii_nodes: Vec<Uuid> = r.try_get("pii_nodes").unwrap_or_default();
-        let node_ids: Vec<Uuid> = r.try_get("node_ids").unwrap_or_default();
-        let edge_ids: Vec<Uuid> = r.try_get("edge_ids").unwrap_or_default();
-        Json(ComplianceResult {
-            matched_pii_nodes,
-            node_ids,
-            edge_ids,
-        })
-    } else {
-        Json(ComplianceResult {
-            matched_pii_nodes: vec![],
-            node_ids: vec![],
-            edge_ids: vec![],
-        })
+    // Multi-source BFS, so each node is explained by its nearest PII node.
+    let mut depths: HashMap<Uuid, i32> = matched_pii_nodes.iter().map(|id| (*id, 0)).collect();
+    let mut queue: VecDeque<Uuid> = matched_pii_nodes.iter().copied().collect();
+    while let Some(node) = queue.pop_front() {
+        let d = depths[&node];
+        if d >= depth {
+            continue;
+        }
+        for (edge_id, next) in adjacency.get(&node).map(Vec::as_slice).unwrap_or(&[]) {
+            if depths.contains_key(next) {
+                continue;
+            }
+            depths.insert(*next, d + 1);
+            edge_ids.insert(*edge_id);
+            reasons.entry(*next).or_default().push(PiiReason {
+                signal: PiiSignal::Lineage,
+                edge_id: Some(*edge_id),
+                via_node_id: Some(node),
+                detail: None,
+            });
+            queue.push_back(*next);
+        }
     }
+
+    let node_ids: Vec<Uuid> = depths.keys().copied().collect();
+    let info = sqlx::query("SELECT id, name, kind FROM nodes WHERE id = ANY($1)")
+        .bind(&node_ids)
+        .fetch_all(&state.pool)
+        .await
+        .map_err(ApiError::internal)?;
+
+    let mut nodes: Vec<PiiNode> = info
+        .into_iter()
+        .map(|r| {
+            let node_id: Uuid = r.get("id");
+            PiiNode {
+                node_id,
+                name: r.get("name"),
+                kind: r.get("kind"),
+                depth: depths[&node_id],
+                reasons: reasons.remove(&node_id).unwrap_or_default(),
+            }
+        })
+        .collect();
+    nodes.sort_by(|a, b| (a.depth, &a.name, a.node_id).cmp(&(b.depth, &b.name, b.node_id)));
+
+    let mut edge_ids: Vec<Uuid> = edge_ids.into_iter().collect();
+    edge_ids.sort();
+
+    Ok(Json(ComplianceResult {
+        matched_pii_nodes,
+        node_ids: nodes.iter().map(|n| n.node_id).collect(),
+        edge_ids,
+        nodes,
+    }))
 }
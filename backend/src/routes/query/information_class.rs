@@ -0,0 +1,418 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::{
+    audit::{self, AuditAction, EntityType, RequestContext},
+    auth::AuthActor,
+    models::edge_claim::EdgeClaim,
+    routes::{edges::needs_review::reopen_for_review, AppState},
+};
+
+use crate::routes::edges::helpers::internal_error;
+
+use super::{default_include_needs_review, default_min_confidence};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingSeverity {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ClassRule {
+    /// Data flows to a node with a lower information class.
+    ClassDowngrade,
+    /// Classified data flows to a SaaS/PaaS supplier.
+    ExternalSupplier,
+    /// Classified data flows to a node without an information class.
+    UnclassifiedTarget,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ClassFinding {
+    pub rule: ClassRule,
+    pub severity: FindingSeverity,
+    pub edge_id: Uuid,
+    pub source_id: Uuid,
+    pub source_name: String,
+    pub source_class: Option<String>,
+    pub target_id: Uuid,
+    pub target_name: String,
+    pub target_class: Option<String>,
+    pub target_supplier_types: Vec<String>,
+    /// The edge already has an open `needs_review` claim.
+    pub open_review: bool,
+    pub message_sv: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ClassCheckResponse {
+    pub findings: Vec<ClassFinding>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ClassCheckQuery {
+    #[serde(default = "default_min_severity")]
+    min_severity: FindingSeverity,
+
+    #[serde(default = "default_min_confidence")]
+    min_confidence: i16,
+
+    #[serde(default = "default_include_needs_review")]
+    include_needs_review: bool,
+}
+
+fn default_min_severity() -> FindingSeverity {
+    FindingSeverity::Low
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RaiseReviewsBody {
+    /// Only findings at or above this severity raise a claim; default `high`.
+    #[serde(default)]
+    pub min_severity: Option<FindingSeverity>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RaiseReviewsResponse {
+    pub created: Vec<EdgeClaim>,
+    /// Offending edges that already had an open review.
+    pub skipped_edge_ids: Vec<Uuid>,
+}
+
+/// One directed data flow: typed flows give the direction, `flows_to`
+/// edges run `from_id` -> `to_id`.
+#[derive(sqlx::FromRow)]
+struct FlowRow {
+    edge_id: Uuid,
+    source_id: Uuid,
+    source_name: String,
+    source_class: Option<String>,
+    target_id: Uuid,
+    target_name: String,
+    target_class: Option<String>,
+    target_supplier_types: Vec<String>,
+    open_review: bool,
+}
+
+const FLOWS_SQL: &str = r#"
+WITH ok_edges AS (
+    SELECT e.id, e.kind, e.from_id, e.to_id
+    FROM edges e
+    WHERE EXISTS (
+        SELECT 1 FROM edge_claims c
+        WHERE c.edge_id = e.id AND c.status = ANY($1) AND c.confidence >= $2
+    )
+),
+pairs AS (
+    SELECT e.id AS edge_id, e.from_id AS src, e.to_id AS dst
+    FROM ok_edges e
+    JOIN edge_typed_flows f ON f.edge_id = e.id
+    WHERE f.direction IN ('fran', 'bidirectional')
+    UNION
+    SELECT e.id, e.to_id, e.from_id
+    FROM ok_edges e
+    JOIN edge_typed_flows f ON f.edge_id = e.id
+    WHERE f.direction IN ('till', 'bidirectional')
+    UNION
+    SELECT e.id, e.from_id, e.to_id
+    FROM ok_edges e
+    WHERE e.kind = 'flows_to'
+)
+SELECT
+    p.edge_id,
+    p.src AS source_id,
+    s.name AS source_name,
+    rs.information_class::text AS source_class,
+    p.dst AS target_id,
+    d.name AS target_name,
+    rd.information_class::text AS target_class,
+    ARRAY(
+        SELECT t.supplier_type::text FROM node_supplier_types t
+        WHERE t.node_id = p.dst
+        ORDER BY 1
+    ) AS target_supplier_types,
+    EXISTS (
+        SELECT 1 FROM edge_claims c
+        WHERE c.edge_id = p.edge_id AND c.status = 'needs_review'
+    ) AS open_review
+FROM pairs p
+JOIN nodes s ON s.id = p.src AND s.deleted_at IS NULL
+JOIN nodes d ON d.id = p.dst AND d.deleted_at IS NULL
+LEFT JOIN node_risk rs ON rs.node_id = p.src
+LEFT JOIN node_risk rd ON rd.node_id = p.dst
+ORDER BY s.name, d.name, p.edge_id
+"#;
+
+/// Class ordering, least to most sensitive.
+fn class_rank(class: &str) -> Option<i32> {
+    match class {
+        "oppen" => Some(0),
+        "intern" => Some(1),
+        "begransad" => Some(2),
+        "skyddad" => Some(3),
+        "konfidentiell" => Some(4),
+        _ => None,
+    }
+}
+
+type Rule = fn(&FlowRow) -> Option<(ClassRule, FindingSeverity, String)>;
+
+const RULES: &[Rule] = &[class_downgrade, external_supplier, unclassified_target];
+
+fn class_downgrade(f: &FlowRow) -> Option<(ClassRule, FindingSeverity, String)> {
+    let src = f.source_class.as_deref()?;
+    let dst = f.target_class.as_deref()?;
+    let drop = class_rank(src)? - class_rank(dst)?;
+    let severity = match drop {
+        d if d >= 3 => FindingSeverity::High,
+        2 => FindingSeverity::Medium,
+        1 => FindingSeverity::Low,
+        _ => return None,
+    };
+    Some((
+        ClassRule::ClassDowngrade,
+        severity,
+        format!(
+            "{} ({}) skickar data till {} med lägre informationsklass ({})",
+            f.source_name, src, f.target_name, dst
+        ),
+    ))
+}
+
+fn external_supplier(f: &FlowRow) -> Option<(ClassRule, FindingSeverity, String)> {
+    let src = f.source_class.as_deref()?;
+    let rank = class_rank(src)?;
+    let has = |t: &str| f.target_supplier_types.iter().any(|s| s == t);
+    let (severity, kind) = if rank >= 3 && has("saas") {
+        (FindingSeverity::High, "saas")
+    } else if rank >= 3 && has("paas") {
+        (FindingSeverity::Medium, "paas")
+    } else if rank == 2 && has("saas") {
+        (FindingSeverity::Medium, "saas")
+    } else {
+        return None;
+    };
+    Some((
+        ClassRule::ExternalSupplier,
+        severity,
+        format!(
+            "{} ({}) skickar data till {} som levereras som {}",
+            f.source_name, src, f.target_name, kind
+        ),
+    ))
+}
+
+fn unclassified_target(f: &FlowRow) -> Option<(ClassRule, FindingSeverity, String)> {
+    let src = f.source_class.as_deref()?;
+    if f.target_class.is_some() || class_rank(src)? < 2 {
+        return None;
+    }
+    Some((
+        ClassRule::UnclassifiedTarget,
+        FindingSeverity::Low,
+        format!(
+            "{} ({}) skickar data till {} som saknar informationsklass",
+            f.source_name, src, f.target_name
+        ),
+    ))
+}
+
+async fn evaluate(
+    pool: &PgPool,
+    min_severity: FindingSeverity,
+    min_confidence: i16,
+    include_needs_review: bool,
+) -> Result<Vec<ClassFinding>, ApiError> {
+    let statuses: Vec<&str> = if include_needs_review {
+        vec!["active", "needs_review"]
+    } else {
+        vec!["active"]
+    };
+
+    let flows = sqlx::query_as::<_, FlowRow>(FLOWS_SQL)
+        .bind(&statuses)
+        .bind(min_confidence)
+        .fetch_all(pool)
+        .await
+        .map_err(internal_error)?;
+
+    let mut findings = Vec::new();
+    for f in &flows {
+        for rule in RULES {
+            let Some((rule, severity, message_sv)) = rule(f) else {
+                continue;
+            };
+            if severity < min_severity {
+                continue;
+            }
+            findings.push(ClassFinding {
+                rule,
+                severity,
+                edge_id: f.edge_id,
+                source_id: f.source_id,
+                source_name: f.source_name.clone(),
+                source_class: f.source_class.clone(),
+                target_id: f.target_id,
+                target_name: f.target_name.clone(),
+                target_class: f.target_class.clone(),
+                target_supplier_types: f.target_supplier_types.clone(),
+                open_review: f.open_review,
+                message_sv,
+            });
+        }
+    }
+    findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+
+    Ok(findings)
+}
+
+#[utoipa::path(
+    get,
+    path = "/compliance/information-class",
+    params(ClassCheckQuery),
+    responses(
+        (status = 200, body = ClassCheckResponse),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub(super) async fn check_information_class(
+    State(state): State<AppState>,
+    Query(q): Query<ClassCheckQuery>,
+) -> Result<Json<ClassCheckResponse>, ApiError> {
+    let findings = evaluate(
+        &state.pool,
+        q.min_severity,
+        q.min_confidence,
+        q.include_needs_review,
+    )
+    .await?;
+
+    Ok(Json(ClassCheckResponse { findings }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/compliance/information-class/reviews",
+    request_body = RaiseReviewsBody,
+    responses(
+        (status = 200, body = RaiseReviewsResponse),
+        (status = 401, response = crate::openapi::ErrorResponse)
+    )
+)]
+pub(super) async fn raise_information_class_reviews(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    Json(body): Json<RaiseReviewsBody>,
+) -> Result<Json<RaiseReviewsResponse>, ApiError> {
+    let min_severity = body.min_severity.unwrap_or(FindingSeverity::High);
+    let findings = evaluate(&state.pool, min_severity, 0, true).await?;
+
+    let mut by_edge: BTreeMap<Uuid, Vec<&ClassFinding>> = BTreeMap::new();
+    for f in &findings {
+        by_edge.entry(f.edge_id).or_default().push(f);
+    }
+
+    let mut created = Vec::new();
+    let mut skipped_edge_ids = Vec::new();
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+
+    for (edge_id, edge_findings) in by_edge {
+        if edge_findings.iter().any(|f| f.open_review) {
+            skipped_edge_ids.push(edge_id);
+            continue;
+        }
+
+        // The proposal keeps the current claim's source, confidence,
+        // evidence and flows so approving it restores the edge as it was.
+        let current: Option<(Uuid, String, i16)> = sqlx::query_as(
+            r#"
+            SELECT id, source, confidence
+            FROM edge_claims
+            WHERE edge_id = $1 AND status = 'active'
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(edge_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+
+        let (source, confidence) = current
+            .as_ref()
+            .map(|(_, s, c)| (s.clone(), *c))
+            .unwrap_or_else(|| ("informationsklasskontroll".to_string(), 100));
+
+        let claim = reopen_for_review(
+            &mut tx,
+            edge_id,
+            &source,
+            confidence,
+            &actor.username,
+            current.as_ref().map(|(id, _, _)| *id),
+        )
+        .await?;
+
+        let note = edge_findings
+            .iter()
+            .map(|f| f.message_sv.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        sqlx::query(
+            r#"
+            INSERT INTO edge_claim_evidence (claim_id, evidence_type, reference, note)
+            VALUES ($1, 'other', 'informationsklasskontroll', $2)
+            "#,
+        )
+        .bind(claim.id)
+        .bind(&note)
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+
+        audit::write_audit(
+            &mut tx,
+            ctx,
+            Some(&actor),
+            EntityType::Edge,
+            edge_id,
+            AuditAction::Patch,
+            None,
+            Some(serde_json::json!({
+                "action": "edge_marked_needs_review",
+                "claim_id": claim.id,
+                "rule": "information_class",
+                "findings": edge_findings,
+            })),
+            None,
+        )
+        .await
+        .map_err(internal_error)?;
+
+        created.push(claim);
+    }
+
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(Json(RaiseReviewsResponse {
+        created,
+        skipped_edge_ids,
+    }))
+}
@@ -0,0 +1,307 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::get,
+    Extension, Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Json as SqlJson, PgPool};
+use time::OffsetDateTime;
+use utoipa::{OpenApi, ToSchema};
+use uuid::Uuid;
+
+use crate::auth::AuthActor;
+use crate::error::ApiError;
+use crate::routes::edges::helpers::{internal_error, map_sqlx_error};
+use crate::routes::export::filter_spec::FilterSpec;
+use crate::routes::search::NodeFilters;
+use crate::routes::AppState;
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct SavedSearch {
+    pub id: Uuid,
+    pub name: String,
+    pub owner: String,
+    /// Visible to every user, not just the owner.
+    pub shared: bool,
+    pub q: Option<String>,
+    #[schema(value_type = NodeFilters)]
+    pub facets: SqlJson<NodeFilters>,
+    /// Used as the export filter when an export is given `saved=<id>`.
+    #[schema(value_type = Option<FilterSpec>)]
+    pub filter: Option<SqlJson<FilterSpec>>,
+    #[schema(value_type = crate::openapi::Timestamp)]
+    pub created_at: OffsetDateTime,
+    #[schema(value_type = crate::openapi::Timestamp)]
+    pub updated_at: OffsetDateTime,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewSavedSearch {
+    pub name: String,
+    #[serde(default)]
+    pub shared: bool,
+    pub q: Option<String>,
+    #[serde(default)]
+    pub facets: NodeFilters,
+    pub filter: Option<FilterSpec>,
+}
+
+/// `filter: null` clears the stored filter; leaving it out keeps it.
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateSavedSearch {
+    pub name: Option<String>,
+    pub shared: Option<bool>,
+    pub q: Option<String>,
+    pub facets: Option<NodeFilters>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<FilterSpec>)]
+    pub filter: Option<Option<FilterSpec>>,
+}
+
+fn double_option<'de, D, T>(d: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(d).map(Some)
+}
+
+const COLUMNS: &str = "id, name, owner, shared, q, facets, filter, created_at, updated_at";
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    list_saved_searches,
+    create_saved_search,
+    get_saved_search,
+    update_saved_search,
+    delete_saved_search
+))]
+pub struct SavedSearchesApi;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_saved_searches).post(create_saved_search))
+        .route(
+            "/:id",
+            get(get_saved_search)
+                .patch(update_saved_search)
+                .delete(delete_saved_search),
+        )
+}
+
+fn not_found() -> ApiError {
+    ApiError::not_found("Sparad sökning finns inte")
+}
+
+fn clean_name(name: &str) -> Result<String, ApiError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ApiError::validation("name", "Namn saknas"));
+    }
+    Ok(name.to_string())
+}
+
+fn check_filter(filter: Option<&FilterSpec>) -> Result<(), ApiError> {
+    match filter {
+        Some(f) => f.validate().map_err(|e| ApiError::validation("filter", e)),
+        None => Ok(()),
+    }
+}
+
+fn clean_q(q: Option<String>) -> Option<String> {
+    q.map(|q| q.trim().to_string()).filter(|q| !q.is_empty())
+}
+
+/// A saved search the actor may read: their own, or a shared one.
+/// Anonymous callers only see shared searches.
+pub async fn load_visible(
+    pool: &PgPool,
+    id: Uuid,
+    actor: Option<&AuthActor>,
+) -> Result<SavedSearch, ApiError> {
+    let sql =
+        format!("SELECT {COLUMNS} FROM saved_searches WHERE id = $1 AND (owner = $2 OR shared)");
+    sqlx::query_as::<_, SavedSearch>(&sql)
+        .bind(id)
+        .bind(actor.map(|a| a.username.as_str()))
+        .fetch_optional(pool)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)
+}
+
+/// Only the owner may change or delete a saved search.
+async fn ensure_owner(pool: &PgPool, id: Uuid, actor: &AuthActor) -> Result<(), ApiError> {
+    let existing = load_visible(pool, id, Some(actor)).await?;
+    if existing.owner != actor.username {
+        return Err(ApiError::forbidden(
+            "Endast ägaren kan ändra en sparad sökning",
+        ));
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/",
+    responses((status = 200, body = Vec<SavedSearch>))
+)]
+async fn list_saved_searches(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AuthActor>,
+) -> Result<Json<Vec<SavedSearch>>, ApiError> {
+    let sql = format!(
+        r#"
+        SELECT {COLUMNS}
+        FROM saved_searches
+        WHERE owner = $1 OR shared
+        ORDER BY (owner = $1) DESC, name, id
+        "#
+    );
+    let rows = sqlx::query_as::<_, SavedSearch>(&sql)
+        .bind(&actor.username)
+        .fetch_all(&state.pool)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(rows))
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, body = SavedSearch),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
+async fn get_saved_search(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AuthActor>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<SavedSearch>, ApiError> {
+    Ok(Json(load_visible(&state.pool, id, Some(&actor)).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/",
+    request_body = NewSavedSearch,
+    responses(
+        (status = 201, body = SavedSearch),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse)
+    )
+)]
+async fn create_saved_search(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AuthActor>,
+    Json(body): Json<NewSavedSearch>,
+) -> Result<(StatusCode, Json<SavedSearch>), ApiError> {
+    let name = clean_name(&body.name)?;
+    check_filter(body.filter.as_ref())?;
+
+    let sql = format!(
+        r#"
+        INSERT INTO saved_searches (name, owner, shared, q, facets, filter)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING {COLUMNS}
+        "#
+    );
+    let row = sqlx::query_as::<_, SavedSearch>(&sql)
+        .bind(name)
+        .bind(&actor.username)
+        .bind(body.shared)
+        .bind(clean_q(body.q))
+        .bind(SqlJson(body.facets))
+        .bind(body.filter.map(SqlJson))
+        .fetch_one(&state.pool)
+        .await
+        .map_err(map_sqlx_error)?;
+
+    Ok((StatusCode::CREATED, Json(row)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/{id}",
+    params(("id" = Uuid, Path)),
+    request_body = UpdateSavedSearch,
+    responses(
+        (status = 200, body = SavedSearch),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 403, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse),
+        (status = 409, response = crate::openapi::ErrorResponse)
+    )
+)]
+async fn update_saved_search(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AuthActor>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<UpdateSavedSearch>,
+) -> Result<Json<SavedSearch>, ApiError> {
+    ensure_owner(&state.pool, id, &actor).await?;
+
+    let name = body.name.as_deref().map(clean_name).transpose()?;
+    check_filter(body.filter.as_ref().and_then(Option::as_ref))?;
+    let q_given = body.q.is_some();
+    let filter_given = body.filter.is_some();
+
+    let sql = format!(
+        r#"
+        UPDATE saved_searches
+        SET name = COALESCE($2, name),
+            shared = COALESCE($3, shared),
+            q = CASE WHEN $4 THEN $5 ELSE q END,
+            facets = COALESCE($6, facets),
+            filter = CASE WHEN $7 THEN $8 ELSE filter END,
+            updated_at = now()
+        WHERE id = $1
+        RETURNING {COLUMNS}
+        "#
+    );
+    let row = sqlx::query_as::<_, SavedSearch>(&sql)
+        .bind(id)
+        .bind(name)
+        .bind(body.shared)
+        .bind(q_given)
+        .bind(clean_q(body.q))
+        .bind(body.facets.map(SqlJson))
+        .bind(filter_given)
+        .bind(body.filter.flatten().map(SqlJson))
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(map_sqlx_error)?
+        .ok_or_else(not_found)?;
+
+    Ok(Json(row))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 204),
+        (status = 403, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
+async fn delete_saved_search(
+    State(state): State<AppState>,
+    Extension(actor): Extension<AuthActor>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    ensure_owner(&state.pool, id, &actor).await?;
+
+    sqlx::query("DELETE FROM saved_searches WHERE id = $1")
+        .bind(id)
+        .execute(&state.pool)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,4 +1,5 @@
 use axum::{routing::get, Json, Router};
+use utoipa::OpenApi;
 
 use crate::routes::AppState;
 
@@ -9,10 +10,19 @@ mod ui;
 #[allow(unused_imports)]
 pub use types::*;
 
+#[derive(OpenApi)]
+#[openapi(paths(kinds_handler))]
+pub struct SchemaApi;
+
 pub fn router() -> Router<AppState> {
     Router::<AppState>::new().route("/kinds", get(kinds_handler))
 }
 
+#[utoipa::path(
+    get,
+    path = "/kinds",
+    responses((status = 200, body = types::KindsResponse))
+)]
 async fn kinds_handler() -> Json<types::KindsResponse> {
     Json(kinds::kinds_response())
 }
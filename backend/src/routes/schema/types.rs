@@ -1,13 +1,14 @@
 use serde::Serialize;
+use utoipa::ToSchema;
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct RecommendedKey {
     pub key: String,
     pub description: Option<String>,
     pub examples: Option<Vec<String>>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum UiInputType {
     Text,
@@ -16,7 +17,7 @@ pub enum UiInputType {
     Select,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct UiFieldHint {
     pub key: String,
     pub label_sv: String,
@@ -29,7 +30,7 @@ pub struct UiFieldHint {
     pub help_sv: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct NodeKindInfo {
     pub kind: String,
     pub recommended_metadata_keys: Vec<RecommendedKey>,
@@ -37,7 +38,7 @@ pub struct NodeKindInfo {
     pub ui_hints: Option<Vec<UiFieldHint>>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct EdgeKindInfo {
     pub kind: String,
     pub recommended_metadata_keys: Vec<RecommendedKey>,
@@ -45,7 +46,7 @@ pub struct EdgeKindInfo {
     pub ui_hints: Option<Vec<UiFieldHint>>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct KindsResponse {
     pub node_kinds: Vec<NodeKindInfo>,
     pub edge_kinds: Vec<EdgeKindInfo>,
@@ -1,51 +1,595 @@
 use axum::{extract::Query, routing::get, Json, Router};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, OpenApi, ToSchema};
 use uuid::Uuid;
 
+use crate::error::ApiError;
 use crate::routes::AppState;
 
-#[derive(Deserialize)]
+/// Shared by every search query: `$1` is the raw query string.
+///
+/// `ts` ORs the Swedish and English parses so either stemming matches;
+/// `raw` is what the trigram side compares against (`node_search.search_text`
+/// is stored lowercased and unaccented), and `pat` is `raw` escaped for
+/// `LIKE ... ESCAPE '\'`, so `%` and `_` in a query match themselves.
+const QUERY_CTE: &str = r#"
+    q AS (
+        SELECT
+            websearch_to_tsquery('swedish', $1) || websearch_to_tsquery('english', $1) AS ts,
+            lower(unaccent($1)) AS raw,
+            replace(replace(replace(lower(unaccent($1)), '\', '\\'), '%', '\%'), '_', '\_') AS pat
+    )
+"#;
+
+/// Snippet markers; replaced with `<mark>` after the rest is HTML-escaped.
+const HIGHLIGHT: &str =
+    "StartSel=\u{2}, StopSel=\u{3}, MaxWords=18, MinWords=6, MaxFragments=2, FragmentDelimiter=\" … \"";
+
+/// Node match shared by hits and facet counts. Binds `$2..$7` are the facet
+/// filters in [`NodeFilters`] order.
+const NODE_MATCH: &str = r#"
+    FROM nodes n
+    JOIN node_search s ON s.node_id = n.id
+    CROSS JOIN q
+    WHERE n.deleted_at IS NULL
+      AND (
+        s.search_vector @@ q.ts
+        OR q.raw <% s.search_text
+        OR s.search_text LIKE '%' || q.pat || '%' ESCAPE '\'
+      )
+      AND ($2::text IS NULL OR n.kind = $2)
+      AND ($3::text IS NULL OR n.owning_department::text = $3)
+      AND ($4::text IS NULL OR COALESCE(
+            n.metadata->>'miljö', n.metadata->>'miljo',
+            n.metadata->>'env', n.metadata->>'environment'
+          ) = $4)
+      AND ($5::text IS NULL OR EXISTS (
+            SELECT 1 FROM node_risk r
+            WHERE r.node_id = n.id AND r.business_criticality::text = $5
+          ))
+      AND ($6::text IS NULL OR EXISTS (
+            SELECT 1 FROM node_risk r
+            WHERE r.node_id = n.id AND r.information_class::text = $6
+          ))
+      AND ($7::text IS NULL OR EXISTS (
+            SELECT 1 FROM node_suppliers ns
+            JOIN suppliers sp ON sp.id = ns.supplier_id
+            WHERE ns.node_id = n.id AND sp.name = $7
+          ))
+"#;
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct NodeSearchQuery {
     pub q: String,
     pub kind: Option<String>,
     pub limit: Option<i64>,
+    pub offset: Option<i64>,
 }
 
-#[derive(serde::Serialize)]
+#[derive(Serialize, ToSchema, sqlx::FromRow)]
 pub struct NodeSearchResult {
     pub id: Uuid,
     pub name: String,
     pub kind: String,
+    pub rank: f32,
+    /// HTML: matched terms wrapped in `<mark>`, everything else escaped.
+    pub snippet: String,
+}
+
+/// Facet drill-down; every filter narrows nodes only.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct NodeFilters {
+    pub kind: Option<String>,
+    pub owning_department: Option<String>,
+    pub env: Option<String>,
+    pub business_criticality: Option<String>,
+    pub information_class: Option<String>,
+    pub supplier: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    pub q: String,
+    /// Comma separated subset of `node,edge,evidence` (default: all).
+    pub types: Option<String>,
+    pub kind: Option<String>,
+    pub owning_department: Option<String>,
+    pub env: Option<String>,
+    pub business_criticality: Option<String>,
+    pub information_class: Option<String>,
+    pub supplier: Option<String>,
+    pub limit: Option<i64>,
+}
+
+impl SearchQuery {
+    fn filters(&self) -> NodeFilters {
+        let clean = |v: &Option<String>| {
+            v.as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        NodeFilters {
+            kind: clean(&self.kind),
+            owning_department: clean(&self.owning_department),
+            env: clean(&self.env),
+            business_criticality: clean(&self.business_criticality),
+            information_class: clean(&self.information_class),
+            supplier: clean(&self.supplier),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchEntity {
+    Node,
+    Edge,
+    Evidence,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SearchHit {
+    pub entity_type: SearchEntity,
+    pub id: Uuid,
+    /// Node kind, edge kind or evidence type.
+    pub kind: String,
+    pub title: String,
+    pub rank: f32,
+    /// HTML: matched terms wrapped in `<mark>`, everything else escaped.
+    pub snippet: String,
+    /// Evidence: the edge its claim belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edge_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_id: Option<Uuid>,
+}
+
+#[derive(Serialize, ToSchema, sqlx::FromRow)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: i64,
+}
+
+/// Counts over every matching node, not just the returned page.
+#[derive(Default, Serialize, ToSchema)]
+pub struct SearchFacets {
+    pub kind: Vec<FacetCount>,
+    pub owning_department: Vec<FacetCount>,
+    pub env: Vec<FacetCount>,
+    pub business_criticality: Vec<FacetCount>,
+    pub information_class: Vec<FacetCount>,
+    pub supplier: Vec<FacetCount>,
 }
 
+#[derive(Serialize, ToSchema)]
+pub struct SearchResponse {
+    pub q: String,
+    pub hits: Vec<SearchHit>,
+    pub facets: SearchFacets,
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(search, search_nodes))]
+pub struct SearchApi;
+
 pub fn router() -> Router<AppState> {
-    Router::new().route("/nodes", get(search_nodes))
+    Router::new()
+        .route("/", get(search))
+        .route("/nodes", get(search_nodes))
 }
 
+/// The trimmed query; shorter than 2 characters would match nearly
+/// everything through the substring and trigram sides.
+fn search_text(q: &str) -> Result<&str, ApiError> {
+    let text = q.trim();
+    if text.chars().count() < 2 {
+        return Err(ApiError::validation(
+            "q",
+            "Sökningen måste vara minst 2 tecken",
+        ));
+    }
+    Ok(text)
+}
+
+fn clamp_limit(limit: Option<i64>) -> i64 {
+    limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+}
+
+/// Escapes the headline text and turns the SQL-side markers into `<mark>`.
+fn render_snippet(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len() + 16);
+    for ch in raw.chars() {
+        match ch {
+            '\u{2}' => out.push_str("<mark>"),
+            '\u{3}' => out.push_str("</mark>"),
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[utoipa::path(
+    get,
+    path = "/nodes",
+    params(NodeSearchQuery),
+    responses(
+        (status = 200, body = Vec<NodeSearchResult>),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 500, response = crate::openapi::ErrorResponse)
+    )
+)]
 async fn search_nodes(
     axum::extract::State(state): axum::extract::State<AppState>,
     Query(q): Query<NodeSearchQuery>,
-) -> Result<Json<Vec<NodeSearchResult>>, (axum::http::StatusCode, String)> {
-    let limit = q.limit.unwrap_or(20).min(50);
+) -> Result<Json<Vec<NodeSearchResult>>, ApiError> {
+    let limit = clamp_limit(q.limit);
+    let offset = q.offset.unwrap_or(0).max(0);
 
-    let rows = sqlx::query_as!(
-        NodeSearchResult,
+    let filters = NodeFilters {
+        kind: q.kind.clone(),
+        ..Default::default()
+    };
+    let mut rows = query_nodes(&state, search_text(&q.q)?, &filters, limit, offset).await?;
+    for r in rows.iter_mut() {
+        r.snippet = render_snippet(&r.snippet);
+    }
+
+    Ok(Json(rows))
+}
+
+async fn query_nodes(
+    state: &AppState,
+    q: &str,
+    filters: &NodeFilters,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<NodeSearchResult>, ApiError> {
+    // Name hits first (exact, then prefix), then full-text rank plus trigram
+    // similarity. The substring match keeps type-ahead working on partial
+    // words that neither full-text nor trigram would catch.
+    let sql = format!(
+        r#"
+        WITH {QUERY_CTE}
+        SELECT
+            n.id,
+            n.name,
+            n.kind,
+            (
+                ts_rank_cd(s.search_vector, q.ts, 32)
+                + word_similarity(q.raw, s.search_text)
+                + CASE
+                    WHEN lower(unaccent(n.name)) = q.raw THEN 2
+                    WHEN lower(unaccent(n.name)) LIKE q.pat || '%' ESCAPE '\' THEN 1
+                    ELSE 0
+                  END
+            )::real AS rank,
+            ts_headline('swedish', s.document, q.ts, '{HIGHLIGHT}') AS snippet
+        {NODE_MATCH}
+        ORDER BY rank DESC, n.name
+        LIMIT $8 OFFSET $9
+        "#
+    );
+
+    bind_filters(sqlx::query_as::<_, NodeSearchResult>(&sql).bind(q), filters)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&state.pool)
+        .await
+        .map_err(ApiError::internal)
+}
+
+fn bind_filters<'q, O>(
+    query: sqlx::query::QueryAs<'q, sqlx::Postgres, O, sqlx::postgres::PgArguments>,
+    f: &'q NodeFilters,
+) -> sqlx::query::QueryAs<'q, sqlx::Postgres, O, sqlx::postgres::PgArguments> {
+    query
+        .bind(f.kind.as_deref())
+        .bind(f.owning_department.as_deref())
+        .bind(f.env.as_deref())
+        .bind(f.business_criticality.as_deref())
+        .bind(f.information_class.as_deref())
+        .bind(f.supplier.as_deref())
+}
+
+#[derive(sqlx::FromRow)]
+struct FacetRow {
+    facet: String,
+    value: String,
+    count: i64,
+}
+
+async fn query_facets(
+    state: &AppState,
+    q: &str,
+    filters: &NodeFilters,
+) -> Result<SearchFacets, ApiError> {
+    let sql = format!(
         r#"
-        SELECT id, name, kind
-        FROM nodes
-        WHERE deleted_at IS NULL
-          AND name ILIKE '%' || $1 || '%'
-          AND ($2::text IS NULL OR kind = $2)
-        ORDER BY name
-        LIMIT $3
-        "#,
-        q.q,
-        q.kind,
-        limit
+        WITH {QUERY_CTE},
+        m AS (
+            SELECT
+                n.id,
+                n.kind,
+                n.owning_department::text AS owning_department,
+                COALESCE(
+                    n.metadata->>'miljö', n.metadata->>'miljo',
+                    n.metadata->>'env', n.metadata->>'environment'
+                ) AS env
+            {NODE_MATCH}
+        )
+        SELECT 'kind' AS facet, kind AS value, count(*) AS count
+        FROM m GROUP BY 2
+        UNION ALL
+        SELECT 'owning_department', owning_department, count(*)
+        FROM m WHERE owning_department IS NOT NULL GROUP BY 2
+        UNION ALL
+        SELECT 'env', env, count(*)
+        FROM m WHERE env IS NOT NULL AND env <> '' GROUP BY 2
+        UNION ALL
+        SELECT 'business_criticality', r.business_criticality::text, count(*)
+        FROM m JOIN node_risk r ON r.node_id = m.id
+        WHERE r.business_criticality IS NOT NULL GROUP BY 2
+        UNION ALL
+        SELECT 'information_class', r.information_class::text, count(*)
+        FROM m JOIN node_risk r ON r.node_id = m.id
+        WHERE r.information_class IS NOT NULL GROUP BY 2
+        UNION ALL
+        SELECT 'supplier', sp.name, count(DISTINCT m.id)
+        FROM m
+        JOIN node_suppliers ns ON ns.node_id = m.id
+        JOIN suppliers sp ON sp.id = ns.supplier_id
+        GROUP BY 2
+        ORDER BY 1, 3 DESC, 2
+        "#
+    );
+
+    let rows = bind_filters(sqlx::query_as::<_, FacetRow>(&sql).bind(q), filters)
+        .fetch_all(&state.pool)
+        .await
+        .map_err(ApiError::internal)?;
+
+    let mut facets = SearchFacets::default();
+    for r in rows {
+        let bucket = match r.facet.as_str() {
+            "kind" => &mut facets.kind,
+            "owning_department" => &mut facets.owning_department,
+            "env" => &mut facets.env,
+            "business_criticality" => &mut facets.business_criticality,
+            "information_class" => &mut facets.information_class,
+            "supplier" => &mut facets.supplier,
+            _ => continue,
+        };
+        bucket.push(FacetCount {
+            value: r.value,
+            count: r.count,
+        });
+    }
+    Ok(facets)
+}
+
+#[derive(sqlx::FromRow)]
+struct EdgeHitRow {
+    id: Uuid,
+    kind: String,
+    from_id: Uuid,
+    to_id: Uuid,
+    from_name: String,
+    to_name: String,
+    rank: f32,
+    snippet: String,
+}
+
+async fn query_edges(state: &AppState, q: &str, limit: i64) -> Result<Vec<SearchHit>, ApiError> {
+    let sql = format!(
+        r#"
+        WITH {QUERY_CTE}
+        SELECT
+            e.id,
+            e.kind,
+            e.from_id,
+            e.to_id,
+            fn.name AS from_name,
+            tn.name AS to_name,
+            (ts_rank_cd(d.v, q.ts, 32) + word_similarity(q.raw, d.t))::real AS rank,
+            ts_headline('swedish', d.document, q.ts, '{HIGHLIGHT}') AS snippet
+        FROM edges e
+        JOIN nodes fn ON fn.id = e.from_id AND fn.deleted_at IS NULL
+        JOIN nodes tn ON tn.id = e.to_id AND tn.deleted_at IS NULL
+        CROSS JOIN q
+        CROSS JOIN LATERAL (
+            SELECT
+                doc AS document,
+                search_vector(doc, 'A') AS v,
+                lower(unaccent(doc)) AS t
+            FROM (
+                SELECT concat_ws(' — ', e.kind, NULLIF(jsonb_search_text(e.metadata), '')) AS doc
+            ) x
+        ) d
+        WHERE d.v @@ q.ts
+           OR q.raw <% d.t
+           OR d.t LIKE '%' || q.pat || '%' ESCAPE '\'
+        ORDER BY rank DESC
+        LIMIT $2
+        "#
+    );
+
+    let rows = sqlx::query_as::<_, EdgeHitRow>(&sql)
+        .bind(q)
+        .bind(limit)
+        .fetch_all(&state.pool)
+        .await
+        .map_err(ApiError::internal)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| SearchHit {
+            entity_type: SearchEntity::Edge,
+            id: r.id,
+            kind: r.kind,
+            title: format!("{} → {}", r.from_name, r.to_name),
+            rank: r.rank,
+            snippet: render_snippet(&r.snippet),
+            edge_id: None,
+            from_id: Some(r.from_id),
+            to_id: Some(r.to_id),
+        })
+        .collect())
+}
+
+#[derive(sqlx::FromRow)]
+struct EvidenceHitRow {
+    id: Uuid,
+    edge_id: Uuid,
+    evidence_type: String,
+    reference: String,
+    rank: f32,
+    snippet: String,
+}
+
+async fn query_evidence(state: &AppState, q: &str, limit: i64) -> Result<Vec<SearchHit>, ApiError> {
+    let sql = format!(
+        r#"
+        WITH {QUERY_CTE}
+        SELECT
+            ev.id,
+            c.edge_id,
+            ev.evidence_type,
+            ev.reference,
+            (
+                ts_rank_cd(search_vector(d.document, 'A'), q.ts, 32)
+                + similarity(lower(ev.reference), q.raw)
+            )::real AS rank,
+            ts_headline('swedish', d.document, q.ts, '{HIGHLIGHT}') AS snippet
+        FROM edge_claim_evidence ev
+        JOIN edge_claims c ON c.id = ev.claim_id
+        CROSS JOIN q
+        CROSS JOIN LATERAL (
+            SELECT concat_ws(' — ', ev.reference, ev.note) AS document
+        ) d
+        WHERE search_vector(d.document, 'A') @@ q.ts
+           OR q.raw <% lower(ev.reference)
+           OR lower(ev.reference) LIKE '%' || q.pat || '%' ESCAPE '\'
+        ORDER BY rank DESC
+        LIMIT $2
+        "#
+    );
+
+    let rows = sqlx::query_as::<_, EvidenceHitRow>(&sql)
+        .bind(q)
+        .bind(limit)
+        .fetch_all(&state.pool)
+        .await
+        .map_err(ApiError::internal)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| SearchHit {
+            entity_type: SearchEntity::Evidence,
+            id: r.id,
+            kind: r.evidence_type,
+            title: r.reference,
+            rank: r.rank,
+            snippet: render_snippet(&r.snippet),
+            edge_id: Some(r.edge_id),
+            from_id: None,
+            to_id: None,
+        })
+        .collect())
+}
+
+fn parse_types(types: Option<&str>) -> Result<Vec<SearchEntity>, ApiError> {
+    let Some(types) = types else {
+        return Ok(vec![
+            SearchEntity::Node,
+            SearchEntity::Edge,
+            SearchEntity::Evidence,
+        ]);
+    };
+
+    let mut out = Vec::new();
+    for t in types.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        let e = match t {
+            "node" => SearchEntity::Node,
+            "edge" => SearchEntity::Edge,
+            "evidence" => SearchEntity::Evidence,
+            other => {
+                return Err(ApiError::validation(
+                    "types",
+                    format!("Okänd typ '{other}' (tillåtna: node, edge, evidence)"),
+                ))
+            }
+        };
+        if !out.contains(&e) {
+            out.push(e);
+        }
+    }
+    Ok(out)
+}
+
+/// Ranked search over nodes, edges and claim evidence in one list.
+#[utoipa::path(
+    get,
+    path = "/",
+    params(SearchQuery),
+    responses(
+        (status = 200, body = SearchResponse),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 500, response = crate::openapi::ErrorResponse)
     )
-    .fetch_all(&state.pool)
-    .await
-    .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+)]
+async fn search(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    Query(q): Query<SearchQuery>,
+) -> Result<Json<SearchResponse>, ApiError> {
+    let text = search_text(&q.q)?;
 
-    Ok(Json(rows))
+    let limit = clamp_limit(q.limit);
+    let types = parse_types(q.types.as_deref())?;
+    let filters = q.filters();
+
+    let mut hits = Vec::new();
+    for t in types {
+        match t {
+            SearchEntity::Node => {
+                let nodes = query_nodes(&state, text, &filters, limit, 0).await?;
+                hits.extend(nodes.into_iter().map(|n| SearchHit {
+                    entity_type: SearchEntity::Node,
+                    id: n.id,
+                    kind: n.kind,
+                    title: n.name,
+                    rank: n.rank,
+                    snippet: render_snippet(&n.snippet),
+                    edge_id: None,
+                    from_id: None,
+                    to_id: None,
+                }));
+            }
+            SearchEntity::Edge => hits.extend(query_edges(&state, text, limit).await?),
+            SearchEntity::Evidence => hits.extend(query_evidence(&state, text, limit).await?),
+        }
+    }
+
+    hits.sort_by(|a, b| b.rank.total_cmp(&a.rank));
+    hits.truncate(limit as usize);
+
+    let facets = query_facets(&state, text, &filters).await?;
+
+    Ok(Json(SearchResponse {
+        q: text.to_string(),
+        hits,
+        facets,
+    }))
 }
@@ -0,0 +1,494 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::audit::{self, AuditAction, EntityType, RequestContext};
+use crate::auth::AuthActor;
+use crate::routes::edges::helpers::{internal_error, map_sqlx_error};
+use crate::routes::AppState;
+use crate::webhooks::{self, KNOWN_EVENTS};
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct WebhookSubscription {
+    pub id: Uuid,
+    pub name: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    pub created_by: Option<String>,
+    #[schema(value_type = crate::openapi::Timestamp)]
+    pub created_at: OffsetDateTime,
+    #[schema(value_type = crate::openapi::Timestamp)]
+    pub updated_at: OffsetDateTime,
+}
+
+/// Returned once on create; the secret is never readable afterwards.
+#[derive(Serialize, ToSchema)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub subscription: WebhookSubscription,
+    pub secret: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewWebhook {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub events: Vec<String>,
+    pub secret: Option<String>,
+    pub active: Option<bool>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateWebhook {
+    pub name: Option<String>,
+    pub url: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub outbox_id: Uuid,
+    pub event: String,
+    pub attempt: i32,
+    #[schema(value_type = crate::openapi::Timestamp)]
+    pub at: OffsetDateTime,
+    pub success: bool,
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+    /// Current outbox state: pending | delivered | failed
+    pub outbox_status: String,
+    #[schema(value_type = Option<crate::openapi::Timestamp>)]
+    pub next_attempt_at: Option<OffsetDateTime>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeliveriesQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TestDeliveryResponse {
+    pub outbox_id: Uuid,
+}
+
+const SUBSCRIPTION_COLUMNS: &str =
+    "id, name, url, events, active, created_by, created_at, updated_at";
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    list_webhooks,
+    create_webhook,
+    get_webhook,
+    update_webhook,
+    delete_webhook,
+    list_deliveries,
+    test_webhook
+))]
+pub struct WebhooksApi;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_webhooks).post(create_webhook))
+        .route(
+            "/:id",
+            get(get_webhook)
+                .patch(update_webhook)
+                .delete(delete_webhook),
+        )
+        .route("/:id/deliveries", get(list_deliveries))
+        .route("/:id/test", post(test_webhook))
+}
+
+async fn validate_url(state: &AppState, url: &str) -> Result<(), ApiError> {
+    state
+        .webhook_targets
+        .check(url)
+        .await
+        .map_err(|message| ApiError::validation("url", message))
+}
+
+fn audit_json(subscription: &WebhookSubscription) -> serde_json::Value {
+    serde_json::to_value(subscription).unwrap_or_default()
+}
+
+fn validate_events(events: &[String]) -> Result<Vec<String>, ApiError> {
+    let mut out: Vec<String> = Vec::new();
+    for e in events {
+        let e = e.trim();
+        if !KNOWN_EVENTS.contains(&e) {
+            return Err(ApiError::validation(
+                "events",
+                format!("Okänd händelse: {}", e),
+            ));
+        }
+        if !out.iter().any(|x| x == e) {
+            out.push(e.to_string());
+        }
+    }
+    Ok(out)
+}
+
+fn not_found() -> ApiError {
+    ApiError::not_found("Webhook finns inte")
+}
+
+fn generate_secret() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+#[utoipa::path(
+    get,
+    path = "/",
+    responses((status = 200, body = Vec<WebhookSubscription>))
+)]
+async fn list_webhooks(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<WebhookSubscription>>, ApiError> {
+    let sql = format!("SELECT {SUBSCRIPTION_COLUMNS} FROM webhook_subscriptions ORDER BY name, id");
+    let rows = sqlx::query_as::<_, WebhookSubscription>(&sql)
+        .fetch_all(&state.pool)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(rows))
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 200, body = WebhookSubscription),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
+async fn get_webhook(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<WebhookSubscription>, ApiError> {
+    let sql = format!("SELECT {SUBSCRIPTION_COLUMNS} FROM webhook_subscriptions WHERE id = $1");
+    let row = sqlx::query_as::<_, WebhookSubscription>(&sql)
+        .bind(id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(not_found)?;
+
+    Ok(Json(row))
+}
+
+#[utoipa::path(
+    post,
+    path = "/",
+    request_body = NewWebhook,
+    responses(
+        (status = 201, body = CreatedWebhook),
+        (status = 400, response = crate::openapi::ErrorResponse)
+    )
+)]
+async fn create_webhook(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    Json(body): Json<NewWebhook>,
+) -> Result<(StatusCode, Json<CreatedWebhook>), ApiError> {
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err(ApiError::bad_request("Namn saknas"));
+    }
+    validate_url(&state, &body.url).await?;
+    let events = validate_events(&body.events)?;
+
+    let secret = match body.secret.as_deref().map(str::trim) {
+        Some(s) if s.len() >= 16 => s.to_string(),
+        Some(_) => {
+            return Err(ApiError::bad_request("Hemligheten måste vara minst 16 tecken"))
+        }
+        None => generate_secret(),
+    };
+
+    let sql = format!(
+        r#"
+        INSERT INTO webhook_subscriptions (name, url, secret, events, active, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING {SUBSCRIPTION_COLUMNS}
+        "#
+    );
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let subscription = sqlx::query_as::<_, WebhookSubscription>(&sql)
+        .bind(name)
+        .bind(body.url.trim())
+        .bind(&secret)
+        .bind(&events)
+        .bind(body.active.unwrap_or(true))
+        .bind(&actor.username)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(map_sqlx_error)?;
+
+    audit::write_audit(
+        &mut tx,
+        ctx,
+        Some(&actor),
+        EntityType::Webhook,
+        subscription.id,
+        AuditAction::Create,
+        None,
+        None,
+        Some(audit_json(&subscription)),
+    )
+    .await
+    .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedWebhook {
+            subscription,
+            secret,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/{id}",
+    params(("id" = Uuid, Path)),
+    request_body = UpdateWebhook,
+    responses(
+        (status = 200, body = WebhookSubscription),
+        (status = 400, response = crate::openapi::ErrorResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
+async fn update_webhook(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<UpdateWebhook>,
+) -> Result<Json<WebhookSubscription>, ApiError> {
+    if let Some(name) = body.name.as_deref() {
+        if name.trim().is_empty() {
+            return Err(ApiError::bad_request("Namn saknas"));
+        }
+    }
+    if let Some(url) = body.url.as_deref() {
+        validate_url(&state, url).await?;
+    }
+    let events = body.events.as_deref().map(validate_events).transpose()?;
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let before = sqlx::query_as::<_, WebhookSubscription>(&format!(
+        "SELECT {SUBSCRIPTION_COLUMNS} FROM webhook_subscriptions WHERE id = $1 FOR UPDATE"
+    ))
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(internal_error)?
+    .ok_or_else(not_found)?;
+
+    let sql = format!(
+        r#"
+        UPDATE webhook_subscriptions
+        SET name = COALESCE($2, name),
+            url = COALESCE($3, url),
+            events = COALESCE($4, events),
+            active = COALESCE($5, active),
+            updated_at = now()
+        WHERE id = $1
+        RETURNING {SUBSCRIPTION_COLUMNS}
+        "#
+    );
+    let row = sqlx::query_as::<_, WebhookSubscription>(&sql)
+        .bind(id)
+        .bind(body.name.as_deref().map(str::trim))
+        .bind(body.url.as_deref().map(str::trim))
+        .bind(events)
+        .bind(body.active)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(map_sqlx_error)?
+        .ok_or_else(not_found)?;
+
+    audit::write_audit(
+        &mut tx,
+        ctx,
+        Some(&actor),
+        EntityType::Webhook,
+        id,
+        AuditAction::Patch,
+        Some(audit_json(&before)),
+        None,
+        Some(audit_json(&row)),
+    )
+    .await
+    .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(Json(row))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 204),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
+async fn delete_webhook(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let deleted = sqlx::query_as::<_, WebhookSubscription>(&format!(
+        "DELETE FROM webhook_subscriptions WHERE id = $1 RETURNING {SUBSCRIPTION_COLUMNS}"
+    ))
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(internal_error)?
+    .ok_or_else(not_found)?;
+
+    audit::write_audit(
+        &mut tx,
+        ctx,
+        Some(&actor),
+        EntityType::Webhook,
+        id,
+        AuditAction::Delete,
+        Some(audit_json(&deleted)),
+        None,
+        None,
+    )
+    .await
+    .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}/deliveries",
+    params(("id" = Uuid, Path), DeliveriesQuery),
+    responses(
+        (status = 200, body = Vec<WebhookDelivery>),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
+async fn list_deliveries(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(q): Query<DeliveriesQuery>,
+) -> Result<Json<Vec<WebhookDelivery>>, ApiError> {
+    let limit = q.limit.unwrap_or(50).clamp(1, 500);
+
+    let (exists,): (bool,) =
+        sqlx::query_as("SELECT EXISTS(SELECT 1 FROM webhook_subscriptions WHERE id = $1)")
+            .bind(id)
+            .fetch_one(&state.pool)
+            .await
+            .map_err(internal_error)?;
+    if !exists {
+        return Err(not_found());
+    }
+
+    let rows = sqlx::query_as::<_, WebhookDelivery>(
+        r#"
+        SELECT
+          d.id,
+          d.outbox_id,
+          d.event,
+          d.attempt,
+          d.at,
+          d.success,
+          d.status_code,
+          d.error,
+          d.duration_ms,
+          o.status AS outbox_status,
+          CASE WHEN o.status = 'pending' THEN o.next_attempt_at END AS next_attempt_at
+        FROM webhook_deliveries d
+        JOIN webhook_outbox o ON o.id = d.outbox_id
+        WHERE d.subscription_id = $1
+        ORDER BY d.at DESC, d.id
+        LIMIT $2
+        "#,
+    )
+    .bind(id)
+    .bind(limit)
+    .fetch_all(&state.pool)
+    .await
+    .map_err(internal_error)?;
+
+    Ok(Json(rows))
+}
+
+#[utoipa::path(
+    post,
+    path = "/{id}/test",
+    params(("id" = Uuid, Path)),
+    responses(
+        (status = 202, body = TestDeliveryResponse),
+        (status = 404, response = crate::openapi::ErrorResponse)
+    )
+)]
+async fn test_webhook(
+    State(state): State<AppState>,
+    Extension(ctx): Extension<RequestContext>,
+    Extension(actor): Extension<AuthActor>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<TestDeliveryResponse>), ApiError> {
+    let (exists,): (bool,) =
+        sqlx::query_as("SELECT EXISTS(SELECT 1 FROM webhook_subscriptions WHERE id = $1)")
+            .bind(id)
+            .fetch_one(&state.pool)
+            .await
+            .map_err(internal_error)?;
+    if !exists {
+        return Err(not_found());
+    }
+
+    let mut tx = state.pool.begin().await.map_err(internal_error)?;
+    let outbox_id = webhooks::enqueue_test(&mut tx, id)
+        .await
+        .map_err(internal_error)?;
+
+    audit::write_audit(
+        &mut tx,
+        ctx,
+        Some(&actor),
+        EntityType::Webhook,
+        id,
+        AuditAction::Patch,
+        None,
+        Some(serde_json::json!({
+            "action": "webhook_test_queued",
+            "outbox_id": outbox_id
+        })),
+        None,
+    )
+    .await
+    .map_err(internal_error)?;
+    tx.commit().await.map_err(internal_error)?;
+
+    Ok((StatusCode::ACCEPTED, Json(TestDeliveryResponse { outbox_id })))
+}
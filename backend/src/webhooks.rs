@@ -0,0 +1,532 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use sqlx::{PgConnection, PgPool};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::audit::{AuditAction, EntityType, RequestContext};
+use crate::auth::AuthActor;
+
+pub const EVENT_NODE_CRITICAL_DELETED: &str = "node.critical_deleted";
+pub const EVENT_CLAIM_NEEDS_REVIEW: &str = "claim.needs_review";
+pub const EVENT_IMPORT_COMPLETED: &str = "import.completed";
+/// Only sent by `POST /webhooks/:id/test`, never matched by filters.
+pub const EVENT_TEST: &str = "webhook.test";
+
+pub const KNOWN_EVENTS: &[&str] = &[
+    EVENT_NODE_CRITICAL_DELETED,
+    EVENT_CLAIM_NEEDS_REVIEW,
+    EVENT_IMPORT_COMPLETED,
+];
+
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+const BATCH_SIZE: i64 = 20;
+const BACKOFF_BASE_SECONDS: i64 = 30;
+const BACKOFF_MAX_SECONDS: i64 = 60 * 60;
+const ERROR_BODY_MAX: usize = 500;
+
+#[derive(Debug, Clone)]
+pub struct WorkerConfig {
+    pub poll_interval: Duration,
+    pub max_attempts: i32,
+    pub request_timeout: Duration,
+    pub targets: TargetPolicy,
+}
+
+/// Where subscriptions may point. A URL whose host resolves to a loopback,
+/// private, link-local or otherwise non-public address is refused unless
+/// the host is listed in `WEBHOOK_ALLOWED_HOSTS`; otherwise any token holder
+/// could have the server POST into its own network. Checked when a
+/// subscription is saved and again before every send, since DNS answers
+/// can change in between. The delivery client resolves through
+/// [`PolicyResolver`], so the addresses it connects to are the ones that
+/// passed the check, not a second answer from a rebinding name.
+#[derive(Debug, Clone, Default)]
+pub struct TargetPolicy {
+    /// Lower-case host names or IP literals.
+    pub allowed_hosts: Vec<String>,
+}
+
+impl TargetPolicy {
+    /// `Err` carries a message for the user.
+    pub async fn check(&self, url: &str) -> Result<(), String> {
+        let url = reqwest::Url::parse(url.trim())
+            .map_err(|_| "URL måste börja med http:// eller https://".to_string())?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err("URL måste börja med http:// eller https://".to_string());
+        }
+        let Some(host) = url.host_str() else {
+            return Err("URL saknar värd".to_string());
+        };
+        let bare = host.trim_start_matches('[').trim_end_matches(']').to_lowercase();
+        if self.allowed_hosts.contains(&bare) {
+            return Ok(());
+        }
+
+        match bare.parse::<IpAddr>() {
+            Ok(ip) => self.check_addrs(&bare, &[ip]),
+            Err(_) => {
+                let port = url.port_or_known_default().unwrap_or(443);
+                self.resolve(&bare, port).await.map(|_| ())
+            }
+        }
+    }
+
+    /// Looks `host` up and returns its addresses if every one is allowed.
+    async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|_| format!("Värden '{host}' kan inte slås upp"))?
+            .collect();
+        let ips: Vec<IpAddr> = addrs.iter().map(SocketAddr::ip).collect();
+        self.check_addrs(host, &ips)?;
+        Ok(addrs)
+    }
+
+    fn check_addrs(&self, host: &str, addrs: &[IpAddr]) -> Result<(), String> {
+        if addrs.is_empty() {
+            return Err(format!("Värden '{host}' kan inte slås upp"));
+        }
+        if self.allowed_hosts.iter().any(|h| h == host) {
+            return Ok(());
+        }
+        match addrs.iter().find(|ip| !is_public(**ip)) {
+            Some(ip) => Err(format!("Adressen {ip} är inte tillåten som webhook-mål")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// DNS for the delivery client: the same lookup and address check as
+/// [`TargetPolicy::check`], done at connect time so the connection goes to
+/// an address that was checked.
+struct PolicyResolver(TargetPolicy);
+
+impl reqwest::dns::Resolve for PolicyResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let policy = self.0.clone();
+        let host = name.as_str().to_lowercase();
+        Box::pin(async move {
+            // reqwest puts the URL's port on the addresses afterwards.
+            let addrs = policy.resolve(&host, 0).await?;
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                || a == 0
+                || a >= 240
+                // Carrier-grade NAT and benchmarking ranges.
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 198 && (b == 18 || b == 19)))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // Unique local (fc00::/7), link-local (fe80::/10), documentation.
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first == 0x2001 && v6.segments()[1] == 0x0db8))
+        }
+    }
+}
+
+fn envelope(event: &str, data: Value) -> Value {
+    json!({
+        "event": event,
+        "occurred_at": OffsetDateTime::now_utc().format(&Rfc3339).unwrap_or_default(),
+        "data": data,
+    })
+}
+
+/// Queues `event` for every active subscription whose filter matches.
+/// Runs on the caller's connection so the outbox row commits with the change.
+pub async fn enqueue(conn: &mut PgConnection, event: &str, data: Value) -> Result<u64, sqlx::Error> {
+    let res = sqlx::query(
+        r#"
+        INSERT INTO webhook_outbox (subscription_id, event, payload)
+        SELECT id, $1, $2
+        FROM webhook_subscriptions
+        WHERE active AND (cardinality(events) = 0 OR $1 = ANY(events))
+        "#,
+    )
+    .bind(event)
+    .bind(envelope(event, data))
+    .execute(conn)
+    .await?;
+
+    Ok(res.rows_affected())
+}
+
+/// Queues a `webhook.test` delivery for a single subscription.
+pub async fn enqueue_test(
+    conn: &mut PgConnection,
+    subscription_id: Uuid,
+) -> Result<Uuid, sqlx::Error> {
+    let data = json!({ "subscription_id": subscription_id });
+    let (id,): (Uuid,) = sqlx::query_as(
+        r#"
+        INSERT INTO webhook_outbox (subscription_id, event, payload)
+        VALUES ($1, $2, $3)
+        RETURNING id
+        "#,
+    )
+    .bind(subscription_id)
+    .bind(EVENT_TEST)
+    .bind(envelope(EVENT_TEST, data))
+    .fetch_one(conn)
+    .await?;
+
+    Ok(id)
+}
+
+fn truthy(v: &Value) -> bool {
+    match v {
+        Value::Bool(b) => *b,
+        Value::String(s) => matches!(
+            s.trim().to_lowercase().as_str(),
+            "true" | "ja" | "yes" | "1"
+        ),
+        Value::Number(n) => n.as_i64() == Some(1),
+        _ => false,
+    }
+}
+
+fn is_critical_node(node: &Value) -> bool {
+    let Some(meta) = node.get("metadata").and_then(|m| m.as_object()) else {
+        return false;
+    };
+    ["critical", "kritisk", "is_critical"]
+        .iter()
+        .any(|k| meta.get(*k).map(truthy).unwrap_or(false))
+}
+
+/// Derives webhook events from an audit row; called by `write_audit`.
+#[allow(clippy::too_many_arguments)]
+pub async fn enqueue_for_audit(
+    conn: &mut PgConnection,
+    ctx: RequestContext,
+    actor: Option<&AuthActor>,
+    entity_type: EntityType,
+    entity_id: Uuid,
+    action: AuditAction,
+    before: Option<&Value>,
+    patch: Option<&Value>,
+) -> Result<(), sqlx::Error> {
+    let actor_username = actor.map(|a| a.username.clone());
+
+    if let (EntityType::Node, AuditAction::Delete) = (entity_type, action) {
+        let meta_critical = before.map(is_critical_node).unwrap_or(false);
+        let (risk_high,): (bool,) = sqlx::query_as(
+            r#"
+            SELECT EXISTS(
+              SELECT 1 FROM node_risk
+              WHERE node_id = $1 AND business_criticality = 'high'
+            )
+            "#,
+        )
+        .bind(entity_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if meta_critical || risk_high {
+            let data = json!({
+                "node_id": entity_id,
+                "kind": before.and_then(|b| b.get("kind")).cloned(),
+                "name": before.and_then(|b| b.get("name")).cloned(),
+                "deleted_by": actor_username,
+                "correlation_id": ctx.request_id,
+            });
+            enqueue(&mut *conn, EVENT_NODE_CRITICAL_DELETED, data).await?;
+        }
+    }
+
+    let patch_action = patch.and_then(|p| p.get("action")).and_then(|a| a.as_str());
+    let patch_status = patch.and_then(|p| p.get("status")).and_then(|s| s.as_str());
+
+    let needs_review = match patch_action {
+        Some("edge_marked_needs_review")
+        | Some("node_marked_needs_review")
+        | Some("proposal_created") => true,
+        Some("claim_created") | Some("node_claim_created") => patch_status == Some("needs_review"),
+        _ => false,
+    };
+
+    if needs_review {
+        let data = json!({
+            "entity_type": entity_type.as_str(),
+            "entity_id": entity_id,
+            "claim_id": patch.and_then(|p| p.get("claim_id")).cloned(),
+            "import_batch_id": patch.and_then(|p| p.get("import_batch_id")).cloned(),
+            "action": patch_action,
+            "actor": actor_username,
+            "correlation_id": ctx.request_id,
+        });
+        enqueue(&mut *conn, EVENT_CLAIM_NEEDS_REVIEW, data).await?;
+    }
+
+    Ok(())
+}
+
+/// `hex(HMAC-SHA256(secret, "<timestamp>.<body>"))`
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn backoff_seconds(attempt: i32) -> i64 {
+    let exp = (attempt - 1).clamp(0, 16) as u32;
+    (BACKOFF_BASE_SECONDS * 2_i64.pow(exp)).min(BACKOFF_MAX_SECONDS)
+}
+
+#[derive(sqlx::FromRow)]
+struct DueDelivery {
+    id: Uuid,
+    subscription_id: Uuid,
+    event: String,
+    payload: Value,
+    attempts: i32,
+    url: String,
+    secret: String,
+}
+
+struct AttemptResult {
+    status_code: Option<i32>,
+    error: Option<String>,
+    duration_ms: i64,
+}
+
+impl AttemptResult {
+    fn success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+async fn send(client: &reqwest::Client, d: &DueDelivery) -> AttemptResult {
+    let started = Instant::now();
+    let body = serde_json::to_vec(&d.payload).unwrap_or_default();
+    let ts = OffsetDateTime::now_utc().unix_timestamp();
+    let signature = sign(&d.secret, ts, &body);
+
+    let res = client
+        .post(&d.url)
+        .header("content-type", "application/json")
+        .header("x-webhook-id", d.id.to_string())
+        .header("x-webhook-event", &d.event)
+        .header("x-webhook-timestamp", ts.to_string())
+        .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+        .body(body)
+        .send()
+        .await;
+
+    let (status_code, error) = match res {
+        Ok(r) if r.status().is_success() => (Some(r.status().as_u16() as i32), None),
+        Ok(r) => {
+            let status = r.status();
+            let text: String = r
+                .text()
+                .await
+                .unwrap_or_default()
+                .chars()
+                .take(ERROR_BODY_MAX)
+                .collect();
+            (Some(status.as_u16() as i32), Some(format!("HTTP {}: {}", status, text)))
+        }
+        Err(e) => (None, Some(e.to_string())),
+    };
+
+    AttemptResult {
+        status_code,
+        error,
+        duration_ms: started.elapsed().as_millis() as i64,
+    }
+}
+
+/// Claims up to `BATCH_SIZE` due rows in one short statement. Claimed rows
+/// stay `pending` but get `next_attempt_at` pushed past the time the whole
+/// batch may take to send (a lease): other workers skip them, and if this
+/// one dies mid-batch they simply come due again.
+async fn claim_due(pool: &PgPool, cfg: &WorkerConfig) -> Result<Vec<DueDelivery>, sqlx::Error> {
+    let lease_seconds = cfg.request_timeout.as_secs_f64() * BATCH_SIZE as f64 + 60.0;
+
+    sqlx::query_as(
+        r#"
+        WITH due AS (
+          SELECT o.id
+          FROM webhook_outbox o
+          JOIN webhook_subscriptions s ON s.id = o.subscription_id
+          WHERE o.status = 'pending'
+            AND o.next_attempt_at <= now()
+            AND s.active
+          ORDER BY o.next_attempt_at
+          LIMIT $1
+          FOR UPDATE OF o SKIP LOCKED
+        )
+        UPDATE webhook_outbox o
+        SET next_attempt_at = now() + make_interval(secs => $2)
+        FROM due, webhook_subscriptions s
+        WHERE o.id = due.id
+          AND s.id = o.subscription_id
+        RETURNING o.id, o.subscription_id, o.event, o.payload, o.attempts, s.url, s.secret
+        "#,
+    )
+    .bind(BATCH_SIZE)
+    .bind(lease_seconds)
+    .fetch_all(pool)
+    .await
+}
+
+/// Logs one attempt and moves the outbox row on, in its own transaction so
+/// a failure here does not undo the bookkeeping of other deliveries.
+async fn record_attempt(
+    pool: &PgPool,
+    cfg: &WorkerConfig,
+    d: &DueDelivery,
+    attempt: i32,
+    r: &AttemptResult,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO webhook_deliveries (
+          outbox_id, subscription_id, event, attempt, success, status_code, error, duration_ms
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+    )
+    .bind(d.id)
+    .bind(d.subscription_id)
+    .bind(&d.event)
+    .bind(attempt)
+    .bind(r.success())
+    .bind(r.status_code)
+    .bind(&r.error)
+    .bind(r.duration_ms)
+    .execute(&mut *tx)
+    .await?;
+
+    let status = if r.success() {
+        "delivered"
+    } else if attempt >= cfg.max_attempts {
+        "failed"
+    } else {
+        "pending"
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE webhook_outbox
+        SET status = $2,
+            attempts = $3,
+            last_error = $4,
+            next_attempt_at = now() + make_interval(secs => $5),
+            delivered_at = CASE WHEN $2 = 'delivered' THEN now() ELSE NULL END
+        WHERE id = $1
+        "#,
+    )
+    .bind(d.id)
+    .bind(status)
+    .bind(attempt)
+    .bind(&r.error)
+    .bind(backoff_seconds(attempt) as f64)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await
+}
+
+/// Delivers one batch of due outbox rows. Returns how many were attempted.
+/// No transaction is open while a request is in flight.
+async fn deliver_due(
+    pool: &PgPool,
+    client: &reqwest::Client,
+    cfg: &WorkerConfig,
+) -> Result<usize, sqlx::Error> {
+    let due = claim_due(pool, cfg).await?;
+
+    for d in &due {
+        let attempt = d.attempts + 1;
+        let r = match cfg.targets.check(&d.url).await {
+            Ok(()) => send(client, d).await,
+            Err(error) => AttemptResult {
+                status_code: None,
+                error: Some(error),
+                duration_ms: 0,
+            },
+        };
+
+        if !r.success() {
+            tracing::warn!(
+                "webhook delivery {} to {} failed (attempt {}): {}",
+                d.id,
+                d.url,
+                attempt,
+                r.error.as_deref().unwrap_or_default()
+            );
+        }
+
+        // The row keeps its lease if this fails and is retried later.
+        if let Err(e) = record_attempt(pool, cfg, d, attempt, &r).await {
+            tracing::warn!("webhook delivery {}: could not record attempt: {}", d.id, e);
+        }
+    }
+
+    Ok(due.len())
+}
+
+/// Polls the outbox and delivers pending rows until shutdown.
+pub fn spawn_worker(pool: PgPool, cfg: WorkerConfig) {
+    tokio::spawn(async move {
+        let client = match reqwest::Client::builder()
+            .timeout(cfg.request_timeout)
+            .user_agent("infra-graph-webhooks")
+            // A redirect could lead anywhere; the target check covers only the URL.
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(PolicyResolver(cfg.targets.clone())))
+            .build()
+        {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::error!("webhook worker disabled: {}", e);
+                return;
+            }
+        };
+
+        loop {
+            match deliver_due(&pool, &client, &cfg).await {
+                Ok(n) if n > 0 => continue,
+                Ok(_) => {}
+                Err(e) => tracing::warn!("webhook worker error: {}", e),
+            }
+            tokio::time::sleep(cfg.poll_interval).await;
+        }
+    });
+}
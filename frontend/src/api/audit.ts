@@ -1,4 +1,5 @@
 import { getAuthToken } from "@/api/client";
+import { readErrorText } from "./http";
 
 export type AuditLogEntry = {
   id: string;
@@ -44,7 +45,7 @@ async function jsonFetch<T>(path: string): Promise<T> {
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(`${res.status} ${res.statusText}${text ? `: ${text}` : ""}`);
   }
 
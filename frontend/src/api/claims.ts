@@ -1,4 +1,5 @@
 import { getAuthToken } from "./client";
+import { readErrorText } from "./http";
 
 function withAuthHeaders(headers?: HeadersInit): HeadersInit {
   const token = getAuthToken();
@@ -32,7 +33,7 @@ async function jsonFetch<T>(path: string, init?: RequestInit): Promise<T> {
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(`${res.status} ${res.statusText}${text ? `: ${text}` : ""}`);
   }
 
@@ -5,6 +5,7 @@ import type {
   PathResult,
   SchemaKindsResponse,
 } from "./types";
+import { readErrorText } from "./http";
 
 const AUTH_TOKEN_KEY = "ig_auth_token";
 const API_BASE = "/api";
@@ -64,7 +65,7 @@ async function jsonFetch<T>(path: string, init?: RequestInit): Promise<T> {
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(`${res.status} ${res.statusText}${text ? `: ${text}` : ""}`);
   }
 
@@ -122,6 +123,9 @@ export type NodeSearchResult = {
   id: string;
   name: string;
   kind: string;
+  rank: number;
+  /** HTML with matches wrapped in <mark>; all other text is escaped. */
+  snippet: string;
 };
 
 export function searchNodes(q: string, kind?: string): Promise<NodeSearchResult[]> {
@@ -160,7 +164,7 @@ export async function deleteNode(nodeId: string, ifMatch: string): Promise<void>
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(`${res.status} ${res.statusText}${text ? `: ${text}` : ""}`);
   }
 }
@@ -199,7 +203,7 @@ export async function deleteEdge(edgeId: string, ifMatch: string): Promise<void>
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(`${res.status} ${res.statusText}${text ? `: ${text}` : ""}`);
   }
 }
@@ -221,7 +225,7 @@ export async function loginLocal(username: string, password: string): Promise<Au
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(`${res.status} ${res.statusText}${text ? `: ${text}` : ""}`);
   }
 
@@ -237,7 +241,7 @@ export async function fetchMe(): Promise<AuthMeResponse> {
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(`${res.status} ${res.statusText}${text ? `: ${text}` : ""}`);
   }
 
@@ -1,3 +1,5 @@
+import { readErrorText } from "./http";
+
 export type DataDomainRow = {
   id: string;
   name: string;
@@ -23,7 +25,7 @@ async function jsonFetch<T>(path: string, init?: RequestInit): Promise<T> {
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(`${res.status} ${res.statusText}${text ? `: ${text}` : ""}`);
   }
 
@@ -1,5 +1,6 @@
 import { getAuthToken } from "./client";
 import type { EdgeClaimFlow, FlowDirection } from "./types";
+import { readErrorText } from "./http";
 
 function authHeaders(): HeadersInit {
   const token = getAuthToken();
@@ -34,6 +35,7 @@ export type EdgeClaim = {
   created_at: string | number[];
   updated_at: string | number[] | null;
   last_verified_at: string | number[] | null;
+  version: number;
   evidence: EdgeClaimEvidence[];
   flows: EdgeClaimFlow[];
 };
@@ -44,7 +46,7 @@ export async function getEdgeClaims(edgeId: string): Promise<EdgeClaim[]> {
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(text || "Failed to fetch edge claims");
   }
 
@@ -78,7 +80,7 @@ export async function createEdgeClaim(
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(text || "Failed to create edge claim");
   }
 
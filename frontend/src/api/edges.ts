@@ -1,4 +1,5 @@
 import { getAuthToken } from "./client";
+import { readErrorText } from "./http";
 
 export type NewEdgePayload = {
   from_id: string;
@@ -19,7 +20,7 @@ export async function createEdge(payload: NewEdgePayload) {
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(text || "Failed to create edge");
   }
 
@@ -37,6 +38,6 @@ export async function deleteEdge(edgeId: string, ifMatch: string): Promise<void>
 
   if (res.status === 204) return;
 
-  const text = await res.text().catch(() => "");
+  const text = await readErrorText(res);
   throw new Error(text || "Kunde inte ta bort relation");
 }
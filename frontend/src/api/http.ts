@@ -20,6 +20,21 @@ async function readTextSafe(res: Response) {
   }
 }
 
+/** Error bodies are application/problem+json; prefer the Swedish message. */
+function problemMessage(body: string): string {
+  try {
+    const problem = JSON.parse(body);
+    if (typeof problem?.message_sv === "string") return problem.message_sv;
+  } catch {
+    // not JSON, fall through
+  }
+  return body;
+}
+
+export async function readErrorText(res: Response) {
+  return problemMessage(await readTextSafe(res));
+}
+
 export async function apiFetch<T>(
   path: string,
   init?: RequestInit & { ifMatch?: string }
@@ -39,7 +54,7 @@ export async function apiFetch<T>(
 
   if (!res.ok) {
     const body = await readTextSafe(res);
-    const err: ApiError = new Error(body || `API error ${res.status}`);
+    const err: ApiError = new Error(problemMessage(body) || `API error ${res.status}`);
     err.status = res.status;
     err.body = body;
     throw err;
@@ -1,4 +1,5 @@
 import type { GraphLink } from "./types";
+import { readErrorText } from "./http";
 
 type Json = Record<string, any>;
 
@@ -12,7 +13,7 @@ async function jsonFetch<T>(path: string, init?: RequestInit): Promise<T> {
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(`${res.status} ${res.statusText}${text ? `: ${text}` : ""}`);
   }
 
@@ -1,5 +1,6 @@
 import { getAuthToken } from "./client";
 import type { NodeClaim } from "./nodeClaims";
+import { readErrorText } from "./http";
 
 function authHeaders(): HeadersInit {
   const token = getAuthToken();
@@ -23,7 +24,7 @@ export function approveNodeClaim(
     headers: { ...authHeaders(), "If-Match": v },
   }).then(async (res) => {
     if (!res.ok) {
-      const text = await res.text().catch(() => "");
+      const text = await readErrorText(res);
       throw new Error(text || "Failed to approve node claim");
     }
     return res.json();
@@ -48,7 +49,7 @@ export function rejectNodeClaim(
     body: JSON.stringify({ reason: reason ?? undefined }),
   }).then(async (res) => {
     if (!res.ok) {
-      const text = await res.text().catch(() => "");
+      const text = await readErrorText(res);
       throw new Error(text || "Failed to reject node claim");
     }
     return res.json();
@@ -1,4 +1,5 @@
 import { getAuthToken } from "./client";
+import { readErrorText } from "./http";
 
 function authHeaders(): HeadersInit {
   const token = getAuthToken();
@@ -15,6 +16,7 @@ export type NodeClaim = {
   created_at: string | number[];
   updated_at: string | number[] | null;
   last_verified_at: string | number[] | null;
+  version: number;
 };
 
 export async function getNodeClaims(nodeId: string): Promise<NodeClaim[]> {
@@ -23,7 +25,7 @@ export async function getNodeClaims(nodeId: string): Promise<NodeClaim[]> {
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(text || "Failed to fetch node claims");
   }
 
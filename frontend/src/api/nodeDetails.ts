@@ -1,4 +1,5 @@
 import { getAuthToken } from "./client";
+import { readErrorText } from "./http";
 
 export type NodeParty = {
   id: string;
@@ -87,7 +88,7 @@ async function jsonFetch<T>(path: string, init?: RequestInit): Promise<T> {
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     const msg = `${res.status} ${res.statusText}${text ? `: ${text}` : ""}`;
     const err: any = new Error(msg);
     err.status = res.status;
@@ -112,7 +113,7 @@ async function jsonFetchWithEtag<T>(
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     const msg = `${res.status} ${res.statusText}${text ? `: ${text}` : ""}`;
     const err: any = new Error(msg);
     err.status = res.status;
@@ -1,4 +1,5 @@
 import { getAuthToken } from "./client";
+import { readErrorText } from "./http";
 
 export type NewNodePayload = {
   kind: string;
@@ -49,7 +50,7 @@ async function jsonFetch<T>(path: string, init?: RequestInit): Promise<T> {
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     const err: any = new Error(text || `HTTP ${res.status}`);
     err.status = res.status;
     err.message = text || err.message;
@@ -145,7 +146,7 @@ export async function updateNode(
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     const err: any = new Error(text || "Kunde inte uppdatera objekt");
     err.status = res.status;
     err.message = text || err.message;
@@ -163,7 +164,7 @@ export async function createNode(payload: NewNodePayload) {
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(text || "Kunde inte skapa objekt");
   }
 
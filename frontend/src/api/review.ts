@@ -1,4 +1,5 @@
 import { getAuthToken } from "./client";
+import { readErrorText } from "./http";
 
 export type NeedsReviewEdge = {
   edge_id: string;
@@ -29,7 +30,7 @@ export async function listEdgesNeedingReview(): Promise<NeedsReviewEdge[]> {
     headers: { ...authHeader() },
   });
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(text || "Failed to load needs_review queue");
   }
   return (await res.json()) as NeedsReviewEdge[];
@@ -40,7 +41,7 @@ export async function listNodesNeedingReview(): Promise<NeedsReviewNode[]> {
     headers: { ...authHeader() },
   });
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(text || "Failed to load needs_review queue");
   }
   return (await res.json()) as NeedsReviewNode[];
@@ -60,7 +61,7 @@ export async function markEdgeNeedsReview(
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(text || "Failed to create review request");
   }
 
@@ -81,7 +82,7 @@ export async function markNodeNeedsReview(
   });
 
   if (!res.ok) {
-    const text = await res.text().catch(() => "");
+    const text = await readErrorText(res);
     throw new Error(text || "Failed to create review request");
   }
 
@@ -71,10 +71,34 @@ export type PathsResponse = {
   paths: PathResult[];
 };
 
+export type PiiSignal =
+  | "node_risk"
+  | "node_metadata"
+  | "edge_metadata"
+  | "claim_flow_category"
+  | "typed_flow_domain"
+  | "lineage";
+
+export type PiiReason = {
+  signal: PiiSignal;
+  edge_id: string | null;
+  via_node_id: string | null;
+  detail: string | null;
+};
+
+export type PiiNode = {
+  node_id: string;
+  name: string;
+  kind: string;
+  depth: number;
+  reasons: PiiReason[];
+};
+
 export type ComplianceResult = {
   matched_pii_nodes?: string[];
   node_ids: string[];
   edge_ids: string[];
+  nodes?: PiiNode[];
 };
 
 export type ComplianceResponse = {
@@ -24,7 +24,7 @@ import { NodeInspectorBody } from "@/components/inspector/parts/NodeInspectorBod
 import { EdgeInspectorBody } from "@/components/inspector/parts/EdgeInspectorBody";
 import { EdgeRelationCard } from "@/components/inspector/parts/EdgeRelationCard";
 import { NewConnectionCard } from "@/components/inspector/parts/NewConnectionCard";
-import { flowSummaryDir, ifMatchFromUpdatedAt, pickCurrentClaim, updatedAtToIfMatch } from "@/components/inspector/parts/inspectorPanelUtils";
+import { claimIfMatch, flowSummaryDir, ifMatchFromUpdatedAt, pickCurrentClaim } from "@/components/inspector/parts/inspectorPanelUtils";
 
 type Props = {
   me: { username: string; role: string } | null;
@@ -388,7 +388,7 @@ export function InspectorPanel({
     const claim = selectedEdge ? (currentClaim as any) : (currentNodeClaim as any);
     if (!claim) return;
     if (claim.status !== "needs_review" && claim.status !== "rejected") return;
-    const ifMatch = updatedAtToIfMatch(claim.updated_at);
+    const ifMatch = claimIfMatch(claim);
     if (!ifMatch) {
       onError("Saknar updated_at för ärendet (If-Match)");
       return;
@@ -423,7 +423,7 @@ export function InspectorPanel({
     const claim = selectedEdge ? (currentClaim as any) : (currentNodeClaim as any);
     if (!claim) return;
     if (claim.status !== "needs_review") return;
-    const ifMatch = updatedAtToIfMatch(claim.updated_at);
+    const ifMatch = claimIfMatch(claim);
     if (!ifMatch) {
       onError("Saknar updated_at för ärendet (If-Match)");
       return;
@@ -505,7 +505,7 @@ export function InspectorPanel({
         claim={currentReviewClaim}
         me={me}
         reviewActionLoading={reviewActionLoading}
-        canAct={!!updatedAtToIfMatch(currentReviewClaim?.updated_at)}
+        canAct={!!claimIfMatch(currentReviewClaim)}
         onApprove={doApproveReview}
         onReject={doRejectReview}
       />
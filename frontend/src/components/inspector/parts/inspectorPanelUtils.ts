@@ -83,3 +83,9 @@ export function updatedAtToIfMatch(v: any): string {
     return "";
   }
 }
+
+/** Prefers the row version (`v<n>`); falls back to the legacy updated_at ETag. */
+export function claimIfMatch(claim: any): string {
+  if (typeof claim?.version === "number") return `v${claim.version}`;
+  return updatedAtToIfMatch(claim?.updated_at);
+}